@@ -0,0 +1,77 @@
+use std::{
+    fs,
+    process::Command,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// Returns the short hash of the current git commit, if built from a checkout.
+fn git_hash() -> String {
+    Command::new("git")
+        .args(["rev-parse", "--short", "HEAD"])
+        .output()
+        .ok()
+        .filter(|o| o.status.success())
+        .and_then(|o| String::from_utf8(o.stdout).ok())
+        .map(|s| s.trim().to_owned())
+        .filter(|s| !s.is_empty())
+        .unwrap_or_else(|| String::from("unknown"))
+}
+
+/// Returns the current UTC date as YYYY-MM-DD.
+fn build_date() -> String {
+    let secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0);
+    let z = secs / 86_400 + 719_468;
+    let era = z / 146_097;
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+/// Returns the serenity version resolved in the lock file.
+fn serenity_version() -> String {
+    let lock = fs::read_to_string("Cargo.lock").unwrap_or_default();
+    let mut lines = lock.lines();
+    while let Some(line) = lines.next() {
+        if line.trim() == r#"name = "serenity""# {
+            if let Some(version) = lines.next() {
+                return version
+                    .trim()
+                    .trim_start_matches("version = ")
+                    .trim_matches('"')
+                    .to_owned();
+            }
+        }
+    }
+    String::from("unknown")
+}
+
+/// Returns the git files to watch so a new commit rebuilds with its hash.
+///
+/// `.git/HEAD` only changes when switching branches, so the branch it
+/// points at is watched too, along with `packed-refs` for packed branches.
+fn git_watch_paths() -> Vec<String> {
+    let mut paths = vec![String::from(".git/HEAD"), String::from(".git/packed-refs")];
+    let head = fs::read_to_string(".git/HEAD").unwrap_or_default();
+    if let Some(reference) = head.trim().strip_prefix("ref: ") {
+        paths.push(format!(".git/{}", reference));
+    }
+    paths
+}
+
+fn main() {
+    println!("cargo:rustc-env=GIT_HASH={}", git_hash());
+    println!("cargo:rustc-env=BUILD_DATE={}", build_date());
+    println!("cargo:rustc-env=SERENITY_VERSION={}", serenity_version());
+    for path in git_watch_paths() {
+        println!("cargo:rerun-if-changed={}", path);
+    }
+    println!("cargo:rerun-if-changed=Cargo.lock");
+}
@@ -0,0 +1,462 @@
+use failure::Error;
+use log::{debug, warn};
+use rand::thread_rng;
+use serenity::{
+    client::Context,
+    framework::standard::{Args, CommandResult, macros::command},
+    model::channel::Message,
+    utils::MessageBuilder,
+};
+use std::{path::Path, thread};
+use crate::util::{
+    characters::CharacterStore,
+    constants::LOAD_PATH,
+    permissions::is_admin,
+    probability::FairnessTest,
+    render::summary_table,
+    respond::{send, Reply},
+    roster::{parse_roster, RosterError, RosterRow},
+    text::count,
+};
+
+/// Largest roster accepted, in bytes.
+const MAX_ROSTER_BYTES: u64 = 256 * 1024;
+/// Most roster problems listed in one reply.
+const MAX_ERRORS_SHOWN: usize = 10;
+/// Fewest and most d10s rolled by `!admin dietest`.
+const MIN_DIETEST_ROLLS: u64 = 100;
+const MAX_DIETEST_ROLLS: u64 = 1_000_000;
+const USAGE: &str = "`!admin import-roster` (attach a CSV file with a header row of `name,owner,<stats...>`)
+`!admin dietest <rolls>`
+`!admin compact [confirm]`";
+
+/// Input to the admin command.
+#[derive(Debug)]
+pub struct AdminInput {
+    pub guild_id: Option<u64>,
+    pub is_admin: bool,
+    pub line: String,
+    pub attachment: Option<Vec<u8>>,
+    /// Discord ID and tag (`name#1234`) of each member of the server.
+    pub members: Vec<(u64, String)>,
+}
+
+/// Check an attached roster's name and size before downloading it.
+///
+/// # Arguments
+///
+/// * `filename` - attachment file name
+/// * `size` - attachment size in bytes
+///
+/// # Examples
+///
+/// ```rust
+/// check_roster_meta(&attachment.filename, attachment.size)?;
+/// ```
+pub fn check_roster_meta(filename: &str, size: u64) -> Result<(), String> {
+    if !filename.to_lowercase().ends_with(".csv") {
+        return Err(String::from("Rosters must be CSV files."));
+    }
+    if size > MAX_ROSTER_BYTES {
+        return Err(format!(
+            "Rosters must be at most {} KB.",
+            MAX_ROSTER_BYTES / 1024
+        ));
+    }
+    Ok(())
+}
+
+/// Parse `dietest <rolls>`, returning the number of d10s to roll.
+///
+/// Returns `None` for other subcommands, and an error message when the
+/// number is missing or out of range.
+///
+/// # Arguments
+///
+/// * `line` - what was typed after `!admin`
+///
+/// # Examples
+///
+/// ```rust
+/// assert_eq!(parse_dietest("dietest 100000"), Some(Ok(100_000)));
+/// ```
+fn parse_dietest(line: &str) -> Option<Result<u64, String>> {
+    let mut parts = line.split_whitespace();
+    if parts.next() != Some("dietest") {
+        return None;
+    }
+    let rolls = match (parts.next().map(str::parse::<u64>), parts.next()) {
+        (Some(Ok(rolls)), None) => rolls,
+        _ => return Some(Err(String::from("`!admin dietest <rolls>`"))),
+    };
+    if !(MIN_DIETEST_ROLLS..=MAX_DIETEST_ROLLS).contains(&rolls) {
+        return Some(Err(format!(
+            "Die tests can roll from {} to {} dice.",
+            MIN_DIETEST_ROLLS, MAX_DIETEST_ROLLS
+        )));
+    }
+    Some(Ok(rolls))
+}
+
+/// Find the Discord ID for a roster's owner cell.
+///
+/// Accepts an ID, a mention, or the tag of a server member.
+///
+/// # Arguments
+///
+/// * `owner` - contents of the owner cell
+/// * `members` - ID and tag of each server member
+///
+/// # Examples
+///
+/// ```rust
+/// let owner_id = resolve_owner("Ada#1234", &members);
+/// ```
+fn resolve_owner(owner: &str, members: &[(u64, String)]) -> Option<u64> {
+    let id = owner
+        .trim_start_matches("<@")
+        .trim_start_matches('!')
+        .trim_end_matches('>');
+    if let Ok(id) = id.parse::<u64>() {
+        return Some(id);
+    }
+    members
+        .iter()
+        .find(|(_, tag)| tag.eq_ignore_ascii_case(owner))
+        .map(|&(id, _)| id)
+}
+
+/// Reply listing why a roster wasn't imported.
+///
+/// # Arguments
+///
+/// * `errors` - problems found in the roster
+///
+/// # Examples
+///
+/// ```rust
+/// let reply = roster_problems(&errors);
+/// ```
+fn roster_problems(errors: &[RosterError]) -> Reply {
+    debug!("Roster has {} problems", errors.len());
+    let mut listed: Vec<String> = errors
+        .iter()
+        .take(MAX_ERRORS_SHOWN)
+        .map(ToString::to_string)
+        .collect();
+    if errors.len() > MAX_ERRORS_SHOWN {
+        listed.push(format!("... and {} more", errors.len() - MAX_ERRORS_SHOWN));
+    }
+    let response = MessageBuilder::new()
+        .push("The roster was not imported:")
+        .push_codeblock_safe(listed.join("\n"), None)
+        .build();
+    Reply::Refuse(response)
+}
+
+/// Check that no user would end up owning two characters from a roster.
+///
+/// Each user has one character, so a roster can't give one owner several
+/// characters, or give an owner who already has a character another one.
+///
+/// # Arguments
+///
+/// * `rows` - the roster's characters
+/// * `owner_ids` - the Discord ID each row's owner resolved to, if any
+/// * `store` - character store the roster is imported into
+///
+/// # Examples
+///
+/// ```rust
+/// let errors = check_owners(&rows, &owner_ids, &store);
+/// ```
+fn check_owners(
+    rows: &[RosterRow],
+    owner_ids: &[Option<u64>],
+    store: &CharacterStore,
+) -> Vec<RosterError> {
+    let mut errors = vec![];
+    let mut owned: Vec<(u64, &RosterRow)> = vec![];
+    for (row, owner_id) in rows.iter().zip(owner_ids) {
+        let (id, owner) = match (owner_id, &row.owner) {
+            (Some(id), Some(owner)) => (*id, owner),
+            _ => continue,
+        };
+        let message = if let Some((_, first)) = owned.iter().find(|(o, _)| *o == id) {
+            format!("`{}` already owns `{}` on line {}", owner, first.name, first.line)
+        } else {
+            owned.push((id, row));
+            match store.get(id, &row.name) {
+                Some(existing) if existing.name() != row.name => {
+                    format!("`{}` already has a character, `{}`", owner, existing.name())
+                }
+                _ => continue,
+            }
+        };
+        errors.push(RosterError {
+            line: row.line,
+            column: 2,
+            message,
+        });
+    }
+    errors
+}
+
+/// Create or update every character in an attached roster.
+///
+/// Nothing is changed unless the whole roster is valid and saved.
+fn import_roster(
+    input: &AdminInput,
+    store: &mut CharacterStore,
+    path: &Path,
+) -> Result<Vec<Reply>, Error> {
+    let data = match &input.attachment {
+        Some(data) => data,
+        None => return Ok(vec![Reply::Refuse(String::from(USAGE))]),
+    };
+    let rows = match parse_roster(data) {
+        Ok(rows) => rows,
+        Err(errors) => return Ok(vec![roster_problems(&errors)]),
+    };
+    if rows.is_empty() {
+        return Ok(vec![Reply::Refuse(String::from(
+            "The roster has no characters in it.",
+        ))]);
+    }
+    let owner_ids: Vec<Option<u64>> = rows
+        .iter()
+        .map(|r| r.owner.as_ref().and_then(|o| resolve_owner(o, &input.members)))
+        .collect();
+    let errors = check_owners(&rows, &owner_ids, store);
+    if !errors.is_empty() {
+        return Ok(vec![roster_problems(&errors)]);
+    }
+    let mut updated = store.clone();
+    let mut summary = vec![];
+    let mut unresolved = vec![];
+    for (roster_row, &owner_id) in rows.iter().zip(&owner_ids) {
+        // Characters without an owner are claimed by the first user with their name
+        let exists = match owner_id {
+            Some(id) => updated.get(id, &roster_row.name).is_some(),
+            None => updated.get_by_name(&roster_row.name).is_some(),
+        };
+        let status = if exists { "updated" } else { "created" };
+        let character = match owner_id {
+            Some(id) => updated.get_mut(id, &roster_row.name),
+            None => updated.get_by_name_mut(&roster_row.name),
+        };
+        for (stat, value) in &roster_row.stats {
+            character.set_value(stat, *value);
+        }
+        let owner = match (&roster_row.owner, owner_id) {
+            (_, Some(id)) => {
+                character.set_last_writer(id);
+                id.to_string()
+            }
+            (Some(owner), None) => {
+                unresolved.push(owner.as_str());
+                String::from("-")
+            }
+            (None, None) => String::from("-"),
+        };
+        summary.push(vec![
+            roster_row.name.clone(),
+            owner,
+            roster_row.stats.len().to_string(),
+            String::from(status),
+        ]);
+    }
+    updated.save(path)?;
+    *store = updated;
+
+    let mut builder = MessageBuilder::new();
+    builder
+        .push("Imported ")
+        .push(count(rows.len() as i64, "character", "characters"))
+        .push(":")
+        .push_codeblock_safe(
+            summary_table(&["Character", "Owner", "Stats", ""], &summary),
+            None,
+        );
+    if !unresolved.is_empty() {
+        builder
+            .push(
+                "\nThese owners aren't members of this server, \
+                 so their characters have no owner: ",
+            )
+            .push_safe(unresolved.join(", "));
+    }
+    Ok(vec![Reply::Say(builder.build())])
+}
+
+/// List the characters with nothing worth keeping, removing them once confirmed.
+///
+/// # Arguments
+///
+/// * `confirmed` - whether to remove them, rather than only list them
+/// * `store` - character store to update
+/// * `path` - path the store is saved to
+///
+/// # Examples
+///
+/// ```rust
+/// let replies = compact(false, &mut store, &LOAD_PATH)?;
+/// ```
+fn compact(confirmed: bool, store: &mut CharacterStore, path: &Path) -> Result<Vec<Reply>, Error> {
+    let empty = store.empty_characters();
+    if empty.is_empty() {
+        return Ok(vec![Reply::Say(String::from(
+            "There are no empty characters to remove.",
+        ))]);
+    }
+    if !confirmed {
+        let response = MessageBuilder::new()
+            .push(count(empty.len() as i64, "character has", "characters have"))
+            .push(" no stats or other data: ")
+            .push_safe(empty.join(", "))
+            .push("\nUse `!admin compact confirm` to remove them.")
+            .build();
+        return Ok(vec![Reply::Say(response)]);
+    }
+    let removed = store.remove_empty();
+    store.save(path)?;
+    let response = MessageBuilder::new()
+        .push("Removed ")
+        .push(count(removed.len() as i64, "empty character", "empty characters"))
+        .push(": ")
+        .push_safe(removed.join(", "))
+        .build();
+    Ok(vec![Reply::Say(response)])
+}
+
+/// Handle an admin command, returning the replies to send.
+///
+/// # Arguments
+///
+/// * `input` - who invoked the command and what they typed after `!admin`
+/// * `store` - character store to update
+/// * `path` - path the store is saved to
+///
+/// # Examples
+///
+/// ```rust
+/// let replies = handle_admin(&input, &mut store, &LOAD_PATH)?;
+/// ```
+pub fn handle_admin(
+    input: &AdminInput,
+    store: &mut CharacterStore,
+    path: &Path,
+) -> Result<Vec<Reply>, Error> {
+    if input.guild_id.is_none() {
+        return Ok(vec![Reply::Refuse(String::from(
+            "Admin commands can only be used in a server.",
+        ))]);
+    }
+    if !input.is_admin {
+        return Ok(vec![Reply::Refuse(String::from(
+            "You need the Manage Server permission to use admin commands.",
+        ))]);
+    }
+    if let Some(rolls) = parse_dietest(&input.line) {
+        let reply = match rolls {
+            Ok(rolls) => Reply::Say(format!("Rolling {} d10s. The results will follow.", rolls)),
+            Err(e) => Reply::Refuse(e),
+        };
+        return Ok(vec![reply]);
+    }
+    match input.line.trim() {
+        "import-roster" => import_roster(input, store, path),
+        "compact" => compact(false, store, path),
+        "compact confirm" => compact(true, store, path),
+        _ => Ok(vec![Reply::Refuse(String::from(USAGE))]),
+    }
+}
+
+#[command]
+pub fn admin(context: &mut Context, message: &Message, args: Args) -> CommandResult {
+    let is_admin = is_admin(context, message);
+    let attachment = match message.attachments.first() {
+        Some(attachment) if is_admin => {
+            if let Err(e) = check_roster_meta(&attachment.filename, attachment.size) {
+                message.channel_id.say(&context.http, &e)?;
+                return Ok(());
+            }
+            Some(attachment.download()?)
+        }
+        _ => None,
+    };
+    let members = match message.guild(&context.cache) {
+        Some(guild) => guild
+            .read()
+            .members
+            .values()
+            .map(|m| {
+                let user = m.user.read();
+                (user.id.0, user.tag())
+            })
+            .collect(),
+        None => vec![],
+    };
+    let input = AdminInput {
+        guild_id: message.guild_id.map(|g| g.0),
+        is_admin,
+        line: args.rest().to_owned(),
+        attachment,
+        members,
+    };
+    let mut cs = CharacterStore::from_file(&LOAD_PATH)?;
+    let replies = handle_admin(&input, &mut cs, &LOAD_PATH)?;
+    send(context, message, replies)?;
+    let permitted = is_admin && input.guild_id.is_some();
+    if let (Some(Ok(rolls)), true) = (parse_dietest(&input.line), permitted) {
+        let http = context.http.clone();
+        let channel_id = message.channel_id;
+        thread::spawn(move || {
+            let report = FairnessTest::run(rolls, &mut thread_rng()).report();
+            if let Err(e) = channel_id.say(&http, &report) {
+                warn!("Could not send die test results: {:?}", e);
+            }
+        });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::{check_roster_meta, parse_dietest, resolve_owner};
+
+    #[test]
+    fn test_check_roster_meta() {
+        assert!(check_roster_meta("pregens.csv", 1024).is_ok());
+        assert!(check_roster_meta("PREGENS.CSV", 1024).is_ok());
+        assert!(check_roster_meta("pregens.xlsx", 1024).is_err());
+        assert!(check_roster_meta("pregens.csv", 10 * 1024 * 1024).is_err());
+    }
+
+    #[test]
+    fn test_resolve_owner() {
+        let members = vec![(10, String::from("Ada#1234")), (20, String::from("Ben#0001"))];
+        assert_eq!(resolve_owner("Ada#1234", &members), Some(10));
+        assert_eq!(resolve_owner("ben#0001", &members), Some(20));
+        assert_eq!(resolve_owner("123456789", &members), Some(123_456_789));
+        assert_eq!(resolve_owner("<@!30>", &members), Some(30));
+        assert_eq!(resolve_owner("<@30>", &members), Some(30));
+        assert_eq!(resolve_owner("Cal#9999", &members), None);
+    }
+
+    #[test]
+    fn test_parse_dietest() {
+        assert_eq!(parse_dietest("dietest 100000"), Some(Ok(100_000)));
+        assert_eq!(parse_dietest(" dietest  100 "), Some(Ok(100)));
+        assert_eq!(
+            parse_dietest("dietest 10"),
+            Some(Err(String::from("Die tests can roll from 100 to 1000000 dice.")))
+        );
+        assert_eq!(
+            parse_dietest("dietest lots"),
+            Some(Err(String::from("`!admin dietest <rolls>`")))
+        );
+        assert_eq!(parse_dietest("dietest"), Some(Err(String::from("`!admin dietest <rolls>`"))));
+        assert_eq!(parse_dietest("import-roster"), None);
+    }
+}
@@ -0,0 +1,240 @@
+use failure::Error;
+use log::debug;
+use serenity::{
+    client::Context,
+    framework::standard::{Args, CommandResult, macros::command},
+    model::{channel::Message, id::{ChannelId, UserId}},
+    utils::MessageBuilder,
+};
+use std::path::Path;
+use crate::util::{
+    args::split_args,
+    characters::{Buff, CharacterStore},
+    constants::LOAD_PATH,
+    expression::MAX_POOL,
+    respond::{send, Reply},
+    text::count,
+};
+
+const USAGE: &str = "`!buff add <stat_name> <+dice> \"<source>\" [scenes:<number>]`
+`!buff list`
+`!buff clear`";
+
+/// Input to the buff and scene commands.
+#[derive(Debug)]
+pub struct BuffInput {
+    pub user_id: UserId,
+    pub user_name: String,
+    pub channel_id: ChannelId,
+    pub line: String,
+}
+
+/// Parse how many scenes a buff lasts, like `scenes:2`.
+///
+/// # Arguments
+///
+/// * `arg` - the argument, if one was given
+///
+/// # Examples
+///
+/// ```rust
+/// let scenes = parse_scenes(Some("scenes:2"));
+/// ```
+fn parse_scenes(arg: Option<&str>) -> Option<u32> {
+    let arg = match arg {
+        Some(a) => a,
+        None => return Some(1),
+    };
+    let mut parts = arg.splitn(2, ':');
+    match (parts.next(), parts.next()) {
+        (Some(key), Some(scenes)) if key.eq_ignore_ascii_case("scenes") => {
+            scenes.parse::<u32>().ok().filter(|&n| n > 0)
+        }
+        _ => None,
+    }
+}
+
+/// Handle a buff command, returning the replies to send.
+///
+/// Changes are saved to the store's file before returning.
+///
+/// # Arguments
+///
+/// * `input` - who invoked the command and what they typed after `!buff`
+/// * `store` - character store to read and update
+/// * `path` - path the store is saved to
+///
+/// # Examples
+///
+/// ```rust
+/// let replies = handle_buff(&input, &mut store, &LOAD_PATH)?;
+/// ```
+pub fn handle_buff(
+    input: &BuffInput,
+    store: &mut CharacterStore,
+    path: &Path,
+) -> Result<Vec<Reply>, Error> {
+    let args = split_args(&input.line);
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    debug!("Buff args are: {:?}", args);
+    if args.first() == Some(&"list") {
+        let buffs = match store.get(input.user_id.0, &input.user_name) {
+            Some(character) if !character.buffs().is_empty() => character.buffs(),
+            _ => return Ok(vec![Reply::Say(String::from("You have no active buffs."))]),
+        };
+        let lines: Vec<String> = buffs.iter().map(Buff::to_string).collect();
+        let response = MessageBuilder::new()
+            .push_codeblock_safe(lines.join("\n"), None)
+            .build();
+        return Ok(vec![Reply::Say(response)]);
+    }
+    match args.as_slice() {
+        ["add", stat, dice, source, rest @ ..] if rest.len() <= 1 => {
+            let dice = match dice.parse::<i64>() {
+                Ok(d) if d != 0 && (-MAX_POOL..=MAX_POOL).contains(&d) => d,
+                _ => return Ok(vec![Reply::Refuse(String::from(USAGE))]),
+            };
+            let scenes = match parse_scenes(rest.first().copied()) {
+                Some(s) => s,
+                None => return Ok(vec![Reply::Refuse(String::from(USAGE))]),
+            };
+            let character = store.get_mut(input.user_id.0, &input.user_name);
+            character.set_last_writer(input.user_id.0);
+            character.add_buff(Buff {
+                stat: stat.to_string(),
+                dice,
+                source: source.to_string(),
+                scenes,
+            });
+            let added = character.buffs().last().map(Buff::to_string);
+            store.save(path)?;
+            let response = MessageBuilder::new()
+                .push("Added ")
+                .push_safe(added.unwrap_or_default())
+                .push(".")
+                .build();
+            Ok(vec![Reply::Say(response)])
+        }
+        ["clear"] => {
+            let cleared = match store.get(input.user_id.0, &input.user_name) {
+                Some(_) => store.get_mut(input.user_id.0, &input.user_name).clear_buffs(),
+                None => 0,
+            };
+            if cleared > 0 {
+                store.save(path)?;
+            }
+            Ok(vec![Reply::Say(format!(
+                "Cleared {}.",
+                count(cleared as i64, "buff", "buffs")
+            ))])
+        }
+        _ => Ok(vec![Reply::Refuse(String::from(USAGE))]),
+    }
+}
+
+/// Handle a scene command, returning the replies to send.
+///
+/// Ending a scene counts down the author's buffs, removing any that expire,
+/// and ends every character's conditions that last until the end of the
+/// scene in this channel.
+///
+/// # Arguments
+///
+/// * `input` - who invoked the command and what they typed after `!scene`
+/// * `store` - character store to read and update
+/// * `path` - path the store is saved to
+///
+/// # Examples
+///
+/// ```rust
+/// let replies = handle_scene(&input, &mut store, &LOAD_PATH)?;
+/// ```
+pub fn handle_scene(
+    input: &BuffInput,
+    store: &mut CharacterStore,
+    path: &Path,
+) -> Result<Vec<Reply>, Error> {
+    if input.line.trim() != "end" {
+        return Ok(vec![Reply::Refuse(String::from("`!scene end`"))]);
+    }
+    let expired = match store.get(input.user_id.0, &input.user_name) {
+        Some(character) if !character.buffs().is_empty() => {
+            let character = store.get_mut(input.user_id.0, &input.user_name);
+            character.set_last_writer(input.user_id.0);
+            character.end_scene()
+        }
+        _ => vec![],
+    };
+    let ended = store.end_scene_conditions(input.channel_id.0);
+    if !expired.is_empty() || !ended.is_empty() {
+        store.save(path)?;
+    }
+    let mut response = MessageBuilder::new();
+    if expired.is_empty() {
+        response.push("Scene ended. No buffs expired.");
+    } else {
+        let expired: Vec<String> = expired
+            .iter()
+            .map(|b| format!("{} {:+} ({})", b.stat, b.dice, b.source))
+            .collect();
+        response
+            .push("Scene ended. Expired: ")
+            .push_safe(expired.join(", "));
+    }
+    if !ended.is_empty() {
+        let ended: Vec<String> = ended
+            .iter()
+            .map(|(name, conditions)| {
+                let names: Vec<&str> = conditions.iter().map(|c| c.name.as_str()).collect();
+                format!("{} ({})", name, names.join(", "))
+            })
+            .collect();
+        response
+            .push("\nConditions ended: ")
+            .push_safe(ended.join(", "));
+    }
+    Ok(vec![Reply::Say(response.build())])
+}
+
+#[command]
+pub fn buff(context: &mut Context, message: &Message, args: Args) -> CommandResult {
+    let input = BuffInput {
+        user_id: message.author.id,
+        user_name: message.author.name.clone(),
+        channel_id: message.channel_id,
+        line: args.rest().to_owned(),
+    };
+    let mut cs = CharacterStore::from_file(&LOAD_PATH)?;
+    let replies = handle_buff(&input, &mut cs, &LOAD_PATH)?;
+    send(context, message, replies)?;
+    Ok(())
+}
+
+#[command]
+pub fn scene(context: &mut Context, message: &Message, args: Args) -> CommandResult {
+    let input = BuffInput {
+        user_id: message.author.id,
+        user_name: message.author.name.clone(),
+        channel_id: message.channel_id,
+        line: args.rest().to_owned(),
+    };
+    let mut cs = CharacterStore::from_file(&LOAD_PATH)?;
+    let replies = handle_scene(&input, &mut cs, &LOAD_PATH)?;
+    send(context, message, replies)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::parse_scenes;
+
+    #[test]
+    fn test_parse_scenes() {
+        assert_eq!(parse_scenes(None), Some(1));
+        assert_eq!(parse_scenes(Some("scenes:3")), Some(3));
+        assert_eq!(parse_scenes(Some("Scenes:2")), Some(2));
+        assert_eq!(parse_scenes(Some("scenes:0")), None);
+        assert_eq!(parse_scenes(Some("scenes:lots")), None);
+        assert_eq!(parse_scenes(Some("3")), None);
+    }
+}
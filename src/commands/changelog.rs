@@ -0,0 +1,70 @@
+use serenity::{
+    client::Context,
+    framework::standard::{CommandResult, macros::command},
+    model::channel::Message,
+    utils::MessageBuilder,
+};
+
+const CHANGELOG: &str = include_str!("../../CHANGELOG.md");
+
+/// Returns the first `## ` section of a changelog, header included.
+///
+/// # Arguments
+///
+/// * `text` - full changelog text
+///
+/// # Examples
+///
+/// ```rust
+/// let section = latest_section(CHANGELOG);
+/// ```
+fn latest_section(text: &str) -> Option<&str> {
+    let start = if text.starts_with("## ") {
+        0
+    } else {
+        text.find("\n## ")? + 1
+    };
+    let rest = &text[start..];
+    let end = rest.find("\n## ").unwrap_or(rest.len());
+    Some(rest[..end].trim())
+}
+
+#[command]
+pub fn changelog(context: &mut Context, message: &Message) -> CommandResult {
+    let section = latest_section(CHANGELOG).unwrap_or("No changelog entries");
+    let response = MessageBuilder::new()
+        .push_codeblock(section, Some("md"))
+        .build();
+    message.channel_id.say(&context.http, &response)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::latest_section;
+
+    #[test]
+    fn test_latest_section() {
+        let text = "# Changelog\n\n## 0.2.0\n\n* b\n* c\n\n## 0.1.0\n\n* a\n";
+
+        assert_eq!(latest_section(text), Some("## 0.2.0\n\n* b\n* c"));
+    }
+
+    #[test]
+    fn test_latest_section_only_section() {
+        assert_eq!(latest_section("## 0.1.0\n* a\n"), Some("## 0.1.0\n* a"));
+    }
+
+    #[test]
+    fn test_latest_section_missing() {
+        assert_eq!(latest_section("# Changelog\n\nnothing yet"), None);
+        assert_eq!(latest_section(""), None);
+    }
+
+    #[test]
+    fn test_latest_section_ignores_inline_hashes() {
+        let text = "## 1.0\n* uses ## in text\n### sub\n* x\n## 0.9\n";
+
+        assert_eq!(latest_section(text), Some("## 1.0\n* uses ## in text\n### sub\n* x"));
+    }
+}
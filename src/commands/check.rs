@@ -0,0 +1,30 @@
+use serenity::{
+    client::Context,
+    framework::standard::{Args, CommandResult, macros::command},
+    model::channel::Message,
+};
+use crate::{
+    commands::roll::{handle_check, CheckInput},
+    util::{
+        constants::{LOAD_PATH, SETTINGS_PATH},
+        characters::CharacterStore,
+        respond::send,
+        settings::SettingsStore,
+    },
+};
+
+#[command]
+pub fn check(context: &mut Context, message: &Message, args: Args) -> CommandResult {
+    let settings = SettingsStore::from_file(&SETTINGS_PATH)?;
+    let guild = message.guild_id.and_then(|g| settings.get(g.0));
+    let input = CheckInput {
+        user_id: message.author.id,
+        user_name: message.author.name.clone(),
+        line: args.rest().to_owned(),
+        constants: guild.map(|g| g.constants.clone()).unwrap_or_default(),
+    };
+    let cs = CharacterStore::from_file(&LOAD_PATH)?;
+    let replies = handle_check(&input, &cs);
+    send(context, message, replies)?;
+    Ok(())
+}
@@ -0,0 +1,175 @@
+use log::debug;
+use serenity::{
+    client::Context,
+    framework::standard::{Args, CommandResult, macros::command},
+    model::{channel::Message, id::MessageId},
+};
+use crate::util::{
+    cleanup::{history_pages, plan_deletion, Deletion},
+    respond::{send, Reply},
+    text::count,
+    time::now,
+};
+
+/// Most messages one cleanup scans.
+const MAX_SCAN: u64 = 500;
+/// Most messages one cleanup deletes one request at a time.
+const MAX_SINGLE_DELETES: usize = 20;
+const USAGE: &str = "`!cleanup <messages to scan, up to 500>`";
+
+/// Parse the number of messages to scan.
+///
+/// # Arguments
+///
+/// * `line` - what was typed after `!cleanup`
+///
+/// # Examples
+///
+/// ```rust
+/// assert_eq!(parse_scan_count("50"), Ok(50));
+/// ```
+fn parse_scan_count(line: &str) -> Result<u64, String> {
+    match line.trim().parse::<u64>() {
+        Ok(n) if (1..=MAX_SCAN).contains(&n) => Ok(n),
+        _ => Err(String::from(USAGE)),
+    }
+}
+
+/// Describe what a cleanup removed.
+///
+/// # Arguments
+///
+/// * `deletion` - messages that were deleted
+/// * `scanned` - number of messages scanned
+///
+/// # Examples
+///
+/// ```rust
+/// let response = cleanup_summary(&deletion, 50);
+/// ```
+fn cleanup_summary(deletion: &Deletion, scanned: u64) -> String {
+    let total = deletion.total();
+    if total == 0 && deletion.skipped == 0 {
+        return format!(
+            "None of the last {} were mine.",
+            count(scanned as i64, "message", "messages")
+        );
+    }
+    let mut response = format!("Removed {}.", count(total as i64, "message", "messages"));
+    if deletion.skipped > 0 {
+        response.push_str(&format!(
+            " Left {} older than 14 days, which have to be removed one at a time.",
+            deletion.skipped
+        ));
+    }
+    response
+}
+
+#[command]
+pub fn cleanup(context: &mut Context, message: &Message, args: Args) -> CommandResult {
+    let scan = match parse_scan_count(args.rest()) {
+        Ok(n) => n,
+        Err(e) => {
+            send(context, message, vec![Reply::Refuse(e)])?;
+            return Ok(());
+        }
+    };
+    let guild = match message.guild(&context.cache) {
+        Some(g) => g,
+        None => {
+            let response = "Messages can only be cleaned up in a server channel.";
+            send(context, message, vec![Reply::Refuse(String::from(response))])?;
+            return Ok(());
+        }
+    };
+    let bot_id = context.cache.read().user.id;
+    let (invoker, bot) = {
+        let guild = guild.read();
+        (
+            guild.permissions_in(message.channel_id, message.author.id),
+            guild.permissions_in(message.channel_id, bot_id),
+        )
+    };
+    if !invoker.manage_messages() {
+        let response = "You need the Manage Messages permission to clean up this channel.";
+        send(context, message, vec![Reply::Refuse(String::from(response))])?;
+        return Ok(());
+    }
+    if !bot.manage_messages() {
+        let response = "I need the Manage Messages permission to clean up this channel.";
+        send(context, message, vec![Reply::Refuse(String::from(response))])?;
+        return Ok(());
+    }
+    let mut ids = vec![];
+    let mut before = message.id;
+    for limit in history_pages(scan) {
+        // Pages come newest first
+        let page = message
+            .channel_id
+            .messages(&context.http, |r| r.before(before).limit(limit))?;
+        ids.extend(page.iter().filter(|m| m.author.id == bot_id).map(|m| m.id.0));
+        match page.last() {
+            Some(oldest) if page.len() as u64 == limit => before = oldest.id,
+            _ => break,
+        }
+    }
+    let deletion = plan_deletion(&ids, now(), MAX_SINGLE_DELETES);
+    debug!(
+        "Cleaning up {} bulk batches and {} single messages, skipping {}",
+        deletion.bulk.len(),
+        deletion.single.len(),
+        deletion.skipped
+    );
+    for batch in &deletion.bulk {
+        message
+            .channel_id
+            .delete_messages(&context.http, batch.iter().map(|&id| MessageId(id)))?;
+    }
+    for &id in &deletion.single {
+        message.channel_id.delete_message(&context.http, MessageId(id))?;
+    }
+    message
+        .channel_id
+        .say(&context.http, cleanup_summary(&deletion, scan))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::{cleanup_summary, parse_scan_count, USAGE};
+    use crate::util::cleanup::Deletion;
+
+    #[test]
+    fn test_parse_scan_count() {
+        assert_eq!(parse_scan_count("50"), Ok(50));
+        assert_eq!(parse_scan_count(" 500 "), Ok(500));
+        assert_eq!(parse_scan_count("0"), Err(String::from(USAGE)));
+        assert_eq!(parse_scan_count("501"), Err(String::from(USAGE)));
+        assert_eq!(parse_scan_count(""), Err(String::from(USAGE)));
+        assert_eq!(parse_scan_count("all"), Err(String::from(USAGE)));
+    }
+
+    #[test]
+    fn test_cleanup_summary() {
+        assert_eq!(
+            cleanup_summary(&Deletion::default(), 50),
+            "None of the last 50 messages were mine."
+        );
+        let deletion = Deletion {
+            bulk: vec![vec![1, 2]],
+            single: vec![3],
+            skipped: 0,
+        };
+        assert_eq!(cleanup_summary(&deletion, 50), "Removed 3 messages.");
+        let deletion = Deletion {
+            bulk: vec![],
+            single: vec![3],
+            skipped: 4,
+        };
+        assert_eq!(
+            cleanup_summary(&deletion, 50),
+            "Removed 1 message. Left 4 older than 14 days, \
+             which have to be removed one at a time."
+        );
+    }
+}
@@ -0,0 +1,128 @@
+use failure::Error;
+use log::debug;
+use serenity::{
+    client::Context,
+    framework::standard::{Args, CommandResult, macros::command},
+    model::{channel::Message, id::{ChannelId, UserId}},
+    utils::MessageBuilder,
+};
+use std::path::Path;
+use crate::util::{
+    args::split_args,
+    characters::{CharacterStore, Condition},
+    constants::LOAD_PATH,
+    respond::{send, Reply},
+};
+
+const USAGE: &str = "`!condition add \"<name>\" [scene]`
+`!condition remove \"<name>\"`
+`!condition list`";
+
+/// Input to the condition command.
+#[derive(Debug)]
+pub struct ConditionInput {
+    pub user_id: UserId,
+    pub user_name: String,
+    pub channel_id: ChannelId,
+    pub line: String,
+}
+
+/// Handle a condition command, returning the replies to send.
+///
+/// Conditions added with `scene` end when `!scene end` is used in the
+/// channel they were added in.
+///
+/// # Arguments
+///
+/// * `input` - who invoked the command and what they typed after `!condition`
+/// * `store` - character store to read and update
+/// * `path` - path the store is saved to
+///
+/// # Examples
+///
+/// ```rust
+/// let replies = handle_condition(&input, &mut store, &LOAD_PATH)?;
+/// ```
+pub fn handle_condition(
+    input: &ConditionInput,
+    store: &mut CharacterStore,
+    path: &Path,
+) -> Result<Vec<Reply>, Error> {
+    let args = split_args(&input.line);
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    debug!("Condition args are: {:?}", args);
+    if args.first() == Some(&"list") {
+        let conditions = match store.get(input.user_id.0, &input.user_name) {
+            Some(character) if !character.conditions().is_empty() => character.conditions(),
+            _ => return Ok(vec![Reply::Say(String::from("You have no conditions."))]),
+        };
+        let lines: Vec<String> = conditions.iter().map(Condition::to_string).collect();
+        let response = MessageBuilder::new()
+            .push_codeblock_safe(lines.join("\n"), None)
+            .build();
+        return Ok(vec![Reply::Say(response)]);
+    }
+    match args.as_slice() {
+        ["add", name] | ["add", name, "scene"] => {
+            let scene_channel = if args.len() == 3 {
+                Some(input.channel_id.0)
+            } else {
+                None
+            };
+            let character = store.get_mut(input.user_id.0, &input.user_name);
+            character.set_last_writer(input.user_id.0);
+            character.add_condition(Condition {
+                name: name.to_string(),
+                scene_channel,
+            });
+            store.save(path)?;
+            let response = MessageBuilder::new()
+                .push("Added condition ")
+                .push_bold_safe(*name)
+                .push(if scene_channel.is_some() {
+                    ", which ends with this scene."
+                } else {
+                    "."
+                })
+                .build();
+            Ok(vec![Reply::Say(response)])
+        }
+        ["remove", name] => {
+            let removed = match store.get(input.user_id.0, &input.user_name) {
+                Some(_) => {
+                    let character = store.get_mut(input.user_id.0, &input.user_name);
+                    character.set_last_writer(input.user_id.0);
+                    character.remove_condition(name)
+                }
+                None => false,
+            };
+            if !removed {
+                return Ok(vec![Reply::Refuse(String::from(
+                    "You don't have that condition.",
+                ))]);
+            }
+            store.save(path)?;
+            let response = MessageBuilder::new()
+                .push("Removed condition ")
+                .push_bold_safe(*name)
+                .push(".")
+                .build();
+            Ok(vec![Reply::Say(response)])
+        }
+        _ => Ok(vec![Reply::Refuse(String::from(USAGE))]),
+    }
+}
+
+#[command]
+pub fn condition(context: &mut Context, message: &Message, args: Args) -> CommandResult {
+    let input = ConditionInput {
+        user_id: message.author.id,
+        user_name: message.author.name.clone(),
+        channel_id: message.channel_id,
+        line: args.rest().to_owned(),
+    };
+    let mut cs = CharacterStore::from_file(&LOAD_PATH)?;
+    let replies = handle_condition(&input, &mut cs, &LOAD_PATH)?;
+    send(context, message, replies)?;
+    Ok(())
+}
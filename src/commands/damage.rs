@@ -0,0 +1,90 @@
+use failure::Error;
+use log::debug;
+use serenity::{
+    client::Context,
+    framework::standard::{Args, CommandResult, macros::command},
+    model::{channel::Message, id::UserId},
+    utils::MessageBuilder,
+};
+use std::path::Path;
+use crate::util::{
+    args::split_args,
+    characters::{CharacterStore, Damage},
+    constants::LOAD_PATH,
+    respond::{send, Reply},
+};
+
+/// Most points of damage taken or healed at once.
+const MAX_AMOUNT: u64 = 100;
+const USAGE: &str = "`!damage take <amount> bashing|lethal|agg` (amount up to 100)
+`!damage heal <amount> bashing|lethal|agg`";
+
+/// Input to the damage command.
+#[derive(Debug)]
+pub struct DamageInput {
+    pub user_id: UserId,
+    pub user_name: String,
+    pub line: String,
+}
+
+/// Handle a damage command, returning the replies to send.
+///
+/// Damage is marked on, or healed from, the author's health track, which
+/// is saved and shown in the reply.
+///
+/// # Arguments
+///
+/// * `input` - who invoked the command and what they typed after `!damage`
+/// * `store` - character store to read and update
+/// * `path` - path the store is saved to
+///
+/// # Examples
+///
+/// ```rust
+/// let replies = handle_damage(&input, &mut store, &LOAD_PATH)?;
+/// ```
+pub fn handle_damage(
+    input: &DamageInput,
+    store: &mut CharacterStore,
+    path: &Path,
+) -> Result<Vec<Reply>, Error> {
+    let args = split_args(&input.line);
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    debug!("Damage args are: {:?}", args);
+    let (heal, amount, kind) = match args.as_slice() {
+        [action @ "take", amount, kind] | [action @ "heal", amount, kind] => {
+            match (amount.parse::<u64>(), Damage::parse(kind)) {
+                (Ok(amount), Some(kind)) if amount > 0 && amount <= MAX_AMOUNT => {
+                    (*action == "heal", amount, kind)
+                }
+                _ => return Ok(vec![Reply::Refuse(String::from(USAGE))]),
+            }
+        }
+        _ => return Ok(vec![Reply::Refuse(String::from(USAGE))]),
+    };
+    let character = store.get_mut(input.user_id.0, &input.user_name);
+    character.set_last_writer(input.user_id.0);
+    if heal {
+        character.health_mut().heal(kind, amount);
+    } else {
+        character.health_mut().take(kind, amount);
+    }
+    let response = MessageBuilder::new()
+        .push_codeblock(character.health(), None)
+        .build();
+    store.save(path)?;
+    Ok(vec![Reply::Say(response)])
+}
+
+#[command]
+pub fn damage(context: &mut Context, message: &Message, args: Args) -> CommandResult {
+    let input = DamageInput {
+        user_id: message.author.id,
+        user_name: message.author.name.clone(),
+        line: args.rest().to_owned(),
+    };
+    let mut cs = CharacterStore::from_file(&LOAD_PATH)?;
+    let replies = handle_damage(&input, &mut cs, &LOAD_PATH)?;
+    send(context, message, replies)?;
+    Ok(())
+}
@@ -0,0 +1,96 @@
+use failure::Error;
+use serenity::{
+    client::Context,
+    framework::standard::{Args, CommandResult, macros::command},
+    model::{channel::Message, id::UserId},
+    utils::MessageBuilder,
+};
+use std::path::Path;
+use crate::util::{
+    constants::LOAD_PATH,
+    characters::CharacterStore,
+    respond::{send, Reply},
+    time::{format_time, now, TimeStyle},
+};
+
+/// How long a delegation lasts, in seconds.
+const DELEGATION_SECONDS: u64 = 24 * 60 * 60;
+
+/// Input to the delegate command.
+#[derive(Debug)]
+pub struct DelegateInput {
+    pub user_id: UserId,
+    pub line: String,
+    pub mentions: Vec<UserId>,
+    pub now: u64,
+}
+
+/// Handle a delegate command, returning the replies to send.
+///
+/// # Arguments
+///
+/// * `input` - who invoked the command and what they typed after `!delegate`
+/// * `store` - character store holding delegations
+/// * `path` - path the store is saved to
+///
+/// # Examples
+///
+/// ```rust
+/// let replies = handle_delegate(&input, &mut store, &LOAD_PATH)?;
+/// ```
+pub fn handle_delegate(
+    input: &DelegateInput,
+    store: &mut CharacterStore,
+    path: &Path,
+) -> Result<Vec<Reply>, Error> {
+    if input.line.trim() == "revoke" {
+        let response = if store.revoke_delegation(input.user_id.0, input.now) {
+            "Delegation revoked."
+        } else {
+            "You have no active delegation."
+        };
+        store.save(path)?;
+        return Ok(vec![Reply::Say(String::from(response))]);
+    }
+    let grantee = match input.mentions.first() {
+        Some(g) if input.mentions.len() == 1 => *g,
+        _ => {
+            return Ok(vec![Reply::Refuse(String::from(
+                "`!delegate @user` or `!delegate revoke`",
+            ))]);
+        }
+    };
+    if grantee == input.user_id {
+        return Ok(vec![Reply::Refuse(String::from(
+            "You can already roll for yourself.",
+        ))]);
+    }
+    let expires = input.now + DELEGATION_SECONDS;
+    store.delegate(input.user_id.0, grantee.0, expires);
+    store.save(path)?;
+    let response = MessageBuilder::new()
+        .mention(&grantee)
+        .push(" can roll for ")
+        .mention(&input.user_id)
+        .push(" with `!roll for @user <dice>` until ")
+        .push(format_time(expires, TimeStyle::Full, false))
+        .push(" (")
+        .push(format_time(expires, TimeStyle::Relative, false))
+        .push("), or until `!delegate revoke`.")
+        .build();
+    Ok(vec![Reply::Say(response)])
+}
+
+#[command]
+pub fn delegate(context: &mut Context, message: &Message, args: Args) -> CommandResult {
+    let input = DelegateInput {
+        user_id: message.author.id,
+        line: args.rest().to_owned(),
+        mentions: message.mentions.iter().map(|u| u.id).collect(),
+        now: now(),
+    };
+    let mut cs = CharacterStore::from_file(&LOAD_PATH)?;
+    let replies = handle_delegate(&input, &mut cs, &LOAD_PATH)?;
+    send(context, message, replies)?;
+    Ok(())
+}
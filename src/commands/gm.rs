@@ -0,0 +1,91 @@
+use rand::{thread_rng, Rng};
+use serenity::{
+    client::Context,
+    framework::standard::{Args, CommandResult, macros::command},
+    model::{channel::Message, id::UserId},
+};
+use std::collections::BTreeMap;
+use crate::{
+    commands::roll::{handle_group_check, GROUP_CHECK_USAGE},
+    util::{
+        characters::CharacterStore,
+        constants::{LOAD_PATH, SETTINGS_PATH},
+        permissions::is_admin,
+        respond::{send, Reply},
+        settings::SettingsStore,
+    },
+};
+
+/// Input to the GM command.
+#[derive(Debug)]
+pub struct GmInput {
+    pub guild_id: Option<u64>,
+    pub is_admin: bool,
+    pub line: String,
+    pub mentions: Vec<(UserId, String)>,
+    /// The guild's named constants for roll expressions.
+    pub constants: BTreeMap<String, i64>,
+}
+
+/// Handle a GM command, returning the replies to send.
+///
+/// GM commands are for server admins, who run the games in their server.
+///
+/// # Arguments
+///
+/// * `input` - who used the command and what they typed after `!gm`
+/// * `store` - character store to resolve attributes from
+/// * `rng` - source of randomness
+///
+/// # Examples
+///
+/// ```rust
+/// let replies = handle_gm(&input, &store, &mut thread_rng());
+/// ```
+pub fn handle_gm<R: Rng + ?Sized>(
+    input: &GmInput,
+    store: &CharacterStore,
+    rng: &mut R,
+) -> Vec<Reply> {
+    if input.guild_id.is_none() {
+        return vec![Reply::Refuse(String::from(
+            "GM commands can only be used in a server.",
+        ))];
+    }
+    if !input.is_admin {
+        return vec![Reply::Refuse(String::from(
+            "You need the Manage Server permission to use GM commands.",
+        ))];
+    }
+    let line = input.line.trim();
+    let mut parts = line.splitn(2, char::is_whitespace);
+    match parts.next() {
+        Some(subcommand) if subcommand.eq_ignore_ascii_case("groupcheck") => {
+            let line = parts.next().unwrap_or("");
+            handle_group_check(line, &input.mentions, &input.constants, store, rng)
+        }
+        _ => vec![Reply::Refuse(String::from(GROUP_CHECK_USAGE))],
+    }
+}
+
+#[command]
+#[bucket = "rolls"]
+pub fn gm(context: &mut Context, message: &Message, args: Args) -> CommandResult {
+    let settings = SettingsStore::from_file(&SETTINGS_PATH)?;
+    let guild = message.guild_id.and_then(|g| settings.get(g.0));
+    let input = GmInput {
+        guild_id: message.guild_id.map(|g| g.0),
+        is_admin: is_admin(context, message),
+        line: args.rest().to_owned(),
+        mentions: message
+            .mentions
+            .iter()
+            .map(|u| (u.id, u.name.clone()))
+            .collect(),
+        constants: guild.map(|g| g.constants.clone()).unwrap_or_default(),
+    };
+    let cs = CharacterStore::from_file(&LOAD_PATH)?;
+    let replies = handle_gm(&input, &cs, &mut thread_rng());
+    send(context, message, replies)?;
+    Ok(())
+}
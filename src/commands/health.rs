@@ -1,11 +1,93 @@
+use failure::Error;
+use log::debug;
 use serenity::{
     client::Context,
     framework::standard::{Args, CommandResult, macros::command},
-    model::channel::Message,
+    model::{channel::Message, id::UserId},
+    utils::MessageBuilder,
 };
+use std::path::Path;
+use crate::util::{
+    args::split_args,
+    characters::CharacterStore,
+    constants::LOAD_PATH,
+    respond::{send, Reply},
+};
+
+const USAGE: &str = "`!health`
+`!health max <boxes>`";
+
+/// Input to the health command.
+#[derive(Debug)]
+pub struct HealthInput {
+    pub user_id: UserId,
+    pub user_name: String,
+    pub line: String,
+}
+
+/// Handle a health command, returning the replies to send.
+///
+/// With no arguments the author's health track is shown. Setting the
+/// number of boxes is refused if it would drop damage already marked.
+///
+/// # Arguments
+///
+/// * `input` - who invoked the command and what they typed after `!health`
+/// * `store` - character store to read and update
+/// * `path` - path the store is saved to
+///
+/// # Examples
+///
+/// ```rust
+/// let replies = handle_health(&input, &mut store, &LOAD_PATH)?;
+/// ```
+pub fn handle_health(
+    input: &HealthInput,
+    store: &mut CharacterStore,
+    path: &Path,
+) -> Result<Vec<Reply>, Error> {
+    let args = split_args(&input.line);
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    debug!("Health args are: {:?}", args);
+    match args.as_slice() {
+        [] => {
+            let response = match store.get(input.user_id.0, &input.user_name) {
+                Some(character) => MessageBuilder::new()
+                    .push_codeblock(character.health(), None)
+                    .build(),
+                None => String::from("You have no character yet."),
+            };
+            Ok(vec![Reply::Say(response)])
+        }
+        ["max", boxes] => {
+            let boxes = match boxes.parse::<u64>() {
+                Ok(b) => b,
+                Err(_) => return Ok(vec![Reply::Refuse(String::from(USAGE))]),
+            };
+            let character = store.get_mut(input.user_id.0, &input.user_name);
+            if let Err(reason) = character.health_mut().set_max(boxes) {
+                return Ok(vec![Reply::Refuse(reason)]);
+            }
+            character.set_last_writer(input.user_id.0);
+            let response = MessageBuilder::new()
+                .push_codeblock(character.health(), None)
+                .build();
+            store.save(path)?;
+            Ok(vec![Reply::Say(response)])
+        }
+        _ => Ok(vec![Reply::Refuse(String::from(USAGE))]),
+    }
+}
 
 #[command]
-pub fn health(_context: &mut Context, _message: &Message, _args: Args) -> CommandResult {
-    // TODO
+pub fn health(context: &mut Context, message: &Message, args: Args) -> CommandResult {
+    let input = HealthInput {
+        user_id: message.author.id,
+        user_name: message.author.name.clone(),
+        line: args.rest().to_owned(),
+    };
+    let mut cs = CharacterStore::from_file(&LOAD_PATH)?;
+    let replies = handle_health(&input, &mut cs, &LOAD_PATH)?;
+    send(context, message, replies)?;
     Ok(())
 }
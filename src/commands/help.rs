@@ -15,22 +15,148 @@ To use, type '!roll # <mod>', where # is a positive number or 'chance', and <wha
 
 Note that the '<what>' portion is optional.
 
+Add 'ephemeral' to a roll to have the result and your command deleted after a minute.
+Add 'verbose' to a roll of 10 or more dice to also see how often each face came up.
+A pool reduced below 1 rolls a chance die; add 'min1' to a roll to roll 1 regular die instead.
+Add 'rote' to a roll to roll each failed die once more; re-rolled dice are shown in [brackets].
+Add 'wp' or 'willpower' to a roll to spend willpower for 3 more dice.
+Add 'trace' to a roll to see how each word was read and where every die came from.
+For an extended action, '!roll extended <roll> target <successes> [limit <rolls>]' rolls until the successes add up, up to as many rolls as the pool has dice unless limited.
+Add 'fair' to a roll to show the seed its dice came from, so anyone can roll them again with 'cod_dice_roller verify <seed> <dice>'.
+
 Examples:
 
 * !roll 4
 * !roll chance
+* !roll chance 3 - roll several chance dice, for house rules that allow it
 * !roll 10 9again
 
 You can also edit a character reference with the following commands:
 
 * !stats print|show
+* !stats sheet md - get your sheet as a Markdown file
+* !stats audit [supernatural] - check your sheet against the character creation rules
 * !stats edit <name> <value>
 * !stats bulk name1=value1 name2=value2 name3=value3 ...
+* !stats delete <name>
+* !stats clear confirm - remove every stat
+* !stats import, followed by a code block with one 'name: value' per line
+* !stats modifier <name> 9again|8again|no10again|clear
+* !stats exceptional <name> <1-5|clear> - successes needed for an exceptional success with that stat
+
+Values can be numbers, dots like '••' (or '**' or 'oo', up to 10), words like 'three', or '3 dots'.
+
+* !stats bonusrule add <name> <+dice> \"<source>\"
+* !stats bonusrule list|remove <number>
+
+A stat's modifier applies to any roll using that stat, unless the roll names its own.
+Bonus rules add dice to any roll using that stat; add 'nobonus' to a roll to skip them.
+Attributes and skills can be shortened, like 'dex' for dexterity or 'ak' for animal_ken.
+Stats can't be named with a number or a roll word like 'chance' or '9again'.
+
+Temporary buffs, like from spells or drugs, add to a stat until enough scenes end:
+
+* !buff add <name> <+dice> \"<source>\" [scenes:<number>]
+* !buff list|clear
+* !scene end - count down your buffs by a scene, and end this channel's scene conditions
+
+Conditions, like Stunned, stay on your character until removed, or until the scene ends if added with 'scene':
+
+* !condition add <name> [scene]
+* !condition list|remove <name>
+
+Set how many boxes your health track has, then mark damage on it, or heal it. Once the track is full, more damage upgrades bashing to lethal and lethal to aggravated:
+
+* !health [max <1-20>]
+* !damage take|heal <amount> bashing|lethal|agg
 
 Then, you can roll using those references, like:
 
 !character edit strength 3
 !roll strength + 1 9again
+!roll min(wits, composure) + (occult - 1)
+
+To see what a roll would use without rolling it, use '!check', like '!check strength + 1 9again'.
+
+If you can't make it, let someone else roll with your character for 24 hours:
+
+* !delegate @friend
+* !delegate revoke
+
+They can then roll with '!roll for @you strength + 1'.
+
+To put a plan to a vote, use '!vote \"Take the sewer route?\" 120'. Everyone's first reaction counts, and the result is posted after the given seconds (10 to 600, 60 by default).
+
+Merits:
+
+* !merit <name> - show a merit's reference image; part of the name is enough
+* !merit list - list every merit, including this server's own
+
+Server admins can add their own merits, optionally attaching a PNG image:
+
+* !settings merit add \"<name>\" <category> [description]
+* !settings merit remove \"<name>\"
+
+Server admins can also award a beat for each player's first roll of the week:
+
+* !settings weeklybeat on [reset day] [UTC offset, i.e. +02:00]
+* !settings weeklybeat off
+
+Anyone can set their own time zone, so their weekly beat resets at their local midnight:
+
+* !settings mytimezone <time zone, i.e. Europe/Warsaw|clear>
+
+They can also show dice faces as the server's own emoji:
+
+* !settings dice-emoji <face> <emoji|clear>
+* !settings dice-emoji show
+
+Or start each roll result with a marker for its outcome, for servers without embeds (‼️ dramatic failure, ✖ failure, ✔ success, ✨ exceptional):
+
+* !settings outcome-markers on|off
+* !settings outcome-markers <outcome> <marker|default>
+
+Or keep channels to results by deleting the commands that asked for them, other than settings and admin commands (the bot needs Manage Messages):
+
+* !settings delete-invocations on|off
+
+Or let players use the command names they're used to, with aliases that can include leading arguments:
+
+* !settings alias add <name> <command and arguments>, like '!settings alias add sheet stats print'
+* !settings alias remove <name>
+* !settings alias list
+
+Or name numbers for the whole chronicle, like tension, which rolls can use like stats (a character's own stat of the same name wins):
+
+* !settings const set <name> <value>, like '!settings const set tension 2' for '!roll wits + occult + tension'
+* !settings const remove <name>
+* !settings const list
+
+And create or update characters from a CSV roster, with a header row of name, owner and stat names:
+
+* !admin import-roster (attach the CSV file)
+
+To check the bot's dice aren't biased, roll a large batch and test how evenly the faces came up:
+
+* !admin dietest <rolls> - roll from 100 to 1000000 d10s
+
+To tidy up, list characters with no stats or other data, then remove them:
+
+* !admin compact [confirm]
+
+To run a group check, roll the same stats for each mentioned player and show who passed (a pool below 1 rolls a chance die):
+
+* !gm groupcheck <roll> vs <successes> @player... - up to 10 players
+
+Run up to 3 commands from one message by separating them with ';', like '!roll 5; !roll chance'.
+
+Other commands:
+
+* !version - show the bot's version
+* !changelog - show the latest changes
+* !invite - get a link to invite the bot to your server
+* !permissions check - list permissions the bot is missing in this channel
+* !cleanup <messages> - remove the bot's messages among the last 1 to 500 in this channel (needs Manage Messages)
 ";
 
 #[command]
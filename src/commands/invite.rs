@@ -0,0 +1,15 @@
+use serenity::{
+    client::Context,
+    framework::standard::{CommandResult, macros::command},
+    model::channel::Message,
+};
+use crate::util::permissions::invite_url;
+
+#[command]
+pub fn invite(context: &mut Context, message: &Message) -> CommandResult {
+    let bot_id = context.cache.read().user.id;
+    message
+        .channel_id
+        .say(&context.http, invite_url(bot_id.0))?;
+    Ok(())
+}
@@ -1,12 +1,21 @@
-use log::{debug, info};
+use log::{debug, info, warn};
 use lazy_static::lazy_static;
 use serenity::{
     client::Context,
     framework::standard::{CommandResult, macros::command},
-    http::AttachmentType,
     model::channel::Message,
+    utils::MessageBuilder,
+};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+use crate::util::{
+    constants::{MERITS_ALLOWED_PATH, MERITS_DIR, SETTINGS_PATH},
+    respond::{chunk, send, Reply, MESSAGE_LIMIT},
+    settings::{GuildSettings, SettingsStore},
+    text::sanitize,
 };
-use std::path::Path;
 
 lazy_static! {
     static ref MERIT_NAMES: Vec<&'static str> = vec![
@@ -114,6 +123,337 @@ lazy_static! {
         "Street Fighting",
         "Unarmed Defense",
     ];
+    /// Built-in merits the chronicle allows, if it limits them.
+    pub static ref ALLOWED_MERITS: Option<Vec<&'static str>> =
+        load_allowed_merits(&MERITS_ALLOWED_PATH);
+}
+
+/// Split a list of allowed merits into the built-in merits it names and
+/// the lines that aren't built-in merits.
+///
+/// Names match ignoring case; blank lines are skipped.
+///
+/// # Arguments
+///
+/// * `text` - one merit name per line
+///
+/// # Examples
+///
+/// ```rust
+/// let (allowed, unknown) = parse_allowed_merits("Giant\nHardy\n");
+/// ```
+pub fn parse_allowed_merits(text: &str) -> (Vec<&'static str>, Vec<String>) {
+    let lines: Vec<&str> = text
+        .lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty())
+        .collect();
+    let allowed = MERIT_NAMES
+        .iter()
+        .filter(|name| lines.iter().any(|l| l.eq_ignore_ascii_case(name)))
+        .cloned()
+        .collect();
+    let unknown = lines
+        .iter()
+        .filter(|l| !MERIT_NAMES.iter().any(|name| name.eq_ignore_ascii_case(l)))
+        .map(|l| l.to_string())
+        .collect();
+    (allowed, unknown)
+}
+
+/// Load the built-in merits a chronicle allows, if it has a list of them.
+///
+/// Lines that aren't built-in merits are logged and ignored.
+///
+/// # Arguments
+///
+/// * `path` - path to the list, one merit per line
+///
+/// # Examples
+///
+/// ```rust
+/// let allowed = load_allowed_merits(&MERITS_ALLOWED_PATH);
+/// ```
+pub fn load_allowed_merits(path: &Path) -> Option<Vec<&'static str>> {
+    let text = match fs::read_to_string(path) {
+        Ok(t) => t,
+        Err(_) => return None,
+    };
+    let (allowed, unknown) = parse_allowed_merits(&text);
+    for line in unknown {
+        warn!("{} lists `{}`, which is not a built-in merit", path.display(), line);
+    }
+    info!("Allowing {} built-in merits from {}", allowed.len(), path.display());
+    Some(allowed)
+}
+
+/// A merit available in a guild.
+#[derive(Debug, PartialEq)]
+pub struct MeritEntry {
+    pub name: String,
+    pub description: Option<String>,
+    pub custom: bool,
+}
+
+/// Returns the built-in merits merged with a guild's custom merits.
+///
+/// A custom merit replaces a built-in one with the same name. When the
+/// chronicle limits the built-in merits, only the allowed ones are kept.
+///
+/// # Arguments
+///
+/// * `guild` - the guild's settings, if any
+/// * `allowed` - built-in merits allowed, if limited
+///
+/// # Examples
+///
+/// ```rust
+/// let merits = merged_merits(settings_store.get(guild_id), ALLOWED_MERITS.as_deref());
+/// ```
+pub fn merged_merits(
+    guild: Option<&GuildSettings>,
+    allowed: Option<&[&'static str]>,
+) -> Vec<MeritEntry> {
+    let custom = guild.map(|g| g.merits.as_slice()).unwrap_or(&[]);
+    let mut merits: Vec<MeritEntry> = MERIT_NAMES
+        .iter()
+        .filter(|name| allowed.is_none_or(|a| a.contains(*name)))
+        .filter(|name| !custom.iter().any(|c| c.name.eq_ignore_ascii_case(name)))
+        .map(|name| MeritEntry {
+            name: name.to_string(),
+            description: None,
+            custom: false,
+        })
+        .collect();
+    merits.extend(custom.iter().map(|c| MeritEntry {
+        name: c.name.clone(),
+        description: if c.description.is_empty() {
+            None
+        } else {
+            Some(c.description.clone())
+        },
+        custom: true,
+    }));
+    merits
+}
+
+/// How a merit name given to `!merit` matched the merits available.
+#[derive(Debug, PartialEq)]
+pub enum MeritMatch {
+    /// Exactly one merit matched, by its full name.
+    Unique(String),
+    /// Several merits matched equally well.
+    Ambiguous(Vec<String>),
+    /// No merit matched.
+    NoMatch,
+}
+
+/// Resolve a merit name the user typed to a merit's full name.
+///
+/// Names match ignoring case: an exact match wins, then names starting
+/// with the text, then names containing it. The first of those to match
+/// anything decides the result.
+///
+/// # Arguments
+///
+/// * `name_match` - merit name, or part of one, to look for
+/// * `names` - full names of the merits available
+///
+/// # Examples
+///
+/// ```rust
+/// let resolved = resolve_merit_name("reflexes", &MERIT_NAMES);
+/// ```
+pub fn resolve_merit_name(name_match: &str, names: &[&str]) -> MeritMatch {
+    let needle = name_match.trim().to_lowercase();
+    if needle.is_empty() {
+        return MeritMatch::NoMatch;
+    }
+    let lowered: Vec<(String, &str)> = names.iter().map(|n| (n.to_lowercase(), *n)).collect();
+    if let Some((_, name)) = lowered.iter().find(|(l, _)| *l == needle) {
+        return MeritMatch::Unique(name.to_string());
+    }
+    let prefixed: Vec<&str> = lowered
+        .iter()
+        .filter(|(l, _)| l.starts_with(&needle))
+        .map(|(_, n)| *n)
+        .collect();
+    let candidates: Vec<&str> = if prefixed.is_empty() {
+        lowered
+            .iter()
+            .filter(|(l, _)| l.contains(&needle))
+            .map(|(_, n)| *n)
+            .collect()
+    } else {
+        prefixed
+    };
+    match candidates.as_slice() {
+        [] => MeritMatch::NoMatch,
+        [name] => MeritMatch::Unique(name.to_string()),
+        _ => MeritMatch::Ambiguous(candidates.iter().map(|n| n.to_string()).collect()),
+    }
+}
+
+/// Returns the image file name for a merit.
+///
+/// Spaces become `_` and only letters, numbers, `_`, `-` and `'` are kept,
+/// so a name can never point outside the merits directory.
+///
+/// # Arguments
+///
+/// * `name` - merit name
+///
+/// # Examples
+///
+/// ```rust
+/// let file_name = merit_file_name("Fast Reflexes");
+/// ```
+pub fn merit_file_name(name: &str) -> String {
+    let stem: String = name
+        .trim()
+        .to_lowercase()
+        .chars()
+        .map(|c| if c == ' ' { '_' } else { c })
+        .filter(|c| c.is_alphanumeric() || "_-'".contains(*c))
+        .collect();
+    format!("{}.png", stem)
+}
+
+/// Returns the directory holding a guild's own merit images.
+///
+/// # Arguments
+///
+/// * `merits_dir` - base merits directory
+/// * `guild_id` - guild the images belong to
+///
+/// # Examples
+///
+/// ```rust
+/// let dir = guild_merits_dir(&MERITS_DIR, guild_id);
+/// ```
+pub fn guild_merits_dir(merits_dir: &Path, guild_id: u64) -> PathBuf {
+    merits_dir.join(guild_id.to_string())
+}
+
+/// Returns the path to a merit image, preferring the guild's own.
+///
+/// # Arguments
+///
+/// * `merits_dir` - base merits directory
+/// * `guild_id` - guild the lookup is for, if any
+/// * `file_name` - merit image file name
+///
+/// # Examples
+///
+/// ```rust
+/// let path = merit_image_path(&MERITS_DIR, Some(guild_id), "fast_reflexes.png");
+/// ```
+fn merit_image_path(
+    merits_dir: &Path,
+    guild_id: Option<u64>,
+    file_name: &str,
+) -> Option<PathBuf> {
+    let guild_path = guild_id.map(|g| guild_merits_dir(merits_dir, g).join(file_name));
+    let shared_path = merits_dir.join(file_name);
+    guild_path
+        .into_iter()
+        .chain(Some(shared_path))
+        .find(|p| p.exists())
+}
+
+/// Read a merit image to send, checking that there's an image to send.
+///
+/// Returns why it can't be sent otherwise, for the logs.
+///
+/// # Arguments
+///
+/// * `path` - path to the image
+///
+/// # Examples
+///
+/// ```rust
+/// let bytes = read_merit_image(&MERITS_DIR.join("giant.png"))?;
+/// ```
+pub fn read_merit_image(path: &Path) -> Result<Vec<u8>, String> {
+    let bytes = fs::read(path).map_err(|e| format!("it can't be read: {}", e))?;
+    if bytes.is_empty() {
+        return Err(String::from("it is empty"));
+    }
+    Ok(bytes)
+}
+
+/// Name to attach a merit image as, using only characters Discord keeps
+/// in attachment names so the embed can refer to it.
+///
+/// # Arguments
+///
+/// * `file_name` - merit image file name
+///
+/// # Examples
+///
+/// ```rust
+/// let name = attachment_name("smith's_tools.png");
+/// ```
+pub fn attachment_name(file_name: &str) -> String {
+    file_name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '.' || c == '_' || c == '-' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+/// Replies listing the names of the merits, split to fit in messages.
+///
+/// # Arguments
+///
+/// * `merits` - merits to list
+///
+/// # Examples
+///
+/// ```rust
+/// let replies = merit_list(&merged_merits(guild, ALLOWED_MERITS.as_deref()));
+/// ```
+pub fn merit_list(merits: &[MeritEntry]) -> Vec<Reply> {
+    let names: Vec<String> = merits.iter().map(|m| sanitize(&m.name)).collect();
+    chunk(&names, ", ", MESSAGE_LIMIT).into_iter().map(Reply::Say).collect()
+}
+
+/// Reply for a merit when there is no image to show.
+///
+/// A merit with a description shows it; otherwise the reply says why there
+/// is no image, refusing merits that can't be found.
+///
+/// # Arguments
+///
+/// * `entry` - the merit, if it's one this guild has
+/// * `image_unavailable` - whether the merit has an image that couldn't be sent
+///
+/// # Examples
+///
+/// ```rust
+/// let reply = merit_text(entry, true);
+/// ```
+pub fn merit_text(entry: Option<&MeritEntry>, image_unavailable: bool) -> Reply {
+    match entry {
+        Some(entry) if entry.description.is_some() || (entry.custom && !image_unavailable) => {
+            Reply::Say(
+                MessageBuilder::new()
+                    .push_bold(sanitize(&entry.name))
+                    .push_line("")
+                    .push_safe(entry.description.clone().unwrap_or_default())
+                    .build(),
+            )
+        }
+        _ if image_unavailable => {
+            Reply::Say(String::from("The reference image for that merit is unavailable."))
+        }
+        _ => Reply::Refuse(String::from("Could not find merit.")),
+    }
 }
 
 #[command]
@@ -123,25 +463,315 @@ pub fn merit(context: &mut Context, message: &Message) -> CommandResult {
         return Ok(());
     }
     let first_space_index = message.content.chars().position(|c| c == ' ').unwrap();
-    let name_match = &message.content[(first_space_index + 1)..];
+    let name_match = message.content[(first_space_index + 1)..].trim();
     debug!("Merit name match is: {}", &name_match);
-    let name_stub = name_match.replace(" ", "_").to_lowercase();
-    let file_name = format!("{}.png", name_stub);
-    debug!("Looking up merit image: {}", &file_name);
-    let file_path = Path::new("./merits").join(&file_name);
-    if !file_path.exists() {
-        message
-            .channel_id
-            .say(&context.http, "Could not find merit.")?;
+    let settings = SettingsStore::from_file(&SETTINGS_PATH)?;
+    let guild_id = message.guild_id.map(|g| g.0);
+    let guild = guild_id.and_then(|g| settings.get(g));
+    let merits = merged_merits(guild, ALLOWED_MERITS.as_deref());
+    if name_match == "list" {
+        send(context, message, merit_list(&merits))?;
         return Ok(());
     }
-    message.channel_id.send_message(&context.http, |m| {
-        m.embed(|e| {
-            e.title(&name_match);
-            e.attachment(&file_name);
-            e
-        });
-        m.add_file(AttachmentType::Path(&file_path))
-    })?;
+    let names: Vec<&str> = merits.iter().map(|m| m.name.as_str()).collect();
+    // A name that matches nothing is still looked up, for images of other merits
+    let name_match = match resolve_merit_name(name_match, &names) {
+        MeritMatch::Unique(name) => name,
+        MeritMatch::Ambiguous(candidates) => {
+            debug!("Merit name matches {} merits", candidates.len());
+            let mut replies =
+                vec![Reply::Refuse(String::from("That could be any of these merits:"))];
+            let candidates: Vec<String> = candidates.iter().map(|c| sanitize(c)).collect();
+            replies.extend(chunk(&candidates, ", ", MESSAGE_LIMIT).into_iter().map(Reply::Say));
+            send(context, message, replies)?;
+            return Ok(());
+        }
+        MeritMatch::NoMatch => name_match.to_owned(),
+    };
+    let name_match = name_match.as_str();
+    let entry = merits
+        .iter()
+        .find(|m| m.name.eq_ignore_ascii_case(name_match));
+    let description = entry.and_then(|m| m.description.clone());
+    let file_name = merit_file_name(name_match);
+    debug!("Looking up merit image: {}", &file_name);
+    // A chronicle limiting its merits shows nothing for the ones it left out
+    let file_path = if entry.is_none() && ALLOWED_MERITS.is_some() {
+        None
+    } else {
+        merit_image_path(&MERITS_DIR, guild_id, &file_name)
+    };
+    let image_unavailable = match file_path {
+        Some(file_path) => {
+            let path = file_path.display();
+            match read_merit_image(&file_path) {
+                Ok(bytes) => {
+                    let attachment = attachment_name(&file_name);
+                    let sent = message.channel_id.send_message(&context.http, |m| {
+                        m.embed(|e| {
+                            e.title(name_match);
+                            e.attachment(&attachment);
+                            if let Some(description) = &description {
+                                e.description(description);
+                            }
+                            e
+                        });
+                        m.add_file((bytes.as_slice(), attachment.as_str()))
+                    });
+                    match sent {
+                        Ok(_) => return Ok(()),
+                        Err(e) => warn!("Could not send merit image {}: {}", path, e),
+                    }
+                }
+                Err(reason) => warn!("Merit image {} is unavailable: {}", path, reason),
+            }
+            true
+        }
+        None => false,
+    };
+    send(context, message, vec![merit_text(entry, image_unavailable)])?;
     Ok(())
 }
+
+#[cfg(test)]
+mod test {
+    use super::{
+        attachment_name, load_allowed_merits, merged_merits, merit_file_name, merit_image_path,
+        merit_text, parse_allowed_merits, read_merit_image, resolve_merit_name, MeritEntry,
+        MeritMatch, MERIT_NAMES,
+    };
+    use crate::util::{
+        respond::Reply,
+        settings::{CustomMerit, GuildSettings},
+    };
+    use std::fs;
+    use tempdir::TempDir;
+
+    fn custom(name: &str, description: &str) -> CustomMerit {
+        CustomMerit {
+            name: name.to_owned(),
+            category: String::from("fighting"),
+            description: description.to_owned(),
+        }
+    }
+
+    #[test]
+    fn test_merged_merits_builtin_only() {
+        let merits = merged_merits(None, None);
+
+        assert_eq!(merits.len(), MERIT_NAMES.len());
+        assert!(merits.iter().all(|m| !m.custom));
+    }
+
+    #[test]
+    fn test_merged_merits_adds_custom() {
+        let mut gs = GuildSettings::default();
+        gs.add_merit(custom("Ancestral Blade", ""));
+        let merits = merged_merits(Some(&gs), None);
+
+        assert_eq!(merits.len(), MERIT_NAMES.len() + 1);
+
+        let last = merits.last().unwrap();
+
+        assert_eq!(last.name, "Ancestral Blade");
+        assert_eq!(last.description, None);
+        assert!(last.custom);
+    }
+
+    #[test]
+    fn test_merged_merits_custom_wins() {
+        let mut gs = GuildSettings::default();
+        gs.add_merit(custom("fast reflexes", "Homebrew version"));
+        let merits = merged_merits(Some(&gs), None);
+
+        assert_eq!(merits.len(), MERIT_NAMES.len());
+
+        let matching: Vec<_> = merits
+            .iter()
+            .filter(|m| m.name.eq_ignore_ascii_case("Fast Reflexes"))
+            .collect();
+
+        assert_eq!(matching.len(), 1);
+        assert!(matching[0].custom);
+        assert_eq!(matching[0].description, Some(String::from("Homebrew version")));
+    }
+
+    #[test]
+    fn test_parse_allowed_merits() {
+        let (allowed, unknown) = parse_allowed_merits("giant\n\n  Fast Reflexes \nHomebrew Hex\n");
+
+        assert_eq!(allowed, vec!["Fast Reflexes", "Giant"]);
+        assert_eq!(unknown, vec![String::from("Homebrew Hex")]);
+    }
+
+    #[test]
+    fn test_load_allowed_merits() {
+        let temp = TempDir::new("dicebot").unwrap();
+        let path = temp.path().join("merits_allowed.txt");
+
+        assert_eq!(load_allowed_merits(&path), None);
+
+        fs::write(&path, "Giant\nHardy\nNot A Merit\n").unwrap();
+
+        assert_eq!(load_allowed_merits(&path), Some(vec!["Giant", "Hardy"]));
+    }
+
+    #[test]
+    fn test_merged_merits_allowed_keeps_custom() {
+        let mut gs = GuildSettings::default();
+        gs.add_merit(custom("Ancestral Blade", ""));
+        gs.add_merit(custom("Parkour", "Homebrew version"));
+        let merits = merged_merits(Some(&gs), Some(&["Giant", "Parkour"][..]));
+        let names: Vec<&str> = merits.iter().map(|m| m.name.as_str()).collect();
+
+        assert_eq!(names, vec!["Giant", "Ancestral Blade", "Parkour"]);
+        assert!(merits[2].custom);
+    }
+
+    #[test]
+    fn test_read_merit_image() {
+        let temp = TempDir::new("dicebot").unwrap();
+        let image = temp.path().join("giant.png");
+        let empty = temp.path().join("hardy.png");
+        fs::write(&image, "image").unwrap();
+        fs::write(&empty, "").unwrap();
+
+        assert_eq!(read_merit_image(&image), Ok(b"image".to_vec()));
+        assert_eq!(read_merit_image(&empty), Err(String::from("it is empty")));
+        assert!(read_merit_image(&temp.path().join("parkour.png")).is_err());
+        assert!(read_merit_image(temp.path()).is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_read_merit_image_unreadable() {
+        let temp = TempDir::new("dicebot").unwrap();
+        let link = temp.path().join("giant.png");
+        std::os::unix::fs::symlink(temp.path().join("missing.png"), &link).unwrap();
+
+        assert!(link.symlink_metadata().is_ok());
+        let reason = read_merit_image(&link).unwrap_err();
+        assert!(reason.starts_with("it can't be read: "), "{}", reason);
+    }
+
+    #[test]
+    fn test_attachment_name() {
+        assert_eq!(attachment_name("fast_reflexes.png"), "fast_reflexes.png");
+        assert_eq!(attachment_name("smith's_tools.png"), "smith_s_tools.png");
+        assert_eq!(attachment_name("vice-ridden.png"), "vice-ridden.png");
+        assert_eq!(attachment_name("épée.png"), "_p_e.png");
+    }
+
+    #[test]
+    fn test_merit_text() {
+        let builtin = MeritEntry {
+            name: String::from("Giant"),
+            description: None,
+            custom: false,
+        };
+        let custom = MeritEntry {
+            name: String::from("Ancestral Blade"),
+            description: None,
+            custom: true,
+        };
+        let described = MeritEntry {
+            name: String::from("Ancestral Blade"),
+            description: Some(String::from("A family sword.")),
+            custom: true,
+        };
+
+        let not_found = Reply::Refuse(String::from("Could not find merit."));
+        let unavailable =
+            Reply::Say(String::from("The reference image for that merit is unavailable."));
+        assert_eq!(merit_text(None, false), not_found);
+        assert_eq!(merit_text(Some(&builtin), false), not_found);
+        assert_eq!(merit_text(Some(&builtin), true), unavailable);
+        assert_eq!(
+            merit_text(Some(&custom), false),
+            Reply::Say(String::from("**Ancestral Blade**\n"))
+        );
+        assert_eq!(merit_text(Some(&custom), true), unavailable);
+        assert_eq!(
+            merit_text(Some(&described), true),
+            Reply::Say(String::from("**Ancestral Blade**\nA family sword."))
+        );
+    }
+
+    #[test]
+    fn test_resolve_merit_name() {
+        let unique = |name: &str| MeritMatch::Unique(name.to_owned());
+
+        assert_eq!(resolve_merit_name("GIANT", &MERIT_NAMES), unique("Giant"));
+        assert_eq!(resolve_merit_name("Fast reflexe", &MERIT_NAMES), unique("Fast Reflexes"));
+        assert_eq!(resolve_merit_name(" reflexes ", &MERIT_NAMES), unique("Fast Reflexes"));
+        // Starting with the text beats containing it
+        assert_eq!(resolve_merit_name("mind", &MERIT_NAMES), unique("Mind of a Madman"));
+    }
+
+    #[test]
+    fn test_resolve_merit_name_ambiguous() {
+        assert_eq!(
+            resolve_merit_name("defense", &MERIT_NAMES),
+            MeritMatch::Ambiguous(vec![
+                String::from("Armed Defense"),
+                String::from("Unarmed Defense"),
+            ])
+        );
+        assert_eq!(
+            resolve_merit_name("iron", &MERIT_NAMES),
+            MeritMatch::Ambiguous(vec![
+                String::from("Iron Stamina"),
+                String::from("Iron Will"),
+                String::from("Iron Skin"),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_resolve_merit_name_no_match() {
+        assert_eq!(resolve_merit_name("flight", &MERIT_NAMES), MeritMatch::NoMatch);
+        assert_eq!(resolve_merit_name("  ", &MERIT_NAMES), MeritMatch::NoMatch);
+        assert_eq!(resolve_merit_name("giant", &[]), MeritMatch::NoMatch);
+    }
+
+    #[test]
+    fn test_merit_file_name() {
+        assert_eq!(merit_file_name("Fast Reflexes"), "fast_reflexes.png");
+        assert_eq!(merit_file_name(" Giant "), "giant.png");
+        assert_eq!(merit_file_name("Vice-Ridden"), "vice-ridden.png");
+        assert_eq!(merit_file_name("Smith's Tools"), "smith's_tools.png");
+    }
+
+    #[test]
+    fn test_merit_file_name_stays_in_directory() {
+        assert_eq!(merit_file_name("../../x"), "x.png");
+        assert_eq!(merit_file_name("..\\..\\giant"), "giant.png");
+        assert_eq!(merit_file_name("/etc/cron.d/x"), "etccrondx.png");
+        assert_eq!(merit_file_name("a\0b"), "ab.png");
+        assert_eq!(merit_file_name("../.."), ".png");
+    }
+
+    #[test]
+    fn test_merit_image_path_prefers_guild() {
+        let temp = TempDir::new("dicebot").unwrap();
+        let guild_dir = temp.path().join("1");
+        fs::create_dir(&guild_dir).unwrap();
+        fs::write(temp.path().join("giant.png"), "shared").unwrap();
+        fs::write(temp.path().join("hardy.png"), "shared").unwrap();
+        fs::write(guild_dir.join("giant.png"), "guild").unwrap();
+
+        assert_eq!(
+            merit_image_path(temp.path(), Some(1), "giant.png"),
+            Some(guild_dir.join("giant.png"))
+        );
+        assert_eq!(
+            merit_image_path(temp.path(), Some(1), "hardy.png"),
+            Some(temp.path().join("hardy.png"))
+        );
+        assert_eq!(
+            merit_image_path(temp.path(), None, "giant.png"),
+            Some(temp.path().join("giant.png"))
+        );
+        assert_eq!(merit_image_path(temp.path(), Some(1), "parkour.png"), None);
+    }
+}
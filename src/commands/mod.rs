@@ -1,5 +1,21 @@
+pub mod admin;
+pub mod buff;
+pub mod changelog;
+pub mod check;
+pub mod cleanup;
+pub mod condition;
+pub mod damage;
+pub mod delegate;
+pub mod gm;
 pub mod help;
 pub mod health;
+pub mod invite;
 pub mod merit;
+pub mod permissions;
 pub mod roll;
+#[cfg(test)]
+mod scenarios;
+pub mod settings;
 pub mod stats;
+pub mod version;
+pub mod vote;
@@ -0,0 +1,44 @@
+use log::debug;
+use serenity::{
+    client::Context,
+    framework::standard::{Args, CommandResult, macros::command},
+    model::channel::Message,
+    utils::MessageBuilder,
+};
+use crate::util::{
+    permissions::missing_requirements,
+    respond::{send, Reply},
+};
+
+#[command]
+pub fn permissions(context: &mut Context, message: &Message, args: Args) -> CommandResult {
+    if args.current() != Some("check") {
+        send(context, message, vec![Reply::Refuse(String::from("`!permissions check`"))])?;
+        return Ok(());
+    }
+    let guild = match message.guild(&context.cache) {
+        Some(g) => g,
+        None => {
+            let response = "Permissions can only be checked in a server channel.";
+            send(context, message, vec![Reply::Refuse(String::from(response))])?;
+            return Ok(());
+        }
+    };
+    let bot_id = context.cache.read().user.id;
+    let actual = guild.read().permissions_in(message.channel_id, bot_id);
+    debug!("Bot permissions in channel: {:?}", actual);
+    let missing = missing_requirements(actual);
+    if missing.is_empty() {
+        message
+            .channel_id
+            .say(&context.http, "All required permissions are granted in this channel.")?;
+        return Ok(());
+    }
+    let mut builder = MessageBuilder::new();
+    builder.push_line("Missing permissions in this channel:");
+    for requirement in missing {
+        builder.push_line(format!("* {} - breaks {}", requirement.name, requirement.features));
+    }
+    message.channel_id.say(&context.http, builder.build())?;
+    Ok(())
+}
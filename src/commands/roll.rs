@@ -1,28 +1,63 @@
+use chrono_tz::Tz;
+use failure::Error;
 use lazy_static::lazy_static;
 use log::debug;
 use rand::{
     distributions::{Distribution, Uniform},
     thread_rng,
+    Rng,
+    RngCore,
 };
 use regex::Regex;
 use serenity::{
     client::Context,
     framework::standard::{Args, CommandResult, macros::command},
-    model::channel::Message,
-    utils::MessageBuilder,
+    model::{channel::Message, id::UserId},
+    utils::{parse_username, MessageBuilder},
 };
-use std::{collections::HashMap, fmt};
+use std::{collections::BTreeMap, fmt, path::Path};
 use crate::util::{
-    constants::LOAD_PATH,
+    constants::{LOAD_PATH, SETTINGS_PATH},
     characters::{Character, CharacterStore},
+    expression::{parse, Lookups, ParseError, MAX_POOL},
+    fairness::{commitment, new_seed, parse_seed, seeded_rng, to_hex},
+    outcome::{classify, classify_chance, Outcome},
+    render::summary_table,
+    respond::{send, Reply},
+    roll_tokens::{
+        CHANCE, EPHEMERAL, EXTENDED, FAIR, IGNORE_BONUSES, MIN_ONE, REGEX_AGAIN, REGEX_NUMERIC,
+        ROTE, TRACE, VERBOSE, WILLPOWER, WILLPOWER_SHORT,
+    },
+    settings::{SettingsStore, WeeklyBeat},
+    stat_names::canonical,
+    text::{count, plural, sanitize},
+    time::{now, parse_timezone, week_start, zoned_week_start},
+    trace::{render_trace, TraceEvent},
 };
 
-const CHANCE: &str = "chance";
+/// Successes needed for an exceptional success, unless a stat lowers it.
+const EXCEPTIONAL_SUCCESSES: u64 = 5;
+/// Shown after the dice of an exceptional success.
+const EXCEPTIONAL_NOTE: &str = " - exceptional success!";
+/// Dice added to a roll by spending willpower.
+const WILLPOWER_DICE: i64 = 3;
+/// Most chance dice rolled at once.
+const MAX_CHANCE_DICE: u64 = 10;
+/// Smallest pool that gets a histogram in verbose output.
+const HISTOGRAM_MIN_DICE: u64 = 10;
+/// Most marks shown for one face before switching to a count.
+const HISTOGRAM_MAX_MARKS: usize = 5;
+/// Most players rolled for in one group check.
+const MAX_GROUP_CHECK_PLAYERS: usize = 10;
+pub const GROUP_CHECK_USAGE: &str = "`!gm groupcheck <roll> vs <successes> @player...`";
+/// Most rolls made for one extended action.
+const MAX_EXTENDED_ROLLS: u64 = 20;
+/// Longest list of an extended action's rolls shown with their dice.
+const MAX_EXTENDED_CHARS: usize = 1200;
+const EXTENDED_USAGE: &str = "`!roll extended <roll> target <successes> [limit <rolls>]`";
 
 lazy_static! {
-    static ref REGEX_NUMERIC: Regex = Regex::new(r#"^\d+$"#).unwrap();
     static ref REGEX_WHITESPACE: Regex = Regex::new(r#"\W{2,}"#).unwrap();
-    static ref REGEX_AGAIN: Regex = Regex::new(r#"^(?:no)?\d+again$"#).unwrap();
 }
 
 /// The types of modifiers that can be applied to a roll.
@@ -37,6 +72,31 @@ enum RollModifier {
     NoAgain,
 }
 
+impl RollModifier {
+    /// How favorable the modifier is, higher re-rolling more dice.
+    fn rank(&self) -> u8 {
+        match self {
+            RollModifier::NoAgain => 0,
+            RollModifier::Again10 => 1,
+            RollModifier::Again9 => 2,
+            RollModifier::Again8 => 3,
+        }
+    }
+
+    /// The roll token for the modifier.
+    fn name(&self) -> &'static str {
+        match self {
+            RollModifier::NoAgain => "no10again",
+            RollModifier::Again10 => "10again",
+            RollModifier::Again9 => "9again",
+            RollModifier::Again8 => "8again",
+        }
+    }
+}
+
+/// Modifiers that can be stored as a stat's default.
+pub const STAT_MODIFIERS: &[&str] = &["9again", "8again", "no10again"];
+
 /// Returns the `RollModifier` for the string.
 ///
 /// # Arguments
@@ -83,6 +143,8 @@ fn roll_again(val: u64, modifier: &RollModifier) -> bool {
 struct Roll {
     val: u64,
     is_bonus: bool,
+    /// Whether the die re-rolled a failure for a rote.
+    is_rote: bool,
 }
 
 impl fmt::Display for Roll {
@@ -90,102 +152,486 @@ impl fmt::Display for Roll {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         if self.is_bonus {
             write!(f, "({})", self.val)
+        } else if self.is_rote {
+            write!(f, "[{}]", self.val)
         } else {
             write!(f, "{}", self.val)
         }
     }
 }
 
-/// Roll dice.
+/// Show rolls, using a guild's emoji for any faces that have one.
+///
+/// # Arguments
+///
+/// * `rolls` - rolls to show
+/// * `emoji` - emoji keyed by face
+///
+/// # Examples
+///
+/// ```rust
+/// let text = render_rolls(&rolls, &guild.dice_emoji);
+/// ```
+fn render_rolls(rolls: &[Roll], emoji: &BTreeMap<u8, String>) -> String {
+    rolls
+        .iter()
+        .map(|r| match emoji.get(&(r.val as u8)) {
+            Some(e) if r.is_bonus => format!("({})", e),
+            Some(e) if r.is_rote => format!("[{}]", e),
+            Some(e) => e.clone(),
+            None => r.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Roll one die and any bonus dice the modifier earns it, returning the
+/// die's own face.
 ///
 /// # Arguments
 ///
-/// * `dice` - string to roll
+/// * `rolls` - rolls to add to
+/// * `is_rote` - whether the die re-rolls a failure for a rote
 /// * `modifier` - roll modifier
+/// * `rng` - source of randomness
 ///
 /// # Examples
 ///
 /// ```rust
-/// let result = roll("5", &RollModifier::Again9);
+/// let face = roll_die(&mut rolls, false, &RollModifier::Again10, &mut thread_rng());
 /// ```
-fn roll_dice(dice: &str, modifier: &RollModifier) -> Vec<Roll> {
+fn roll_die<R: Rng + ?Sized>(
+    rolls: &mut Vec<Roll>,
+    is_rote: bool,
+    modifier: &RollModifier,
+    rng: &mut R,
+) -> u64 {
     let between = Uniform::new_inclusive(1, 10);
-    let mut rng = thread_rng();
+    let face = between.sample(rng);
+    rolls.push(Roll {
+        val: face,
+        is_bonus: false,
+        is_rote,
+    });
+    let mut last = face;
+    while roll_again(last, modifier) {
+        last = between.sample(rng);
+        rolls.push(Roll {
+            val: last,
+            is_bonus: true,
+            is_rote: false,
+        });
+    }
+    face
+}
 
-    if dice == CHANCE {
-        let val = between.sample(&mut rng);
-        vec![Roll {
-            val,
-            is_bonus: false,
-        }]
+/// Roll dice.
+///
+/// Chance dice are never rolled again, whatever the modifier. With a rote,
+/// each die of the pool that fails is rolled once more, and the new die can
+/// earn bonus dice like any other.
+///
+/// # Arguments
+///
+/// * `dice` - number of dice to roll
+/// * `chance` - whether they are chance dice
+/// * `rote` - whether the roll has the rote quality
+/// * `modifier` - roll modifier
+/// * `rng` - source of randomness
+///
+/// # Examples
+///
+/// ```rust
+/// let result = roll_dice(5, false, false, &RollModifier::Again9, &mut thread_rng());
+/// ```
+fn roll_dice<R: Rng + ?Sized>(
+    dice: u64,
+    chance: bool,
+    rote: bool,
+    modifier: &RollModifier,
+    rng: &mut R,
+) -> Vec<Roll> {
+    if chance {
+        let between = Uniform::new_inclusive(1, 10);
+        (0..dice)
+            .map(|_| Roll {
+                val: between.sample(rng),
+                is_bonus: false,
+                is_rote: false,
+            })
+            .collect()
     } else {
         let mut rolls = vec![];
-        for _ in 1..=dice.parse::<u64>().unwrap() {
-            let mut first = true;
-            loop {
-                let next_val = between.sample(&mut rng);
-                rolls.push(Roll {
-                    val: next_val,
-                    is_bonus: !first,
-                });
-                if !roll_again(next_val, modifier) {
-                    break;
-                }
-                first = false;
+        for _ in 0..dice {
+            let face = roll_die(&mut rolls, false, modifier, rng);
+            if rote && face < 8 {
+                roll_die(&mut rolls, true, modifier, rng);
             }
         }
         rolls
     }
 }
 
+/// The rolls made for an extended action.
+#[derive(Debug)]
+struct ExtendedResult {
+    /// Dice and successes of each roll, in order.
+    rolls: Vec<(Vec<Roll>, u64)>,
+    /// Successes from every roll together.
+    total: u64,
+    /// Whether the successes reached the target.
+    reached: bool,
+    /// Whether a chance die dramatically failed, ending the action.
+    dramatic: bool,
+}
+
+/// Roll the same pool again and again until the successes reach a target
+/// or the rolls run out.
+///
+/// A pool below 1 rolls a chance die each time, and a dramatic failure on
+/// one ends the action.
+///
+/// # Arguments
+///
+/// * `pool` - dice in each roll
+/// * `rote` - whether each roll has the rote quality
+/// * `modifier` - roll modifier
+/// * `target` - successes needed
+/// * `limit` - most rolls to make
+/// * `rng` - source of randomness
+///
+/// # Examples
+///
+/// ```rust
+/// let result = roll_extended(5, false, &RollModifier::Again10, 10, 5, &mut thread_rng());
+/// ```
+fn roll_extended<R: Rng + ?Sized>(
+    pool: i64,
+    rote: bool,
+    modifier: &RollModifier,
+    target: u64,
+    limit: u64,
+    rng: &mut R,
+) -> ExtendedResult {
+    let mut result = ExtendedResult {
+        rolls: vec![],
+        total: 0,
+        reached: false,
+        dramatic: false,
+    };
+    for _ in 0..limit {
+        let (rolls, hits) = if pool < 1 {
+            let rolls = roll_dice(1, true, false, &RollModifier::NoAgain, rng);
+            let faces: Vec<u64> = rolls.iter().map(|r| r.val).collect();
+            result.dramatic = classify_chance(&faces) == Outcome::DramaticFailure;
+            let hits = faces.iter().filter(|&&f| f == 10).count() as u64;
+            (rolls, hits)
+        } else {
+            let rolls = roll_dice(pool as u64, false, rote, modifier, rng);
+            let hits = successes(&rolls);
+            (rolls, hits)
+        };
+        result.total += hits;
+        result.rolls.push((rolls, hits));
+        result.reached = result.total >= target;
+        if result.reached || result.dramatic {
+            break;
+        }
+    }
+    result
+}
+
+/// Split an extended action into its roll, target and limit, like
+/// `wits + investigation target 10 limit 5`.
+///
+/// # Arguments
+///
+/// * `line` - what was typed after `extended`
+///
+/// # Examples
+///
+/// ```rust
+/// let (roll, target, limit) = parse_extended("wits + 2 target 10").unwrap();
+/// ```
+fn parse_extended(line: &str) -> Option<(String, u64, Option<u64>)> {
+    let words: Vec<&str> = line.split_whitespace().collect();
+    let at = words.iter().position(|w| w.eq_ignore_ascii_case("target"))?;
+    if at == 0 {
+        return None;
+    }
+    let roll = words[..at].join(" ");
+    match &words[at + 1..] {
+        [target] => Some((roll, target.parse().ok()?, None)),
+        [target, word, limit] if word.eq_ignore_ascii_case("limit") => {
+            Some((roll, target.parse().ok()?, Some(limit.parse().ok()?)))
+        }
+        _ => None,
+    }
+}
+
 #[derive(Debug)]
 struct AttribRollResult {
     pool: i64,
     modifier: RollModifier,
-    attributes: HashMap<String, i64>,
+    /// Stat and stored modifier used when none was given in the roll.
+    default_modifier: Option<(String, String)>,
+    attributes: Vec<(String, i64)>,
+    /// Guild constants used, with their values.
+    constants: Vec<(String, i64)>,
     attribs_not_found: Vec<String>,
+    /// Source and dice of each bonus rule applied.
+    bonuses: Vec<(String, i64)>,
+    /// Stat and successes needed, when a stat in the pool lowers the
+    /// exceptional success threshold.
+    exceptional: Option<(String, u64)>,
+    /// Whether failed dice are rolled again once, for a rote.
+    rote: bool,
+    /// Whether willpower was spent for extra dice.
+    willpower: bool,
+    /// How the line was read into the pool, in order.
+    trace: Vec<TraceEvent>,
+}
+
+/// Blank out a standalone token in a roll line, keeping the rest of the
+/// line where it was so parse errors still point into it as typed.
+///
+/// Returns the line and whether the token was present.
+///
+/// # Arguments
+///
+/// * `line` - roll line
+/// * `token` - token to blank out, matched case-insensitively
+///
+/// # Examples
+///
+/// ```rust
+/// let (line, rote) = blank_token(line, ROTE);
+/// ```
+fn blank_token(line: String, token: &str) -> (String, bool) {
+    if !line.split_whitespace().any(|p| p.eq_ignore_ascii_case(token)) {
+        return (line, false);
+    }
+    let line = line
+        .split(' ')
+        .map(|p| {
+            if p.eq_ignore_ascii_case(token) {
+                " ".repeat(p.len())
+            } else {
+                p.to_owned()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ");
+    (line, true)
+}
+
+/// Refuse a pool too big for a roll to use.
+///
+/// # Arguments
+///
+/// * `pool` - dice in the pool, after any adjustments
+///
+/// # Examples
+///
+/// ```rust
+/// check_pool(pool)?;
+/// ```
+fn check_pool(pool: i64) -> Result<(), ParseError> {
+    if (-MAX_POOL..=MAX_POOL).contains(&pool) {
+        Ok(())
+    } else {
+        Err(ParseError {
+            position: 0,
+            message: format!(
+                "a pool of {} dice is out of range; rolls use at most {}",
+                pool, MAX_POOL
+            ),
+        })
+    }
+}
+
+/// Work out the dice typed and the pool for a roll of a plain number of dice.
+///
+/// # Arguments
+///
+/// * `dice` - number of dice as typed
+/// * `willpower` - whether willpower was spent for extra dice
+///
+/// # Examples
+///
+/// ```rust
+/// let (base, typed) = numeric_pool("5", true)?;
+/// ```
+fn numeric_pool(dice: &str, willpower: bool) -> Result<(i64, i64), ParseError> {
+    let base = dice.parse::<i64>().map_err(|_| ParseError {
+        position: 0,
+        message: format!("`{}` is too large", dice),
+    })?;
+    let typed = if willpower {
+        base.saturating_add(WILLPOWER_DICE)
+    } else {
+        base
+    };
+    check_pool(typed)?;
+    Ok((base, typed))
 }
 
-fn roll_attribs(character: &Character, line: &str) -> AttribRollResult {
-    let mut attributes = HashMap::new();
-    let mut attribs_not_found = vec![];
+/// Work out the dice pool and modifier for a roll using a character's stats.
+///
+/// The first again token sets the modifier; the rest of the line is parsed
+/// as an expression, so parse errors point into the line as typed.
+/// The character's bonus rules add dice for stats named in the expression,
+/// unless the line includes `nobonus`. A `rote` token makes it a rote, and
+/// a `willpower` or `wp` token spends willpower for extra dice. Names the
+/// character has no stat for use the guild's constant of that name, if any.
+///
+/// # Arguments
+///
+/// * `character` - character to look stats up on
+/// * `line` - roll line, such as `min(wits, 2) + athletics 9again`
+/// * `constants` - the guild's named constants
+///
+/// # Examples
+///
+/// ```rust
+/// let result = roll_attribs(&character, "strength + brawl", &guild.constants)?;
+/// ```
+fn roll_attribs(
+    character: &Character,
+    line: &str,
+    constants: &BTreeMap<String, i64>,
+) -> Result<AttribRollResult, ParseError> {
     let again_parts: Vec<&str> = line
         .split_whitespace()
         .filter(|p| REGEX_AGAIN.is_match(p))
         .collect();
-    let (line, modifier) = if again_parts.is_empty() {
-        (line.to_owned(), "10again")
+    let mut trace = vec![];
+    let (line, explicit_modifier) = if again_parts.is_empty() {
+        (line.to_owned(), None)
     } else {
-        (line.replace(again_parts[0], ""), again_parts[0].trim())
+        trace.push(TraceEvent::Modifier(again_parts[0].to_lowercase()));
+        (
+            line.replace(again_parts[0], &" ".repeat(again_parts[0].len())),
+            Some(again_parts[0].trim()),
+        )
     };
-    let line = line.replace("+", " + ").replace("-", " - ");
-
-    let mut pool = 0i64;
-    let mut multiplier = 1i8;
-    for part in line.split_whitespace() {
-        let part = part.trim();
-        if part == "-" {
-            multiplier = -1;
-            continue;
-        }
-        if REGEX_NUMERIC.is_match(&part) {
-            pool += part.parse::<i64>().unwrap() * i64::from(multiplier);
-        } else if part != "+" {
-            let (found, val) = character.get_value(part);
-            if !found {
-                attribs_not_found.push(part.to_owned());
-            } else {
-                attributes.insert(part.to_owned(), val);
-            }
-            pool += val * i64::from(multiplier);
+    let mut line = line;
+    let mut reserved = vec![];
+    for token in &[IGNORE_BONUSES, ROTE, WILLPOWER, WILLPOWER_SHORT] {
+        let (blanked, found) = blank_token(line, token);
+        line = blanked;
+        if found {
+            trace.push(TraceEvent::Reserved(token.to_string()));
+            reserved.push(*token);
         }
-        multiplier = 1;
     }
-    AttribRollResult {
+    let ignore_bonuses = reserved.contains(&IGNORE_BONUSES);
+    let rote = reserved.contains(&ROTE);
+    let willpower = reserved.contains(&WILLPOWER) || reserved.contains(&WILLPOWER_SHORT);
+    let mut lookups = Lookups::default();
+    let mut pool = parse(&line)?.evaluate(character, constants, &mut lookups);
+    let Lookups {
+        found: attributes,
+        missing: attribs_not_found,
+        constants: constants_used,
+        trace: lookup_trace,
+    } = lookups;
+    trace.extend(lookup_trace);
+    let bonuses: Vec<(String, i64)> = if ignore_bonuses {
+        vec![]
+    } else {
+        character
+            .bonus_rules()
+            .iter()
+            .filter(|r| {
+                attributes
+                    .iter()
+                    .map(|(k, _)| k)
+                    .chain(attribs_not_found.iter())
+                    .any(|k| canonical(k) == r.stat)
+            })
+            .map(|r| (r.source.clone(), r.dice))
+            .collect()
+    };
+    for (source, dice) in &bonuses {
+        trace.push(TraceEvent::Adjustment(source.clone(), *dice));
+        pool = pool.saturating_add(*dice);
+    }
+    if willpower {
+        trace.push(TraceEvent::Adjustment(String::from(WILLPOWER), WILLPOWER_DICE));
+        pool = pool.saturating_add(WILLPOWER_DICE);
+    }
+    check_pool(pool)?;
+    let (modifier, default_modifier) = match explicit_modifier {
+        Some(m) => (mod_for_str(m), None),
+        None => {
+            let best = attributes
+                .iter()
+                .rev()
+                .filter_map(|(k, _)| character.get_modifier(k).map(|m| (k, m)))
+                .max_by_key(|(_, m)| mod_for_str(m).rank());
+            match best {
+                Some((k, m)) => {
+                    trace.push(TraceEvent::StatModifier(k.to_owned(), m.to_owned()));
+                    (mod_for_str(m), Some((k.to_owned(), m.to_owned())))
+                }
+                None => (RollModifier::Again10, None),
+            }
+        }
+    };
+    let exceptional = attributes
+        .iter()
+        .filter_map(|(k, _)| character.get_exceptional(k).map(|t| (canonical(k), t)))
+        .min_by_key(|(_, t)| *t);
+    Ok(AttribRollResult {
         pool,
-        modifier: mod_for_str(modifier),
+        modifier,
+        default_modifier,
         attributes,
+        constants: constants_used,
         attribs_not_found,
+        bonuses,
+        exceptional,
+        rote,
+        willpower,
+        trace,
+    })
+}
+
+/// Show how often each face came up, like `1:✗✗ 8:✓✓ 10:✓✓✓`.
+///
+/// Faces that didn't come up are left out. A face that came up more than
+/// 5 times is shown as a count instead, like `10×6`.
+///
+/// # Arguments
+///
+/// * `rolls` - rolls to count, including bonus dice
+/// * `threshold` - lowest face that counts as a success
+///
+/// # Examples
+///
+/// ```rust
+/// let line = histogram(&rolls, 8);
+/// ```
+fn histogram(rolls: &[Roll], threshold: u64) -> String {
+    (1..=10)
+        .filter_map(|face| {
+            let times = rolls.iter().filter(|r| r.val == face).count();
+            let mark = if face >= threshold { "✓" } else { "✗" };
+            match times {
+                0 => None,
+                t if t > HISTOGRAM_MAX_MARKS => Some(format!("{}×{}", face, t)),
+                t => Some(format!("{}:{}", face, mark.repeat(t))),
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Add a histogram line to verbose output of a large pool.
+fn push_histogram(builder: &mut MessageBuilder, rolls: &[Roll], pool: u64, verbose: bool) {
+    if verbose && pool >= HISTOGRAM_MIN_DICE {
+        builder.push("\n").push(histogram(rolls, 8));
     }
 }
 
@@ -201,167 +647,1426 @@ fn roll_attribs(character: &Character, line: &str) -> AttribRollResult {
 /// let sc = count_successes(&rolls);
 /// ```
 fn count_successes(rolls: &[Roll]) -> String {
-    let count = rolls.iter().filter(|e| e.val > 7).count();
-    let text = if count != 1 { "successes" } else { "success" };
-    format!("{} {}: ", count, text)
+    format!("{}: ", count(successes(rolls) as i64, "success", "successes"))
 }
 
-#[command]
-pub fn roll(context: &mut Context, message: &Message, args: Args) -> CommandResult {
-    if args.is_empty() {
-        debug!("No args supplied to roll command");
-        return Ok(());
-    }
-    let dice = args.parse::<String>().unwrap();
-    if dice == CHANCE || REGEX_NUMERIC.is_match(&dice) {
-        let result = roll_dice(&dice, &mod_for_str(&message.content));
-        let response = if dice == CHANCE {
-            if result[0].val == 10 {
-                MessageBuilder::new()
-                    .mention(&message.author)
-                    .push(" rolled a chance die and succeeded!")
-                    .build()
-            } else {
-                MessageBuilder::new()
-                    .mention(&message.author)
-                    .push(" rolled a chance die and failed: ")
-                    .push(result[0].val)
-                    .build()
-            }
-        } else {
-            MessageBuilder::new()
-                .mention(&message.author)
-                .push(" rolled ")
-                .push(dice)
-                .push(" dice and got ")
-                .push(count_successes(&result))
-                .push(
-                    result
-                        .iter()
-                        .map(|e| format!("{}", e))
-                        .collect::<Vec<_>>()
-                        .join(", "),
-                )
-                .build()
-        };
-        message.channel_id.say(&context.http, &response)?;
-    } else {
-        let cs = CharacterStore::from_file(&LOAD_PATH).unwrap();
-        let new_character = Character::new(&message.author.name);
-        let character = match cs.get(&message.author.name) {
-            Some(c) => c,
-            None => &new_character,
-        };
-        let attrib_result = roll_attribs(&character, &message.content.trim().replace("!roll ", ""));
-        let roll_result = roll_dice(&attrib_result.pool.to_string(), &attrib_result.modifier);
-        let mut builder = MessageBuilder::new()
-            .mention(&message.author)
-            .push(" rolled ")
-            .push(attrib_result.pool)
-            .push(" dice [")
-            .push(
-                attrib_result
-                    .attributes
-                    .iter()
-                    .map(|(k, v)| format!("{} = {}", k, v))
-                    .collect::<Vec<_>>()
-                    .join(", "),
-            )
-            .push("] and got ")
-            .push(count_successes(&roll_result))
-            .push(
-                roll_result
-                    .iter()
-                    .map(|e| format!("{}", e))
-                    .collect::<Vec<_>>()
-                    .join(", "),
-            )
-            .clone();
-        if !attrib_result.attribs_not_found.is_empty() {
-            builder
-                .push("\n\nWarning: these attributes were not found and defaulted to 0: ")
-                .push(
-                    attrib_result
-                        .attribs_not_found
-                        .iter()
-                        .map(|a| a.to_string())
-                        .collect::<Vec<_>>()
-                        .join(", "),
-                );
-        }
-        message.channel_id.say(&context.http, &builder.build())?;
-    }
-    Ok(())
+/// Number of successes in the rolls.
+fn successes(rolls: &[Roll]) -> u64 {
+    rolls.iter().filter(|e| e.val > 7).count() as u64
 }
 
-#[cfg(test)]
-mod test {
-    use super::{count_successes, mod_for_str, Roll, roll_again, roll_attribs, RollModifier};
-    use crate::util::characters::Character;
+/// Summarize chance dice, which succeed on a 10 and dramatically fail on a 1.
+///
+/// # Arguments
+///
+/// * `rolls` - chance dice rolls
+///
+/// # Examples
+///
+/// ```rust
+/// let summary = summarize_chance(&rolls);
+/// ```
+fn summarize_chance(rolls: &[Roll]) -> String {
+    let successes = rolls.iter().filter(|r| r.val == 10).count() as i64;
+    let dramatic = rolls.iter().filter(|r| r.val == 1).count() as i64;
+    let failures = rolls.len() as i64 - successes - dramatic;
+    let parts: Vec<String> = [
+        (successes, "success", "successes"),
+        (dramatic, "dramatic failure", "dramatic failures"),
+        (failures, "failure", "failures"),
+    ]
+    .iter()
+    .filter(|(n, _, _)| *n > 0)
+    .map(|&(n, one, many)| count(n, one, many))
+    .collect();
+    parts.join(", ")
+}
 
-    #[test]
-    fn test_mod_for_str() {
-        assert_eq!(mod_for_str(""), RollModifier::Again10);
-        assert_eq!(mod_for_str("9again"), RollModifier::Again9);
-        assert_eq!(mod_for_str("8again"), RollModifier::Again8);
-        assert_eq!(mod_for_str("no10again"), RollModifier::NoAgain);
+/// What a single chance die did, shown after "rolled a chance die and".
+///
+/// A plain failure is followed by the die, so it ends with ": ".
+///
+/// # Arguments
+///
+/// * `outcome` - outcome of the chance die
+///
+/// # Examples
+///
+/// ```rust
+/// assert_eq!(chance_verdict(Outcome::Success), "succeeded!");
+/// ```
+fn chance_verdict(outcome: Outcome) -> &'static str {
+    match outcome {
+        Outcome::Success | Outcome::Exceptional => "succeeded!",
+        Outcome::DramaticFailure => "DRAMATICALLY failed!",
+        Outcome::Failure => "failed: ",
     }
+}
 
-    #[test]
-    fn test_roll_again() {
-        assert!(!roll_again(10, &RollModifier::NoAgain));
-
-        assert!(roll_again(10, &RollModifier::Again10));
-        assert!(!roll_again(9, &RollModifier::Again10));
-        assert!(!roll_again(8, &RollModifier::Again10));
-        assert!(!roll_again(7, &RollModifier::Again10));
-
-        assert!(roll_again(10, &RollModifier::Again9));
-        assert!(roll_again(9, &RollModifier::Again9));
-        assert!(!roll_again(8, &RollModifier::Again9));
-        assert!(!roll_again(7, &RollModifier::Again8));
+/// Input to the roll command.
+#[derive(Debug)]
+pub struct RollInput {
+    pub user_id: UserId,
+    pub user_name: String,
+    pub line: String,
+    pub mentions: Vec<(UserId, String)>,
+    pub now: u64,
+    /// The guild's weekly participation beat setting, if enabled.
+    pub weekly_beat: Option<WeeklyBeat>,
+    /// The roller's own time zone, if set, which their weekly beat resets in.
+    pub timezone: Option<Tz>,
+    /// The guild's emoji for die faces.
+    pub dice_emoji: BTreeMap<u8, String>,
+    /// The guild's own outcome markers, if it prefixes results with them.
+    pub outcome_markers: Option<BTreeMap<Outcome, String>>,
+    /// The guild's named constants for roll expressions.
+    pub constants: BTreeMap<String, i64>,
+}
 
-        assert!(roll_again(10, &RollModifier::Again8));
-        assert!(roll_again(9, &RollModifier::Again8));
-        assert!(roll_again(8, &RollModifier::Again8));
-        assert!(!roll_again(7, &RollModifier::Again8));
+/// Split a leading `for @user` off a roll line.
+///
+/// # Arguments
+///
+/// * `line` - what was typed after `!roll`
+///
+/// # Examples
+///
+/// ```rust
+/// let (user_id, rest) = parse_for_prefix("for <@1234> strength + 1").unwrap();
+/// ```
+fn parse_for_prefix(line: &str) -> Option<(UserId, &str)> {
+    let mut parts = line.split_whitespace();
+    if parts.next()? != "for" {
+        return None;
     }
+    let mention = parts.next()?;
+    let user_id = parse_username(mention)?;
+    let rest = &line[line.find(mention)? + mention.len()..];
+    Some((UserId(user_id), rest.trim()))
+}
 
-    #[test]
-    fn test_count_successes() {
-        let cs = count_successes(&[Roll {
-            val: 1,
-            is_bonus: false,
-        }]);
-
-        assert_eq!(cs, "0 successes: ");
+/// Remove a standalone flag token from a roll line.
+///
+/// Returns the remaining line and whether the flag was present.
+///
+/// # Arguments
+///
+/// * `line` - roll line
+/// * `flag` - token to remove, matched case-insensitively
+///
+/// # Examples
+///
+/// ```rust
+/// let (line, ephemeral) = take_flag("5 ephemeral", EPHEMERAL);
+/// ```
+fn take_flag(line: &str, flag: &str) -> (String, bool) {
+    let found = line.split_whitespace().any(|p| p.eq_ignore_ascii_case(flag));
+    let rest = line
+        .split_whitespace()
+        .filter(|p| !p.eq_ignore_ascii_case(flag))
+        .collect::<Vec<_>>()
+        .join(" ");
+    (rest, found)
+}
 
-        let cs = count_successes(&[Roll {
-            val: 10,
-            is_bonus: false,
-        }]);
+/// Wrap a roll response in the right kind of reply.
+fn roll_reply(text: String, ephemeral: bool) -> Reply {
+    if ephemeral {
+        Reply::Ephemeral(text)
+    } else {
+        Reply::Say(text)
+    }
+}
 
-        assert_eq!(cs, "1 success: ");
+/// Wrap the reason a roll couldn't be made in the right kind of reply.
+fn roll_refusal(text: String, ephemeral: bool) -> Reply {
+    if ephemeral {
+        Reply::Ephemeral(text)
+    } else {
+        Reply::Refuse(text)
+    }
+}
+
+/// Start a reply that names the roller.
+///
+/// # Arguments
+///
+/// * `user_id` - user who rolled
+/// * `on_behalf_of` - name of the user whose character was used, if not the roller's
+///
+/// # Examples
+///
+/// ```rust
+/// let response = reply_builder(&user_id, None).push(" rolled").build();
+/// ```
+fn reply_builder(user_id: &UserId, on_behalf_of: Option<&str>) -> MessageBuilder {
+    let mut builder = MessageBuilder::new();
+    builder.mention(user_id);
+    if let Some(name) = on_behalf_of {
+        builder.push(" (for ").push_safe(name).push(")");
+    }
+    builder
+}
+
+/// Push each roll of an extended action on its own line.
+///
+/// When listing every die would make the message too long, only each
+/// roll's successes are shown.
+///
+/// # Arguments
+///
+/// * `builder` - message to add to
+/// * `result` - rolls of the extended action
+/// * `emoji` - the guild's emoji for die faces
+///
+/// # Examples
+///
+/// ```rust
+/// push_extended_rolls(&mut builder, &result, &input.dice_emoji);
+/// ```
+fn push_extended_rolls(
+    builder: &mut MessageBuilder,
+    result: &ExtendedResult,
+    emoji: &BTreeMap<u8, String>,
+) {
+    let lines: Vec<(String, String)> = result
+        .rolls
+        .iter()
+        .enumerate()
+        .map(|(i, (rolls, hits))| {
+            (
+                format!("Roll {}: {}", i + 1, count(*hits as i64, "success", "successes")),
+                render_rolls(rolls, emoji),
+            )
+        })
+        .collect();
+    let length: usize = lines.iter().map(|(head, dice)| head.len() + dice.len() + 3).sum();
+    for (head, dice) in lines {
+        builder.push("\n").push(head);
+        if length <= MAX_EXTENDED_CHARS {
+            builder.push(": ").push(dice);
+        }
+    }
+}
+
+/// Push a resolved pool, like "4 dice [wits = 3, Gun Nut +1] with 8again from brawl".
+///
+/// # Arguments
+///
+/// * `builder` - message to add to
+/// * `result` - resolved pool
+/// * `show_explicit` - whether to also show a modifier given in the roll line
+///
+/// # Examples
+///
+/// ```rust
+/// push_pool(&mut builder, &attrib_result, false);
+/// ```
+fn push_pool(builder: &mut MessageBuilder, result: &AttribRollResult, show_explicit: bool) {
+    builder
+        .push(count(result.pool, "die", "dice"))
+        .push(" [")
+        .push(
+            result
+                .attributes
+                .iter()
+                .map(|(k, v)| format!("{} = {}", sanitize(k), v))
+                .chain(
+                    result
+                        .constants
+                        .iter()
+                        .map(|(k, v)| format!("{} (guild) = {}", sanitize(k), v)),
+                )
+                .chain(
+                    result
+                        .bonuses
+                        .iter()
+                        .map(|(source, dice)| format!("{} {:+}", sanitize(source), dice)),
+                )
+                .chain(
+                    Some(format!("{} = {:+}", WILLPOWER, WILLPOWER_DICE))
+                        .filter(|_| result.willpower),
+                )
+                .collect::<Vec<_>>()
+                .join(", "),
+        )
+        .push("]");
+    match &result.default_modifier {
+        Some((stat, modifier)) => {
+            builder
+                .push(" with ")
+                .push(modifier)
+                .push(" from ")
+                .push_safe(stat);
+        }
+        None if show_explicit && result.modifier != RollModifier::Again10 => {
+            builder.push(" with ").push(result.modifier.name());
+        }
+        None => {}
+    }
+    if result.rote {
+        builder.push(" as a rote");
+    }
+}
+
+/// Dice rolled for a pool.
+///
+/// A pool below 1 rolls no dice, or a single die with `min1`, for powers
+/// that take dice away but can't leave fewer than one.
+///
+/// # Arguments
+///
+/// * `pool` - dice in the pool after every stat and modifier
+/// * `min_one` - whether the roll had the `min1` token
+///
+/// # Examples
+///
+/// ```rust
+/// assert_eq!(dice_for_pool(-3, true), 1);
+/// ```
+fn dice_for_pool(pool: i64, min_one: bool) -> u64 {
+    if min_one {
+        pool.max(1) as u64
+    } else {
+        pool.max(0) as u64
+    }
+}
+
+/// Push a note when `min1` raised the pool to a single die.
+fn push_floor(builder: &mut MessageBuilder, pool: i64, min_one: bool) {
+    if min_one && pool < 1 {
+        builder.push(", raised to 1 die by ").push(MIN_ONE);
+    }
+}
+
+/// Successes needed for an exceptional success with a resolved pool.
+fn exceptional_needed(result: &AttribRollResult) -> u64 {
+    result
+        .exceptional
+        .as_ref()
+        .map_or(EXCEPTIONAL_SUCCESSES, |(_, t)| *t)
+}
+
+/// Push a note when the roll is an exceptional success.
+///
+/// # Arguments
+///
+/// * `builder` - message to add to
+/// * `result` - resolved pool
+/// * `successes` - successes rolled
+///
+/// # Examples
+///
+/// ```rust
+/// push_exceptional(&mut builder, &attrib_result, successes(&rolls));
+/// ```
+fn push_exceptional(builder: &mut MessageBuilder, result: &AttribRollResult, successes: u64) {
+    if successes < exceptional_needed(result) {
+        return;
+    }
+    builder.push(EXCEPTIONAL_NOTE);
+    if let Some((stat, needed)) = &result.exceptional {
+        builder
+            .push(" (")
+            .push(count(*needed as i64, "success", "successes"))
+            .push(" needed with ")
+            .push_safe(stat)
+            .push(")");
+    }
+}
+
+/// Push a warning listing attributes that weren't found, if there are any.
+///
+/// # Arguments
+///
+/// * `builder` - message to add to
+/// * `result` - resolved pool
+///
+/// # Examples
+///
+/// ```rust
+/// push_not_found_warning(&mut builder, &attrib_result);
+/// ```
+fn push_not_found_warning(builder: &mut MessageBuilder, result: &AttribRollResult) {
+    if result.attribs_not_found.is_empty() {
+        return;
+    }
+    builder
+        .push("\n\nWarning: ")
+        .push(plural(
+            result.attribs_not_found.len() as i64,
+            "this attribute was",
+            "these attributes were",
+        ))
+        .push(" not found and defaulted to 0: ")
+        .push(
+            result
+                .attribs_not_found
+                .iter()
+                .map(|name| sanitize(name))
+                .collect::<Vec<_>>()
+                .join(", "),
+        );
+}
+
+/// Handle a roll command, returning the replies to send.
+///
+/// A line starting with `for @user` rolls with that user's character,
+/// if they have delegated to the roller. An `ephemeral` token makes
+/// the reply delete itself after a delay. A character's pool reduced
+/// below 1 rolls a chance die, unless a `min1` token keeps it at a single
+/// die. A line starting with `extended` rolls the pool until its successes
+/// reach a target. A `fair` token rolls from a new seed, shown with the
+/// result so anyone can roll the same dice again. A `trace` token shows how
+/// the line was read into the dice rolled. With weekly beats enabled, the
+/// roller's first roll of the week earns their character a beat.
+///
+/// # Arguments
+///
+/// * `input` - who rolled and what they typed after `!roll`
+/// * `store` - character store to resolve attributes from
+/// * `path` - path the store is saved to
+/// * `rng` - source of randomness
+///
+/// # Examples
+///
+/// ```rust
+/// let replies = handle_roll(&input, &mut store, &LOAD_PATH, &mut thread_rng())?;
+/// ```
+pub fn handle_roll<R: Rng>(
+    input: &RollInput,
+    store: &mut CharacterStore,
+    path: &Path,
+    rng: &mut R,
+) -> Result<Vec<Reply>, Error> {
+    let (owner_id, owner_name, on_behalf_of, line) = match parse_for_prefix(input.line.trim()) {
+        Some((grantor, rest)) => {
+            let name = match input.mentions.iter().find(|(id, _)| *id == grantor) {
+                Some((_, name)) => name.clone(),
+                None => return Ok(vec![]),
+            };
+            if !store.is_delegated(grantor.0, input.user_id.0, input.now) {
+                return Ok(vec![Reply::Refuse(
+                    MessageBuilder::new()
+                        .push_safe(&name)
+                        .push(" has not delegated their rolls to you. They can use `!delegate @you`.")
+                        .build(),
+                )]);
+            }
+            (grantor.0, name.clone(), Some(name), rest)
+        }
+        None => (input.user_id.0, input.user_name.clone(), None, input.line.trim()),
+    };
+    let on_behalf_of = on_behalf_of.as_deref();
+    let mut events = vec![TraceEvent::Tokens(
+        line.split_whitespace().map(str::to_lowercase).collect(),
+    )];
+    let (line, ephemeral) = take_flag(line, EPHEMERAL);
+    let (line, verbose) = take_flag(&line, VERBOSE);
+    let (line, fair) = take_flag(&line, FAIR);
+    let (line, min_one) = take_flag(&line, MIN_ONE);
+    let (line, trace) = take_flag(&line, TRACE);
+    for (flag, taken) in &[
+        (EPHEMERAL, ephemeral),
+        (VERBOSE, verbose),
+        (FAIR, fair),
+        (MIN_ONE, min_one),
+        (TRACE, trace),
+    ] {
+        if *taken {
+            events.push(TraceEvent::Reserved(flag.to_string()));
+        }
+    }
+    let line = line.as_str();
+    let dice = match line.split_whitespace().next() {
+        Some(d) => d,
+        None => {
+            debug!("No args supplied to roll command");
+            return Ok(vec![]);
+        }
+    };
+    if fair && dice.eq_ignore_ascii_case(EXTENDED) {
+        return Ok(vec![Reply::Refuse(String::from(
+            "Extended actions can't be fair rolls, since each roll's dice can't be checked.",
+        ))]);
+    }
+    // Fair rolls take their dice from a seed that is shown with the result
+    let seed = if fair { Some(new_seed(rng)) } else { None };
+    let mut seeded;
+    let rng: &mut dyn RngCore = match &seed {
+        Some(seed) => {
+            seeded = seeded_rng(seed);
+            &mut seeded
+        }
+        None => rng,
+    };
+    let (response, outcome, spec) = if dice.eq_ignore_ascii_case(EXTENDED) {
+        let (roll, target, limit) = match parse_extended(&line[dice.len()..]) {
+            Some(parsed) => parsed,
+            None => return Ok(vec![Reply::Refuse(String::from(EXTENDED_USAGE))]),
+        };
+        let new_character = Character::new(&owner_name);
+        let character = match store.get(owner_id, &owner_name) {
+            Some(c) => c,
+            None => &new_character,
+        };
+        let attrib_result = match roll_attribs(character, &roll, &input.constants) {
+            Ok(r) => r,
+            Err(e) => {
+                let response = reply_builder(&input.user_id, on_behalf_of)
+                    .push(" could not read that roll: ")
+                    .push_codeblock_safe(e.render(&roll), None)
+                    .build();
+                return Ok(vec![roll_refusal(response, ephemeral)]);
+            }
+        };
+        events.extend(attrib_result.trace.iter().cloned());
+        let pool = if min_one {
+            attrib_result.pool.max(1)
+        } else {
+            attrib_result.pool
+        };
+        let limit = limit.unwrap_or_else(|| (pool.max(1) as u64).min(MAX_EXTENDED_ROLLS));
+        if target < 1 || !(1..=MAX_EXTENDED_ROLLS).contains(&limit) {
+            return Ok(vec![Reply::Refuse(format!(
+                "Extended actions need a target of at least 1 success and from 1 to {} rolls.",
+                MAX_EXTENDED_ROLLS
+            ))]);
+        }
+        let result = roll_extended(
+            pool,
+            attrib_result.rote,
+            &attrib_result.modifier,
+            target,
+            limit,
+            rng,
+        );
+        let mut builder = reply_builder(&input.user_id, on_behalf_of)
+            .push(" rolled an extended action of ")
+            .clone();
+        push_pool(&mut builder, &attrib_result, true);
+        push_floor(&mut builder, attrib_result.pool, min_one);
+        if pool < 1 {
+            builder.push(", pool reduced below 1, rolling a chance die each time");
+        }
+        builder
+            .push(", needing ")
+            .push(count(target as i64, "success", "successes"))
+            .push(":");
+        push_extended_rolls(&mut builder, &result, &input.dice_emoji);
+        let rolls = count(result.rolls.len() as i64, "roll", "rolls");
+        let outcome = if result.reached {
+            builder
+                .push("\nReached ")
+                .push(count(target as i64, "success", "successes"))
+                .push(" after ")
+                .push(rolls)
+                .push("!");
+            Outcome::Success
+        } else if result.dramatic {
+            builder
+                .push("\nDRAMATICALLY failed on roll ")
+                .push(result.rolls.len().to_string())
+                .push(", ending the action.");
+            Outcome::DramaticFailure
+        } else {
+            builder
+                .push("\nFailed to reach the target after ")
+                .push(rolls)
+                .push(", with ")
+                .push(count(result.total as i64, "success", "successes"))
+                .push(".");
+            Outcome::Failure
+        };
+        push_not_found_warning(&mut builder, &attrib_result);
+        let spec = format!(
+            "{} {} target {} limit {}",
+            EXTENDED,
+            fair_spec(pool.max(0) as u64, &attrib_result.modifier, attrib_result.rote),
+            target,
+            limit
+        );
+        (builder.build(), outcome, spec)
+    } else if dice == CHANCE {
+        let chance_dice = match line.split_whitespace().nth(1) {
+            Some(n) if REGEX_NUMERIC.is_match(n) => n.parse().unwrap_or(0),
+            _ => 1,
+        };
+        if !(1..=MAX_CHANCE_DICE).contains(&chance_dice) {
+            return Ok(vec![Reply::Refuse(format!(
+                "You can roll from 1 to {} chance dice.",
+                MAX_CHANCE_DICE
+            ))]);
+        }
+        events.push(TraceEvent::Reserved(String::from(CHANCE)));
+        events.push(TraceEvent::Literal(chance_dice as i64));
+        let result = roll_dice(chance_dice, true, false, &RollModifier::NoAgain, rng);
+        let faces: Vec<u64> = result.iter().map(|r| r.val).collect();
+        let outcome = classify_chance(&faces);
+        let response = if chance_dice > 1 {
+            reply_builder(&input.user_id, on_behalf_of)
+                .push(" rolled ")
+                .push(chance_dice.to_string())
+                .push(" chance dice and got ")
+                .push(summarize_chance(&result))
+                .push(": ")
+                .push(render_rolls(&result, &input.dice_emoji))
+                .build()
+        } else {
+            let mut builder = reply_builder(&input.user_id, on_behalf_of)
+                .push(" rolled a chance die and ")
+                .push(chance_verdict(outcome))
+                .clone();
+            if outcome == Outcome::Failure {
+                builder.push(render_rolls(&result, &input.dice_emoji));
+            }
+            builder.build()
+        };
+        (response, outcome, format!("{} {}", CHANCE, chance_dice))
+    } else if REGEX_NUMERIC.is_match(dice) {
+        let has_token =
+            |token: &str| line.split_whitespace().any(|p| p.eq_ignore_ascii_case(token));
+        let rote = has_token(ROTE);
+        let willpower = has_token(WILLPOWER) || has_token(WILLPOWER_SHORT);
+        let (base, typed) = match numeric_pool(dice, willpower) {
+            Ok(pools) => pools,
+            Err(e) => {
+                let response = reply_builder(&input.user_id, on_behalf_of)
+                    .push(" could not read that roll: ")
+                    .push_codeblock_safe(e.render(line), None)
+                    .build();
+                return Ok(vec![roll_refusal(response, ephemeral)]);
+            }
+        };
+        events.push(TraceEvent::Literal(base));
+        if let Some(again) = line.split_whitespace().find(|p| REGEX_AGAIN.is_match(p)) {
+            events.push(TraceEvent::Modifier(again.to_lowercase()));
+        }
+        for token in &[ROTE, WILLPOWER, WILLPOWER_SHORT] {
+            if has_token(token) {
+                events.push(TraceEvent::Reserved(token.to_string()));
+            }
+        }
+        if willpower {
+            events.push(TraceEvent::Adjustment(String::from(WILLPOWER), WILLPOWER_DICE));
+        }
+        let pool = dice_for_pool(typed, min_one);
+        let modifier = mod_for_str(line);
+        let result = roll_dice(pool, false, rote, &modifier, rng);
+        let mut builder = reply_builder(&input.user_id, on_behalf_of)
+            .push(" rolled ")
+            .push(typed.to_string())
+            .push(" ")
+            .push(plural(typed, "die", "dice"))
+            .clone();
+        if willpower {
+            builder.push(" (willpower spent)");
+        }
+        push_floor(&mut builder, typed, min_one);
+        if rote {
+            builder.push(" as a rote");
+        }
+        builder
+            .push(" and got ")
+            .push(count_successes(&result))
+            .push(render_rolls(&result, &input.dice_emoji));
+        let outcome = classify(successes(&result), EXCEPTIONAL_SUCCESSES);
+        if outcome == Outcome::Exceptional {
+            builder.push(EXCEPTIONAL_NOTE);
+        }
+        push_histogram(&mut builder, &result, pool, verbose);
+        (builder.build(), outcome, fair_spec(pool, &modifier, rote))
+    } else {
+        let new_character = Character::new(&owner_name);
+        let character = match store.get(owner_id, &owner_name) {
+            Some(c) => c,
+            None => &new_character,
+        };
+        let attrib_result = match roll_attribs(character, line, &input.constants) {
+            Ok(r) => r,
+            Err(e) => {
+                let response = reply_builder(&input.user_id, on_behalf_of)
+                    .push(" could not read that roll: ")
+                    .push_codeblock_safe(e.render(line), None)
+                    .build();
+                return Ok(vec![roll_refusal(response, ephemeral)]);
+            }
+        };
+        events.extend(attrib_result.trace.iter().cloned());
+        let mut builder = reply_builder(&input.user_id, on_behalf_of)
+            .push(" rolled ")
+            .clone();
+        push_pool(&mut builder, &attrib_result, false);
+        if attrib_result.pool < 1 && !min_one {
+            // A pool reduced to nothing rolls a chance die
+            let result = roll_dice(1, true, false, &RollModifier::NoAgain, rng);
+            let faces: Vec<u64> = result.iter().map(|r| r.val).collect();
+            let outcome = classify_chance(&faces);
+            builder
+                .push(", pool reduced below 1, rolling a chance die instead, and ")
+                .push(chance_verdict(outcome));
+            if outcome == Outcome::Failure {
+                builder.push(render_rolls(&result, &input.dice_emoji));
+            }
+            push_not_found_warning(&mut builder, &attrib_result);
+            (builder.build(), outcome, format!("{} 1", CHANCE))
+        } else {
+            let pool = dice_for_pool(attrib_result.pool, min_one);
+            let roll_result = roll_dice(
+                pool,
+                false,
+                attrib_result.rote,
+                &attrib_result.modifier,
+                rng,
+            );
+            push_floor(&mut builder, attrib_result.pool, min_one);
+            builder
+                .push(" and got ")
+                .push(count_successes(&roll_result))
+                .push(render_rolls(&roll_result, &input.dice_emoji));
+            push_exceptional(&mut builder, &attrib_result, successes(&roll_result));
+            push_histogram(&mut builder, &roll_result, pool, verbose);
+            push_not_found_warning(&mut builder, &attrib_result);
+            let outcome = classify(successes(&roll_result), exceptional_needed(&attrib_result));
+            let spec = fair_spec(pool, &attrib_result.modifier, attrib_result.rote);
+            (builder.build(), outcome, spec)
+        }
+    };
+    let response = if trace {
+        events.push(TraceEvent::Spec(spec.clone()));
+        MessageBuilder::new()
+            .push(response)
+            .push("\n")
+            .push_codeblock_safe(render_trace(&events), None)
+            .build()
+    } else {
+        response
+    };
+    let response = match &input.outcome_markers {
+        Some(overrides) => format!("{} {}", outcome.marker(overrides), response),
+        None => response,
+    };
+    let response = match &seed {
+        Some(seed) => format!(
+            "{}\n\nSeed: `{}`\nCheck these dice with `{} verify {} {}`",
+            response,
+            to_hex(seed),
+            env!("CARGO_PKG_NAME"),
+            to_hex(seed),
+            spec
+        ),
+        None => response,
+    };
+    let response = match &input.weekly_beat {
+        Some(weekly_beat) => {
+            let start = match input.timezone {
+                Some(tz) => zoned_week_start(input.now, weekly_beat.reset_day, tz),
+                None => week_start(
+                    input.now,
+                    weekly_beat.reset_day,
+                    weekly_beat.utc_offset_minutes,
+                ),
+            };
+            let character = store.get_mut(input.user_id.0, &input.user_name);
+            if character.award_weekly_beat(input.now, start) {
+                character.set_last_writer(input.user_id.0);
+                let beats = character.beats();
+                store.save(path)?;
+                MessageBuilder::new()
+                    .push(response)
+                    .push("\n\n")
+                    .push_safe(&input.user_name)
+                    .push(" earned this week's participation beat! (")
+                    .push(count(beats as i64, "beat", "beats"))
+                    .push(")")
+                    .build()
+            } else {
+                response
+            }
+        }
+        _ => response,
+    };
+    let mut replies = vec![];
+    if let Some(seed) = &seed {
+        replies.push(Reply::Say(format!(
+            "Fair roll: the dice come from a seed with SHA-256 `{}`",
+            commitment(seed)
+        )));
+    }
+    replies.push(roll_reply(response, ephemeral));
+    Ok(replies)
+}
+
+/// Dice shown with a fair roll, for checking it with `verify`.
+///
+/// # Arguments
+///
+/// * `pool` - dice rolled
+/// * `modifier` - roll modifier
+/// * `rote` - whether the roll was a rote
+///
+/// # Examples
+///
+/// ```rust
+/// assert_eq!(fair_spec(5, &RollModifier::Again9, true), "5 9again rote");
+/// ```
+fn fair_spec(pool: u64, modifier: &RollModifier, rote: bool) -> String {
+    if rote {
+        format!("{} {} {}", pool, modifier.name(), ROTE)
+    } else {
+        format!("{} {}", pool, modifier.name())
+    }
+}
+
+/// Roll a fair roll's dice again from its seed.
+///
+/// Returns the dice as the roll showed them, so anyone can check that the
+/// bot didn't pick them.
+///
+/// # Arguments
+///
+/// * `seed` - seed shown with the roll, as hex
+/// * `spec` - dice shown with the roll, like "5 9again", "5 10again rote" or "chance 1"
+///
+/// # Examples
+///
+/// ```rust
+/// let dice = verify_fair_roll(&seed, "5 10again")?;
+/// ```
+pub fn verify_fair_roll(seed: &str, spec: &str) -> Result<String, String> {
+    let seed = match parse_seed(seed) {
+        Some(s) => s,
+        None => return Err(format!("`{}` is not a seed of 64 hex digits.", seed)),
+    };
+    let parts: Vec<&str> = spec.split_whitespace().collect();
+    let (dice, chance, rote, modifier) = match parts.as_slice() {
+        [CHANCE, count] => (count.parse::<u64>().ok(), true, false, RollModifier::NoAgain),
+        [count, modifier] => (count.parse::<u64>().ok(), false, false, mod_for_str(modifier)),
+        [count, modifier, rote] if rote.eq_ignore_ascii_case(ROTE) => {
+            (count.parse::<u64>().ok(), false, true, mod_for_str(modifier))
+        }
+        _ => (None, false, false, RollModifier::Again10),
+    };
+    let dice = match dice {
+        Some(d) => d,
+        None => {
+            return Err(format!(
+                "`{}` is not a roll to check, like `5 10again` or `chance 1`.",
+                spec
+            ));
+        }
+    };
+    let rolls = roll_dice(dice, chance, rote, &modifier, &mut seeded_rng(&seed));
+    Ok(render_rolls(&rolls, &BTreeMap::new()))
+}
+
+/// Input to the check command.
+#[derive(Debug)]
+pub struct CheckInput {
+    pub user_id: UserId,
+    pub user_name: String,
+    pub line: String,
+    /// The guild's named constants for roll expressions.
+    pub constants: BTreeMap<String, i64>,
+}
+
+/// Handle a check command, describing a stat roll without rolling it.
+///
+/// Resolves the pool exactly as `!roll` does, so the two can't disagree.
+///
+/// # Arguments
+///
+/// * `input` - who checked and what they typed after `!check`
+/// * `store` - character store to resolve attributes from
+///
+/// # Examples
+///
+/// ```rust
+/// let replies = handle_check(&input, &store);
+/// ```
+pub fn handle_check(input: &CheckInput, store: &CharacterStore) -> Vec<Reply> {
+    let line = input.line.trim();
+    if line.is_empty() {
+        debug!("No args supplied to check command");
+        return vec![];
+    }
+    let (line, min_one) = take_flag(line, MIN_ONE);
+    let line = line.as_str();
+    let new_character = Character::new(&input.user_name);
+    let character = store
+        .get(input.user_id.0, &input.user_name)
+        .unwrap_or(&new_character);
+    let mut builder = reply_builder(&input.user_id, None);
+    match roll_attribs(character, line, &input.constants) {
+        Ok(result) => {
+            builder.push(" would roll ");
+            push_pool(&mut builder, &result, true);
+            push_floor(&mut builder, result.pool, min_one);
+            push_not_found_warning(&mut builder, &result);
+        }
+        Err(e) => {
+            builder
+                .push(" could not read that roll: ")
+                .push_codeblock_safe(e.render(line), None);
+            return vec![Reply::Refuse(builder.build())];
+        }
+    }
+    vec![Reply::Say(builder.build())]
+}
+
+/// Parse a group check, like `wits + composure vs 3 @alice @bob`.
+///
+/// Returns the roll expression, the successes needed and the players
+/// mentioned, in order and without repeats, or a message explaining what
+/// is wrong.
+///
+/// # Arguments
+///
+/// * `line` - what was typed after `groupcheck`
+///
+/// # Examples
+///
+/// ```rust
+/// let (expression, target, players) = parse_group_check("wits vs 2 <@1> <@2>")?;
+/// ```
+fn parse_group_check(line: &str) -> Result<(String, u64, Vec<UserId>), String> {
+    let parts: Vec<&str> = line.split_whitespace().collect();
+    let vs = match parts.iter().position(|p| p.eq_ignore_ascii_case("vs")) {
+        Some(i) if i > 0 => i,
+        _ => return Err(String::from(GROUP_CHECK_USAGE)),
+    };
+    let target = match parts.get(vs + 1).and_then(|t| t.parse::<u64>().ok()) {
+        Some(t) if t > 0 => t,
+        _ => return Err(String::from(GROUP_CHECK_USAGE)),
+    };
+    let mut players = vec![];
+    for mention in &parts[vs + 2..] {
+        let user_id = match parse_username(mention) {
+            Some(id) => UserId(id),
+            None => {
+                return Err(MessageBuilder::new()
+                    .push_mono_safe(mention)
+                    .push(" is not a player mention.")
+                    .build());
+            }
+        };
+        if !players.contains(&user_id) {
+            players.push(user_id);
+        }
+    }
+    if players.is_empty() {
+        return Err(String::from("Mention the players to roll for."));
+    }
+    if players.len() > MAX_GROUP_CHECK_PLAYERS {
+        return Err(format!(
+            "A group check can roll for at most {} players.",
+            MAX_GROUP_CHECK_PLAYERS
+        ));
+    }
+    Ok((parts[..vs].join(" "), target, players))
+}
+
+/// Handle a group check, rolling the same expression for each mentioned
+/// player's character and showing the results in one table.
+///
+/// Each pool is resolved exactly as `!roll` does for that character. A
+/// player whose pool comes to less than 1 rolls a chance die, which
+/// dramatically fails on a 1.
+///
+/// # Arguments
+///
+/// * `line` - what was typed after `groupcheck`
+/// * `mentions` - Discord ID and name of each user mentioned
+/// * `constants` - the guild's named constants for roll expressions
+/// * `store` - character store to resolve attributes from
+/// * `rng` - source of randomness
+///
+/// # Examples
+///
+/// ```rust
+/// let replies =
+///     handle_group_check("wits vs 2 <@1>", &mentions, &constants, &store, &mut thread_rng());
+/// ```
+pub fn handle_group_check<R: Rng + ?Sized>(
+    line: &str,
+    mentions: &[(UserId, String)],
+    constants: &BTreeMap<String, i64>,
+    store: &CharacterStore,
+    rng: &mut R,
+) -> Vec<Reply> {
+    let (expression, target, players) = match parse_group_check(line) {
+        Ok(parsed) => parsed,
+        Err(e) => return vec![Reply::Refuse(e)],
+    };
+    let mut rows = vec![];
+    for player in players {
+        let name = match mentions.iter().find(|(id, _)| *id == player) {
+            Some((_, name)) => name,
+            None => continue,
+        };
+        let new_character = Character::new(name);
+        let character = store.get(player.0, name).unwrap_or(&new_character);
+        let result = match roll_attribs(character, &expression, constants) {
+            Ok(r) => r,
+            Err(e) => {
+                let response = MessageBuilder::new()
+                    .push("Could not read that roll: ")
+                    .push_codeblock_safe(e.render(&expression), None)
+                    .build();
+                return vec![Reply::Refuse(response)];
+            }
+        };
+        let (pool, hits, outcome) = if result.pool < 1 {
+            let rolls = roll_dice(1, true, false, &RollModifier::NoAgain, rng);
+            let faces: Vec<u64> = rolls.iter().map(|r| r.val).collect();
+            let hits = rolls.iter().filter(|r| r.val == 10).count() as u64;
+            (String::from(CHANCE), hits, classify_chance(&faces))
+        } else {
+            let rolls =
+                roll_dice(result.pool as u64, false, result.rote, &result.modifier, rng);
+            let hits = successes(&rolls);
+            (result.pool.to_string(), hits, classify(hits, exceptional_needed(&result)))
+        };
+        let verdict = if outcome == Outcome::DramaticFailure {
+            "dramatic failure"
+        } else if hits >= target {
+            "pass"
+        } else {
+            "fail"
+        };
+        rows.push(vec![name.clone(), pool, hits.to_string(), String::from(verdict)]);
+    }
+    let response = MessageBuilder::new()
+        .push("Group check of ")
+        .push_mono_safe(&expression)
+        .push(", needing ")
+        .push(count(target as i64, "success", "successes"))
+        .push(":")
+        .push_codeblock_safe(
+            summary_table(&["Player", "Pool", "Successes", "Result"], &rows),
+            None,
+        )
+        .build();
+    vec![Reply::Say(response)]
+}
+
+#[command]
+#[bucket = "rolls"]
+pub fn roll(context: &mut Context, message: &Message, args: Args) -> CommandResult {
+    let settings = SettingsStore::from_file(&SETTINGS_PATH)?;
+    let guild = message.guild_id.and_then(|g| settings.get(g.0));
+    let input = RollInput {
+        user_id: message.author.id,
+        user_name: message.author.name.clone(),
+        line: args.rest().to_owned(),
+        mentions: message
+            .mentions
+            .iter()
+            .map(|u| (u.id, u.name.clone()))
+            .collect(),
+        now: now(),
+        weekly_beat: guild.and_then(|g| g.weekly_beat.clone()),
+        timezone: settings
+            .user(message.author.id.0)
+            .and_then(|u| u.timezone.as_ref())
+            .and_then(|tz| parse_timezone(tz).ok()),
+        dice_emoji: guild.map(|g| g.dice_emoji.clone()).unwrap_or_default(),
+        outcome_markers: guild
+            .filter(|g| g.outcome_markers)
+            .map(|g| g.outcome_marker_symbols.clone()),
+        constants: guild.map(|g| g.constants.clone()).unwrap_or_default(),
+    };
+    let mut cs = CharacterStore::from_file(&LOAD_PATH)?;
+    let replies = handle_roll(&input, &mut cs, &LOAD_PATH, &mut thread_rng())?;
+    send(context, message, replies)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        AttribRollResult, chance_verdict, count_successes, dice_for_pool, histogram, mod_for_str,
+        numeric_pool, parse_extended, parse_for_prefix, parse_group_check, render_rolls, Roll,
+        roll_again, roll_attribs, roll_dice, roll_extended, RollModifier, summarize_chance,
+        take_flag, verify_fair_roll, GROUP_CHECK_USAGE,
+    };
+    use rand::{rngs::StdRng, SeedableRng};
+    use serenity::model::id::UserId;
+    use std::collections::BTreeMap;
+    use crate::util::{
+        characters::{BonusRule, Character},
+        outcome::classify_chance,
+        testing::ScriptedRng,
+    };
+
+    fn seeded(seed: u64) -> StdRng {
+        StdRng::seed_from_u64(seed)
+    }
+
+    #[test]
+    fn test_verify_fair_roll() {
+        let seed = "07".repeat(32);
+        let dice = verify_fair_roll(&seed, "5 10again").unwrap();
+
+        assert_eq!(verify_fair_roll(&seed, "5 10again"), Ok(dice.clone()));
+        assert_eq!(dice.split(", ").filter(|d| !d.starts_with('(')).count(), 5);
+        assert_eq!(
+            verify_fair_roll(&seed, "chance 3").unwrap().split(", ").count(),
+            3
+        );
+        assert_ne!(verify_fair_roll(&"08".repeat(32), "5 10again"), Ok(dice));
+        let rote = verify_fair_roll(&seed, "5 10again rote").unwrap();
+        let pool = rote.split(", ").filter(|d| !d.starts_with('(') && !d.starts_with('['));
+        assert_eq!(pool.count(), 5);
+        assert_eq!(verify_fair_roll(&seed, "5 10again rote"), Ok(rote));
+    }
+
+    #[test]
+    fn test_roll_dice_modifiers() {
+        let faces = [10, 9, 8, 2, 7, 1];
+        let cases = [
+            (RollModifier::Again10, "10, (9), 8, 2"),
+            (RollModifier::Again9, "10, (9), (8), 2, 7"),
+            (RollModifier::Again8, "10, (9), (8), (2), 7, 1"),
+            (RollModifier::NoAgain, "10, 9, 8"),
+        ];
+        for (modifier, expected) in cases.iter() {
+            let rolls = roll_dice(3, false, false, modifier, &mut ScriptedRng::new(&faces));
+            assert_eq!(
+                render_rolls(&rolls, &BTreeMap::new()),
+                *expected,
+                "modifier: {:?}",
+                modifier
+            );
+        }
+    }
+
+    #[test]
+    fn test_roll_dice_seeded() {
+        for seed in 0..50 {
+            let rolls = roll_dice(5, false, false, &RollModifier::NoAgain, &mut seeded(seed));
+            assert_eq!(rolls.len(), 5);
+            assert!(rolls.iter().all(|r| !r.is_bonus && (1..=10).contains(&r.val)));
+
+            for (modifier, lowest) in &[(RollModifier::Again10, 10), (RollModifier::Again8, 8)] {
+                let rolls = roll_dice(5, false, false, modifier, &mut seeded(seed));
+                let again = rolls.iter().filter(|r| r.val >= *lowest).count();
+                let bonus = rolls.iter().filter(|r| r.is_bonus).count();
+                assert_eq!(bonus, again, "seed {} with {:?}", seed, modifier);
+                assert_eq!(rolls.len(), 5 + bonus);
+            }
+
+            let chance = roll_dice(1, true, false, &RollModifier::Again8, &mut seeded(seed));
+            assert_eq!(chance.len(), 1);
+            assert!(!chance[0].is_bonus);
+        }
+        let first = roll_dice(10, false, false, &RollModifier::Again10, &mut seeded(7));
+        let again = roll_dice(10, false, false, &RollModifier::Again10, &mut seeded(7));
+        assert_eq!(
+            render_rolls(&first, &BTreeMap::new()),
+            render_rolls(&again, &BTreeMap::new())
+        );
+    }
+
+    #[test]
+    fn test_roll_dice_rote() {
+        let mut rng = ScriptedRng::new(&[3, 5, 9, 1, 10, 2, 10, 4, 7, 8]);
+        let rolls = roll_dice(5, false, true, &RollModifier::Again10, &mut rng);
+
+        assert_eq!(
+            render_rolls(&rolls, &BTreeMap::new()),
+            "3, [5], 9, 1, [10], (2), 10, (4), 7, [8]"
+        );
+        assert_eq!(rolls.iter().filter(|r| r.is_rote).count(), 3);
+        assert_eq!(count_successes(&rolls), "4 successes: ");
+    }
+
+    #[test]
+    fn test_roll_extended_reaches_target_early() {
+        let mut rng = ScriptedRng::new(&[8, 9, 2, 10, 4, 3, 1, 8, 1, 5]);
+        let result = roll_extended(3, false, &RollModifier::Again10, 4, 5, &mut rng);
+
+        assert!(result.reached);
+        assert!(!result.dramatic);
+        assert_eq!(result.total, 4);
+        let hits: Vec<u64> = result.rolls.iter().map(|(_, hits)| *hits).collect();
+        assert_eq!(hits, vec![2, 1, 1]);
+        assert_eq!(render_rolls(&result.rolls[1].0, &BTreeMap::new()), "10, (4), 3, 1");
+    }
+
+    #[test]
+    fn test_roll_extended_exhausts_limit() {
+        let mut rng = ScriptedRng::new(&[8, 2, 3, 4, 9, 5]);
+        let result = roll_extended(2, false, &RollModifier::Again10, 10, 3, &mut rng);
+
+        assert!(!result.reached);
+        assert_eq!(result.rolls.len(), 3);
+        assert_eq!(result.total, 2);
+    }
+
+    #[test]
+    fn test_roll_extended_chance_die() {
+        let mut rng = ScriptedRng::new(&[10, 9, 10]);
+        let result = roll_extended(0, false, &RollModifier::Again10, 2, 5, &mut rng);
+        assert!(result.reached);
+        assert_eq!(result.rolls.len(), 3);
+
+        let mut rng = ScriptedRng::new(&[5, 1, 10]);
+        let result = roll_extended(-2, false, &RollModifier::Again10, 2, 5, &mut rng);
+        assert!(!result.reached);
+        assert!(result.dramatic);
+        assert_eq!(result.rolls.len(), 2);
+        assert_eq!(result.total, 0);
+    }
+
+    #[test]
+    fn test_parse_extended() {
+        assert_eq!(
+            parse_extended(" wits + investigation target 10 limit 5"),
+            Some((String::from("wits + investigation"), 10, Some(5)))
+        );
+        assert_eq!(parse_extended("4 TARGET 6"), Some((String::from("4"), 6, None)));
+        assert_eq!(parse_extended("target 6"), None);
+        assert_eq!(parse_extended("wits + 2"), None);
+        assert_eq!(parse_extended("wits target lots"), None);
+        assert_eq!(parse_extended("wits target 6 limit"), None);
+        assert_eq!(parse_extended("wits target 6 tries 2"), None);
+    }
+
+    #[test]
+    fn test_chance_verdict() {
+        assert_eq!(chance_verdict(classify_chance(&[10])), "succeeded!");
+        assert_eq!(chance_verdict(classify_chance(&[1])), "DRAMATICALLY failed!");
+        for face in 2..10 {
+            assert_eq!(chance_verdict(classify_chance(&[face])), "failed: ");
+        }
+    }
+
+    #[test]
+    fn test_roll_dice_chance_is_never_rote() {
+        let mut rng = ScriptedRng::new(&[3, 5]);
+        let rolls = roll_dice(1, true, true, &RollModifier::Again10, &mut rng);
+
+        assert_eq!(render_rolls(&rolls, &BTreeMap::new()), "3");
+    }
+
+    #[test]
+    fn test_verify_fair_roll_errors() {
+        assert_eq!(
+            verify_fair_roll("abc", "5 10again"),
+            Err(String::from("`abc` is not a seed of 64 hex digits."))
+        );
+        for spec in &["", "5", "lots 10again", "chance", "chance many", "5 10again twice"] {
+            assert!(verify_fair_roll(&"07".repeat(32), spec).is_err(), "{}", spec);
+        }
+    }
+
+    #[test]
+    fn test_dice_for_pool() {
+        assert_eq!(dice_for_pool(0, false), 0);
+        assert_eq!(dice_for_pool(-3, false), 0);
+        assert_eq!(dice_for_pool(1, false), 1);
+        assert_eq!(dice_for_pool(0, true), 1);
+        assert_eq!(dice_for_pool(-3, true), 1);
+        assert_eq!(dice_for_pool(1, true), 1);
+        assert_eq!(dice_for_pool(4, true), 4);
+    }
+
+    #[test]
+    fn test_take_flag() {
+        assert_eq!(take_flag("5 ephemeral", "ephemeral"), (String::from("5"), true));
+        assert_eq!(
+            take_flag("Ephemeral strength + 1", "ephemeral"),
+            (String::from("strength + 1"), true)
+        );
+        assert_eq!(take_flag("5 9again", "ephemeral"), (String::from("5 9again"), false));
+        assert_eq!(
+            take_flag("ephemerality", "ephemeral"),
+            (String::from("ephemerality"), false)
+        );
+    }
+
+    fn rolls_of(faces: &[u64]) -> Vec<Roll> {
+        faces
+            .iter()
+            .map(|&val| Roll {
+                val,
+                is_bonus: false,
+                is_rote: false,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_histogram() {
+        assert_eq!(
+            histogram(&rolls_of(&[1, 5, 10, 8, 9, 1, 10, 8, 10, 3]), 8),
+            "1:✗✗ 3:✗ 5:✗ 8:✓✓ 9:✓ 10:✓✓✓"
+        );
+        assert_eq!(histogram(&rolls_of(&[7, 7, 7, 7, 7]), 8), "7:✗✗✗✗✗");
+        assert_eq!(
+            histogram(&rolls_of(&[10, 10, 10, 10, 10, 10, 2, 2, 2, 2, 2, 2, 2]), 8),
+            "2×7 10×6"
+        );
+        assert_eq!(histogram(&rolls_of(&[8, 7]), 7), "7:✓ 8:✓");
+        assert_eq!(histogram(&[], 8), "");
+    }
+
+    #[test]
+    fn test_parse_for_prefix() {
+        assert_eq!(
+            parse_for_prefix("for <@1234> strength + 1"),
+            Some((UserId(1234), "strength + 1"))
+        );
+        assert_eq!(parse_for_prefix("for <@!1234>   5 9again"), Some((UserId(1234), "5 9again")));
+        assert_eq!(parse_for_prefix("for <@1234>"), Some((UserId(1234), "")));
+        assert_eq!(parse_for_prefix("for strength"), None);
+        assert_eq!(parse_for_prefix("strength for <@1234>"), None);
+        assert_eq!(parse_for_prefix("force + 1"), None);
+        assert_eq!(parse_for_prefix(""), None);
+    }
+
+    #[test]
+    fn test_parse_group_check() {
+        assert_eq!(
+            parse_group_check("wits + composure VS 3 <@1> <@!2> <@1>"),
+            Ok((String::from("wits + composure"), 3, vec![UserId(1), UserId(2)]))
+        );
+        assert_eq!(
+            parse_group_check("min(wits, 2) vs 1 <@1>"),
+            Ok((String::from("min(wits, 2)"), 1, vec![UserId(1)]))
+        );
+        for line in &["", "wits 3 <@1>", "vs 3 <@1>", "wits vs <@1>", "wits vs 0 <@1>"] {
+            assert_eq!(parse_group_check(line), Err(String::from(GROUP_CHECK_USAGE)), "{}", line);
+        }
+        assert_eq!(
+            parse_group_check("wits vs 2 @bob"),
+            Err(String::from("`@bob` is not a player mention."))
+        );
+    }
+
+    #[test]
+    fn test_mod_for_str() {
+        assert_eq!(mod_for_str(""), RollModifier::Again10);
+        assert_eq!(mod_for_str("9again"), RollModifier::Again9);
+        assert_eq!(mod_for_str("8again"), RollModifier::Again8);
+        assert_eq!(mod_for_str("no10again"), RollModifier::NoAgain);
+    }
+
+    #[test]
+    fn test_roll_again() {
+        assert!(!roll_again(10, &RollModifier::NoAgain));
+
+        assert!(roll_again(10, &RollModifier::Again10));
+        assert!(!roll_again(9, &RollModifier::Again10));
+        assert!(!roll_again(8, &RollModifier::Again10));
+        assert!(!roll_again(7, &RollModifier::Again10));
+
+        assert!(roll_again(10, &RollModifier::Again9));
+        assert!(roll_again(9, &RollModifier::Again9));
+        assert!(!roll_again(8, &RollModifier::Again9));
+        assert!(!roll_again(7, &RollModifier::Again8));
+
+        assert!(roll_again(10, &RollModifier::Again8));
+        assert!(roll_again(9, &RollModifier::Again8));
+        assert!(roll_again(8, &RollModifier::Again8));
+        assert!(!roll_again(7, &RollModifier::Again8));
+    }
+
+    #[test]
+    fn test_render_rolls() {
+        let rolls = [
+            Roll {
+                val: 10,
+                is_bonus: false,
+                is_rote: false,
+            },
+            Roll {
+                val: 10,
+                is_bonus: true,
+                is_rote: false,
+            },
+            Roll {
+                val: 3,
+                is_bonus: true,
+                is_rote: false,
+            },
+            Roll {
+                val: 1,
+                is_bonus: false,
+                is_rote: false,
+            },
+        ];
+        let mut emoji = BTreeMap::new();
+
+        assert_eq!(render_rolls(&rolls, &emoji), "10, (10), (3), 1");
+
+        emoji.insert(10, String::from("<:crit:1>"));
+        emoji.insert(1, String::from("💀"));
+
+        assert_eq!(render_rolls(&rolls, &emoji), "<:crit:1>, (<:crit:1>), (3), 💀");
+    }
+
+    #[test]
+    fn test_count_successes() {
+        let cs = count_successes(&[Roll {
+            val: 1,
+            is_bonus: false,
+            is_rote: false,
+        }]);
+
+        assert_eq!(cs, "0 successes: ");
+
+        let cs = count_successes(&[Roll {
+            val: 10,
+            is_bonus: false,
+            is_rote: false,
+        }]);
+
+        assert_eq!(cs, "1 success: ");
 
         let cs = count_successes(&[
             Roll {
                 val: 10,
                 is_bonus: false,
+                is_rote: false,
             },
             Roll {
                 val: 8,
                 is_bonus: false,
+                is_rote: false,
             },
         ]);
 
         assert_eq!(cs, "2 successes: ");
     }
 
+    #[test]
+    fn test_summarize_chance() {
+        let rolls = |vals: &[u64]| -> Vec<Roll> {
+            vals.iter()
+                .map(|&val| Roll {
+                    val,
+                    is_bonus: false,
+                    is_rote: false,
+                })
+                .collect()
+        };
+
+        assert_eq!(summarize_chance(&rolls(&[10, 10])), "2 successes");
+        assert_eq!(summarize_chance(&rolls(&[1, 1, 1])), "3 dramatic failures");
+        assert_eq!(summarize_chance(&rolls(&[5, 9])), "2 failures");
+        assert_eq!(
+            summarize_chance(&rolls(&[10, 1, 7])),
+            "1 success, 1 dramatic failure, 1 failure"
+        );
+    }
+
     #[test]
     fn test_roll_attribs() {
         let s = "  strength +  athletics- 1 9again";
         let mut c = Character::new("");
-        let res = roll_attribs(&c, &s);
+        let res = roll_attribs(&c, s, &BTreeMap::new()).unwrap();
 
         assert_eq!(res.pool, -1);
         assert_eq!(res.modifier, RollModifier::Again9);
@@ -369,10 +2074,218 @@ mod test {
 
         c.set_value("strength", 3);
         c.set_value("athletics", 1);
-        let res = roll_attribs(&c, &s);
+        let res = roll_attribs(&c, s, &BTreeMap::new()).unwrap();
+
+        assert_eq!(res.pool, 3);
+        assert_eq!(res.modifier, RollModifier::Again9);
+        assert!(res.attribs_not_found.is_empty());
+    }
+
+    #[test]
+    fn test_roll_attribs_pool_out_of_range() {
+        let mut c = Character::new("");
+        c.set_value("strength", i64::MAX);
+
+        assert_eq!(
+            roll_attribs(&c, "strength + strength wp", &BTreeMap::new())
+                .unwrap_err()
+                .message,
+            format!("a pool of {} dice is out of range; rolls use at most 100", i64::MAX)
+        );
+        assert_eq!(
+            roll_attribs(&c, "- strength - 100", &BTreeMap::new())
+                .unwrap_err()
+                .message,
+            format!("a pool of {} dice is out of range; rolls use at most 100", i64::MIN)
+        );
+        assert_eq!(
+            roll_attribs(&c, "60 + 60", &BTreeMap::new()).unwrap_err().message,
+            "a pool of 120 dice is out of range; rolls use at most 100"
+        );
+        assert_eq!(roll_attribs(&c, "60 + 40", &BTreeMap::new()).unwrap().pool, 100);
+    }
+
+    #[test]
+    fn test_numeric_pool() {
+        assert_eq!(numeric_pool("5", false), Ok((5, 5)));
+        assert_eq!(numeric_pool("5", true), Ok((5, 8)));
+        assert_eq!(numeric_pool("100", false), Ok((100, 100)));
+        assert_eq!(
+            numeric_pool("98", true).unwrap_err().message,
+            "a pool of 101 dice is out of range; rolls use at most 100"
+        );
+        assert_eq!(
+            numeric_pool("1000000000", false).unwrap_err().message,
+            "a pool of 1000000000 dice is out of range; rolls use at most 100"
+        );
+        assert_eq!(
+            numeric_pool("9223372036854775807", true).unwrap_err().message,
+            format!("a pool of {} dice is out of range; rolls use at most 100", i64::MAX)
+        );
+        assert_eq!(
+            numeric_pool("99999999999999999999", false).unwrap_err().message,
+            "`99999999999999999999` is too large"
+        );
+    }
+
+    #[test]
+    fn test_roll_attribs_modifier_precedence() {
+        let mut c = Character::new("");
+        c.set_value("strength", 2);
+        c.set_value("brawl", 2);
+        c.set_value("athletics", 2);
+
+        let res = roll_attribs(&c, "strength + brawl", &BTreeMap::new()).unwrap();
+
+        assert_eq!(res.modifier, RollModifier::Again10);
+        assert_eq!(res.default_modifier, None);
+
+        c.set_modifier("brawl", "9again");
+        c.set_modifier("athletics", "8again");
+        let res = roll_attribs(&c, "strength + brawl", &BTreeMap::new()).unwrap();
+
+        assert_eq!(res.modifier, RollModifier::Again9);
+        assert_eq!(
+            res.default_modifier,
+            Some((String::from("brawl"), String::from("9again")))
+        );
+
+        let res = roll_attribs(&c, "brawl + athletics", &BTreeMap::new()).unwrap();
+
+        assert_eq!(res.modifier, RollModifier::Again8);
+        assert_eq!(
+            res.default_modifier,
+            Some((String::from("athletics"), String::from("8again")))
+        );
+
+        let res = roll_attribs(&c, "brawl + athletics no10again", &BTreeMap::new()).unwrap();
+
+        assert_eq!(res.modifier, RollModifier::NoAgain);
+        assert_eq!(res.default_modifier, None);
+    }
+
+    #[test]
+    fn test_roll_attribs_modifier_ties_use_first_stat() {
+        let mut c = Character::new("");
+        c.set_value("strength", 2);
+        c.set_value("athletics", 2);
+        c.set_value("brawl", 2);
+        c.set_modifier("brawl", "8again");
+        c.set_modifier("athletics", "8again");
+        c.set_modifier("strength", "no10again");
+
+        let res = roll_attribs(&c, "strength + athletics + brawl", &BTreeMap::new()).unwrap();
+
+        assert_eq!(res.modifier, RollModifier::Again8);
+        assert_eq!(
+            res.default_modifier,
+            Some((String::from("athletics"), String::from("8again")))
+        );
+    }
+
+    fn with_bonus_rules() -> Character {
+        let mut c = Character::new("");
+        c.set_value("dexterity", 2);
+        c.set_value("firearms", 1);
+        c.add_bonus_rule(BonusRule {
+            stat: String::from("firearms"),
+            dice: 1,
+            source: String::from("Professional Training"),
+        });
+        c.add_bonus_rule(BonusRule {
+            stat: String::from("Firearms"),
+            dice: 2,
+            source: String::from("Gun Nut"),
+        });
+        c.add_bonus_rule(BonusRule {
+            stat: String::from("brawl"),
+            dice: 1,
+            source: String::from("Street Fighting"),
+        });
+        c
+    }
+
+    fn roll_with_bonus_rules(line: &str) -> AttribRollResult {
+        roll_attribs(&with_bonus_rules(), line, &BTreeMap::new()).unwrap()
+    }
+
+    #[test]
+    fn test_roll_attribs_bonus_rules_stack() {
+        let res = roll_with_bonus_rules("dexterity + FIREARMS");
+
+        assert_eq!(res.pool, 6);
+        assert_eq!(
+            res.bonuses,
+            vec![(String::from("Professional Training"), 1), (String::from("Gun Nut"), 2)]
+        );
+    }
+
+    #[test]
+    fn test_roll_attribs_bonus_rules_need_trigger_stat() {
+        let res = roll_with_bonus_rules("dexterity + 1");
+
+        assert_eq!(res.pool, 3);
+        assert!(res.bonuses.is_empty());
+
+        let res = roll_with_bonus_rules("dexterity + brawl");
 
         assert_eq!(res.pool, 3);
+        assert_eq!(res.bonuses, vec![(String::from("Street Fighting"), 1)]);
+    }
+
+    #[test]
+    fn test_roll_attribs_bonus_rules_opt_out() {
+        let res = roll_with_bonus_rules("dexterity + firearms nobonus 9again");
+
+        assert_eq!(res.pool, 3);
+        assert!(res.bonuses.is_empty());
+        assert!(res.attribs_not_found.is_empty());
+        assert_eq!(res.modifier, RollModifier::Again9);
+    }
+
+    #[test]
+    fn test_roll_attribs_rote() {
+        let res = roll_with_bonus_rules("dexterity + firearms ROTE 9again");
+
+        assert!(res.rote);
         assert_eq!(res.modifier, RollModifier::Again9);
         assert!(res.attribs_not_found.is_empty());
+        assert!(!roll_with_bonus_rules("dexterity + firearms").rote);
+    }
+
+    #[test]
+    fn test_roll_attribs_constants() {
+        let mut constants = BTreeMap::new();
+        constants.insert(String::from("tension"), 2);
+        constants.insert(String::from("firearms"), 5);
+        let res = roll_attribs(&with_bonus_rules(), "firearms + tension", &constants).unwrap();
+
+        // The character's firearms wins over the guild's, and still triggers bonus rules
+        assert_eq!(res.pool, 6);
+        assert_eq!(res.attributes, vec![(String::from("firearms"), 1)]);
+        assert_eq!(res.constants, vec![(String::from("tension"), 2)]);
+        assert!(res.attribs_not_found.is_empty());
+    }
+
+    #[test]
+    fn test_roll_attribs_willpower() {
+        let res = roll_with_bonus_rules("dexterity + firearms wp");
+
+        assert!(res.willpower);
+        assert_eq!(res.pool, 9);
+        assert!(res.attribs_not_found.is_empty());
+        let res = roll_with_bonus_rules("dexterity + firearms Willpower");
+        assert!(res.willpower);
+        assert!(!roll_with_bonus_rules("dexterity + firearms").willpower);
+    }
+
+    #[test]
+    fn test_roll_attribs_bonus_rule_removed() {
+        let mut c = with_bonus_rules();
+        c.remove_bonus_rule(2);
+        let res = roll_attribs(&c, "dexterity + firearms", &BTreeMap::new()).unwrap();
+
+        assert_eq!(res.pool, 4);
+        assert_eq!(res.bonuses, vec![(String::from("Professional Training"), 1)]);
     }
 }
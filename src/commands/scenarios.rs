@@ -0,0 +1,2431 @@
+//! End-to-end scenarios driving the command handlers against a tempdir store.
+
+use serenity::model::id::{ChannelId, UserId};
+use std::{collections::BTreeMap, path::PathBuf};
+use tempdir::TempDir;
+use crate::{
+    commands::{
+        admin::{handle_admin, AdminInput},
+        buff::{handle_buff, handle_scene, BuffInput},
+        condition::{handle_condition, ConditionInput},
+        damage::{handle_damage, DamageInput},
+        delegate::{handle_delegate, DelegateInput},
+        gm::{handle_gm, GmInput},
+        health::{handle_health, HealthInput},
+        merit::{merit_list, merit_text, MeritEntry},
+        roll::{handle_check, handle_roll, verify_fair_roll, CheckInput, RollInput},
+        settings::{handle_settings, SettingsInput},
+        stats::{handle_stats, StatsInput},
+    },
+    util::{
+        aliases::expand_alias,
+        characters::{Character, CharacterStore},
+        fairness::{commitment, parse_seed},
+        outcome::Outcome,
+        respond::Reply,
+        settings::SettingsStore,
+        testing::ScriptedRng,
+        time::parse_timezone,
+    },
+};
+
+/// Discord ID of each user in the scenarios, by name.
+const USERS: &[(&str, u64)] = &[
+    ("Paul", 1),
+    ("Alex", 2),
+    ("Ben", 3),
+    ("Newbie", 4),
+    ("Ada", 10),
+    ("Bo", 11),
+    ("Cy", 12),
+    ("Di", 13),
+];
+
+fn user_id(name: &str) -> u64 {
+    USERS
+        .iter()
+        .find(|(n, _)| *n == name)
+        .map(|&(_, id)| id)
+        .unwrap_or_else(|| panic!("no user named {}", name))
+}
+
+/// The named user's character.
+fn character<'a>(store: &'a CharacterStore, name: &str) -> Option<&'a Character> {
+    store.get(user_id(name), name)
+}
+
+struct Harness {
+    _temp: TempDir,
+    path: PathBuf,
+    store: CharacterStore,
+    settings_path: PathBuf,
+    merits_dir: PathBuf,
+    settings: SettingsStore,
+}
+
+impl Harness {
+    fn new() -> Self {
+        let temp = TempDir::new("dicebot").unwrap();
+        let path = temp.path().join("data.json");
+        let store = CharacterStore::from_file(&path).unwrap();
+        let settings_path = temp.path().join("settings.json");
+        let settings = SettingsStore::from_file(&settings_path).unwrap();
+        let merits_dir = temp.path().join("merits");
+        Harness {
+            _temp: temp,
+            path,
+            store,
+            settings_path,
+            merits_dir,
+            settings,
+        }
+    }
+
+    fn admin(&mut self, is_admin: bool, line: &str, csv: Option<&str>) -> Vec<Reply> {
+        let input = AdminInput {
+            guild_id: Some(1),
+            is_admin,
+            line: line.to_owned(),
+            attachment: csv.map(|c| c.as_bytes().to_vec()),
+            members: vec![(10, String::from("Ada#1234"))],
+        };
+        handle_admin(&input, &mut self.store, &self.path).unwrap()
+    }
+
+    fn settings(&mut self, is_admin: bool, line: &str, image: Option<&[u8]>) -> Vec<Reply> {
+        let input = SettingsInput {
+            user_id: 1,
+            guild_id: Some(1),
+            is_admin,
+            line: line.to_owned(),
+            image: image.map(|i| i.to_vec()),
+        };
+        handle_settings(&input, &mut self.settings, &self.settings_path, &self.merits_dir)
+            .unwrap()
+    }
+
+    fn dice_emoji(&self) -> BTreeMap<u8, String> {
+        self.settings.get(1).map(|g| g.dice_emoji.clone()).unwrap_or_default()
+    }
+
+    fn outcome_markers(&self) -> Option<BTreeMap<Outcome, String>> {
+        self.settings
+            .get(1)
+            .filter(|g| g.outcome_markers)
+            .map(|g| g.outcome_marker_symbols.clone())
+    }
+
+    fn constants(&self) -> BTreeMap<String, i64> {
+        self.settings.get(1).map(|g| g.constants.clone()).unwrap_or_default()
+    }
+
+    fn roll(&mut self, user_name: &str, line: &str, faces: &[u64]) -> Vec<Reply> {
+        let input = RollInput {
+            user_id: UserId(user_id(user_name)),
+            user_name: user_name.to_owned(),
+            line: line.to_owned(),
+            mentions: vec![],
+            now: 0,
+            weekly_beat: None,
+            timezone: None,
+            dice_emoji: self.dice_emoji(),
+            outcome_markers: self.outcome_markers(),
+            constants: self.constants(),
+        };
+        handle_roll(&input, &mut self.store, &self.path, &mut ScriptedRng::new(faces)).unwrap()
+    }
+
+    fn roll_for(
+        &mut self,
+        roller: u64,
+        owner: (u64, &str),
+        line: &str,
+        now: u64,
+        faces: &[u64],
+    ) -> Vec<Reply> {
+        let input = RollInput {
+            user_id: UserId(roller),
+            user_name: String::from("Roller"),
+            line: format!("for <@{}> {}", owner.0, line),
+            mentions: vec![(UserId(owner.0), owner.1.to_owned())],
+            now,
+            weekly_beat: None,
+            timezone: None,
+            dice_emoji: self.dice_emoji(),
+            outcome_markers: self.outcome_markers(),
+            constants: self.constants(),
+        };
+        handle_roll(&input, &mut self.store, &self.path, &mut ScriptedRng::new(faces)).unwrap()
+    }
+
+    fn roll_weekly(&mut self, user_name: &str, line: &str, now: u64, faces: &[u64]) -> Vec<Reply> {
+        let input = RollInput {
+            user_id: UserId(user_id(user_name)),
+            user_name: user_name.to_owned(),
+            line: line.to_owned(),
+            mentions: vec![],
+            now,
+            weekly_beat: self.settings.get(1).and_then(|g| g.weekly_beat.clone()),
+            timezone: self
+                .settings
+                .user(user_id(user_name))
+                .and_then(|u| u.timezone.as_ref())
+                .and_then(|tz| parse_timezone(tz).ok()),
+            dice_emoji: self.dice_emoji(),
+            outcome_markers: self.outcome_markers(),
+            constants: self.constants(),
+        };
+        handle_roll(&input, &mut self.store, &self.path, &mut ScriptedRng::new(faces)).unwrap()
+    }
+
+    fn check(&self, user_name: &str, line: &str) -> Vec<Reply> {
+        let input = CheckInput {
+            user_id: UserId(user_id(user_name)),
+            user_name: user_name.to_owned(),
+            line: line.to_owned(),
+            constants: self.constants(),
+        };
+        handle_check(&input, &self.store)
+    }
+
+    fn gm(&self, is_admin: bool, line: &str, players: &[(u64, &str)], faces: &[u64]) -> Vec<Reply> {
+        let input = GmInput {
+            guild_id: Some(1),
+            is_admin,
+            line: line.to_owned(),
+            mentions: players
+                .iter()
+                .map(|&(id, name)| (UserId(id), name.to_owned()))
+                .collect(),
+            constants: self.constants(),
+        };
+        handle_gm(&input, &self.store, &mut ScriptedRng::new(faces))
+    }
+
+    fn delegate(&mut self, user_id: u64, line: &str, mentions: &[u64], now: u64) -> Vec<Reply> {
+        let input = DelegateInput {
+            user_id: UserId(user_id),
+            line: line.to_owned(),
+            mentions: mentions.iter().map(|&id| UserId(id)).collect(),
+            now,
+        };
+        handle_delegate(&input, &mut self.store, &self.path).unwrap()
+    }
+
+    fn stats(&mut self, user_name: &str, line: &str) -> Vec<Reply> {
+        self.stats_as(user_id(user_name), user_name, line)
+    }
+
+    fn stats_as(&mut self, user_id: u64, user_name: &str, line: &str) -> Vec<Reply> {
+        let input = StatsInput {
+            user_id: UserId(user_id),
+            user_name: user_name.to_owned(),
+            line: line.to_owned(),
+        };
+        handle_stats(&input, &mut self.store, &self.path).unwrap()
+    }
+
+    fn buff(&mut self, user_name: &str, line: &str) -> Vec<Reply> {
+        let input = BuffInput {
+            user_id: UserId(user_id(user_name)),
+            user_name: user_name.to_owned(),
+            channel_id: ChannelId(100),
+            line: line.to_owned(),
+        };
+        handle_buff(&input, &mut self.store, &self.path).unwrap()
+    }
+
+    fn scene(&mut self, user_name: &str, line: &str) -> Vec<Reply> {
+        self.scene_in(100, user_name, line)
+    }
+
+    fn scene_in(&mut self, channel_id: u64, user_name: &str, line: &str) -> Vec<Reply> {
+        let input = BuffInput {
+            user_id: UserId(user_id(user_name)),
+            user_name: user_name.to_owned(),
+            channel_id: ChannelId(channel_id),
+            line: line.to_owned(),
+        };
+        handle_scene(&input, &mut self.store, &self.path).unwrap()
+    }
+
+    fn condition_in(&mut self, channel_id: u64, user_name: &str, line: &str) -> Vec<Reply> {
+        let input = ConditionInput {
+            user_id: UserId(user_id(user_name)),
+            user_name: user_name.to_owned(),
+            channel_id: ChannelId(channel_id),
+            line: line.to_owned(),
+        };
+        handle_condition(&input, &mut self.store, &self.path).unwrap()
+    }
+
+    fn damage(&mut self, user_name: &str, line: &str) -> Vec<Reply> {
+        let input = DamageInput {
+            user_id: UserId(user_id(user_name)),
+            user_name: user_name.to_owned(),
+            line: line.to_owned(),
+        };
+        handle_damage(&input, &mut self.store, &self.path).unwrap()
+    }
+
+    fn health(&mut self, user_name: &str, line: &str) -> Vec<Reply> {
+        let input = HealthInput {
+            user_id: UserId(user_id(user_name)),
+            user_name: user_name.to_owned(),
+            line: line.to_owned(),
+        };
+        handle_health(&input, &mut self.store, &self.path).unwrap()
+    }
+
+    fn saved(&self) -> CharacterStore {
+        CharacterStore::from_file(&self.path).unwrap()
+    }
+}
+
+fn says(text: &str) -> Vec<Reply> {
+    vec![Reply::Say(text.to_owned())]
+}
+
+fn refuses(text: &str) -> Vec<Reply> {
+    vec![Reply::Refuse(text.to_owned())]
+}
+
+#[test]
+fn roll_without_args_replies_nothing() {
+    let mut h = Harness::new();
+
+    assert!(h.roll("Paul", "", &[]).is_empty());
+    assert!(h.roll("Paul", "   ", &[]).is_empty());
+}
+
+#[test]
+fn roll_numeric_no_successes() {
+    let mut h = Harness::new();
+
+    assert_eq!(
+        h.roll("Paul", "3", &[2, 5, 7]),
+        says("<@1> rolled 3 dice and got 0 successes: 2, 5, 7")
+    );
+}
+
+#[test]
+fn roll_numeric_some_successes() {
+    let mut h = Harness::new();
+
+    assert_eq!(
+        h.roll("Paul", "3", &[8, 9, 1]),
+        says("<@1> rolled 3 dice and got 2 successes: 8, 9, 1")
+    );
+}
+
+#[test]
+fn roll_numeric_ten_again_adds_bonus_die() {
+    let mut h = Harness::new();
+
+    assert_eq!(
+        h.roll("Paul", "1", &[10, 4]),
+        says("<@1> rolled 1 die and got 1 success: 10, (4)")
+    );
+}
+
+#[test]
+fn roll_numeric_chained_bonus_dice() {
+    let mut h = Harness::new();
+
+    assert_eq!(
+        h.roll("Paul", "1", &[10, 10, 3]),
+        says("<@1> rolled 1 die and got 2 successes: 10, (10), (3)")
+    );
+}
+
+#[test]
+fn roll_numeric_nine_again() {
+    let mut h = Harness::new();
+
+    assert_eq!(
+        h.roll("Paul", "2 9again", &[9, 3, 5]),
+        says("<@1> rolled 2 dice and got 1 success: 9, (3), 5")
+    );
+}
+
+#[test]
+fn roll_numeric_eight_again() {
+    let mut h = Harness::new();
+
+    assert_eq!(
+        h.roll("Paul", "2 8again", &[8, 9, 2, 1]),
+        says("<@1> rolled 2 dice and got 2 successes: 8, (9), (2), 1")
+    );
+}
+
+#[test]
+fn roll_numeric_no_ten_again() {
+    let mut h = Harness::new();
+
+    assert_eq!(
+        h.roll("Paul", "1 no10again", &[10]),
+        says("<@1> rolled 1 die and got 1 success: 10")
+    );
+}
+
+#[test]
+fn roll_numeric_trims_whitespace() {
+    let mut h = Harness::new();
+
+    assert_eq!(
+        h.roll("Paul", "  2  ", &[1, 2]),
+        says("<@1> rolled 2 dice and got 0 successes: 1, 2")
+    );
+}
+
+#[test]
+fn roll_chance_success() {
+    let mut h = Harness::new();
+
+    assert_eq!(
+        h.roll("Paul", "chance", &[10]),
+        says("<@1> rolled a chance die and succeeded!")
+    );
+}
+
+#[test]
+fn roll_chance_failure() {
+    let mut h = Harness::new();
+
+    assert_eq!(
+        h.roll("Paul", "chance", &[7]),
+        says("<@1> rolled a chance die and failed: 7")
+    );
+}
+
+#[test]
+fn roll_chance_dramatic_failure() {
+    let mut h = Harness::new();
+
+    assert_eq!(
+        h.roll("Paul", "chance", &[1]),
+        says("<@1> rolled a chance die and DRAMATICALLY failed!")
+    );
+}
+
+#[test]
+fn roll_chance_ignores_again_modifier() {
+    let mut h = Harness::new();
+
+    assert_eq!(
+        h.roll("Paul", "chance 9again", &[9]),
+        says("<@1> rolled a chance die and failed: 9")
+    );
+}
+
+#[test]
+fn roll_single_attribute() {
+    let mut h = Harness::new();
+    h.stats("Paul", "edit strength 3");
+
+    assert_eq!(
+        h.roll("Paul", "strength", &[8, 2, 10, 5]),
+        says("<@1> rolled 3 dice [strength = 3] and got 2 successes: 8, 2, 10, (5)")
+    );
+}
+
+#[test]
+fn roll_attribute_plus_literal() {
+    let mut h = Harness::new();
+    h.stats("Paul", "edit strength 3");
+
+    assert_eq!(
+        h.roll("Paul", "strength + 1", &[1, 2, 3, 4]),
+        says("<@1> rolled 4 dice [strength = 3] and got 0 successes: 1, 2, 3, 4")
+    );
+}
+
+#[test]
+fn roll_attribute_minus_literal() {
+    let mut h = Harness::new();
+    h.stats("Paul", "edit strength 3");
+
+    assert_eq!(
+        h.roll("Paul", "strength-1", &[8, 8]),
+        says("<@1> rolled 2 dice [strength = 3] and got 2 successes: 8, 8")
+    );
+}
+
+#[test]
+fn roll_two_attributes_with_modifier() {
+    let mut h = Harness::new();
+    h.stats("Paul", "bulk strength=2 athletics=1");
+
+    assert_eq!(
+        h.roll("Paul", "strength + athletics 9again", &[9, 1, 2, 3]),
+        says("<@1> rolled 3 dice [strength = 2, athletics = 1] and got 1 success: 9, (1), 2, 3")
+    );
+}
+
+#[test]
+fn roll_attribute_lookup_is_case_insensitive() {
+    let mut h = Harness::new();
+    h.stats("Paul", "edit strength 1");
+
+    assert_eq!(
+        h.roll("Paul", "Strength", &[4]),
+        says("<@1> rolled 1 die [Strength = 1] and got 0 successes: 4")
+    );
+}
+
+#[test]
+fn roll_warns_about_missing_attributes() {
+    let mut h = Harness::new();
+    h.stats("Paul", "edit strength 2");
+
+    assert_eq!(
+        h.roll("Paul", "strength + athletcs", &[3, 8]),
+        says(
+            "<@1> rolled 2 dice [strength = 2] and got 1 success: 3, 8\n\n\
+             Warning: this attribute was not found and defaulted to 0: athletcs"
+        )
+    );
+}
+
+#[test]
+fn roll_with_parentheses_and_functions() {
+    let mut h = Harness::new();
+    h.stats("Paul", "bulk wits=3 dexterity=2 athletics=1");
+
+    assert_eq!(
+        h.roll("Paul", "min(wits, dexterity) + (athletics - 1) 9again", &[9, 1, 4]),
+        says(
+            "<@1> rolled 2 dice [wits = 3, dexterity = 2, athletics = 1] \
+             and got 1 success: 9, (1), 4"
+        )
+    );
+}
+
+#[test]
+fn roll_min_one_floors_pool_at_one_die() {
+    let mut h = Harness::new();
+    h.stats("Paul", "bulk wits=2 occult=1");
+
+    assert_eq!(
+        h.roll("Paul", "wits - 2", &[8]),
+        says(
+            "<@1> rolled 0 dice [wits = 2], pool reduced below 1, rolling a chance die instead, \
+             and failed: 8"
+        )
+    );
+    assert_eq!(
+        h.roll("Paul", "wits - 2 min1", &[8]),
+        says("<@1> rolled 0 dice [wits = 2], raised to 1 die by min1 and got 1 success: 8")
+    );
+    assert_eq!(
+        h.roll("Paul", "occult - 4", &[10]),
+        says(
+            "<@1> rolled -3 dice [occult = 1], pool reduced below 1, rolling a chance die instead, \
+             and succeeded!"
+        )
+    );
+    assert_eq!(
+        h.roll("Paul", "min1 occult - 4 9again", &[9, 3]),
+        says(
+            "<@1> rolled -3 dice [occult = 1], raised to 1 die by min1 \
+             and got 1 success: 9, (3)"
+        )
+    );
+    assert_eq!(
+        h.roll("Paul", "wits - 1", &[4]),
+        says("<@1> rolled 1 die [wits = 2] and got 0 successes: 4")
+    );
+    assert_eq!(
+        h.roll("Paul", "wits - 1 min1", &[4]),
+        says("<@1> rolled 1 die [wits = 2] and got 0 successes: 4")
+    );
+    assert_eq!(
+        h.roll("Paul", "0 min1", &[10, 2]),
+        says("<@1> rolled 0 dice, raised to 1 die by min1 and got 1 success: 10, (2)")
+    );
+    assert_eq!(
+        h.check("Paul", "occult - 4 min1"),
+        says("<@1> would roll -3 dice [occult = 1], raised to 1 die by min1")
+    );
+}
+
+#[test]
+fn roll_rote_rerolls_failed_dice_once() {
+    let mut h = Harness::new();
+    h.stats("Paul", "bulk strength=2 athletics=1");
+
+    assert_eq!(
+        h.roll("Paul", "3 rote", &[2, 8, 9, 5, 1]),
+        says("<@1> rolled 3 dice as a rote and got 2 successes: 2, [8], 9, 5, [1]")
+    );
+    assert_eq!(
+        h.roll("Paul", "strength + athletics rote 9again", &[4, 9, 3, 7, 8, 10, 2]),
+        says(
+            "<@1> rolled 3 dice [strength = 2, athletics = 1] as a rote \
+             and got 3 successes: 4, [9], (3), 7, [8], 10, (2)"
+        )
+    );
+    assert_eq!(
+        h.check("Paul", "strength + athletics rote"),
+        says("<@1> would roll 3 dice [strength = 2, athletics = 1] as a rote")
+    );
+}
+
+#[test]
+fn roll_willpower_adds_three_dice() {
+    let mut h = Harness::new();
+    h.stats("Paul", "bulk strength=3 athletics=2");
+
+    assert_eq!(
+        h.roll("Paul", "strength + athletics wp", &[1, 2, 3, 4, 5, 6, 7, 8]),
+        says(
+            "<@1> rolled 8 dice [strength = 3, athletics = 2, willpower = +3] \
+             and got 1 success: 1, 2, 3, 4, 5, 6, 7, 8"
+        )
+    );
+    assert_eq!(
+        h.roll("Paul", "4 willpower", &[1, 2, 3, 4, 5, 6, 8]),
+        says("<@1> rolled 7 dice (willpower spent) and got 1 success: 1, 2, 3, 4, 5, 6, 8")
+    );
+}
+
+#[test]
+fn reduced_pools_roll_a_chance_die() {
+    let mut h = Harness::new();
+    h.stats("Paul", "bulk strength=2 wits=3");
+
+    assert_eq!(
+        h.roll("Paul", "strength - 2", &[1]),
+        says(
+            "<@1> rolled 0 dice [strength = 2], pool reduced below 1, rolling a chance die \
+             instead, and DRAMATICALLY failed!"
+        )
+    );
+    assert_eq!(
+        h.roll("Paul", "strength - 5 9again", &[9]),
+        says(
+            "<@1> rolled -3 dice [strength = 2], pool reduced below 1, rolling a chance die \
+             instead, and failed: 9"
+        )
+    );
+    assert_eq!(
+        h.roll("Paul", "wits - 2", &[10, 3]),
+        says("<@1> rolled 1 die [wits = 3] and got 1 success: 10, (3)")
+    );
+}
+
+#[test]
+fn fair_rolls_can_be_verified() {
+    let mut h = Harness::new();
+    h.stats("Paul", "bulk wits=3 brawl=2");
+    // Each seed is drawn from four scripted faces
+    let seeds: [&[u64]; 3] = [&[1, 2, 3, 4], &[10, 9, 8, 7], &[5, 5, 5, 5]];
+    let lines = ["5 fair", "fair 3 9again", "chance 2 fair", "wits + brawl 8again fair"];
+
+    for faces in &seeds {
+        for line in &lines {
+            let (committed, text) = match h.roll("Paul", line, faces).as_slice() {
+                [Reply::Say(committed), Reply::Say(text)] => (committed.clone(), text.clone()),
+                other => panic!("{}: {:?}", line, other),
+            };
+            let check = text.rsplit("verify ").next().unwrap().trim_end_matches('`');
+            let mut parts = check.splitn(2, ' ');
+            let (seed, spec) = (parts.next().unwrap(), parts.next().unwrap());
+            let dice = verify_fair_roll(seed, spec).unwrap();
+
+            assert!(text.contains(&format!("Seed: `{}`", seed)), "{}", text);
+            assert!(committed.contains(&commitment(&parse_seed(seed).unwrap())));
+            // An exceptional success is noted between the dice and the seed
+            let shown = text.replace(" - exceptional success!", "");
+            assert!(shown.contains(&format!(": {}\n\nSeed", dice)), "{}: {}", dice, text);
+        }
+    }
+}
+
+#[test]
+fn fair_roll_specs() {
+    let mut h = Harness::new();
+    h.stats("Paul", "bulk wits=3 brawl=2");
+    let faces = [1, 2, 3, 4];
+
+    for (line, spec) in &[
+        ("5 fair", "5 10again"),
+        ("3 9again fair", "3 9again"),
+        ("4 no10again fair", "4 no10again"),
+        ("chance fair", "chance 1"),
+        ("wits + brawl fair", "5 10again"),
+    ] {
+        let text = match h.roll("Paul", line, &faces).as_slice() {
+            [_, Reply::Say(text)] => text.clone(),
+            other => panic!("{}: {:?}", line, other),
+        };
+        assert!(text.ends_with(&format!(" {}`", spec)), "{}: {}", line, text);
+    }
+}
+
+#[test]
+fn roll_reports_parse_errors() {
+    let mut h = Harness::new();
+
+    assert_eq!(
+        h.roll("Paul", "wits + )", &[]),
+        refuses(
+            "<@1> could not read that roll: \
+             ```\nunexpected `)` at position 8\nwits + )\n       ^\n```"
+        )
+    );
+    assert_eq!(
+        h.roll("Paul", "1000 wp", &[]),
+        refuses(
+            "<@1> could not read that roll: ```\na pool of 1003 dice is out of range; \
+             rolls use at most 100 at position 1\n1000 wp\n^\n```"
+        )
+    );
+}
+
+#[test]
+fn roll_uses_only_own_character() {
+    let mut h = Harness::new();
+    h.stats("Paul", "edit wits 2");
+
+    assert_eq!(
+        h.roll("Alex", "wits + 1", &[6]),
+        says(
+            "<@2> rolled 1 die [] and got 0 successes: 6\n\n\
+             Warning: this attribute was not found and defaulted to 0: wits"
+        )
+    );
+}
+
+#[test]
+fn stats_without_args_replies_nothing() {
+    let mut h = Harness::new();
+
+    assert!(h.stats("Paul", "").is_empty());
+}
+
+#[test]
+fn stats_unknown_subcommand_replies_nothing() {
+    let mut h = Harness::new();
+
+    assert!(h.stats("Paul", "frobnicate").is_empty());
+}
+
+#[test]
+fn stats_edit_saves_and_acks() {
+    let mut h = Harness::new();
+
+    assert_eq!(h.stats("Paul", "edit Strength 3"), vec![Reply::Ack]);
+    assert_eq!(character(&h.saved(), "Paul").unwrap().get_value("strength"), (true, 3));
+}
+
+#[test]
+fn stats_edit_overwrites() {
+    let mut h = Harness::new();
+    h.stats("Paul", "edit strength 3");
+    h.stats("Paul", "edit strength 4");
+
+    assert_eq!(character(&h.saved(), "Paul").unwrap().get_value("strength"), (true, 4));
+}
+
+#[test]
+fn stats_edit_missing_value() {
+    let mut h = Harness::new();
+
+    assert_eq!(
+        h.stats("Paul", "edit strength"),
+        refuses("`!stats edit <stat_name> <stat_value>`")
+    );
+    assert!(character(&h.saved(), "Paul").is_none());
+}
+
+#[test]
+fn stats_edit_too_many_args() {
+    let mut h = Harness::new();
+
+    assert_eq!(
+        h.stats("Paul", "edit strength 3 4"),
+        refuses("`!stats edit <stat_name> <stat_value>`")
+    );
+}
+
+#[test]
+fn stats_edit_non_numeric_value() {
+    let mut h = Harness::new();
+
+    assert_eq!(
+        h.stats("Paul", "edit strength lots"),
+        refuses("`lots` is not a stat value; use a number like 3, dots like ••• (or *** or ooo), a word like three, or 3 dots")
+    );
+    assert_eq!(
+        h.stats("Paul", "edit strength ••x"),
+        refuses("`••x` is not a stat value; use a number like 3, dots like ••• (or *** or ooo), a word like three, or 3 dots")
+    );
+    assert_eq!(
+        h.stats("Paul", "edit strength •••••••••••"),
+        refuses("`•••••••••••` is more than 10 dots")
+    );
+    assert!(character(&h.saved(), "Paul").is_none());
+}
+
+#[test]
+fn stats_values_as_dots_and_words() {
+    let mut h = Harness::new();
+
+    assert_eq!(h.stats("Paul", "edit brawl ••"), vec![Reply::Ack]);
+    assert_eq!(h.stats("Paul", "edit strength three"), vec![Reply::Ack]);
+    assert_eq!(h.stats("Paul", "bulk wits=*** occult=oo"), vec![Reply::Ack]);
+    assert_eq!(
+        h.stats("Paul", "import\n```\nresolve 3 dots\nmedicine one\n```"),
+        says("Imported 2 stats")
+    );
+
+    let saved = h.saved();
+    let character = character(&saved, "Paul").unwrap();
+    assert_eq!(character.get_value("brawl"), (true, 2));
+    assert_eq!(character.get_value("strength"), (true, 3));
+    assert_eq!(character.get_value("wits"), (true, 3));
+    assert_eq!(character.get_value("occult"), (true, 2));
+    assert_eq!(character.get_value("resolve"), (true, 3));
+    assert_eq!(character.get_value("medicine"), (true, 1));
+}
+
+#[test]
+fn stats_bulk_saves_and_acks() {
+    let mut h = Harness::new();
+
+    assert_eq!(h.stats("Paul", "bulk strength=3 wits=2"), vec![Reply::Ack]);
+
+    let saved = h.saved();
+    let character = character(&saved, "Paul").unwrap();
+
+    assert_eq!(character.get_value("strength"), (true, 3));
+    assert_eq!(character.get_value("wits"), (true, 2));
+}
+
+#[test]
+fn stats_bulk_without_pairs_replies_nothing() {
+    let mut h = Harness::new();
+
+    assert!(h.stats("Paul", "bulk").is_empty());
+}
+
+#[test]
+fn stats_bulk_bad_form_saves_nothing() {
+    let mut h = Harness::new();
+
+    assert_eq!(
+        h.stats("Paul", "bulk strength=3 wits"),
+        refuses("Bulk-editing stats is done in the form: name1=value1 name2=value2 name3=value3 ...")
+    );
+    assert!(character(&h.saved(), "Paul").is_none());
+}
+
+#[test]
+fn stats_bulk_non_numeric_value() {
+    let mut h = Harness::new();
+
+    assert_eq!(
+        h.stats("Paul", "bulk strength=x"),
+        refuses("`x` is not a stat value; use a number like 3, dots like ••• (or *** or ooo), a word like three, or 3 dots")
+    );
+}
+
+#[test]
+fn stats_print_empty_sheet() {
+    let mut h = Harness::new();
+
+    assert_eq!(h.stats("Paul", "print"), says("```\nNo stats info\n```"));
+}
+
+#[test]
+fn stats_show_lists_stats() {
+    let mut h = Harness::new();
+    h.stats("Paul", "bulk strength=3 wits=2");
+
+    let replies = h.stats("Paul", "show");
+
+    assert_eq!(replies.len(), 1);
+    match &replies[0] {
+        Reply::Say(text) => {
+            assert!(text.starts_with("```\n"));
+            assert!(text.contains("strength"));
+            assert!(text.contains("wits"));
+        }
+        other => panic!("Unexpected reply: {:?}", other),
+    }
+}
+
+#[test]
+fn stats_are_per_user() {
+    let mut h = Harness::new();
+    h.stats("Paul", "edit strength 3");
+    h.stats("Alex", "edit strength 1");
+
+    let saved = h.saved();
+
+    assert_eq!(character(&saved, "Paul").unwrap().get_value("strength"), (true, 3));
+    assert_eq!(character(&saved, "Alex").unwrap().get_value("strength"), (true, 1));
+}
+
+#[test]
+fn stats_import_applies_all_lines() {
+    let mut h = Harness::new();
+
+    assert_eq!(
+        h.stats("Paul", "import\n```\nStrength: 3\nbrawl ••\nwits=2\n```"),
+        says("Imported 3 stats")
+    );
+
+    let saved = h.saved();
+    let character = character(&saved, "Paul").unwrap();
+
+    assert_eq!(character.get_value("strength"), (true, 3));
+    assert_eq!(character.get_value("brawl"), (true, 2));
+    assert_eq!(character.get_value("wits"), (true, 2));
+}
+
+#[test]
+fn stats_import_with_errors_applies_nothing() {
+    let mut h = Harness::new();
+
+    assert_eq!(
+        h.stats("Paul", "import\n```\nStrength: 3\nbrawl: lots\n```"),
+        refuses(
+            "Nothing was imported:\nline 2: `lots` is not a stat value; use a number like 3, \
+             dots like ••• (or *** or ooo), a word like three, or 3 dots"
+        )
+    );
+    assert!(character(&h.saved(), "Paul").is_none());
+}
+
+#[test]
+fn stats_import_without_code_block() {
+    let mut h = Harness::new();
+
+    assert_eq!(
+        h.stats("Paul", "import strength: 3"),
+        refuses("Put the stats to import in a code block after `!stats import`, one `name: value` per line")
+    );
+}
+
+#[test]
+fn delegate_then_roll_for_owner() {
+    let mut h = Harness::new();
+    h.stats_as(10, "Paul", "edit strength 2");
+
+    assert_eq!(
+        h.delegate(10, "<@20>", &[20], 0),
+        says(
+            "<@20> can roll for <@10> with `!roll for @user <dice>` until <t:86400:f> \
+             (<t:86400:R>), or until `!delegate revoke`."
+        )
+    );
+    assert_eq!(
+        h.roll_for(20, (10, "Paul"), "strength", 60, &[8, 3]),
+        says("<@20> (for Paul) rolled 2 dice [strength = 2] and got 1 success: 8, 3")
+    );
+}
+
+#[test]
+fn roll_for_without_delegation() {
+    let mut h = Harness::new();
+
+    assert_eq!(
+        h.roll_for(20, (10, "Paul"), "strength", 0, &[]),
+        refuses("Paul has not delegated their rolls to you. They can use `!delegate @you`.")
+    );
+}
+
+#[test]
+fn roll_for_after_expiry() {
+    let mut h = Harness::new();
+    h.delegate(10, "<@20>", &[20], 0);
+
+    assert_eq!(h.roll_for(20, (10, "Paul"), "1", 86_399, &[4]).len(), 1);
+    assert_eq!(
+        h.roll_for(20, (10, "Paul"), "1", 86_400, &[]),
+        refuses("Paul has not delegated their rolls to you. They can use `!delegate @you`.")
+    );
+}
+
+#[test]
+fn roll_for_after_revoke() {
+    let mut h = Harness::new();
+    h.delegate(10, "<@20>", &[20], 0);
+
+    assert_eq!(h.delegate(10, "revoke", &[], 10), says("Delegation revoked."));
+    assert_eq!(h.delegate(10, "revoke", &[], 10), says("You have no active delegation."));
+    assert_eq!(
+        h.roll_for(20, (10, "Paul"), "1", 20, &[]),
+        refuses("Paul has not delegated their rolls to you. They can use `!delegate @you`.")
+    );
+}
+
+#[test]
+fn delegate_requires_one_other_user() {
+    let mut h = Harness::new();
+
+    assert_eq!(h.delegate(10, "", &[], 0), refuses("`!delegate @user` or `!delegate revoke`"));
+    assert_eq!(
+        h.delegate(10, "<@20> <@30>", &[20, 30], 0),
+        refuses("`!delegate @user` or `!delegate revoke`")
+    );
+    assert_eq!(h.delegate(10, "<@10>", &[10], 0), refuses("You can already roll for yourself."));
+}
+
+#[test]
+fn stats_modifier_applies_to_rolls() {
+    let mut h = Harness::new();
+    h.stats("Paul", "bulk strength=1 brawl=1");
+
+    assert_eq!(h.stats("Paul", "modifier Brawl 8AGAIN"), vec![Reply::Ack]);
+    assert_eq!(
+        h.roll("Paul", "strength + brawl", &[8, 2, 3]),
+        says("<@1> rolled 2 dice [strength = 1, brawl = 1] with 8again from brawl and got 1 success: 8, (2), 3")
+    );
+    assert_eq!(
+        h.roll("Paul", "strength + brawl no10again", &[8, 3]),
+        says("<@1> rolled 2 dice [strength = 1, brawl = 1] and got 1 success: 8, 3")
+    );
+    assert_eq!(
+        h.roll("Paul", "strength", &[8]),
+        says("<@1> rolled 1 die [strength = 1] and got 1 success: 8")
+    );
+}
+
+#[test]
+fn stats_modifier_clear() {
+    let mut h = Harness::new();
+    h.stats("Paul", "modifier brawl 9again");
+
+    assert_eq!(h.stats("Paul", "modifier brawl clear"), says("Default modifier cleared."));
+    assert_eq!(
+        h.stats("Paul", "modifier brawl clear"),
+        says("That stat has no default modifier.")
+    );
+    assert_eq!(character(&h.saved(), "Paul").unwrap().get_modifier("brawl"), None);
+}
+
+#[test]
+fn stats_modifier_rejects_unknown() {
+    let mut h = Harness::new();
+
+    assert_eq!(
+        h.stats("Paul", "modifier brawl 7again"),
+        refuses("The modifier must be one of: 9again, 8again, no10again")
+    );
+    assert_eq!(
+        h.stats("Paul", "modifier brawl"),
+        refuses("`!stats modifier <stat_name> <9again|8again|no10again|clear>`")
+    );
+}
+
+#[test]
+fn roll_ephemeral() {
+    let mut h = Harness::new();
+
+    assert_eq!(
+        h.roll("Paul", "2 ephemeral", &[8, 1]),
+        vec![Reply::Ephemeral(String::from("<@1> rolled 2 dice and got 1 success: 8, 1"))]
+    );
+    assert_eq!(
+        h.roll("Paul", "ephemeral chance", &[3]),
+        vec![Reply::Ephemeral(String::from("<@1> rolled a chance die and failed: 3"))]
+    );
+}
+
+#[test]
+fn settings_merit_add_and_remove() {
+    let mut h = Harness::new();
+
+    assert_eq!(
+        h.settings(true, r#"merit add "Ancestral Blade" Fighting Your family sword"#, None),
+        vec![Reply::Ack]
+    );
+
+    let saved = SettingsStore::from_file(&h.settings_path).unwrap();
+    let merit = &saved.get(1).unwrap().merits[0];
+
+    assert_eq!(merit.name, "Ancestral Blade");
+    assert_eq!(merit.category, "fighting");
+    assert_eq!(merit.description, "Your family sword");
+
+    assert_eq!(h.settings(true, r#"merit remove "ancestral blade""#, None), vec![Reply::Ack]);
+    assert_eq!(
+        h.settings(true, r#"merit remove "ancestral blade""#, None),
+        refuses("There is no custom merit by that name.")
+    );
+}
+
+#[test]
+fn settings_merit_image_saved_under_guild() {
+    let mut h = Harness::new();
+    let image = b"\x89PNG\r\n\x1a\nimage data";
+
+    assert_eq!(
+        h.settings(true, r#"merit add "Ancestral Blade" fighting"#, Some(image)),
+        vec![Reply::Ack]
+    );
+    assert!(h.merits_dir.join("1").join("ancestral_blade.png").exists());
+
+    h.settings(true, r#"merit remove "Ancestral Blade""#, None);
+
+    assert!(!h.merits_dir.join("1").join("ancestral_blade.png").exists());
+}
+
+#[test]
+fn settings_merit_image_names_stay_in_guild_dir() {
+    let mut h = Harness::new();
+    let image = b"\x89PNG\r\n\x1a\nimage data";
+
+    assert_eq!(
+        h.settings(true, r#"merit add "../../giant" fighting"#, Some(image)),
+        vec![Reply::Ack]
+    );
+    assert!(h.merits_dir.join("1").join("giant.png").exists());
+    assert!(!h.merits_dir.join("giant.png").exists());
+    assert!(!h.merits_dir.parent().unwrap().join("giant.png").exists());
+
+    h.settings(true, r#"merit remove "../../giant""#, None);
+
+    assert!(!h.merits_dir.join("1").join("giant.png").exists());
+    assert_eq!(
+        h.settings(true, r#"merit add "../.." fighting"#, Some(image)),
+        refuses("Merit names need at least one letter or number.")
+    );
+}
+
+#[test]
+fn settings_merit_rejects_non_png() {
+    let mut h = Harness::new();
+
+    assert_eq!(
+        h.settings(true, r#"merit add "Ancestral Blade" fighting"#, Some(b"GIF89a")),
+        refuses("That attachment is not a valid PNG image.")
+    );
+    assert!(SettingsStore::from_file(&h.settings_path).unwrap().get(1).is_none());
+}
+
+#[test]
+fn settings_require_admin() {
+    let mut h = Harness::new();
+
+    assert_eq!(
+        h.settings(false, r#"merit add "Ancestral Blade" fighting"#, None),
+        refuses("You need the Manage Server permission to change settings.")
+    );
+}
+
+#[test]
+fn settings_weekly_beat() {
+    let mut h = Harness::new();
+
+    assert_eq!(
+        h.settings(true, "weeklybeat on sun -5", None),
+        says(
+            "Each player's first roll of the week now earns a beat. \
+             Weeks start on Sunday at midnight UTC-05:00."
+        )
+    );
+    assert_eq!(
+        h.settings(true, "weeklybeat on someday", None),
+        refuses("`someday` is not a day of the week.")
+    );
+    assert_eq!(h.settings(true, "weeklybeat off", None), vec![Reply::Ack]);
+    assert!(h.settings.get(1).unwrap().weekly_beat.is_none());
+}
+
+#[test]
+fn weekly_beat_awarded_once_per_week() {
+    // Monday 2019-06-03 00:00:00 UTC
+    let monday = 1_559_520_000;
+    let mut h = Harness::new();
+    h.settings(true, "weeklybeat on monday", None);
+
+    assert_eq!(
+        h.roll_weekly("Paul", "1", monday + 3600, &[8]),
+        says(
+            "<@1> rolled 1 die and got 1 success: 8\n\n\
+             Paul earned this week's participation beat! (1 beat)"
+        )
+    );
+    assert_eq!(character(&h.saved(), "Paul").unwrap().beats(), 1);
+
+    // Reloading the store, as after a restart, doesn't award it again
+    h.store = h.saved();
+    assert_eq!(
+        h.roll_weekly("Paul", "1", monday + 6 * 86400, &[3]),
+        says("<@1> rolled 1 die and got 0 successes: 3")
+    );
+
+    assert_eq!(
+        h.roll_weekly("Paul", "chance", monday + 7 * 86400, &[10]),
+        says(
+            "<@1> rolled a chance die and succeeded!\n\n\
+             Paul earned this week's participation beat! (2 beats)"
+        )
+    );
+    assert_eq!(character(&h.saved(), "Paul").unwrap().beats(), 2);
+}
+
+#[test]
+fn settings_my_timezone() {
+    let mut h = Harness::new();
+
+    // Anyone can set their own time zone
+    assert_eq!(h.settings(false, "mytimezone", None), says("You haven't set a time zone."));
+    assert_eq!(
+        h.settings(false, "mytimezone europe/warsaw", None),
+        says("Your time zone is now Europe/Warsaw. Your weekly beat resets at midnight there.")
+    );
+    assert_eq!(h.settings(false, "mytimezone", None), says("Your time zone is Europe/Warsaw."));
+    assert_eq!(
+        h.settings(false, "mytimezone Europe/Warsw", None),
+        refuses("`Europe/Warsw` is not a time zone. Did you mean Europe/Warsaw?")
+    );
+    assert_eq!(
+        h.settings(false, "mytimezone Somewhere", None),
+        refuses("`Somewhere` is not a time zone, like Europe/Warsaw or America/New_York.")
+    );
+    let saved = SettingsStore::from_file(&h.settings_path).unwrap();
+    assert_eq!(saved.user(1).unwrap().timezone, Some(String::from("Europe/Warsaw")));
+
+    assert_eq!(h.settings(false, "mytimezone clear", None), vec![Reply::Ack]);
+    assert_eq!(h.settings(false, "mytimezone", None), says("You haven't set a time zone."));
+}
+
+#[test]
+fn weekly_beat_resets_in_roller_time_zone() {
+    // Monday 2019-06-03 00:00:00 UTC
+    let monday = 1_559_520_000;
+    // Sunday 2019-06-09 23:30 UTC, already Monday in Warsaw
+    let late_sunday = monday + 7 * 86400 - 1800;
+    let mut h = Harness::new();
+    h.settings(true, "weeklybeat on monday", None);
+    h.roll_weekly("Paul", "1", monday + 3600, &[3]);
+
+    // The guild counts weeks in UTC, so it's still the same week
+    assert_eq!(
+        h.roll_weekly("Paul", "1", late_sunday, &[3]),
+        says("<@1> rolled 1 die and got 0 successes: 3")
+    );
+
+    h.settings(false, "mytimezone Europe/Warsaw", None);
+    assert_eq!(
+        h.roll_weekly("Paul", "1", late_sunday, &[3]),
+        says(
+            "<@1> rolled 1 die and got 0 successes: 3\n\n\
+             Paul earned this week's participation beat! (2 beats)"
+        )
+    );
+}
+
+#[test]
+fn weekly_beat_disabled_by_default() {
+    let mut h = Harness::new();
+
+    assert_eq!(
+        h.roll_weekly("Paul", "1", 1_559_520_000, &[8]),
+        says("<@1> rolled 1 die and got 1 success: 8")
+    );
+    assert!(character(&h.store, "Paul").is_none());
+}
+
+#[test]
+fn stats_bonus_rules_apply_to_rolls() {
+    let mut h = Harness::new();
+    h.stats("Paul", "bulk dexterity=2 firearms=1");
+
+    assert_eq!(
+        h.stats("Paul", r#"bonusrule add firearms +1 "Professional Training""#),
+        vec![Reply::Ack]
+    );
+    assert_eq!(
+        h.stats("Paul", "bonusrule list"),
+        says("1. firearms +1 (Professional Training)")
+    );
+    assert_eq!(
+        h.roll("Paul", "dexterity + firearms", &[8, 1, 2, 3]),
+        says(
+            "<@1> rolled 4 dice [dexterity = 2, firearms = 1, Professional Training +1] \
+             and got 1 success: 8, 1, 2, 3"
+        )
+    );
+    assert_eq!(
+        h.stats("Paul", "bonusrule remove 1"),
+        says("Removed bonus rule: firearms +1 (Professional Training)")
+    );
+    assert_eq!(h.stats("Paul", "bonusrule remove 1"), refuses("There is no bonus rule 1."));
+    assert!(character(&h.saved(), "Paul").unwrap().bonus_rules().is_empty());
+}
+
+#[test]
+fn roll_messages_pluralize_counts() {
+    let mut h = Harness::new();
+
+    assert_eq!(
+        h.roll("Paul", "0", &[]),
+        says("<@1> rolled 0 dice and got 0 successes: ")
+    );
+    assert_eq!(
+        h.roll("Paul", "2", &[8, 3]),
+        says("<@1> rolled 2 dice and got 1 success: 8, 3")
+    );
+    assert_eq!(
+        h.roll("Paul", "strength + athletics", &[4]),
+        says(
+            "<@1> rolled 0 dice [], pool reduced below 1, rolling a chance die instead, \
+             and failed: 4\n\n\
+             Warning: these attributes were not found and defaulted to 0: strength, athletics"
+        )
+    );
+    assert_eq!(h.stats("Paul", "import ```wits: 3```"), says("Imported 1 stat"));
+}
+
+#[test]
+fn check_describes_pool_without_rolling() {
+    let mut h = Harness::new();
+    h.stats("Paul", "bulk dexterity=2 firearms=1 brawl=2");
+    h.stats("Paul", r#"bonusrule add firearms +1 "Professional Training""#);
+    h.stats("Paul", "modifier brawl 8again");
+
+    assert_eq!(
+        h.check("Paul", "dexterity + firearms 9again"),
+        says(
+            "<@1> would roll 4 dice [dexterity = 2, firearms = 1, Professional Training +1] \
+             with 9again"
+        )
+    );
+    assert_eq!(
+        h.check("Paul", "brawl + atheltics"),
+        says(
+            "<@1> would roll 2 dice [brawl = 2] with 8again from brawl\n\n\
+             Warning: this attribute was not found and defaulted to 0: atheltics"
+        )
+    );
+    assert_eq!(
+        h.check("Paul", "dexterity +"),
+        refuses(
+            "<@1> could not read that roll: \
+             ```\nunexpected end of expression at position 12\ndexterity +\n           ^\n```"
+        )
+    );
+    assert!(h.check("Paul", "  ").is_empty());
+}
+
+#[test]
+fn check_and_roll_agree_on_pools() {
+    let mut h = Harness::new();
+    h.stats("Paul", "bulk dexterity=2 firearms=1 wits=3");
+    h.stats("Paul", r#"bonusrule add firearms +2 "Gun Nut""#);
+
+    let expressions = [
+        "dexterity + firearms",
+        "dexterity + firearms nobonus",
+        "max(wits, dexterity) - 1",
+        "(wits + dexterity) - firearms 8again",
+        "wits + missing",
+        "min(firearms, 0) + 3",
+    ];
+    let pool = |replies: Vec<Reply>, prefix: &str| match &replies[0] {
+        Reply::Say(text) => text[prefix.len()..].split(' ').next().unwrap().to_owned(),
+        other => panic!("unexpected reply {:?}", other),
+    };
+    for expression in expressions.iter() {
+        let checked = pool(h.check("Paul", expression), "<@1> would roll ");
+        let rolled = pool(h.roll("Paul", expression, &[1; 20]), "<@1> rolled ");
+        assert_eq!(checked, rolled, "expression: {}", expression);
+    }
+}
+
+#[test]
+fn gm_groupcheck_rolls_each_players_character() {
+    let mut h = Harness::new();
+    h.stats("Ada", "bulk wits=2 composure=1");
+    h.stats("Bo", "bulk wits=1");
+    h.stats("Di", "bulk wits=3 composure=2");
+    h.stats("Di", "modifier wits 9again");
+    let players = [(10, "Ada"), (11, "Bo"), (12, "Cy"), (13, "Di")];
+
+    // Ada rolls 3 dice, Bo 1 die, Cy a chance die and Di 5 dice with 9again
+    let text = reply_text(h.gm(
+        true,
+        "groupcheck wits + composure vs 2 <@10> <@11> <@12> <@13> <@10>",
+        &players,
+        &[8, 9, 3, 10, 4, 1, 9, 2, 1, 1, 1, 1],
+    ));
+    let rows: Vec<Vec<&str>> = text
+        .lines()
+        .filter(|l| l.starts_with("| "))
+        .map(|l| l.split('|').map(str::trim).filter(|c| !c.is_empty()).collect())
+        .collect();
+
+    assert!(
+        text.starts_with("Group check of `wits + composure`, needing 2 successes:```"),
+        "{}",
+        text
+    );
+    assert_eq!(
+        rows,
+        vec![
+            vec!["Player", "Pool", "Successes", "Result"],
+            vec!["Ada", "3", "2", "pass"],
+            vec!["Bo", "1", "1", "fail"],
+            vec!["Cy", "chance", "0", "dramatic failure"],
+            vec!["Di", "5", "1", "fail"],
+        ]
+    );
+    assert!(character(&h.saved(), "Cy").is_none());
+}
+
+#[test]
+fn gm_groupcheck_errors() {
+    let h = Harness::new();
+    let players = [(10, "Ada")];
+
+    assert_eq!(
+        h.gm(false, "groupcheck wits vs 2 <@10>", &players, &[]),
+        refuses("You need the Manage Server permission to use GM commands.")
+    );
+    assert_eq!(
+        h.gm(true, "lookup wits", &players, &[]),
+        refuses("`!gm groupcheck <roll> vs <successes> @player...`")
+    );
+    assert_eq!(
+        h.gm(true, "groupcheck wits vs two <@10>", &players, &[]),
+        refuses("`!gm groupcheck <roll> vs <successes> @player...`")
+    );
+    assert_eq!(
+        h.gm(true, "groupcheck wits vs 2", &players, &[]),
+        refuses("Mention the players to roll for.")
+    );
+    assert_eq!(
+        h.gm(true, "groupcheck wits vs 2 Ada", &players, &[]),
+        refuses("`Ada` is not a player mention.")
+    );
+    let crowd: String = (100..111).map(|id| format!(" <@{}>", id)).collect();
+    assert_eq!(
+        h.gm(true, &format!("groupcheck wits vs 1{}", crowd), &players, &[]),
+        refuses("A group check can roll for at most 10 players.")
+    );
+    assert_eq!(
+        h.gm(true, "groupcheck wits + vs 1 <@10>", &players, &[]),
+        refuses(
+            "Could not read that roll: \
+             ```\nunexpected end of expression at position 7\nwits +\n      ^\n```"
+        )
+    );
+}
+
+#[test]
+fn settings_delete_invocations() {
+    let mut h = Harness::new();
+    let enabled = |h: &Harness| {
+        SettingsStore::from_file(&h.settings_path)
+            .unwrap()
+            .get(1)
+            .is_some_and(|g| g.delete_invocations)
+    };
+
+    assert!(!enabled(&h));
+    assert_eq!(h.settings(true, "delete-invocations on", None), vec![Reply::Ack]);
+    assert!(enabled(&h));
+    assert_eq!(
+        h.settings(false, "delete-invocations off", None),
+        refuses("You need the Manage Server permission to change settings.")
+    );
+    assert!(enabled(&h));
+    assert_eq!(h.settings(true, "delete-invocations off", None), vec![Reply::Ack]);
+    assert!(!enabled(&h));
+}
+
+#[test]
+fn settings_aliases() {
+    let mut h = Harness::new();
+
+    assert_eq!(h.settings(true, "alias list", None), says("No aliases are set."));
+    assert_eq!(h.settings(true, "alias add r roll", None), vec![Reply::Ack]);
+    assert_eq!(h.settings(true, r#"alias add Sheet "!stats print""#, None), vec![Reply::Ack]);
+    assert_eq!(
+        h.settings(true, "alias add roll stats", None),
+        refuses("`roll` is already a command.")
+    );
+    assert_eq!(
+        h.settings(true, "alias add rr r 5", None),
+        refuses("`r` is an alias; aliases must expand to a command, like `roll`.")
+    );
+    assert_eq!(
+        h.settings(false, "alias add d roll", None),
+        refuses("You need the Manage Server permission to change settings.")
+    );
+    assert_eq!(
+        h.settings(true, "alias list", None),
+        says("`!r` → `!roll`\n`!sheet` → `!stats print`")
+    );
+
+    let saved = SettingsStore::from_file(&h.settings_path).unwrap();
+    let aliases = &saved.get(1).unwrap().aliases;
+    assert_eq!(
+        expand_alias("!r 5 9again", "!", aliases),
+        Some(String::from("!roll 5 9again"))
+    );
+    assert_eq!(expand_alias("!SHEET", "!", aliases), Some(String::from("!stats print")));
+
+    assert_eq!(h.settings(true, "alias remove R", None), vec![Reply::Ack]);
+    assert_eq!(
+        h.settings(true, "alias remove r", None),
+        refuses("There is no alias by that name.")
+    );
+    assert_eq!(h.settings(true, "alias list", None), says("`!sheet` → `!stats print`"));
+}
+
+#[test]
+fn settings_dice_emoji() {
+    let mut h = Harness::new();
+
+    assert_eq!(h.settings(true, "dice-emoji show", None), says("No dice emoji are set."));
+    assert_eq!(h.settings(true, "dice-emoji 10 <:crit:1234>", None), vec![Reply::Ack]);
+    assert_eq!(h.settings(true, "dice-emoji 1 💀", None), vec![Reply::Ack]);
+    assert_eq!(
+        h.settings(true, "dice-emoji show", None),
+        says("1: 💀\n10: <:crit:1234>")
+    );
+    assert_eq!(
+        h.settings(true, "dice-emoji 11 💀", None),
+        refuses("The face must be a number from 1 to 10.")
+    );
+    assert_eq!(
+        h.settings(true, "dice-emoji 9 :crit:", None),
+        refuses("`:crit:` is not an emoji this server can use.")
+    );
+
+    assert_eq!(
+        h.roll("Paul", "3", &[10, 4, 1, 10, 2]),
+        says("<@1> rolled 3 dice and got 2 successes: <:crit:1234>, (4), 💀, <:crit:1234>, (2)")
+    );
+    assert_eq!(
+        h.roll("Paul", "chance 2", &[1, 4]),
+        says("<@1> rolled 2 chance dice and got 1 dramatic failure, 1 failure: 💀, 4")
+    );
+
+    assert_eq!(h.settings(true, "dice-emoji 1 clear", None), vec![Reply::Ack]);
+    assert_eq!(
+        h.roll("Paul", "chance 2", &[1, 4]),
+        says("<@1> rolled 2 chance dice and got 1 dramatic failure, 1 failure: 1, 4")
+    );
+}
+
+#[test]
+fn outcome_markers_on_every_roll() {
+    let mut h = Harness::new();
+    h.stats("Paul", "bulk wits=3");
+
+    // Off by default
+    assert_eq!(
+        h.roll("Paul", "chance", &[1]),
+        says("<@1> rolled a chance die and DRAMATICALLY failed!")
+    );
+    assert_eq!(h.settings(true, "outcome-markers on", None), vec![Reply::Ack]);
+
+    let cases: &[(&str, &[u64], &str)] = &[
+        ("chance", &[1], "‼️"),
+        ("chance", &[4], "✖"),
+        ("chance", &[10], "✔"),
+        ("chance 2", &[1, 3], "‼️"),
+        ("chance 2", &[1, 10], "✔"),
+        ("3", &[2, 3, 4], "✖"),
+        ("3", &[8, 3, 4], "✔"),
+        ("5", &[8, 9, 8, 9, 8], "✨"),
+        ("wits", &[2, 3, 4], "✖"),
+        ("wits + 2", &[8, 9, 3, 4, 5], "✔"),
+        ("wits + 2", &[8, 9, 8, 9, 8], "✨"),
+        ("wits verbose", &[8, 3, 4], "✔"),
+    ];
+    for (line, faces, marker) in cases {
+        let text = match h.roll("Paul", line, faces).as_slice() {
+            [Reply::Say(text)] => text.clone(),
+            other => panic!("{}: {:?}", line, other),
+        };
+        assert!(text.starts_with(&format!("{} <@1> rolled", marker)), "{}: {}", line, text);
+        let markers = ["‼️", "✖", "✔", "✨"]
+            .iter()
+            .map(|m| text.matches(m).count())
+            .sum::<usize>();
+        assert_eq!(markers, 1, "{}: {}", line, text);
+    }
+
+    // Errors aren't outcomes
+    match h.roll("Paul", "wits + )", &[]).as_slice() {
+        [Reply::Refuse(text)] => assert!(text.starts_with("<@1> could not read"), "{}", text),
+        other => panic!("{:?}", other),
+    }
+    assert_eq!(
+        h.settings(true, "outcome-markers success +", None),
+        vec![Reply::Ack]
+    );
+    assert_eq!(
+        h.roll("Paul", "3", &[8, 3, 4]),
+        says("+ <@1> rolled 3 dice and got 1 success: 8, 3, 4")
+    );
+    assert_eq!(
+        h.settings(true, "outcome-markers success default", None),
+        vec![Reply::Ack]
+    );
+    assert_eq!(
+        h.roll("Paul", "3", &[8, 3, 4]),
+        says("✔ <@1> rolled 3 dice and got 1 success: 8, 3, 4")
+    );
+
+    assert_eq!(
+        h.settings(true, "outcome-markers great <b>", None),
+        refuses("`great` is not an outcome; use dramatic-failure, failure, success, exceptional.")
+    );
+    assert_eq!(
+        h.settings(true, "outcome-markers success @here", None),
+        refuses("`@here` can't be a marker; use up to 8 characters without spaces or markup.")
+    );
+    assert_eq!(h.settings(true, "outcome-markers off", None), vec![Reply::Ack]);
+    assert_eq!(
+        h.roll("Paul", "3", &[8, 3, 4]),
+        says("<@1> rolled 3 dice and got 1 success: 8, 3, 4")
+    );
+}
+
+#[test]
+fn stats_sheet_md() {
+    let mut h = Harness::new();
+    h.stats("Paul", "bulk wits=3 brawl=2");
+
+    assert_eq!(
+        h.stats("Paul", "sheet md"),
+        vec![Reply::File {
+            name: String::from("Paul.md"),
+            content: String::from(
+                "# Paul\n\n## Stats\n\n| Stat | Value |\n| --- | --- |\n\
+                 | brawl | 2 •• |\n| wits | 3 ••• |\n"
+            )
+            .into_bytes(),
+        }]
+    );
+    assert_eq!(h.stats("Paul", "sheet"), refuses("`!stats sheet md`"));
+}
+
+#[test]
+fn stats_same_name_users_have_their_own_characters() {
+    let mut h = Harness::new();
+    h.stats_as(1, "Alex", "edit wits 3");
+
+    assert_eq!(h.stats_as(2, "Alex", "edit wits 1"), vec![Reply::Ack]);
+    let saved = h.saved();
+    assert_eq!(saved.get(1, "Alex").unwrap().stats(), vec![("wits", 3)]);
+    assert_eq!(saved.get(2, "Alex").unwrap().stats(), vec![("wits", 1)]);
+
+    // A new Discord name keeps the character
+    h.stats_as(1, "Alexandra", "edit wits 4");
+    let saved = h.saved();
+    assert_eq!(saved.get(1, "Alexandra").unwrap().stats(), vec![("wits", 4)]);
+    assert!(saved.get_by_name("Alex").is_some());
+    assert!(saved.get(3, "Alex").is_none());
+}
+
+#[test]
+fn weekly_beat_awarded_to_rollers_own_character() {
+    let monday = 1_559_520_000;
+    let mut h = Harness::new();
+    h.settings(true, "weeklybeat on monday", None);
+    h.stats_as(2, "Paul", "edit wits 3");
+
+    assert_eq!(
+        h.roll_weekly("Paul", "1", monday + 3600, &[8]),
+        says(
+            "<@1> rolled 1 die and got 1 success: 8\n\n\
+             Paul earned this week's participation beat! (1 beat)"
+        )
+    );
+    let saved = h.saved();
+    assert_eq!(saved.get(1, "Paul").unwrap().beats(), 1);
+    assert_eq!(saved.get(2, "Paul").unwrap().beats(), 0);
+}
+
+#[test]
+fn legacy_characters_are_claimed_on_first_use() {
+    let mut h = Harness::new();
+    std::fs::write(
+        &h.path,
+        r#"{"characters": [
+            {"name": "Paul", "stats": {"wits": 3},
+             "health": {"max": 0, "bashing": 0, "lethal": 0, "aggravated": 0}},
+            {"name": "Alex", "stats": {"wits": 2}, "last_writer_id": 1,
+             "health": {"max": 0, "bashing": 0, "lethal": 0, "aggravated": 0}}
+        ]}"#,
+    )
+    .unwrap();
+    h.store = CharacterStore::from_file(&h.path).unwrap();
+
+    // Alex was last changed by Paul's user, so it isn't someone else's to claim
+    assert_eq!(
+        h.roll("Alex", "wits", &[5]),
+        says(
+            "<@2> rolled 0 dice [], pool reduced below 1, rolling a chance die instead, \
+             and failed: 5\n\n\
+             Warning: this attribute was not found and defaulted to 0: wits"
+        )
+    );
+    assert_eq!(
+        h.roll("Paul", "wits", &[8, 3, 9]),
+        says("<@1> rolled 3 dice [wits = 3] and got 2 successes: 8, 3, 9")
+    );
+    assert_eq!(h.stats("Paul", "edit wits 4"), vec![Reply::Ack]);
+
+    let saved = h.saved();
+    assert_eq!(saved.get_by_name("Paul").unwrap().id(), Some(1));
+    assert_eq!(saved.get_by_name("Alex").unwrap().id(), None);
+    assert_eq!(saved.get(1, "Paul").unwrap().get_value("wits"), (true, 4));
+}
+
+const ROSTER: &str = "name,owner,Wits,Brawl
+Ada Bold,Ada#1234,3,2
+Ben,,4,
+Cal,Cal#9999,1,1
+";
+
+fn reply_text(replies: Vec<Reply>) -> String {
+    match replies.as_slice() {
+        [Reply::Say(text)] => text.clone(),
+        other => panic!("expected one reply, got {:?}", other),
+    }
+}
+
+#[test]
+fn admin_import_roster() {
+    let mut h = Harness::new();
+    h.stats("Ben", "edit wits 1");
+
+    let text = reply_text(h.admin(true, "import-roster", Some(ROSTER)));
+
+    assert!(text.starts_with("Imported 3 characters:```\n"), "{}", text);
+    assert!(text.contains("Ada Bold"), "{}", text);
+    assert!(text.contains("created"), "{}", text);
+    assert!(text.contains("updated"), "{}", text);
+    assert!(text.ends_with("so their characters have no owner: Cal#9999"), "{}", text);
+    let saved = h.saved();
+    assert_eq!(saved.get(10, "Ada Bold").unwrap().stats(), vec![("brawl", 2), ("wits", 3)]);
+    assert_eq!(character(&saved, "Ben").unwrap().stats(), vec![("wits", 4)]);
+    // Cal's owner isn't a member, so the first user named Cal can claim it
+    assert_eq!(saved.get_by_name("Cal").unwrap().id(), None);
+    assert_eq!(saved.get(5, "Cal").unwrap().stats(), vec![("brawl", 1), ("wits", 1)]);
+}
+
+#[test]
+fn admin_import_malformed_roster_changes_nothing() {
+    let mut h = Harness::new();
+    h.stats("Ben", "edit wits 1");
+
+    assert_eq!(
+        h.admin(true, "import-roster", Some("name,owner,wits\nAda,,3\nBen,,lots\n")),
+        refuses(
+            "The roster was not imported:```\n\
+             line 3, column 3: `lots` is not a whole number\n```"
+        )
+    );
+    let saved = h.saved();
+    assert!(character(&saved, "Ada").is_none());
+    assert_eq!(character(&saved, "Ben").unwrap().stats(), vec![("wits", 1)]);
+    assert!(character(&h.store, "Ada").is_none());
+}
+
+const ROSTER_SHARED_OWNER: &str = "name,owner,Wits
+Ada Bold,Ada#1234,3
+Ben,,4
+Ada Bright,Ada#1234,2
+";
+
+#[test]
+fn admin_import_roster_with_shared_owner_changes_nothing() {
+    let mut h = Harness::new();
+
+    assert_eq!(
+        h.admin(true, "import-roster", Some(ROSTER_SHARED_OWNER)),
+        refuses(
+            "The roster was not imported:```\n\
+             line 4, column 2: `Ada#1234` already owns `Ada Bold` on line 2\n```"
+        )
+    );
+    assert!(character(&h.saved(), "Ben").is_none());
+
+    // An owner who already has a character can't be given a second one
+    h.stats_as(10, "Ada", "edit wits 1");
+    assert_eq!(
+        h.admin(true, "import-roster", Some(ROSTER)),
+        refuses(
+            "The roster was not imported:```\n\
+             line 2, column 2: `Ada#1234` already has a character, `Ada`\n```"
+        )
+    );
+    let saved = h.saved();
+    assert_eq!(saved.get(10, "Ada").unwrap().name(), "Ada");
+    assert!(saved.get_by_name("Ada Bold").is_none());
+}
+
+#[test]
+fn admin_requires_manage_server() {
+    let mut h = Harness::new();
+
+    assert_eq!(
+        h.admin(false, "import-roster", Some(ROSTER)),
+        refuses("You need the Manage Server permission to use admin commands.")
+    );
+    assert!(h.store.get_by_name("Ada Bold").is_none());
+    assert_eq!(
+        h.admin(true, "import-roster", None),
+        refuses(
+            "`!admin import-roster` (attach a CSV file with a header row of \
+             `name,owner,<stats...>`)\n`!admin dietest <rolls>`\n`!admin compact [confirm]`"
+        )
+    );
+}
+
+#[test]
+fn admin_compact_removes_empty_characters() {
+    let mut h = Harness::new();
+    // A bulk edit with nothing in it still saves an empty character
+    h.stats("Ada", "bulk");
+    h.stats("Ben", "bulk wits=3");
+
+    assert_eq!(
+        h.admin(true, "compact", None),
+        says(
+            "1 character has no stats or other data: Ada\n\
+             Use `!admin compact confirm` to remove them."
+        )
+    );
+    assert!(character(&h.saved(), "Ada").is_some());
+
+    assert_eq!(
+        h.admin(true, "compact confirm", None),
+        says("Removed 1 empty character: Ada")
+    );
+    assert!(character(&h.saved(), "Ada").is_none());
+    assert!(character(&h.saved(), "Ben").is_some());
+    assert_eq!(
+        h.admin(true, "compact", None),
+        says("There are no empty characters to remove.")
+    );
+    assert_eq!(
+        h.admin(false, "compact confirm", None),
+        refuses("You need the Manage Server permission to use admin commands.")
+    );
+}
+
+#[test]
+fn reading_stats_does_not_create_a_character() {
+    let mut h = Harness::new();
+    h.stats("Ben", "bulk wits=3");
+    let before = std::fs::read_to_string(&h.path).unwrap();
+
+    assert_eq!(h.stats("Newbie", "print"), says("```\nNo stats info\n```"));
+    h.stats("Newbie", "sheet md");
+    h.stats("Newbie", "audit");
+    assert!(character(&h.store, "Newbie").is_none());
+    assert_eq!(std::fs::read_to_string(&h.path).unwrap(), before);
+
+    // Saving for someone else doesn't write out a character for them either
+    h.stats("Ben", "edit wits 4");
+    assert!(character(&h.saved(), "Newbie").is_none());
+    assert!(h.saved().empty_characters().is_empty());
+}
+
+#[test]
+fn admin_dietest_requires_manage_server() {
+    let mut h = Harness::new();
+    assert_eq!(
+        h.admin(false, "dietest 100000", None),
+        refuses("You need the Manage Server permission to use admin commands.")
+    );
+    assert_eq!(
+        h.admin(true, "dietest 100000", None),
+        says("Rolling 100000 d10s. The results will follow.")
+    );
+    assert_eq!(
+        h.admin(true, "dietest 5000000", None),
+        refuses("Die tests can roll from 100 to 1000000 dice.")
+    );
+}
+
+#[test]
+fn stat_aliases_refer_to_one_stat() {
+    let mut h = Harness::new();
+    h.stats("Paul", "edit DEX 3");
+    h.stats("Paul", "edit drive. 1");
+
+    assert_eq!(
+        h.roll("Paul", "dexterity + drive", &[8, 3, 1, 10, 5]),
+        says("<@1> rolled 4 dice [dexterity = 3, drive = 1] and got 2 successes: 8, 3, 1, 10, (5)")
+    );
+    let saved = h.saved();
+    assert_eq!(character(&saved, "Paul").unwrap().stats(), vec![("dexterity", 3), ("drive", 1)]);
+}
+
+#[test]
+fn stats_audit() {
+    let mut h = Harness::new();
+    h.stats("Paul", "bulk int=2 wits=3 res=2 str=2 dex=2 sta=2 pre=2 man=2 com=2 occult=3");
+
+    assert_eq!(
+        h.stats("Paul", "audit"),
+        says(
+            "```\nHealth is not set up.\n\
+             Attributes: mental 4/5 (1 under), physical 3/4 (1 under), social 3/3 \
+             - 2 under budget\n\
+             Skills: mental 3/11 (8 under), physical 0/7 (7 under), social 0/4 (4 under) \
+             - 19 under budget\n```"
+        )
+    );
+    assert_eq!(h.stats("Paul", "audit vampire"), refuses("`!stats audit [supernatural]`"));
+}
+
+#[test]
+fn buffs_add_to_rolls_until_they_expire() {
+    let mut h = Harness::new();
+    h.stats("Paul", "edit strength 2");
+
+    assert_eq!(
+        h.buff("Paul", "add str +2 \"Gift of Vigor\""),
+        says("Added strength +2 (Gift of Vigor, 1 scene left).")
+    );
+    assert_eq!(
+        h.buff("Paul", "add strength -1 \"Hangover\" scenes:2"),
+        says("Added strength -1 (Hangover, 2 scenes left).")
+    );
+    assert_eq!(
+        h.roll("Paul", "strength", &[8, 3, 1]),
+        says("<@1> rolled 3 dice [strength = 3] and got 1 success: 8, 3, 1")
+    );
+
+    assert_eq!(
+        h.scene("Paul", "end"),
+        says("Scene ended. Expired: strength +2 (Gift of Vigor)")
+    );
+    assert_eq!(
+        h.roll("Paul", "strength", &[8]),
+        says("<@1> rolled 1 die [strength = 1] and got 1 success: 8")
+    );
+    assert_eq!(
+        h.buff("Paul", "list"),
+        says("```\nstrength -1 (Hangover, 1 scene left)\n```")
+    );
+
+    assert_eq!(h.buff("Paul", "clear"), says("Cleared 1 buff."));
+    assert_eq!(h.scene("Paul", "end"), says("Scene ended. No buffs expired."));
+    assert_eq!(h.buff("Paul", "list"), says("You have no active buffs."));
+    assert_eq!(character(&h.saved(), "Paul").unwrap().effective_value("strength"), (true, 2));
+}
+
+#[test]
+fn scene_conditions_end_in_their_channel() {
+    let mut h = Harness::new();
+
+    assert_eq!(
+        h.condition_in(100, "Paul", "add Stunned scene"),
+        says("Added condition **Stunned**, which ends with this scene.")
+    );
+    assert_eq!(
+        h.condition_in(100, "Paul", "add \"Beaten Down\""),
+        says("Added condition **Beaten Down**.")
+    );
+    h.condition_in(100, "Ada", "add Blinded scene");
+    h.condition_in(200, "Ben", "add Stunned scene");
+
+    assert_eq!(
+        h.scene_in(100, "Paul", "end"),
+        says("Scene ended. No buffs expired.\nConditions ended: Paul (Stunned), Ada (Blinded)")
+    );
+    assert_eq!(h.condition_in(100, "Paul", "list"), says("```\nBeaten Down\n```"));
+    assert_eq!(
+        h.condition_in(200, "Ben", "list"),
+        says("```\nStunned (ends with the scene)\n```")
+    );
+    assert!(character(&h.saved(), "Ada").unwrap().conditions().is_empty());
+
+    assert_eq!(h.scene_in(100, "Paul", "end"), says("Scene ended. No buffs expired."));
+    assert_eq!(
+        h.condition_in(100, "Paul", "remove \"beaten down\""),
+        says("Removed condition **beaten down**.")
+    );
+    assert_eq!(
+        h.condition_in(100, "Paul", "remove Stunned"),
+        refuses("You don't have that condition.")
+    );
+    assert_eq!(h.condition_in(100, "Paul", "list"), says("You have no conditions."));
+}
+
+#[test]
+fn buff_usage() {
+    let mut h = Harness::new();
+    let usage = "`!buff add <stat_name> <+dice> \"<source>\" [scenes:<number>]`\n\
+                 `!buff list`\n\
+                 `!buff clear`";
+
+    assert_eq!(h.buff("Paul", "add strength lots \"Vigor\""), refuses(usage));
+    assert_eq!(h.buff("Paul", "add strength +101 \"Vigor\""), refuses(usage));
+    assert_eq!(
+        h.buff("Paul", "add strength -9223372036854775808 \"Vigor\""),
+        refuses(usage)
+    );
+    assert_eq!(h.buff("Paul", "add strength +2 \"Vigor\" scenes:0"), refuses(usage));
+    assert_eq!(h.buff("Paul", "add strength +2"), refuses(usage));
+    assert_eq!(h.scene("Paul", "start"), refuses("`!scene end`"));
+    assert!(character(&h.store, "Paul").is_none());
+}
+
+#[test]
+fn roll_several_chance_dice() {
+    let mut h = Harness::new();
+
+    assert_eq!(
+        h.roll("Paul", "chance 3", &[10, 10, 10]),
+        says("<@1> rolled 3 chance dice and got 3 successes: 10, 10, 10")
+    );
+    assert_eq!(
+        h.roll("Paul", "chance 2", &[1, 1]),
+        says("<@1> rolled 2 chance dice and got 2 dramatic failures: 1, 1")
+    );
+    assert_eq!(
+        h.roll("Paul", "chance 3 9again", &[10, 1, 9]),
+        says(
+            "<@1> rolled 3 chance dice and got \
+             1 success, 1 dramatic failure, 1 failure: 10, 1, 9"
+        )
+    );
+    assert_eq!(h.roll("Paul", "chance 1", &[10]), says("<@1> rolled a chance die and succeeded!"));
+    assert_eq!(
+        h.roll("Paul", "chance 11", &[]),
+        refuses("You can roll from 1 to 10 chance dice.")
+    );
+}
+
+#[test]
+fn exceptional_threshold_applies_to_pools_with_the_stat() {
+    let mut h = Harness::new();
+    h.stats("Paul", "bulk dexterity=2 firearms=2 brawl=2");
+    assert_eq!(h.stats("Paul", "exceptional Firearms 3"), vec![Reply::Ack]);
+
+    assert_eq!(
+        h.roll("Paul", "dexterity + firearms", &[8, 9, 8, 2]),
+        says(
+            "<@1> rolled 4 dice [dexterity = 2, firearms = 2] and got 3 successes: 8, 9, 8, 2 \
+             - exceptional success! (3 successes needed with firearms)"
+        )
+    );
+    assert_eq!(
+        h.roll("Paul", "dexterity + brawl", &[8, 9, 8, 2]),
+        says("<@1> rolled 4 dice [dexterity = 2, brawl = 2] and got 3 successes: 8, 9, 8, 2")
+    );
+
+    assert_eq!(
+        h.stats("Paul", "exceptional firearms clear"),
+        says("Exceptional success threshold cleared.")
+    );
+    assert_eq!(
+        h.roll("Paul", "dexterity + firearms", &[8, 9, 8, 2]),
+        says("<@1> rolled 4 dice [dexterity = 2, firearms = 2] and got 3 successes: 8, 9, 8, 2")
+    );
+    assert_eq!(
+        h.stats("Paul", "exceptional firearms 9"),
+        refuses("`!stats exceptional <stat_name> <1-5|clear>`")
+    );
+}
+
+#[test]
+fn exceptional_success_defaults_to_five() {
+    let mut h = Harness::new();
+    h.stats("Paul", "edit strength 5");
+
+    assert_eq!(
+        h.roll("Paul", "strength", &[8, 8, 9, 9, 8]),
+        says(
+            "<@1> rolled 5 dice [strength = 5] and got 5 successes: 8, 8, 9, 9, 8 \
+             - exceptional success!"
+        )
+    );
+}
+
+#[test]
+fn numeric_rolls_report_exceptional_success() {
+    let mut h = Harness::new();
+
+    assert_eq!(
+        h.roll("Paul", "6", &[8, 9, 10, 1, 8, 4, 3]),
+        says("<@1> rolled 6 dice and got 4 successes: 8, 9, 10, (1), 8, 4, 3")
+    );
+    assert_eq!(
+        h.roll("Paul", "6", &[8, 9, 10, 2, 8, 4, 8]),
+        says(
+            "<@1> rolled 6 dice and got 5 successes: 8, 9, 10, (2), 8, 4, 8 \
+             - exceptional success!"
+        )
+    );
+    assert_eq!(
+        h.roll("Paul", "chance 5", &[10, 10, 10, 10, 10]),
+        says("<@1> rolled 5 chance dice and got 5 successes: 10, 10, 10, 10, 10")
+    );
+}
+
+#[test]
+fn verbose_rolls_show_a_histogram() {
+    let mut h = Harness::new();
+    let faces = [1, 5, 10, 8, 9, 1, 3, 2, 2, 4, 6];
+    let result = "and got 3 successes: 1, 5, 10, (8), 9, 1, 3, 2, 2, 4, 6";
+    let histogram = "1:✗✗ 2:✗✗ 3:✗ 4:✗ 5:✗ 6:✗ 8:✓ 9:✓ 10:✓";
+
+    assert_eq!(
+        h.roll("Paul", "10 verbose", &faces),
+        says(&format!("<@1> rolled 10 dice {}\n{}", result, histogram))
+    );
+    assert_eq!(
+        h.roll("Paul", "10", &faces),
+        says(&format!("<@1> rolled 10 dice {}", result))
+    );
+
+    h.stats("Paul", "edit wits 4");
+    h.stats("Paul", "edit occult 6");
+    assert_eq!(
+        h.roll("Paul", "wits + occult verbose", &faces),
+        says(&format!(
+            "<@1> rolled 10 dice [wits = 4, occult = 6] {}\n{}",
+            result, histogram
+        ))
+    );
+    assert_eq!(
+        h.roll("Paul", "9 verbose", &faces[..10]),
+        says("<@1> rolled 9 dice and got 3 successes: 1, 5, 10, (8), 9, 1, 3, 2, 2, 4")
+    );
+}
+
+#[test]
+fn reserved_stat_names_are_refused() {
+    let mut h = Harness::new();
+
+    assert_eq!(
+        h.stats("Paul", "edit 5 3"),
+        refuses("`5` can't be a stat name because numbers in a roll are rolled as dice.")
+    );
+    assert_eq!(
+        h.stats("Paul", "edit 9again 1"),
+        refuses("`9again` can't be a stat name because it is a roll modifier.")
+    );
+    assert_eq!(
+        h.stats("Paul", "bulk wits=3 Chance=2"),
+        refuses("`chance` can't be a stat name because it has its own meaning in rolls.")
+    );
+    assert_eq!(
+        h.stats("Paul", "import\n```\nwits: 3\nverbose: 1\n```"),
+        refuses(
+            "Nothing was imported:\nline 2: `verbose` can't be a stat name because it has its \
+             own meaning in rolls"
+        )
+    );
+    assert!(character(&h.saved(), "Paul").is_none());
+}
+
+#[test]
+fn damage_take_and_heal() {
+    let mut h = Harness::new();
+    std::fs::write(
+        &h.path,
+        r#"{"characters": [
+            {"name": "Paul", "id": 1, "stats": {},
+             "health": {"max": 3, "bashing": 0, "lethal": 0, "aggravated": 0}}
+        ]}"#,
+    )
+    .unwrap();
+    h.store = CharacterStore::from_file(&h.path).unwrap();
+    let boxes = |h: &Harness| character(&h.saved(), "Paul").unwrap().health().boxes();
+
+    let replies = h.damage("Paul", "take 2 bashing");
+    match replies.as_slice() {
+        [Reply::Say(text)] => assert!(text.contains("Health (max 3):"), "{}", text),
+        _ => panic!("unexpected replies: {:?}", replies),
+    }
+    assert_eq!(boxes(&h), vec!["B", "B", " "]);
+    h.damage("Paul", "take 1 lethal");
+    h.damage("Paul", "take 1 agg");
+    assert_eq!(boxes(&h), vec!["A", "L", "B"]);
+    h.damage("Paul", "heal 4 bashing");
+    assert_eq!(boxes(&h), vec!["A", "L", " "]);
+
+    let usage = "`!damage take <amount> bashing|lethal|agg` (amount up to 100)\n\
+                 `!damage heal <amount> bashing|lethal|agg`";
+    assert_eq!(h.damage("Paul", "take 2 fire"), refuses(usage));
+    assert_eq!(h.damage("Paul", "take 0 lethal"), refuses(usage));
+    assert_eq!(h.damage("Paul", "take 101 lethal"), refuses(usage));
+    assert_eq!(h.damage("Paul", "take 18446744073709551615 bashing"), refuses(usage));
+    h.damage("Paul", "take 100 bashing");
+    assert_eq!(boxes(&h), vec!["A", "A", "A"]);
+    assert_eq!(h.damage("Paul", "heal"), refuses(usage));
+}
+
+#[test]
+fn roll_breakdowns_escape_stored_text() {
+    let mut h = Harness::new();
+    h.stats("Paul", "bulk firearms=1");
+    h.stats("Paul", r#"bonusrule add firearms +1 "**@everyone** ~~Gun~~""#);
+
+    assert_eq!(
+        h.roll("Paul", "firearms", &[8, 1]),
+        says(
+            "<@1> rolled 2 dice [firearms = 1, \\*\\*@\u{200B}everyone\\*\\* \\~\\~Gun\\~\\~ +1] \
+             and got 1 success: 8, 1"
+        )
+    );
+}
+
+#[test]
+fn roll_trace_shows_how_the_line_was_read() {
+    let mut h = Harness::new();
+    h.stats("Paul", "bulk dexterity=2 firearms=1");
+    h.stats("Paul", r#"bonusrule add firearms +2 "Gun Nut""#);
+    h.stats("Paul", "modifier firearms 8again");
+    h.buff("Paul", r#"add dexterity +1 "Speed Drug""#);
+
+    assert_eq!(
+        h.roll("Paul", "Dexterity + firearms + athletcs - 1 trace", &[1, 2, 3, 4, 5]),
+        says(
+            "<@1> rolled 5 dice [Dexterity = 3, firearms = 1, Gun Nut +2] with 8again from \
+             firearms and got 0 successes: 1, 2, 3, 4, 5\n\n\
+             Warning: this attribute was not found and defaulted to 0: athletcs\n\
+             ```\n\
+             tokens: dexterity + firearms + athletcs - 1 trace\n\
+             trace: reserved word\n\
+             Dexterity: stat = 2\n\
+             +1 from buff Speed Drug\n\
+             firearms: stat = 1\n\
+             athletcs: unknown, counted as 0\n\
+             1: literal\n\
+             +2 from Gun Nut\n\
+             8again: modifier from firearms\n\
+             rolled: 5 8again\n\
+             ```"
+        )
+    );
+    assert_eq!(
+        h.roll("Paul", "4 wp 9again trace", &[1, 2, 3, 4, 5, 6, 7]),
+        says(
+            "<@1> rolled 7 dice (willpower spent) and got 0 successes: 1, 2, 3, 4, 5, 6, 7\n\
+             ```\n\
+             tokens: 4 wp 9again trace\n\
+             trace: reserved word\n\
+             4: literal\n\
+             9again: modifier\n\
+             wp: reserved word\n\
+             +3 from willpower\n\
+             rolled: 7 9again\n\
+             ```"
+        )
+    );
+}
+
+#[test]
+fn health_max_sets_the_track() {
+    let mut h = Harness::new();
+
+    assert_eq!(h.health("Paul", ""), says("You have no character yet."));
+    assert_eq!(h.health("Paul", "max 0"), refuses("Health can be from 1 to 20."));
+    assert_eq!(h.health("Paul", "max 21"), refuses("Health can be from 1 to 20."));
+    assert_eq!(h.health("Paul", "max lots"), refuses("`!health`\n`!health max <boxes>`"));
+    let replies = h.health("Paul", "max 4");
+    match replies.as_slice() {
+        [Reply::Say(text)] => assert!(text.contains("Health (max 4):"), "{}", text),
+        _ => panic!("unexpected replies: {:?}", replies),
+    }
+    assert_eq!(h.health("Paul", ""), replies);
+    assert_eq!(character(&h.saved(), "Paul").unwrap().health().max(), 4);
+
+    h.damage("Paul", "take 3 lethal");
+    assert_eq!(
+        h.health("Paul", "max 2"),
+        refuses("That's fewer boxes than the 3 already marked; heal some damage first.")
+    );
+    assert_eq!(character(&h.saved(), "Paul").unwrap().health().max(), 4);
+}
+
+#[test]
+fn roll_extended_actions() {
+    let mut h = Harness::new();
+    h.stats("Paul", "bulk wits=2 investigation=1");
+
+    assert_eq!(
+        h.roll("Paul", "extended wits + investigation target 3 limit 2", &[8, 2, 3, 9, 9, 1]),
+        says(
+            "<@1> rolled an extended action of 3 dice [wits = 2, investigation = 1], \
+             needing 3 successes:\n\
+             Roll 1: 1 success: 8, 2, 3\n\
+             Roll 2: 2 successes: 9, 9, 1\n\
+             Reached 3 successes after 2 rolls!"
+        )
+    );
+    assert_eq!(
+        h.roll("Paul", "extended 2 target 5 limit 2", &[8, 1, 2, 3]),
+        says(
+            "<@1> rolled an extended action of 2 dice [], needing 5 successes:\n\
+             Roll 1: 1 success: 8, 1\n\
+             Roll 2: 0 successes: 2, 3\n\
+             Failed to reach the target after 2 rolls, with 1 success."
+        )
+    );
+    assert_eq!(
+        h.roll("Paul", "extended wits - 3 target 1", &[1]),
+        says(
+            "<@1> rolled an extended action of -1 dice [wits = 2], pool reduced below 1, \
+             rolling a chance die each time, needing 1 success:\n\
+             Roll 1: 0 successes: 1\n\
+             DRAMATICALLY failed on roll 1, ending the action."
+        )
+    );
+    assert_eq!(
+        h.roll("Paul", "extended wits", &[]),
+        refuses("`!roll extended <roll> target <successes> [limit <rolls>]`")
+    );
+    assert_eq!(
+        h.roll("Paul", "extended wits target 2 limit 21", &[]),
+        refuses("Extended actions need a target of at least 1 success and from 1 to 20 rolls.")
+    );
+    assert_eq!(
+        h.roll("Paul", "extended wits target 2 fair", &[]),
+        refuses("Extended actions can't be fair rolls, since each roll's dice can't be checked.")
+    );
+}
+
+#[test]
+fn roll_extended_long_actions_leave_out_dice() {
+    let mut h = Harness::new();
+    let faces = vec![2; 20 * 20];
+
+    let replies = h.roll("Paul", "extended 20 target 1 limit 20", &faces);
+    let text = match replies.as_slice() {
+        [Reply::Say(text)] => text.clone(),
+        _ => panic!("unexpected replies: {:?}", replies),
+    };
+    assert!(text.contains("\nRoll 20: 0 successes\n"), "{}", text);
+    assert!(text.ends_with("Failed to reach the target after 20 rolls, with 0 successes."));
+    assert!(text.len() < 2000);
+}
+
+#[test]
+fn roll_extended_default_limit_stops_at_the_most_rolls() {
+    let mut h = Harness::new();
+    let faces = vec![2; 25 * 20];
+
+    let replies = h.roll("Paul", "extended 25 target 1", &faces);
+    let text = match replies.as_slice() {
+        [Reply::Say(text)] => text.clone(),
+        _ => panic!("unexpected replies: {:?}", replies),
+    };
+    assert!(text.ends_with("Failed to reach the target after 20 rolls, with 0 successes."));
+}
+
+#[test]
+fn stats_delete_and_clear() {
+    let mut h = Harness::new();
+    h.stats("Paul", "bulk strenght=3 wits=2 brawl=1");
+
+    assert_eq!(h.stats("Paul", "delete STRENGHT"), says("Deleted strenght."));
+    assert_eq!(h.stats("Paul", "delete strenght"), refuses("That stat doesn't exist."));
+    assert_eq!(h.stats("Paul", "delete"), refuses("`!stats delete <stat_name>`"));
+    assert_eq!(character(&h.saved(), "Paul").unwrap().get_value("strenght"), (false, 0));
+
+    assert_eq!(
+        h.stats("Paul", "clear"),
+        refuses("This removes every stat on your sheet. To go ahead, use `!stats clear confirm`.")
+    );
+    assert_eq!(character(&h.saved(), "Paul").unwrap().stats().len(), 2);
+    assert_eq!(h.stats("Paul", "clear confirm"), says("Cleared 2 stats."));
+    assert!(character(&h.saved(), "Paul").unwrap().stats().is_empty());
+    assert_eq!(h.stats("Paul", "clear confirm"), says("Cleared 0 stats."));
+}
+
+#[test]
+fn guild_constants_in_rolls() {
+    let mut h = Harness::new();
+    h.stats("Paul", "bulk wits=3 occult=1");
+    h.stats("Alex", "bulk wits=2 tension=0");
+
+    assert_eq!(
+        h.settings(false, "const set tension 2", None),
+        refuses("You need the Manage Server permission to change settings.")
+    );
+    assert_eq!(h.settings(true, "const list", None), says("No constants are set."));
+    assert_eq!(h.settings(true, "const set Tension 2", None), vec![Reply::Ack]);
+    assert_eq!(h.settings(true, "const set darkness x", None), refuses("`x` is not a number."));
+    assert_eq!(h.settings(true, "const list", None), says("`tension` = 2"));
+
+    assert_eq!(
+        h.roll("Paul", "wits + occult + tension", &[8, 2, 3, 9, 4, 1]),
+        says(
+            "<@1> rolled 6 dice [wits = 3, occult = 1, tension (guild) = 2] \
+             and got 2 successes: 8, 2, 3, 9, 4, 1"
+        )
+    );
+    assert_eq!(
+        h.check("Paul", "wits + tension"),
+        says("<@1> would roll 5 dice [wits = 3, tension (guild) = 2]")
+    );
+    // A character's own stat wins over the guild's constant
+    assert_eq!(
+        h.check("Alex", "wits + tension"),
+        says("<@2> would roll 2 dice [wits = 2, tension = 0]")
+    );
+
+    assert_eq!(h.settings(true, "const remove TENSION", None), vec![Reply::Ack]);
+    assert_eq!(
+        h.settings(true, "const remove tension", None),
+        refuses("There is no constant by that name.")
+    );
+    assert_eq!(
+        h.check("Paul", "wits + tension"),
+        says(
+            "<@1> would roll 3 dice [wits = 3]\n\n\
+             Warning: this attribute was not found and defaulted to 0: tension"
+        )
+    );
+}
+
+#[test]
+fn hostile_names_stay_inside_their_formatting() {
+    let mut h = Harness::new();
+    let hostile = ["x```y", "s```t", "c```d", "q```r", "m```n"];
+    h.stats("Paul", "edit x```y 3");
+    h.stats("Paul", "modifier x```y 9again");
+    h.buff("Paul", "add x```y +1 \"s```t\" scenes:1");
+    h.condition_in(100, "Paul", "add \"c```d\" scene");
+    let merit = MeritEntry {
+        name: String::from("m```n"),
+        description: Some(String::from("x```y")),
+        custom: true,
+    };
+
+    let replies = vec![
+        h.stats("Paul", "print"),
+        h.stats("Paul", "audit"),
+        h.roll("Paul", "x```y + q```r trace verbose", &[8; 20]),
+        h.check("Paul", "x```y + q```r"),
+        h.buff("Paul", "list"),
+        h.condition_in(100, "Paul", "list"),
+        h.scene_in(100, "Paul", "end"),
+        vec![merit_text(Some(&merit), false)],
+        merit_list(&[merit]),
+    ];
+    for reply in replies.iter() {
+        assert!(!reply.is_empty(), "{:?}", replies);
+    }
+    for reply in replies.iter().flatten() {
+        let text = match reply {
+            Reply::Say(text) | Reply::Refuse(text) | Reply::Ephemeral(text) => text,
+            _ => continue,
+        };
+        for name in hostile.iter() {
+            assert!(!text.contains(name), "`{}` left as typed in: {}", name, text);
+        }
+    }
+}
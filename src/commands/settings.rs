@@ -0,0 +1,681 @@
+use failure::Error;
+use lazy_static::lazy_static;
+use log::debug;
+use regex::Regex;
+use serenity::{
+    client::Context,
+    framework::standard::{Args, CommandResult, macros::command},
+    model::channel::Message,
+};
+use std::{fs, path::Path};
+use crate::{
+    commands::merit::{guild_merits_dir, merit_file_name},
+    util::{
+        aliases::check_alias,
+        args::split_args,
+        constants::{MERITS_DIR, PREFIX, SETTINGS_PATH},
+        fsutil::atomic_write,
+        outcome::{is_valid_marker, Outcome, OUTCOMES},
+        permissions::is_admin,
+        respond::{send, Reply},
+        settings::{CustomMerit, SettingsStore, WeeklyBeat},
+        time::parse_timezone,
+    },
+};
+
+/// Largest merit image accepted, in bytes.
+const MAX_IMAGE_BYTES: u64 = 2 * 1024 * 1024;
+const PNG_MAGIC: &[u8] = b"\x89PNG\r\n\x1a\n";
+const USAGE: &str = "`!settings merit add \"<name>\" <category> [description]` (attach a PNG for an image)
+`!settings merit remove \"<name>\"`
+`!settings weeklybeat on [reset day] [UTC offset, i.e. +02:00]`
+`!settings weeklybeat off`
+`!settings dice-emoji <face> <emoji|clear>`
+`!settings dice-emoji show`
+`!settings outcome-markers on|off`
+`!settings outcome-markers <dramatic-failure|failure|success|exceptional> <marker|default>`
+`!settings delete-invocations on|off`
+`!settings alias add <name> <command and arguments>`
+`!settings alias remove <name>`
+`!settings alias list`
+`!settings const set <name> <value>`
+`!settings const remove <name>`
+`!settings const list`
+`!settings mytimezone <time zone, i.e. Europe/Warsaw|clear>`";
+const WEEKDAYS: [&str; 7] = [
+    "monday",
+    "tuesday",
+    "wednesday",
+    "thursday",
+    "friday",
+    "saturday",
+    "sunday",
+];
+
+/// Input to the settings command.
+#[derive(Debug)]
+pub struct SettingsInput {
+    pub user_id: u64,
+    pub guild_id: Option<u64>,
+    pub is_admin: bool,
+    pub line: String,
+    pub image: Option<Vec<u8>>,
+}
+
+/// Check an attached image's name and size before downloading it.
+///
+/// # Arguments
+///
+/// * `filename` - attachment file name
+/// * `size` - attachment size in bytes
+///
+/// # Examples
+///
+/// ```rust
+/// check_image_meta(&attachment.filename, attachment.size)?;
+/// ```
+pub fn check_image_meta(filename: &str, size: u64) -> Result<(), String> {
+    if !filename.to_lowercase().ends_with(".png") {
+        return Err(String::from("Merit images must be PNG files."));
+    }
+    if size > MAX_IMAGE_BYTES {
+        return Err(format!(
+            "Merit images must be at most {} KB.",
+            MAX_IMAGE_BYTES / 1024
+        ));
+    }
+    Ok(())
+}
+
+/// Check that downloaded bytes are actually a PNG image.
+///
+/// # Arguments
+///
+/// * `bytes` - image contents
+///
+/// # Examples
+///
+/// ```rust
+/// check_png(&bytes)?;
+/// ```
+fn check_png(bytes: &[u8]) -> Result<(), String> {
+    if bytes.starts_with(PNG_MAGIC) {
+        Ok(())
+    } else {
+        Err(String::from("That attachment is not a valid PNG image."))
+    }
+}
+
+lazy_static! {
+    static ref REGEX_CUSTOM_EMOJI: Regex = Regex::new(r#"^<a?:\w{2,32}:\d+>$"#).unwrap();
+}
+
+/// Returns whether the text is a single emoji Discord can display.
+///
+/// Accepts custom emoji like `<:crit:1234>` and short runs of
+/// non-ASCII characters for unicode emoji.
+///
+/// # Arguments
+///
+/// * `emoji` - emoji text
+///
+/// # Examples
+///
+/// ```rust
+/// let valid = is_valid_emoji("<:crit:1234>");
+/// ```
+fn is_valid_emoji(emoji: &str) -> bool {
+    if REGEX_CUSTOM_EMOJI.is_match(emoji) {
+        return true;
+    }
+    let count = emoji.chars().count();
+    count > 0
+        && count <= 16
+        && !emoji.is_ascii()
+        && !emoji
+            .chars()
+            .any(|c| c.is_whitespace() || c.is_ascii_alphabetic() || "<>:@#`".contains(c))
+}
+
+/// Parse a die face from 1 to 10.
+fn parse_face(face: &str) -> Option<u8> {
+    match face.parse::<u8>() {
+        Ok(f) if (1..=10).contains(&f) => Some(f),
+        _ => None,
+    }
+}
+
+/// Parse a day of the week, allowing abbreviations like "tue".
+///
+/// Returns 0 for Monday through 6 for Sunday.
+///
+/// # Arguments
+///
+/// * `day` - day name
+///
+/// # Examples
+///
+/// ```rust
+/// let day = parse_weekday("sun");
+/// ```
+fn parse_weekday(day: &str) -> Option<u8> {
+    let day = day.to_lowercase();
+    if day.len() < 3 {
+        return None;
+    }
+    WEEKDAYS
+        .iter()
+        .position(|d| d.starts_with(&day))
+        .map(|i| i as u8)
+}
+
+/// Parse an offset from UTC, like "+2", "-05:30", or "UTC+1", into minutes.
+///
+/// # Arguments
+///
+/// * `offset` - offset text
+///
+/// # Examples
+///
+/// ```rust
+/// let minutes = parse_utc_offset("+05:30");
+/// ```
+fn parse_utc_offset(offset: &str) -> Option<i32> {
+    let offset = offset.to_lowercase();
+    let offset = offset.trim_start_matches("utc");
+    if offset.is_empty() {
+        return Some(0);
+    }
+    let (sign, rest) = match offset.chars().next()? {
+        '+' => (1, &offset[1..]),
+        '-' => (-1, &offset[1..]),
+        _ => (1, offset),
+    };
+    let (hours, minutes) = match rest.find(':') {
+        Some(i) => (rest[..i].parse::<i32>().ok()?, rest[i + 1..].parse::<i32>().ok()?),
+        None => (rest.parse::<i32>().ok()?, 0),
+    };
+    if hours > 14 || minutes >= 60 || hours < 0 || minutes < 0 {
+        return None;
+    }
+    Some(sign * (hours * 60 + minutes))
+}
+
+/// Format an offset from UTC in minutes, like "UTC+02:00".
+fn format_utc_offset(minutes: i32) -> String {
+    let sign = if minutes < 0 { '-' } else { '+' };
+    let minutes = minutes.abs();
+    format!("UTC{}{:02}:{:02}", sign, minutes / 60, minutes % 60)
+}
+
+/// Returns a line as typed after its first few words.
+///
+/// # Arguments
+///
+/// * `line` - text to skip words of
+/// * `words` - number of words to skip
+///
+/// # Examples
+///
+/// ```rust
+/// assert_eq!(after_words("alias add r roll 5", 3), "roll 5");
+/// ```
+fn after_words(line: &str, words: usize) -> &str {
+    let mut rest = line.trim();
+    for _ in 0..words {
+        let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+        rest = rest[end..].trim_start();
+    }
+    rest
+}
+
+/// Remove one pair of double quotes around the whole text, if it has them.
+fn unquote(text: &str) -> &str {
+    let quoted = text.len() >= 2 && text.starts_with('"') && text.ends_with('"');
+    if quoted && text.matches('"').count() == 2 {
+        &text[1..text.len() - 1]
+    } else {
+        text
+    }
+}
+
+/// Show, set or clear the invoking user's own time zone.
+///
+/// # Arguments
+///
+/// * `user_id` - user whose time zone it is
+/// * `args` - arguments after `mytimezone`
+/// * `settings` - settings store to update
+/// * `path` - path the settings store is saved to
+///
+/// # Examples
+///
+/// ```rust
+/// let replies = handle_user_timezone(user_id, &rest, &mut settings, &SETTINGS_PATH)?;
+/// ```
+fn handle_user_timezone(
+    user_id: u64,
+    args: &[&str],
+    settings: &mut SettingsStore,
+    path: &Path,
+) -> Result<Vec<Reply>, Error> {
+    let name = match args {
+        [] => {
+            let response = match settings.user(user_id).and_then(|u| u.timezone.as_ref()) {
+                Some(tz) => format!("Your time zone is {}.", tz),
+                None => String::from("You haven't set a time zone."),
+            };
+            return Ok(vec![Reply::Say(response)]);
+        }
+        [name] => name,
+        _ => return Ok(vec![Reply::Refuse(String::from(USAGE))]),
+    };
+    if name.eq_ignore_ascii_case("clear") {
+        settings.user_mut(user_id).timezone = None;
+        settings.save(path)?;
+        return Ok(vec![Reply::Ack]);
+    }
+    let tz = match parse_timezone(name) {
+        Ok(tz) => tz,
+        Err(suggestions) => {
+            let response = if suggestions.is_empty() {
+                format!("`{}` is not a time zone, like Europe/Warsaw or America/New_York.", name)
+            } else {
+                format!(
+                    "`{}` is not a time zone. Did you mean {}?",
+                    name,
+                    suggestions.join(" or ")
+                )
+            };
+            return Ok(vec![Reply::Refuse(response)]);
+        }
+    };
+    settings.user_mut(user_id).timezone = Some(tz.name().to_owned());
+    settings.save(path)?;
+    Ok(vec![Reply::Say(format!(
+        "Your time zone is now {}. Your weekly beat resets at midnight there.",
+        tz.name()
+    ))])
+}
+
+/// Handle a settings command, returning the replies to send.
+///
+/// A user's own time zone can be set anywhere, by anyone; everything else
+/// is a server setting that needs the Manage Server permission.
+///
+/// # Arguments
+///
+/// * `input` - who invoked the command and what they typed after `!settings`
+/// * `settings` - settings store to update
+/// * `path` - path the settings store is saved to
+/// * `merits_dir` - base directory for merit images
+///
+/// # Examples
+///
+/// ```rust
+/// let replies = handle_settings(&input, &mut settings, &SETTINGS_PATH, &MERITS_DIR)?;
+/// ```
+pub fn handle_settings(
+    input: &SettingsInput,
+    settings: &mut SettingsStore,
+    path: &Path,
+    merits_dir: &Path,
+) -> Result<Vec<Reply>, Error> {
+    let args = split_args(&input.line);
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    debug!("Settings args are: {:?}", args);
+    if let Some((&"mytimezone", rest)) = args.split_first() {
+        return handle_user_timezone(input.user_id, rest, settings, path);
+    }
+    let guild_id = match input.guild_id {
+        Some(g) => g,
+        None => {
+            return Ok(vec![Reply::Refuse(String::from(
+                "Settings can only be changed in a server.",
+            ))]);
+        }
+    };
+    if !input.is_admin {
+        return Ok(vec![Reply::Refuse(String::from(
+            "You need the Manage Server permission to change settings.",
+        ))]);
+    }
+    match args.as_slice() {
+        ["merit", "add", name, category, description @ ..] if !name.trim().is_empty() => {
+            if merit_file_name(name) == ".png" {
+                return Ok(vec![Reply::Refuse(String::from(
+                    "Merit names need at least one letter or number.",
+                ))]);
+            }
+            if let Some(bytes) = &input.image {
+                if let Err(e) = check_png(bytes) {
+                    return Ok(vec![Reply::Refuse(e)]);
+                }
+                let dir = guild_merits_dir(merits_dir, guild_id);
+                fs::create_dir_all(&dir)?;
+                atomic_write(&dir.join(merit_file_name(name)), bytes)?;
+            }
+            settings.get_mut(guild_id).add_merit(CustomMerit {
+                name: name.trim().to_owned(),
+                category: category.to_lowercase(),
+                description: description.join(" "),
+            });
+            settings.save(path)?;
+            Ok(vec![Reply::Ack])
+        }
+        ["merit", "remove", name] => {
+            if !settings.get_mut(guild_id).remove_merit(name) {
+                return Ok(vec![Reply::Refuse(String::from(
+                    "There is no custom merit by that name.",
+                ))]);
+            }
+            let image = guild_merits_dir(merits_dir, guild_id).join(merit_file_name(name));
+            if image.exists() {
+                fs::remove_file(image)?;
+            }
+            settings.save(path)?;
+            Ok(vec![Reply::Ack])
+        }
+        ["weeklybeat", "on", rest @ ..] if rest.len() <= 2 => {
+            let reset_day = match rest.first() {
+                Some(day) => match parse_weekday(day) {
+                    Some(d) => d,
+                    None => {
+                        return Ok(vec![Reply::Refuse(format!("`{}` is not a day of the week.", day))]);
+                    }
+                },
+                None => 0,
+            };
+            let utc_offset_minutes = match rest.get(1) {
+                Some(offset) => match parse_utc_offset(offset) {
+                    Some(o) => o,
+                    None => {
+                        return Ok(vec![Reply::Refuse(format!(
+                            "`{}` is not a UTC offset, like +02:00 or -5.",
+                            offset
+                        ))]);
+                    }
+                },
+                None => 0,
+            };
+            settings.get_mut(guild_id).weekly_beat = Some(WeeklyBeat {
+                reset_day,
+                utc_offset_minutes,
+            });
+            settings.save(path)?;
+            let mut day = WEEKDAYS[reset_day as usize].to_owned();
+            day[..1].make_ascii_uppercase();
+            Ok(vec![Reply::Say(format!(
+                "Each player's first roll of the week now earns a beat. Weeks start on {} at midnight {}.",
+                day,
+                format_utc_offset(utc_offset_minutes)
+            ))])
+        }
+        ["weeklybeat", "off"] => {
+            settings.get_mut(guild_id).weekly_beat = None;
+            settings.save(path)?;
+            Ok(vec![Reply::Ack])
+        }
+        ["dice-emoji", "show"] => {
+            let dice_emoji = settings.get(guild_id).map(|g| &g.dice_emoji);
+            let lines: Vec<String> = match dice_emoji {
+                Some(map) if !map.is_empty() => map
+                    .iter()
+                    .map(|(face, emoji)| format!("{}: {}", face, emoji))
+                    .collect(),
+                _ => return Ok(vec![Reply::Say(String::from("No dice emoji are set."))]),
+            };
+            Ok(vec![Reply::Say(lines.join("\n"))])
+        }
+        ["dice-emoji", face, emoji] => {
+            let face = match parse_face(face) {
+                Some(f) => f,
+                None => {
+                    return Ok(vec![Reply::Refuse(String::from(
+                        "The face must be a number from 1 to 10.",
+                    ))]);
+                }
+            };
+            if emoji.eq_ignore_ascii_case("clear") {
+                settings.get_mut(guild_id).dice_emoji.remove(&face);
+            } else if is_valid_emoji(emoji) {
+                settings
+                    .get_mut(guild_id)
+                    .dice_emoji
+                    .insert(face, emoji.to_string());
+            } else {
+                return Ok(vec![Reply::Refuse(format!(
+                    "`{}` is not an emoji this server can use.",
+                    emoji
+                ))]);
+            }
+            settings.save(path)?;
+            Ok(vec![Reply::Ack])
+        }
+        ["outcome-markers", toggle @ "on"] | ["outcome-markers", toggle @ "off"] => {
+            settings.get_mut(guild_id).outcome_markers = *toggle == "on";
+            settings.save(path)?;
+            Ok(vec![Reply::Ack])
+        }
+        ["outcome-markers", outcome, marker] => {
+            let outcome = match Outcome::parse(outcome) {
+                Some(o) => o,
+                None => {
+                    let names: Vec<&str> = OUTCOMES.iter().map(|o| o.name()).collect();
+                    return Ok(vec![Reply::Refuse(format!(
+                        "`{}` is not an outcome; use {}.",
+                        outcome,
+                        names.join(", ")
+                    ))]);
+                }
+            };
+            let symbols = &mut settings.get_mut(guild_id).outcome_marker_symbols;
+            if marker.eq_ignore_ascii_case("default") {
+                symbols.remove(&outcome);
+            } else if is_valid_marker(marker) {
+                symbols.insert(outcome, marker.to_string());
+            } else {
+                return Ok(vec![Reply::Refuse(format!(
+                    "`{}` can't be a marker; use up to 8 characters without spaces or markup.",
+                    marker
+                ))]);
+            }
+            settings.save(path)?;
+            Ok(vec![Reply::Ack])
+        }
+        ["delete-invocations", toggle @ "on"] | ["delete-invocations", toggle @ "off"] => {
+            settings.get_mut(guild_id).delete_invocations = *toggle == "on";
+            settings.save(path)?;
+            Ok(vec![Reply::Ack])
+        }
+        ["alias", "add", name, expansion @ ..] if !expansion.is_empty() => {
+            // Taken as typed, so quotes within it still group its arguments
+            let expansion = unquote(after_words(&input.line, 3)).trim_start_matches(PREFIX);
+            let aliases = &mut settings.get_mut(guild_id).aliases;
+            if let Err(e) = check_alias(name, expansion, aliases) {
+                return Ok(vec![Reply::Refuse(e)]);
+            }
+            aliases.insert(name.to_lowercase(), expansion.to_owned());
+            settings.save(path)?;
+            Ok(vec![Reply::Ack])
+        }
+        ["alias", "remove", name] => {
+            if settings
+                .get_mut(guild_id)
+                .aliases
+                .remove(&name.to_lowercase())
+                .is_none()
+            {
+                return Ok(vec![Reply::Refuse(String::from("There is no alias by that name."))]);
+            }
+            settings.save(path)?;
+            Ok(vec![Reply::Ack])
+        }
+        ["alias", "list"] => {
+            let lines: Vec<String> = match settings.get(guild_id).map(|g| &g.aliases) {
+                Some(aliases) if !aliases.is_empty() => aliases
+                    .iter()
+                    .map(|(name, expansion)| {
+                        format!("`{}{}` → `{}{}`", PREFIX, name, PREFIX, expansion)
+                    })
+                    .collect(),
+                _ => return Ok(vec![Reply::Say(String::from("No aliases are set."))]),
+            };
+            Ok(vec![Reply::Say(lines.join("\n"))])
+        }
+        ["const", "set", name, value] => {
+            let value = match value.parse::<i64>() {
+                Ok(v) => v,
+                Err(_) => return Ok(vec![Reply::Refuse(format!("`{}` is not a number.", value))]),
+            };
+            if let Err(e) = settings.get_mut(guild_id).set_constant(name, value) {
+                return Ok(vec![Reply::Refuse(e)]);
+            }
+            settings.save(path)?;
+            Ok(vec![Reply::Ack])
+        }
+        ["const", "remove", name] => {
+            if !settings.get_mut(guild_id).remove_constant(name) {
+                return Ok(vec![Reply::Refuse(String::from("There is no constant by that name."))]);
+            }
+            settings.save(path)?;
+            Ok(vec![Reply::Ack])
+        }
+        ["const", "list"] => {
+            let lines: Vec<String> = match settings.get(guild_id).map(|g| &g.constants) {
+                Some(constants) if !constants.is_empty() => constants
+                    .iter()
+                    .map(|(name, value)| format!("`{}` = {}", name, value))
+                    .collect(),
+                _ => return Ok(vec![Reply::Say(String::from("No constants are set."))]),
+            };
+            Ok(vec![Reply::Say(lines.join("\n"))])
+        }
+        _ => Ok(vec![Reply::Refuse(String::from(USAGE))]),
+    }
+}
+
+#[command]
+pub fn settings(context: &mut Context, message: &Message, args: Args) -> CommandResult {
+    let is_admin = is_admin(context, message);
+    let image = match message.attachments.first() {
+        Some(attachment) if is_admin => {
+            if let Err(e) = check_image_meta(&attachment.filename, attachment.size) {
+                message.channel_id.say(&context.http, &e)?;
+                return Ok(());
+            }
+            Some(attachment.download()?)
+        }
+        _ => None,
+    };
+    let input = SettingsInput {
+        user_id: message.author.id.0,
+        guild_id: message.guild_id.map(|g| g.0),
+        is_admin,
+        line: args.rest().to_owned(),
+        image,
+    };
+    let mut store = SettingsStore::from_file(&SETTINGS_PATH)?;
+    let replies = handle_settings(&input, &mut store, &SETTINGS_PATH, &MERITS_DIR)?;
+    send(context, message, replies)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        after_words, check_image_meta, check_png, format_utc_offset, is_valid_emoji, parse_face,
+        parse_utc_offset, parse_weekday, unquote, MAX_IMAGE_BYTES, PNG_MAGIC,
+    };
+
+    #[test]
+    fn test_after_words() {
+        assert_eq!(after_words("alias add r roll 5", 3), "roll 5");
+        assert_eq!(
+            after_words("  alias  add r   buff add wits +1 \"Blessing\" ", 3),
+            "buff add wits +1 \"Blessing\""
+        );
+        assert_eq!(after_words("alias add r", 3), "");
+    }
+
+    #[test]
+    fn test_unquote() {
+        assert_eq!(unquote("\"stats print\""), "stats print");
+        assert_eq!(unquote("buff add wits +1 \"Blessing\""), "buff add wits +1 \"Blessing\"");
+        assert_eq!(unquote("\"a\" \"b\""), "\"a\" \"b\"");
+        assert_eq!(unquote("\""), "\"");
+    }
+
+    #[test]
+    fn test_check_image_meta() {
+        assert!(check_image_meta("blade.png", 1024).is_ok());
+        assert!(check_image_meta("Blade.PNG", MAX_IMAGE_BYTES).is_ok());
+        assert!(check_image_meta("blade.jpg", 1024).is_err());
+        assert!(check_image_meta("blade.png", MAX_IMAGE_BYTES + 1).is_err());
+    }
+
+    #[test]
+    fn test_check_png() {
+        let mut bytes = PNG_MAGIC.to_vec();
+        bytes.extend_from_slice(b"rest of the image");
+
+        assert!(check_png(&bytes).is_ok());
+        assert!(check_png(b"GIF89a").is_err());
+        assert!(check_png(&[]).is_err());
+    }
+
+    #[test]
+    fn test_parse_weekday() {
+        assert_eq!(parse_weekday("monday"), Some(0));
+        assert_eq!(parse_weekday("Sun"), Some(6));
+        assert_eq!(parse_weekday("thurs"), Some(3));
+        assert_eq!(parse_weekday("t"), None);
+        assert_eq!(parse_weekday("someday"), None);
+    }
+
+    #[test]
+    fn test_parse_utc_offset() {
+        assert_eq!(parse_utc_offset("UTC"), Some(0));
+        assert_eq!(parse_utc_offset("+2"), Some(120));
+        assert_eq!(parse_utc_offset("utc-5"), Some(-300));
+        assert_eq!(parse_utc_offset("+05:30"), Some(330));
+        assert_eq!(parse_utc_offset("-09:30"), Some(-570));
+        assert_eq!(parse_utc_offset("3"), Some(180));
+        assert_eq!(parse_utc_offset("+15"), None);
+        assert_eq!(parse_utc_offset("+02:60"), None);
+        assert_eq!(parse_utc_offset("EST"), None);
+    }
+
+    #[test]
+    fn test_format_utc_offset() {
+        assert_eq!(format_utc_offset(0), "UTC+00:00");
+        assert_eq!(format_utc_offset(330), "UTC+05:30");
+        assert_eq!(format_utc_offset(-570), "UTC-09:30");
+    }
+
+    #[test]
+    fn test_is_valid_emoji() {
+        assert!(is_valid_emoji("<:crit:123456789>"));
+        assert!(is_valid_emoji("<a:spin:42>"));
+        assert!(is_valid_emoji("🎲"));
+        assert!(is_valid_emoji("💥"));
+        assert!(is_valid_emoji("🔟"));
+        assert!(is_valid_emoji("1️⃣"));
+        assert!(!is_valid_emoji(":crit:"));
+        assert!(!is_valid_emoji("<:crit:>"));
+        assert!(!is_valid_emoji("crit"));
+        assert!(!is_valid_emoji("10"));
+        assert!(!is_valid_emoji("🎲 🎲"));
+        assert!(!is_valid_emoji("@everyone🎲"));
+        assert!(!is_valid_emoji(""));
+    }
+
+    #[test]
+    fn test_parse_face() {
+        assert_eq!(parse_face("1"), Some(1));
+        assert_eq!(parse_face("10"), Some(10));
+        assert_eq!(parse_face("0"), None);
+        assert_eq!(parse_face("11"), None);
+        assert_eq!(parse_face("ten"), None);
+    }
+}
@@ -1,77 +1,531 @@
+use failure::Error;
 use log::debug;
 use serenity::{
     client::Context,
     framework::standard::{Args, CommandResult, macros::command},
-    model::channel::Message,
+    model::{channel::Message, id::UserId},
     utils::MessageBuilder,
 };
-use crate::util::{constants::LOAD_PATH, characters::CharacterStore};
+use std::path::Path;
+use crate::{
+    commands::roll::STAT_MODIFIERS,
+    util::{
+        args::split_args,
+        audit::audit,
+        constants::LOAD_PATH,
+        characters::{BonusRule, Character, CharacterStore},
+        export_md,
+        respond::{send, Reply},
+        roll_tokens::reserved_reason,
+        stat_names::canonical,
+        stat_values::{parse_stat_value, DOTS},
+        text::count,
+    },
+};
 
-#[command]
-pub fn stats(context: &mut Context, message: &Message, args: Args) -> CommandResult {
-    let mut args = args;
+/// Subcommands that only read the character.
+const READ_ONLY: [&str; 4] = ["print", "show", "sheet", "audit"];
+const EXCEPTIONAL_USAGE: &str = "`!stats exceptional <stat_name> <1-5|clear>`";
+const CLEAR_USAGE: &str = "This removes every stat on your sheet. \
+To go ahead, use `!stats clear confirm`.";
+const BONUS_RULE_USAGE: &str = "`!stats bonusrule add <stat_name> <+dice> \"<source>\"`
+`!stats bonusrule remove <number>`
+`!stats bonusrule list`";
+
+/// Returns the contents of the first fenced code block in the text.
+///
+/// A language tag on the opening fence is dropped.
+///
+/// # Arguments
+///
+/// * `content` - message content
+///
+/// # Examples
+///
+/// ```rust
+/// let block = extract_code_block("!stats import\n```\nwits: 3\n```");
+/// ```
+fn extract_code_block(content: &str) -> Option<&str> {
+    let start = content.find("```")? + 3;
+    let rest = &content[start..];
+    let block = &rest[..rest.find("```")?];
+    match block.find('\n') {
+        Some(i) if block[..i].chars().all(char::is_alphanumeric) => Some(&block[i + 1..]),
+        _ => Some(block),
+    }
+}
+
+/// Explains why a stat can't be given a name, if it can't.
+///
+/// # Arguments
+///
+/// * `name` - stat name as typed
+///
+/// # Examples
+///
+/// ```rust
+/// if let Some(e) = check_stat_name("5") { /* refuse */ }
+/// ```
+fn check_stat_name(name: &str) -> Option<String> {
+    let name = canonical(name);
+    reserved_reason(&name)
+        .map(|reason| format!("`{}` can't be a stat name because {}", name, reason))
+}
+
+/// Returns where a value written as words or `o` dots starts in a line,
+/// like the `three` in `brawl three`.
+fn word_value_start(line: &str) -> Option<usize> {
+    line.char_indices()
+        .filter(|&(i, c)| i > 0 && !c.is_whitespace() && line[..i].ends_with(char::is_whitespace))
+        .map(|(i, _)| i)
+        .find(|&i| parse_stat_value(&line[i..]).is_ok())
+}
+
+/// Parse one line of an imported stat block.
+///
+/// Accepts `name: value`, `name=value`, and `name value` forms,
+/// where the value may be written as dots or a word. Blank lines yield `None`.
+///
+/// # Arguments
+///
+/// * `line` - line to parse
+///
+/// # Examples
+///
+/// ```rust
+/// let stat = parse_import_line("brawl ••").unwrap();
+/// ```
+fn parse_import_line(line: &str) -> Result<Option<(String, i64)>, String> {
+    let line = line.trim();
+    if line.is_empty() {
+        return Ok(None);
+    }
+    let split_at = match line.find(&[':', '='][..]) {
+        Some(i) => Some((i, i + 1)),
+        None => line
+            .find(|c: char| c.is_ascii_digit() || c == '-' || (c != 'o' && DOTS.contains(&c)))
+            .or_else(|| word_value_start(line))
+            .map(|i| (i, i)),
+    };
+    let (name, value) = match split_at {
+        Some((end, start)) => (line[..end].trim(), line[start..].trim()),
+        None => return Err(format!("missing a value for `{}`", line)),
+    };
+    if name.is_empty() {
+        return Err(String::from("missing a stat name"));
+    }
+    if value.is_empty() {
+        return Err(format!("missing a value for `{}`", name));
+    }
+    let value = parse_stat_value(value)?;
+    let name = name.split_whitespace().collect::<Vec<_>>().join("_");
+    if let Some(e) = check_stat_name(&name) {
+        return Err(e);
+    }
+    Ok(Some((name, value)))
+}
+
+/// Parse every line of an imported stat block.
+///
+/// Either all lines parse and the stats are returned,
+/// or every failing line is reported with its line number.
+///
+/// # Arguments
+///
+/// * `block` - code block contents
+///
+/// # Examples
+///
+/// ```rust
+/// let stats = parse_import("wits: 3\nbrawl ••").unwrap();
+/// ```
+fn parse_import(block: &str) -> Result<Vec<(String, i64)>, Vec<String>> {
+    let mut stats = vec![];
+    let mut errors = vec![];
+    for (index, line) in block.lines().enumerate() {
+        match parse_import_line(line) {
+            Ok(Some(stat)) => stats.push(stat),
+            Ok(None) => {}
+            Err(e) => errors.push(format!("line {}: {}", index + 1, e)),
+        }
+    }
+    if errors.is_empty() {
+        Ok(stats)
+    } else {
+        Err(errors)
+    }
+}
+
+/// Input to the stats command.
+#[derive(Debug)]
+pub struct StatsInput {
+    pub user_id: UserId,
+    pub user_name: String,
+    pub line: String,
+}
+
+/// Reply to a stats subcommand that only reads the character.
+///
+/// # Arguments
+///
+/// * `args` - the subcommand and its arguments
+/// * `character` - character to read
+///
+/// # Examples
+///
+/// ```rust
+/// let replies = read_stats(&["print"], &character);
+/// ```
+fn read_stats(args: &[&str], character: &Character) -> Vec<Reply> {
+    match args {
+        ["sheet", "md", ..] => vec![Reply::File {
+            name: export_md::file_name(character.name()),
+            content: export_md::render(character).into_bytes(),
+        }],
+        ["sheet", ..] => vec![Reply::Refuse(String::from("`!stats sheet md`"))],
+        ["audit", rest @ ..] => {
+            let supernatural = match rest {
+                [] => false,
+                ["supernatural", ..] => true,
+                _ => return vec![Reply::Refuse(String::from("`!stats audit [supernatural]`"))],
+            };
+            let response = MessageBuilder::new()
+                .push_codeblock_safe(audit(character, supernatural).report(), None)
+                .build();
+            vec![Reply::Say(response)]
+        }
+        _ => {
+            let response = MessageBuilder::new()
+                .push_codeblock_safe(character.to_string(), None)
+                .build();
+            vec![Reply::Say(response)]
+        }
+    }
+}
+
+/// Handle a stats command, returning the replies to send.
+///
+/// Changes are saved to the store's file before returning.
+///
+/// # Arguments
+///
+/// * `input` - who invoked the command and what they typed after `!stats`
+/// * `store` - character store to read and update
+/// * `path` - path the store is saved to
+///
+/// # Examples
+///
+/// ```rust
+/// let replies = handle_stats(&input, &mut store, &LOAD_PATH)?;
+/// ```
+pub fn handle_stats(
+    input: &StatsInput,
+    store: &mut CharacterStore,
+    path: &Path,
+) -> Result<Vec<Reply>, Error> {
+    let args: Vec<&str> = input.line.split_whitespace().collect();
     if args.is_empty() {
         debug!("No args supplied to stats command");
-        return Ok(());
-    }
-    let first_arg = args.single::<String>().unwrap();
-    let username = &message.author.name;
-    let mut cs = CharacterStore::from_file(&LOAD_PATH).unwrap();
-    let character = cs.get_mut(username);
-    if first_arg == "print" || first_arg == "show" {
-        let response = MessageBuilder::new()
-            .push_codeblock(&character, None)
-            .build();
-        message.channel_id.say(&context.http, &response)?;
-    } else if first_arg == "edit" {
+        return Ok(vec![]);
+    }
+    let first_arg = args[0];
+    if READ_ONLY.contains(&first_arg) {
+        // Looking at a character that doesn't exist yet mustn't create it
+        let new_character = Character::new(&input.user_name);
+        let character = store.get(input.user_id.0, &input.user_name).unwrap_or(&new_character);
+        return Ok(read_stats(&args, character));
+    }
+    let character = store.get_mut(input.user_id.0, &input.user_name);
+    character.set_last_writer(input.user_id.0);
+    if first_arg == "edit" {
         if args.len() != 3 {
-            message
-                .channel_id
-                .say(&context.http, "`!stats edit <stat_name> <stat_value>`")?;
-            return Ok(());
+            return Ok(vec![Reply::Refuse(String::from(
+                "`!stats edit <stat_name> <stat_value>`",
+            ))]);
+        }
+        let stat_key = args[1];
+        if let Some(e) = check_stat_name(stat_key) {
+            return Ok(vec![Reply::Refuse(format!("{}.", e))]);
         }
-        let stat_key = args.trimmed().single::<String>().unwrap();
-        let stat_value = match args.trimmed().single::<i64>() {
+        let stat_value = match parse_stat_value(args[2]) {
             Ok(i) => i,
-            Err(_) => {
-                message.channel_id.say(
-                    &context.http,
-                    "`The <stat_value> argument must be a number`",
-                )?;
-                return Ok(());
-            }
+            Err(e) => return Ok(vec![Reply::Refuse(e)]),
         };
         debug!("Stats edit args are: {} | {}", stat_key, stat_value);
-        character.set_value(&stat_key, stat_value);
-        cs.save(&LOAD_PATH)?;
-        message.channel_id.say(&context.http, "Got it.")?;
+        character.set_value(stat_key, stat_value);
+        store.save(path)?;
+        Ok(vec![Reply::Ack])
     } else if first_arg == "bulk" {
         let mut change_counter = 0u64;
-        while let Ok(next) = args.trimmed().single::<String>() {
+        for next in &args[1..] {
             let parts: Vec<&str> = next.split('=').collect();
             if parts.len() != 2 {
-                message.channel_id.say(&context.http, "Bulk-editing stats is done in the form: name1=value1 name2=value2 name3=value3 ...")?;
-                return Ok(());
+                return Ok(vec![Reply::Refuse(String::from(
+                    "Bulk-editing stats is done in the form: name1=value1 name2=value2 name3=value3 ...",
+                ))]);
             }
-            let stat_value = match parts[1].parse::<i64>() {
+            if let Some(e) = check_stat_name(parts[0]) {
+                return Ok(vec![Reply::Refuse(format!("{}.", e))]);
+            }
+            let stat_value = match parse_stat_value(parts[1]) {
                 Ok(v) => v,
-                Err(_) => {
-                    message.channel_id.say(
-                        &context.http,
-                        "Bulk-editing stats values must be whole numbers, i.e. 1, 49, 0",
-                    )?;
-                    return Ok(());
-                }
+                Err(e) => return Ok(vec![Reply::Refuse(e)]),
             };
             character.set_value(parts[0], stat_value);
             change_counter += 1;
         }
-        cs.save(&LOAD_PATH)?;
+        store.save(path)?;
         if change_counter > 0 {
-            message
-                .channel_id
-                .say(&context.http, &format!("Saved {} stats", change_counter))?;
+            Ok(vec![Reply::Ack])
+        } else {
+            Ok(vec![])
+        }
+    } else if first_arg == "delete" {
+        if args.len() != 2 {
+            return Ok(vec![Reply::Refuse(String::from("`!stats delete <stat_name>`"))]);
+        }
+        if !character.remove_value(args[1]) {
+            return Ok(vec![Reply::Refuse(String::from("That stat doesn't exist."))]);
+        }
+        store.save(path)?;
+        Ok(vec![Reply::Say(format!("Deleted {}.", canonical(args[1])))])
+    } else if first_arg == "clear" {
+        match &args[1..] {
+            ["confirm"] => {
+                let cleared = character.clear_values();
+                if cleared > 0 {
+                    store.save(path)?;
+                }
+                Ok(vec![Reply::Say(format!(
+                    "Cleared {}.",
+                    count(cleared as i64, "stat", "stats")
+                ))])
+            }
+            _ => Ok(vec![Reply::Refuse(String::from(CLEAR_USAGE))]),
         }
+    } else if first_arg == "modifier" {
+        if args.len() != 3 {
+            return Ok(vec![Reply::Refuse(format!(
+                "`!stats modifier <stat_name> <{}|clear>`",
+                STAT_MODIFIERS.join("|")
+            ))]);
+        }
+        let stat_key = args[1];
+        let modifier = args[2].to_lowercase();
+        if modifier == "clear" {
+            let response = if character.remove_modifier(stat_key) {
+                "Default modifier cleared."
+            } else {
+                "That stat has no default modifier."
+            };
+            store.save(path)?;
+            return Ok(vec![Reply::Say(String::from(response))]);
+        }
+        if !STAT_MODIFIERS.contains(&modifier.as_str()) {
+            return Ok(vec![Reply::Refuse(format!(
+                "The modifier must be one of: {}",
+                STAT_MODIFIERS.join(", ")
+            ))]);
+        }
+        character.set_modifier(stat_key, &modifier);
+        store.save(path)?;
+        Ok(vec![Reply::Ack])
+    } else if first_arg == "exceptional" {
+        if args.len() != 3 {
+            return Ok(vec![Reply::Refuse(String::from(EXCEPTIONAL_USAGE))]);
+        }
+        let stat_key = args[1];
+        if args[2].eq_ignore_ascii_case("clear") {
+            let response = if character.remove_exceptional(stat_key) {
+                "Exceptional success threshold cleared."
+            } else {
+                "That stat has no exceptional success threshold."
+            };
+            store.save(path)?;
+            return Ok(vec![Reply::Say(String::from(response))]);
+        }
+        match args[2].parse::<u64>() {
+            Ok(successes) if (1..=5).contains(&successes) => {
+                character.set_exceptional(stat_key, successes);
+                store.save(path)?;
+                Ok(vec![Reply::Ack])
+            }
+            _ => Ok(vec![Reply::Refuse(String::from(EXCEPTIONAL_USAGE))]),
+        }
+    } else if first_arg == "import" {
+        let block = match extract_code_block(&input.line) {
+            Some(b) => b,
+            None => {
+                return Ok(vec![Reply::Refuse(String::from(
+                    "Put the stats to import in a code block after `!stats import`, one `name: value` per line",
+                ))]);
+            }
+        };
+        let stats = match parse_import(block) {
+            Ok(s) => s,
+            Err(errors) => {
+                return Ok(vec![Reply::Refuse(format!(
+                    "Nothing was imported:\n{}",
+                    errors.join("\n")
+                ))]);
+            }
+        };
+        if stats.is_empty() {
+            return Ok(vec![Reply::Refuse(String::from("No stats found in the code block"))]);
+        }
+        for (key, value) in &stats {
+            character.set_value(key, *value);
+        }
+        store.save(path)?;
+        Ok(vec![Reply::Say(format!(
+            "Imported {}",
+            count(stats.len() as i64, "stat", "stats")
+        ))])
+    } else if first_arg == "bonusrule" {
+        let args = split_args(&input.line);
+        let args: Vec<&str> = args.iter().map(String::as_str).collect();
+        match args.as_slice() {
+            ["bonusrule", "add", stat, dice, source @ ..] if !source.is_empty() => {
+                let dice = match dice.parse::<i64>() {
+                    Ok(d) => d,
+                    Err(_) => {
+                        return Ok(vec![Reply::Refuse(String::from(
+                            "The dice for a bonus rule must be a whole number, i.e. +1",
+                        ))]);
+                    }
+                };
+                character.add_bonus_rule(BonusRule {
+                    stat: stat.to_string(),
+                    dice,
+                    source: source.join(" "),
+                });
+                store.save(path)?;
+                Ok(vec![Reply::Ack])
+            }
+            ["bonusrule", "remove", index] => {
+                let removed = index
+                    .parse::<usize>()
+                    .ok()
+                    .and_then(|i| character.remove_bonus_rule(i));
+                match removed {
+                    Some(rule) => {
+                        store.save(path)?;
+                        Ok(vec![Reply::Say(format!("Removed bonus rule: {}", rule))])
+                    }
+                    None => Ok(vec![Reply::Refuse(format!("There is no bonus rule {}.", index))]),
+                }
+            }
+            ["bonusrule", "list"] => {
+                if character.bonus_rules().is_empty() {
+                    return Ok(vec![Reply::Say(String::from("No bonus rules."))]);
+                }
+                let lines: Vec<String> = character
+                    .bonus_rules()
+                    .iter()
+                    .enumerate()
+                    .map(|(i, r)| format!("{}. {}", i + 1, r))
+                    .collect();
+                Ok(vec![Reply::Say(lines.join("\n"))])
+            }
+            _ => Ok(vec![Reply::Refuse(String::from(BONUS_RULE_USAGE))]),
+        }
+    } else {
+        Ok(vec![])
     }
+}
+
+#[command]
+pub fn stats(context: &mut Context, message: &Message, args: Args) -> CommandResult {
+    let input = StatsInput {
+        user_id: message.author.id,
+        user_name: message.author.name.clone(),
+        line: args.rest().to_owned(),
+    };
+    let mut cs = CharacterStore::from_file(&LOAD_PATH)?;
+    let replies = handle_stats(&input, &mut cs, &LOAD_PATH)?;
+    send(context, message, replies)?;
     Ok(())
 }
+
+#[cfg(test)]
+mod test {
+    use super::{extract_code_block, parse_import, parse_import_line};
+
+    #[test]
+    fn test_extract_code_block() {
+        assert_eq!(extract_code_block("import\n```\nwits: 3\n```"), Some("wits: 3\n"));
+        assert_eq!(extract_code_block("import ```yaml\nwits: 3\n```"), Some("wits: 3\n"));
+        assert_eq!(extract_code_block("import ```wits: 3```"), Some("wits: 3"));
+        assert_eq!(extract_code_block("import wits: 3"), None);
+        assert_eq!(extract_code_block("import ```wits: 3"), None);
+    }
+
+    #[test]
+    fn test_parse_import_value_forms() {
+        let stat = |value| Ok(Some((String::from("brawl"), value)));
+        assert_eq!(parse_import_line("brawl 3"), stat(3));
+        assert_eq!(parse_import_line("brawl: -1"), stat(-1));
+        assert_eq!(parse_import_line("brawl ••"), stat(2));
+        assert_eq!(parse_import_line("brawl • • •"), stat(3));
+        assert_eq!(parse_import_line("brawl ***"), stat(3));
+        assert_eq!(parse_import_line("brawl ooo"), stat(3));
+        assert_eq!(parse_import_line("brawl three"), stat(3));
+        assert_eq!(parse_import_line("brawl 3 dots"), stat(3));
+        assert_eq!(parse_import_line("brawl = two dots"), stat(2));
+        assert_eq!(
+            parse_import_line("animal ken three"),
+            Ok(Some((String::from("animal_ken"), 3)))
+        );
+        assert!(parse_import_line("brawl lots").is_err());
+        assert!(parse_import_line("brawl ••x").is_err());
+    }
+
+    #[test]
+    fn test_parse_import_line() {
+        assert_eq!(parse_import_line("wits: 3"), Ok(Some((String::from("wits"), 3))));
+        assert_eq!(parse_import_line("wits=3"), Ok(Some((String::from("wits"), 3))));
+        assert_eq!(parse_import_line("  wits   3 "), Ok(Some((String::from("wits"), 3))));
+        assert_eq!(parse_import_line("brawl ••"), Ok(Some((String::from("brawl"), 2))));
+        assert_eq!(
+            parse_import_line("Animal Ken : 1"),
+            Ok(Some((String::from("Animal_Ken"), 1)))
+        );
+        assert_eq!(parse_import_line("   "), Ok(None));
+        assert!(parse_import_line("occult: lots").is_err());
+        assert!(parse_import_line(": 2").is_err());
+        assert!(parse_import_line("occult").is_err());
+        assert!(parse_import_line("occult:").is_err());
+    }
+
+    #[test]
+    fn test_parse_import_messy_paste() {
+        let block = "Strength: 3\n dexterity=2\nbrawl ••\n\nAnimal Ken : 1\nwits   3\n";
+        let stats = parse_import(block).unwrap();
+
+        assert_eq!(
+            stats,
+            vec![
+                (String::from("Strength"), 3),
+                (String::from("dexterity"), 2),
+                (String::from("brawl"), 2),
+                (String::from("Animal_Ken"), 1),
+                (String::from("wits"), 3),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_import_reports_line_numbers() {
+        let block = "Strength: 3\noccult: lots\n\nbrawl ••\n: 2\n";
+        let errors = parse_import(block).unwrap_err();
+
+        assert_eq!(
+            errors,
+            vec![
+                "line 2: `lots` is not a stat value; use a number like 3, dots like ••• \
+                 (or *** or ooo), a word like three, or 3 dots",
+                "line 5: missing a stat name",
+            ]
+        );
+    }
+}
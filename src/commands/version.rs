@@ -0,0 +1,24 @@
+use serenity::{
+    client::Context,
+    framework::standard::{CommandResult, macros::command},
+    model::channel::Message,
+    utils::MessageBuilder,
+};
+
+#[command]
+pub fn version(context: &mut Context, message: &Message) -> CommandResult {
+    let response = MessageBuilder::new()
+        .push_codeblock(
+            format!(
+                "cod_dice_roller {}\ncommit:   {}\nbuilt:    {}\nserenity: {}",
+                env!("CARGO_PKG_VERSION"),
+                env!("GIT_HASH"),
+                env!("BUILD_DATE"),
+                env!("SERENITY_VERSION"),
+            ),
+            None,
+        )
+        .build();
+    message.channel_id.say(&context.http, &response)?;
+    Ok(())
+}
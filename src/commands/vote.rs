@@ -0,0 +1,139 @@
+use log::{debug, warn};
+use serenity::{
+    client::Context,
+    framework::standard::{Args, CommandResult, macros::command},
+    model::channel::{Message, ReactionType},
+    utils::MessageBuilder,
+};
+use std::{thread, time::Duration};
+use crate::util::{
+    args::split_args,
+    polls::{OPTIONS, POLLS},
+    respond::{send, Reply},
+};
+
+const USAGE: &str = "`!vote \"<question>\" [seconds]`";
+/// How long a poll stays open, unless a time is given.
+const DEFAULT_SECONDS: u64 = 60;
+/// Shortest and longest time a poll can stay open.
+const MIN_SECONDS: u64 = 10;
+const MAX_SECONDS: u64 = 600;
+
+/// Parse the question and how many seconds the poll stays open.
+///
+/// # Arguments
+///
+/// * `line` - what was typed after `!vote`
+///
+/// # Examples
+///
+/// ```rust
+/// let (question, seconds) = parse_vote("\"Take the sewer route?\" 120")?;
+/// ```
+fn parse_vote(line: &str) -> Result<(String, u64), String> {
+    let args = split_args(line);
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    let (question, seconds) = match args.as_slice() {
+        [question] => (question, DEFAULT_SECONDS),
+        [question, seconds] => match seconds.parse::<u64>() {
+            Ok(s) => (question, s),
+            Err(_) => return Err(String::from(USAGE)),
+        },
+        _ => return Err(String::from(USAGE)),
+    };
+    if !(MIN_SECONDS..=MAX_SECONDS).contains(&seconds) {
+        return Err(format!(
+            "Polls can stay open from {} to {} seconds.",
+            MIN_SECONDS, MAX_SECONDS
+        ));
+    }
+    Ok((question.to_string(), seconds))
+}
+
+#[command]
+pub fn vote(context: &mut Context, message: &Message, args: Args) -> CommandResult {
+    let (question, seconds) = match parse_vote(args.rest()) {
+        Ok(v) => v,
+        Err(response) => {
+            send(context, message, vec![Reply::Refuse(response)])?;
+            return Ok(());
+        }
+    };
+    let instructions = format!(
+        "Vote with {}. Closes in {} seconds.",
+        OPTIONS.join(" "),
+        seconds
+    );
+    let poll_message = message.channel_id.send_message(&context.http, |m| {
+        m.embed(|e| {
+            e.title(&question);
+            e.description(&instructions);
+            e
+        })
+    })?;
+    POLLS.lock().unwrap().open(poll_message.id.0, &question);
+    for option in OPTIONS.iter() {
+        let reacted = poll_message.react(&*context, ReactionType::Unicode((*option).to_owned()));
+        if let Err(e) = reacted {
+            POLLS.lock().unwrap().close(poll_message.id.0);
+            return Err(e.into());
+        }
+    }
+
+    let http = context.http.clone();
+    let channel_id = message.channel_id;
+    let message_id = poll_message.id;
+    thread::spawn(move || {
+        thread::sleep(Duration::from_secs(seconds));
+        let poll = match POLLS.lock().unwrap().close(message_id.0) {
+            Some(p) => p,
+            None => return,
+        };
+        debug!("Closing poll {}: {:?}", message_id, poll);
+        let tally = poll.tally_text();
+        let edited = channel_id.edit_message(&http, message_id, |m| {
+            m.embed(|e| {
+                e.title(&poll.question);
+                e.description(format!("Voting closed. {}", tally));
+                e
+            })
+        });
+        if let Err(e) = edited {
+            warn!("Could not edit poll {}: {:?}", message_id, e);
+        }
+        let response = MessageBuilder::new()
+            .push("Vote closed on ")
+            .push_bold_safe(&poll.question)
+            .push(": ")
+            .push(poll.outcome())
+            .push(".")
+            .build();
+        if let Err(e) = channel_id.say(&http, &response) {
+            warn!("Could not announce poll {}: {:?}", message_id, e);
+        }
+    });
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::{parse_vote, USAGE};
+
+    #[test]
+    fn test_parse_vote() {
+        assert_eq!(
+            parse_vote("\"Take the sewer route?\" 120"),
+            Ok((String::from("Take the sewer route?"), 120))
+        );
+        assert_eq!(
+            parse_vote("\"Take the sewer route?\""),
+            Ok((String::from("Take the sewer route?"), 60))
+        );
+        assert_eq!(
+            parse_vote("\"Take the sewer route?\" 5"),
+            Err(String::from("Polls can stay open from 10 to 600 seconds."))
+        );
+        assert_eq!(parse_vote("\"Take the sewer route?\" soon"), Err(String::from(USAGE)));
+        assert_eq!(parse_vote(""), Err(String::from(USAGE)));
+    }
+}
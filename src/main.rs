@@ -1,4 +1,4 @@
-use log::{debug, error, info, LevelFilter};
+use log::{debug, error, info, warn, LevelFilter};
 use log4rs::{
     append::console::ConsoleAppender,
     config::{
@@ -7,20 +7,40 @@ use log4rs::{
         Logger,
         Root,
     },
-    encode::pattern::PatternEncoder,
+    encode::{pattern::PatternEncoder, Encode},
 };
 use serenity::{
     client::{Client, Context},
-    framework::{StandardFramework, standard::macros::group},
+    framework::{StandardFramework, standard::{DispatchError, macros::group}},
     prelude::EventHandler,
-    model::gateway::Ready,
+    model::{
+        channel::{ChannelType, Reaction, ReactionType},
+        gateway::Ready,
+        guild::Guild,
+        id::ChannelId,
+    },
 };
-use std::{env, path::Path};
+use std::{env, path::Path, process};
 
 mod commands;
-use commands::{help::*, health::*, merit::*, roll::*, stats::*};
+use commands::{
+    admin::*, buff::*, changelog::*, check::*, cleanup::*, condition::*, damage::*, delegate::*,
+    gm::*, help::*, health::*, invite::*, merit::*, permissions::*, roll::*, settings::*,
+    stats::*, version::*, vote::*,
+};
 
 mod util;
+use util::{
+    characters::CharacterStore,
+    constants::{LOAD_PATH, PREFIX, SETTINGS_PATH},
+    ephemeral,
+    framework::SplitFramework,
+    json_log::{clear_command_fields, set_command_fields, JsonEncoder},
+    onboarding::{onboarding_channel, Candidate, ONBOARDING},
+    polls::POLLS,
+    respond,
+    settings::SettingsStore,
+};
 
 struct Handler;
 
@@ -28,24 +48,83 @@ impl EventHandler for Handler {
     fn ready(&self, _context: Context, _ready: Ready) {
         info!("Bot connected");
     }
+
+    fn guild_create(&self, context: Context, guild: Guild, is_new: bool) {
+        if !is_new {
+            return;
+        }
+        let bot_id = context.cache.read().user.id;
+        let candidates: Vec<Candidate> = guild
+            .channels
+            .values()
+            .filter_map(|channel| {
+                let channel = channel.read();
+                if channel.kind != ChannelType::Text {
+                    return None;
+                }
+                Some(Candidate {
+                    id: channel.id.0,
+                    position: channel.position,
+                    writable: guild.permissions_in(channel.id, bot_id).send_messages(),
+                })
+            })
+            .collect();
+        let system_channel = guild.system_channel_id.map(|c| c.0);
+        let channel_id = match onboarding_channel(system_channel, &candidates) {
+            Some(c) => ChannelId(c),
+            None => {
+                warn!("Nowhere to post the onboarding message in {}", guild.id);
+                return;
+            }
+        };
+        if let Err(e) = channel_id.say(&context.http, ONBOARDING) {
+            warn!("Could not post the onboarding message in {}: {:?}", guild.id, e);
+        }
+    }
+
+    fn reaction_add(&self, context: Context, reaction: Reaction) {
+        if reaction.user_id == context.cache.read().user.id {
+            return;
+        }
+        if let ReactionType::Unicode(emoji) = &reaction.emoji {
+            POLLS
+                .lock()
+                .unwrap()
+                .react(reaction.message_id.0, reaction.user_id.0, emoji);
+        }
+    }
+
+    fn reaction_remove(&self, _context: Context, reaction: Reaction) {
+        if let ReactionType::Unicode(emoji) = &reaction.emoji {
+            POLLS
+                .lock()
+                .unwrap()
+                .unreact(reaction.message_id.0, reaction.user_id.0, emoji);
+        }
+    }
 }
 
 group!({
     name: "general",
     options: {},
-    commands: [help, health, merit, roll, stats]
+    commands: [
+        admin, buff, changelog, check, cleanup, condition, damage, delegate, gm, help, health,
+        invite, merit, permissions, roll, scene, settings, stats, version, vote
+    ]
 });
 
 fn setup_logger() {
     if Path::new("./log4rs.yml").exists() {
-        println!("Loading logging config from file");
+        eprintln!("Loading logging config from file");
         log4rs::init_file("log4rs.yml", Default::default())
             .expect("Could not load/apply log4rs configuration");
     } else {
-        println!("Loading coded logging config");
-        let stdout = ConsoleAppender::builder()
-            .encoder(Box::new(PatternEncoder::new("{d} {h({l})} {m}{n}")))
-            .build();
+        eprintln!("Loading coded logging config");
+        let encoder: Box<dyn Encode> = match env::var("LOG_FORMAT") {
+            Ok(ref format) if format.eq_ignore_ascii_case("json") => Box::new(JsonEncoder),
+            _ => Box::new(PatternEncoder::new("{d} {h({l})} {m}{n}")),
+        };
+        let stdout = ConsoleAppender::builder().encoder(encoder).build();
         let config = Config::builder()
             .appender(Appender::builder().build("stdout", Box::new(stdout)))
             .logger(
@@ -60,9 +139,37 @@ fn setup_logger() {
     }
 }
 
+/// Run `verify <seed> <dice>` from the command line, printing the dice a
+/// fair roll's seed gives.
+fn verify(args: &[String]) {
+    let (seed, spec) = match args {
+        [seed, spec @ ..] if !spec.is_empty() => (seed, spec.join(" ")),
+        _ => {
+            eprintln!("Usage: {} verify <seed> <dice> <modifier>", env!("CARGO_PKG_NAME"));
+            process::exit(2);
+        }
+    };
+    match verify_fair_roll(seed, &spec) {
+        Ok(dice) => println!("{}", dice),
+        Err(e) => {
+            eprintln!("{}", e);
+            process::exit(1);
+        }
+    }
+}
+
 fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    if args.first().map(String::as_str) == Some("verify") {
+        verify(&args[1..]);
+        return;
+    }
     setup_logger();
     debug!("Starting up");
+    lazy_static::initialize(&ALLOWED_MERITS);
+    // Nothing can be saving yet, so any temporary files are from a crash
+    CharacterStore::recover(&LOAD_PATH).expect("Could not clean up the character store");
+    SettingsStore::recover(&SETTINGS_PATH).expect("Could not clean up the settings store");
 
     info!("Loading environment");
     kankyo::load().expect("Failed to load .env file");
@@ -70,22 +177,39 @@ fn main() {
 
     info!("Creating client");
     let mut client = Client::new(&token, Handler).expect("Could not create client");
-    client.with_framework(
-        StandardFramework::new()
-            .configure(|c| c.prefix("!").case_insensitivity(true))
-            .group(&GENERAL_GROUP)
-            .after(|_context, _message, command_name, error| {
-                if let Err(why) = error {
-                    error!("Error in command '{}': {:?}", command_name, why);
+    let framework = StandardFramework::new()
+        .configure(|c| c.prefix(PREFIX).case_insensitivity(true))
+        .bucket("rolls", |b| b.time_span(10).limit(5))
+        .group(&GENERAL_GROUP)
+        .before(|_, message, command_name| {
+            set_command_fields(command_name, message.author.id.0, message.guild_id.map(|g| g.0));
+            debug!("Running command '{}'", command_name);
+            true
+        })
+        .after(|context, message, command_name, result| {
+            match &result {
+                Ok(()) => debug!("Finished command '{}'", command_name),
+                Err(why) => error!("Error in command '{}': {:?}", command_name, why),
+            }
+            respond::command_finished(context, message, command_name, result.is_ok());
+            clear_command_fields();
+        })
+        .on_dispatch_error(|context, message, error| {
+            if let DispatchError::Ratelimited(seconds) = error {
+                let response = format!("Slow down! Try again in {} seconds.", seconds);
+                if let Err(e) = message.channel_id.say(&context.http, &response) {
+                    error!("Could not send rate limit notice: {}", e);
                 }
-            })
-            .on_dispatch_error(|_context, message, error| {
-                error!(
-                    "Command error occurred in '{}': {:?}",
-                    message.content, error
-                );
-            }),
-    );
+                return;
+            }
+            error!(
+                "Command error occurred in '{}': {:?}",
+                message.content, error
+            );
+        });
+    client.with_framework(SplitFramework::new(framework, PREFIX));
+
+    ephemeral::spawn_worker(client.cache_and_http.http.clone());
 
     info!("Starting client");
     if let Err(err) = client.start() {
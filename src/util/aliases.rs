@@ -0,0 +1,167 @@
+use std::collections::BTreeMap;
+use crate::util::constants::COMMANDS;
+
+/// Longest alias name.
+const MAX_ALIAS_LEN: usize = 20;
+/// Most aliases a guild can have.
+pub const MAX_ALIASES: usize = 25;
+
+/// Check that an alias can be added to a guild's aliases.
+///
+/// Aliases can't take a command's name, and must expand to a command
+/// rather than another alias, so expanding one never recurses.
+///
+/// # Arguments
+///
+/// * `name` - alias name, without the prefix
+/// * `expansion` - command and any leading arguments it expands to
+/// * `aliases` - the guild's current aliases
+///
+/// # Examples
+///
+/// ```rust
+/// check_alias("r", "roll", &guild.aliases)?;
+/// ```
+pub fn check_alias(
+    name: &str,
+    expansion: &str,
+    aliases: &BTreeMap<String, String>,
+) -> Result<(), String> {
+    let name = name.to_lowercase();
+    let valid_name = !name.is_empty()
+        && name.chars().count() <= MAX_ALIAS_LEN
+        && name
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '-' || c == '_');
+    if !valid_name {
+        return Err(format!(
+            "`{}` can't be an alias; use up to {} letters, numbers, - or _.",
+            name, MAX_ALIAS_LEN
+        ));
+    }
+    if COMMANDS.contains(&name.as_str()) {
+        return Err(format!("`{}` is already a command.", name));
+    }
+    let command = expansion
+        .split_whitespace()
+        .next()
+        .unwrap_or("")
+        .to_lowercase();
+    if !COMMANDS.contains(&command.as_str()) {
+        let reason = if aliases.contains_key(&command) || command == name {
+            "is an alias"
+        } else {
+            "is not a command"
+        };
+        return Err(format!(
+            "`{}` {}; aliases must expand to a command, like `roll`.",
+            command, reason
+        ));
+    }
+    if !aliases.contains_key(&name) && aliases.len() >= MAX_ALIASES {
+        return Err(format!("This server already has {} aliases.", MAX_ALIASES));
+    }
+    Ok(())
+}
+
+/// Expand a command that starts with one of a guild's aliases.
+///
+/// Returns `None` when the command doesn't use an alias. Anything typed
+/// after the alias follows its expansion.
+///
+/// # Arguments
+///
+/// * `command` - a single command, like `!r 5 9again`
+/// * `prefix` - command prefix, such as `!`
+/// * `aliases` - the guild's aliases
+///
+/// # Examples
+///
+/// ```rust
+/// let command = expand_alias("!r 5 9again", "!", &guild.aliases);
+/// ```
+pub fn expand_alias(
+    command: &str,
+    prefix: &str,
+    aliases: &BTreeMap<String, String>,
+) -> Option<String> {
+    let command = command.trim_start();
+    if !command.starts_with(prefix) {
+        return None;
+    }
+    let rest = &command[prefix.len()..];
+    let name_end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+    let expansion = aliases.get(&rest[..name_end].to_lowercase())?;
+    Some(format!("{}{}{}", prefix, expansion, &rest[name_end..]))
+}
+
+#[cfg(test)]
+mod test {
+    use super::{check_alias, expand_alias, MAX_ALIASES};
+    use std::collections::BTreeMap;
+
+    fn aliases(pairs: &[(&str, &str)]) -> BTreeMap<String, String> {
+        pairs
+            .iter()
+            .map(|(name, expansion)| (name.to_string(), expansion.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_expand_alias() {
+        let aliases = aliases(&[("r", "roll"), ("sheet", "stats print")]);
+
+        assert_eq!(
+            expand_alias("!r 5 9again", "!", &aliases),
+            Some(String::from("!roll 5 9again"))
+        );
+        assert_eq!(expand_alias("!R chance", "!", &aliases), Some(String::from("!roll chance")));
+        assert_eq!(expand_alias("!sheet", "!", &aliases), Some(String::from("!stats print")));
+        assert_eq!(expand_alias("!roll 5", "!", &aliases), None);
+        assert_eq!(expand_alias("!rr 5", "!", &aliases), None);
+        assert_eq!(expand_alias("r 5", "!", &aliases), None);
+        assert_eq!(expand_alias("!r 5", "!", &BTreeMap::new()), None);
+    }
+
+    #[test]
+    fn test_check_alias() {
+        let current = aliases(&[("r", "roll")]);
+
+        assert_eq!(check_alias("sheet", "stats print", &current), Ok(()));
+        assert_eq!(check_alias("R", "roll chance", &current), Ok(()));
+        assert_eq!(
+            check_alias("roll", "stats", &current),
+            Err(String::from("`roll` is already a command."))
+        );
+        assert_eq!(
+            check_alias("rr", "r 5", &current),
+            Err(String::from("`r` is an alias; aliases must expand to a command, like `roll`."))
+        );
+        assert_eq!(
+            check_alias("x", "x", &current),
+            Err(String::from("`x` is an alias; aliases must expand to a command, like `roll`."))
+        );
+        assert_eq!(
+            check_alias("sheet", "character print", &current),
+            Err(String::from(
+                "`character` is not a command; aliases must expand to a command, like `roll`."
+            ))
+        );
+        assert!(check_alias("two words", "roll", &current).is_err());
+        assert!(check_alias("<@1>", "roll", &current).is_err());
+        assert!(check_alias("", "roll", &current).is_err());
+    }
+
+    #[test]
+    fn test_check_alias_limit() {
+        let full: BTreeMap<String, String> = (0..MAX_ALIASES)
+            .map(|i| (format!("r{}", i), String::from("roll")))
+            .collect();
+
+        assert_eq!(
+            check_alias("sheet", "stats print", &full),
+            Err(format!("This server already has {} aliases.", MAX_ALIASES))
+        );
+        assert_eq!(check_alias("r0", "roll chance", &full), Ok(()));
+    }
+}
@@ -0,0 +1,148 @@
+/// Split a command line into arguments, keeping double-quoted text together.
+///
+/// # Arguments
+///
+/// * `line` - text after the command name
+///
+/// # Examples
+///
+/// ```rust
+/// let args = split_args(r#"merit add "Ancestral Blade" fighting"#);
+/// ```
+pub fn split_args(line: &str) -> Vec<String> {
+    let mut args = vec![];
+    let mut current = String::new();
+    let mut in_quotes = false;
+    let mut has_arg = false;
+    for c in line.chars() {
+        if c == '"' {
+            in_quotes = !in_quotes;
+            has_arg = true;
+        } else if c.is_whitespace() && !in_quotes {
+            if has_arg {
+                args.push(current.clone());
+                current.clear();
+                has_arg = false;
+            }
+        } else {
+            current.push(c);
+            has_arg = true;
+        }
+    }
+    if has_arg {
+        args.push(current);
+    }
+    args
+}
+
+/// Split a message into the commands it contains, separated by semicolons.
+///
+/// A message that doesn't start with the command prefix is chat, and is
+/// never split. A semicolon only separates commands when the text after it
+/// starts with the command prefix. Semicolons inside double quotes or
+/// backtick code spans are left alone.
+///
+/// # Arguments
+///
+/// * `content` - message content
+/// * `prefix` - command prefix, such as `!`
+///
+/// # Examples
+///
+/// ```rust
+/// let commands = split_commands("!roll 5; !roll chance", "!");
+/// ```
+pub fn split_commands(content: &str, prefix: &str) -> Vec<String> {
+    if !content.trim_start().starts_with(prefix) {
+        let content = content.trim();
+        return if content.is_empty() {
+            vec![]
+        } else {
+            vec![content.to_owned()]
+        };
+    }
+    let mut commands = vec![];
+    let mut current = String::new();
+    let mut in_quotes = false;
+    let mut in_code = false;
+    let mut last = '\0';
+    for (index, c) in content.char_indices() {
+        if c == '`' && !in_quotes {
+            if last != '`' {
+                in_code = !in_code;
+            }
+        } else if c == '"' && !in_code {
+            in_quotes = !in_quotes;
+        } else if c == ';'
+            && !in_quotes
+            && !in_code
+            && content[index + 1..].trim_start().starts_with(prefix)
+        {
+            commands.push(current.trim().to_owned());
+            current.clear();
+            last = c;
+            continue;
+        }
+        current.push(c);
+        last = c;
+    }
+    commands.push(current.trim().to_owned());
+    commands.retain(|c| !c.is_empty());
+    commands
+}
+
+#[cfg(test)]
+mod test {
+    use super::{split_args, split_commands};
+
+    #[test]
+    fn test_split_args() {
+        assert_eq!(split_args("a b  c"), vec!["a", "b", "c"]);
+        assert_eq!(
+            split_args(r#"add "Ancestral Blade" fighting"#),
+            vec!["add", "Ancestral Blade", "fighting"]
+        );
+        assert_eq!(split_args(r#"a "" b"#), vec!["a", "", "b"]);
+        assert_eq!(split_args(r#""unterminated quote"#), vec!["unterminated quote"]);
+        assert!(split_args("   ").is_empty());
+    }
+
+    #[test]
+    fn test_split_commands() {
+        assert_eq!(split_commands("!roll 5", "!"), vec!["!roll 5"]);
+        assert_eq!(split_commands("!roll 5; !roll chance", "!"), vec!["!roll 5", "!roll chance"]);
+        assert_eq!(
+            split_commands("!roll 5;!roll 3 9again ; !stats show", "!"),
+            vec!["!roll 5", "!roll 3 9again", "!stats show"]
+        );
+        assert_eq!(split_commands("!roll 5;", "!"), vec!["!roll 5;"]);
+        assert_eq!(split_commands("!roll 5; not a command", "!"), vec!["!roll 5; not a command"]);
+        assert!(split_commands("", "!").is_empty());
+    }
+
+    #[test]
+    fn test_split_commands_leaves_chat_alone() {
+        assert_eq!(split_commands("lol; !roll 5", "!"), vec!["lol; !roll 5"]);
+        assert_eq!(
+            split_commands("try this; !roll 5; !roll chance", "!"),
+            vec!["try this; !roll 5; !roll chance"]
+        );
+        assert_eq!(split_commands("  !roll 5; !roll 3", "!"), vec!["!roll 5", "!roll 3"]);
+    }
+
+    #[test]
+    fn test_split_commands_ignores_quotes_and_code() {
+        assert_eq!(
+            split_commands(r#"!settings merit add "A; !b" c; !roll 2"#, "!"),
+            vec![r#"!settings merit add "A; !b" c"#, "!roll 2"]
+        );
+        assert_eq!(
+            split_commands("!roll 1 `see; !roll 2`; !roll 3", "!"),
+            vec!["!roll 1 `see; !roll 2`", "!roll 3"]
+        );
+        assert_eq!(
+            split_commands("!stats import\n```\nwits: 3; !roll 2\n```", "!"),
+            vec!["!stats import\n```\nwits: 3; !roll 2\n```"]
+        );
+    }
+}
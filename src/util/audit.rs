@@ -0,0 +1,390 @@
+use std::cmp::Reverse;
+use crate::util::characters::Character;
+
+/// The three categories of attributes and skills, with their traits.
+pub const ATTRIBUTES: [(&str, [&str; 3]); 3] = [
+    ("mental", ["intelligence", "wits", "resolve"]),
+    ("physical", ["strength", "dexterity", "stamina"]),
+    ("social", ["presence", "manipulation", "composure"]),
+];
+pub const SKILLS: [(&str, [&str; 8]); 3] = [
+    (
+        "mental",
+        [
+            "academics",
+            "computer",
+            "crafts",
+            "investigation",
+            "medicine",
+            "occult",
+            "politics",
+            "science",
+        ],
+    ),
+    (
+        "physical",
+        [
+            "athletics",
+            "brawl",
+            "drive",
+            "firearms",
+            "larceny",
+            "stealth",
+            "survival",
+            "weaponry",
+        ],
+    ),
+    (
+        "social",
+        [
+            "animal_ken",
+            "empathy",
+            "expression",
+            "intimidation",
+            "persuasion",
+            "socialize",
+            "streetwise",
+            "subterfuge",
+        ],
+    ),
+];
+
+/// Dots to spend on the primary, secondary and tertiary categories.
+const ATTRIBUTE_BUDGET: [i64; 3] = [5, 4, 3];
+const SKILL_BUDGET: [i64; 3] = [11, 7, 4];
+/// Highest rating a mortal's trait normally has.
+const MAX_RATING: i64 = 5;
+
+/// Dots spent on one category, compared with the budget for its priority.
+#[derive(Debug, PartialEq)]
+pub struct Spend {
+    pub category: &'static str,
+    pub spent: i64,
+    pub budget: i64,
+}
+
+/// What's missing or unusual on a character sheet.
+#[derive(Debug, PartialEq)]
+pub struct Audit {
+    pub missing_attributes: Vec<&'static str>,
+    pub unusual: Vec<(String, i64)>,
+    pub health_missing: bool,
+    pub attributes: Vec<Spend>,
+    pub skills: Vec<Spend>,
+    /// Extra attribute dots from a supernatural template.
+    pub template_dots: i64,
+}
+
+/// Pair categories with budgets, giving the largest budget to the
+/// category with the most dots spent.
+fn assign_budgets(mut spends: Vec<(&'static str, i64)>, budget: [i64; 3]) -> Vec<Spend> {
+    spends.sort_by_key(|s| Reverse(s.1));
+    spends
+        .into_iter()
+        .zip(budget.iter())
+        .map(|((category, spent), &budget)| Spend {
+            category,
+            spent,
+            budget,
+        })
+        .collect()
+}
+
+/// Compare a character against a standard Chronicles of Darkness sheet.
+///
+/// Attributes start with 1 free dot; the rest are spent 5/4/3 across the
+/// mental, physical and social categories, and skills 11/7/4. The
+/// category with the most dots is taken as the primary. A supernatural
+/// template grants 1 more attribute dot, spent in any category.
+///
+/// # Arguments
+///
+/// * `character` - character to check
+/// * `supernatural` - whether the character has a supernatural template
+///
+/// # Examples
+///
+/// ```rust
+/// let audit = audit(&character, false);
+/// ```
+pub fn audit(character: &Character, supernatural: bool) -> Audit {
+    let mut missing_attributes = vec![];
+    let mut attributes = vec![];
+    for (category, names) in &ATTRIBUTES {
+        let mut spent: i64 = 0;
+        for name in names {
+            match character.get_value(name) {
+                (true, value) => spent = spent.saturating_add(value.saturating_sub(1)),
+                (false, _) => missing_attributes.push(*name),
+            }
+        }
+        attributes.push((*category, spent));
+    }
+    let skills = SKILLS
+        .iter()
+        .map(|(category, names)| {
+            let spent = names
+                .iter()
+                .fold(0, |spent: i64, name| spent.saturating_add(character.get_value(name).1));
+            (*category, spent)
+        })
+        .collect();
+
+    let mut unusual: Vec<(String, i64)> = vec![];
+    for (_, names) in &ATTRIBUTES {
+        for name in names {
+            let (found, value) = character.get_value(name);
+            if found && !(1..=MAX_RATING).contains(&value) {
+                unusual.push(((*name).to_owned(), value));
+            }
+        }
+    }
+    for (_, names) in &SKILLS {
+        for name in names {
+            let value = character.get_value(name).1;
+            if !(0..=MAX_RATING).contains(&value) {
+                unusual.push(((*name).to_owned(), value));
+            }
+        }
+    }
+
+    Audit {
+        missing_attributes,
+        unusual,
+        health_missing: character.health().max() == 0,
+        attributes: assign_budgets(attributes, ATTRIBUTE_BUDGET),
+        skills: assign_budgets(skills, SKILL_BUDGET),
+        template_dots: i64::from(supernatural),
+    }
+}
+
+/// Describe spends against budgets, like "mental 5/5, social 4/3 (1 over)".
+fn describe_spends(spends: &[Spend], extra: i64) -> String {
+    let mut parts: Vec<String> = spends
+        .iter()
+        .map(|s| {
+            let difference = s.spent.saturating_sub(s.budget);
+            match difference {
+                0 => format!("{} {}/{}", s.category, s.spent, s.budget),
+                d if d > 0 => format!("{} {}/{} ({} over)", s.category, s.spent, s.budget, d),
+                d => format!(
+                    "{} {}/{} ({} under)",
+                    s.category,
+                    s.spent,
+                    s.budget,
+                    d.unsigned_abs()
+                ),
+            }
+        })
+        .collect();
+    if extra > 0 {
+        parts.push(format!("plus {} template dot", extra));
+    }
+    parts.join(", ")
+}
+
+impl Audit {
+    /// Total dots over (positive) or under (negative) the budgets.
+    fn balance(spends: &[Spend], extra: i64) -> i64 {
+        spends
+            .iter()
+            .fold(0, |total: i64, s| total.saturating_add(s.spent.saturating_sub(s.budget)))
+            .saturating_sub(extra)
+    }
+
+    /// Render the audit as a report, one finding per line.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// let report = audit(&character, false).report();
+    /// ```
+    pub fn report(&self) -> String {
+        let mut lines = vec![];
+        if !self.missing_attributes.is_empty() {
+            lines.push(format!(
+                "Missing attributes: {}",
+                self.missing_attributes.join(", ")
+            ));
+        }
+        if !self.unusual.is_empty() {
+            let unusual: Vec<String> = self
+                .unusual
+                .iter()
+                .map(|(name, value)| format!("{} {}", name, value))
+                .collect();
+            lines.push(format!("Unusual values: {}", unusual.join(", ")));
+        }
+        if self.health_missing {
+            lines.push(String::from("Health is not set up."));
+        }
+        for (label, spends, extra) in &[
+            ("Attributes", &self.attributes, self.template_dots),
+            ("Skills", &self.skills, 0),
+        ] {
+            let balance = Audit::balance(spends, *extra);
+            let total = match balance {
+                0 => String::from("on budget"),
+                b if b > 0 => format!("{} over budget", b),
+                b => format!("{} under budget", b.unsigned_abs()),
+            };
+            lines.push(format!(
+                "{}: {} - {}",
+                label,
+                describe_spends(spends, *extra),
+                total
+            ));
+        }
+        lines.join("\n")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{audit, Spend};
+    use crate::util::characters::Character;
+
+    /// A mortal built exactly to the creation budgets.
+    const STANDARD: &str = r#"{
+        "name": "Ada",
+        "stats": {
+            "intelligence": 3, "wits": 3, "resolve": 2,
+            "strength": 2, "dexterity": 3, "stamina": 2,
+            "presence": 2, "manipulation": 2, "composure": 2,
+            "academics": 3, "computer": 2, "investigation": 3, "occult": 3,
+            "athletics": 2, "brawl": 2, "drive": 1, "stealth": 2,
+            "empathy": 1, "persuasion": 1, "subterfuge": 2
+        },
+        "health": {"max": 7, "bashing": 0, "lethal": 0, "aggravated": 0}
+    }"#;
+
+    fn character(json: &str) -> Character {
+        serde_json::from_str(json).unwrap()
+    }
+
+    fn spend(category: &'static str, spent: i64, budget: i64) -> Spend {
+        Spend {
+            category,
+            spent,
+            budget,
+        }
+    }
+
+    #[test]
+    fn test_audit_standard() {
+        let result = audit(&character(STANDARD), false);
+        assert!(result.missing_attributes.is_empty());
+        assert!(result.unusual.is_empty());
+        assert!(!result.health_missing);
+        assert_eq!(
+            result.attributes,
+            vec![spend("mental", 5, 5), spend("physical", 4, 4), spend("social", 3, 3)]
+        );
+        assert_eq!(
+            result.skills,
+            vec![spend("mental", 11, 11), spend("physical", 7, 7), spend("social", 4, 4)]
+        );
+        assert_eq!(
+            result.report(),
+            "Attributes: mental 5/5, physical 4/4, social 3/3 - on budget\n\
+             Skills: mental 11/11, physical 7/7, social 4/4 - on budget"
+        );
+    }
+
+    #[test]
+    fn test_audit_empty() {
+        let result = audit(&Character::new("Paul"), false);
+        assert_eq!(result.missing_attributes.len(), 9);
+        assert!(result.health_missing);
+        assert_eq!(
+            result.report(),
+            "Missing attributes: intelligence, wits, resolve, strength, dexterity, stamina, \
+             presence, manipulation, composure\n\
+             Health is not set up.\n\
+             Attributes: mental 0/5 (5 under), physical 0/4 (4 under), social 0/3 (3 under) \
+             - 12 under budget\n\
+             Skills: mental 0/11 (11 under), physical 0/7 (7 under), social 0/4 (4 under) \
+             - 22 under budget"
+        );
+    }
+
+    #[test]
+    fn test_audit_unusual_values() {
+        let mut sheet = character(STANDARD);
+        sheet.set_value("strength", 0);
+        sheet.set_value("brawl", 7);
+        sheet.set_value("occult", -1);
+        let result = audit(&sheet, false);
+        assert_eq!(
+            result.unusual,
+            vec![
+                (String::from("strength"), 0),
+                (String::from("occult"), -1),
+                (String::from("brawl"), 7),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_audit_extreme_values() {
+        let mut sheet = character(STANDARD);
+        sheet.set_value("strength", i64::MIN);
+        sheet.set_value("dexterity", i64::MIN);
+        sheet.set_value("brawl", i64::MAX);
+        sheet.set_value("athletics", i64::MAX);
+        let report = audit(&sheet, false).report();
+
+        assert!(report.contains(&format!("strength {}", i64::MIN)), "{}", report);
+        assert!(
+            report.contains(&format!(
+                "physical {}/3 ({} under)",
+                i64::MIN + 1,
+                i64::MIN.unsigned_abs()
+            )),
+            "{}",
+            report
+        );
+        assert!(
+            report.contains(&format!("physical {}/11 ({} over)", i64::MAX, i64::MAX - 11)),
+            "{}",
+            report
+        );
+    }
+
+    #[test]
+    fn test_audit_priorities_follow_spend() {
+        // Social is primary here, so it gets the 5 dot budget
+        let mut sheet = character(STANDARD);
+        sheet.set_value("intelligence", 1);
+        sheet.set_value("wits", 2);
+        sheet.set_value("presence", 4);
+        let result = audit(&sheet, false);
+        assert_eq!(
+            result.attributes,
+            vec![spend("social", 5, 5), spend("physical", 4, 4), spend("mental", 2, 3)]
+        );
+    }
+
+    #[test]
+    fn test_audit_supernatural() {
+        let mut sheet = character(STANDARD);
+        sheet.set_value("resolve", 3);
+        let report = audit(&sheet, false).report();
+        assert_eq!(
+            report.lines().next().unwrap(),
+            "Attributes: mental 6/5 (1 over), physical 4/4, social 3/3 - 1 over budget"
+        );
+        let report = audit(&sheet, true).report();
+        assert_eq!(
+            report.lines().next().unwrap(),
+            "Attributes: mental 6/5 (1 over), physical 4/4, social 3/3, plus 1 template dot \
+             - on budget"
+        );
+        let report = audit(&character(STANDARD), true).report();
+        assert_eq!(
+            report.lines().next().unwrap(),
+            "Attributes: mental 5/5, physical 4/4, social 3/3, plus 1 template dot \
+             - 1 under budget"
+        );
+    }
+}
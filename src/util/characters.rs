@@ -1,7 +1,61 @@
 use failure::Error;
-use prettytable::{cell, format, row, Table};
+use log::warn;
 use serde::{Deserialize, Serialize};
-use std::{collections::HashMap, fmt, fs, path::Path};
+use std::{
+    collections::{hash_map::Entry, HashMap},
+    fmt, fs,
+    path::Path,
+};
+use crate::util::{
+    fsutil::{atomic_write, recover_leftovers},
+    render,
+    roll_tokens::reserved_reason,
+    stat_names::canonical,
+};
+
+/// A kind of damage marked on a health track, from least to most severe.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Damage {
+    Bashing,
+    Lethal,
+    Aggravated,
+}
+
+impl Damage {
+    /// Parse a damage kind, like "bashing", "lethal" or "agg".
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - the damage kind's name
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// let kind = Damage::parse("agg");
+    /// ```
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "bashing" | "b" => Some(Damage::Bashing),
+            "lethal" | "l" => Some(Damage::Lethal),
+            "aggravated" | "agg" | "a" => Some(Damage::Aggravated),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Damage {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Damage::Bashing => "bashing",
+            Damage::Lethal => "lethal",
+            Damage::Aggravated => "aggravated",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Most health boxes a character can have.
+pub const MAX_HEALTH: u64 = 20;
 
 /// Represents a character's health.
 #[derive(Clone, Debug, Deserialize, Serialize)]
@@ -22,6 +76,129 @@ impl Health {
             aggravated: 0,
         }
     }
+
+    /// Number of health boxes.
+    pub fn max(&self) -> u64 {
+        self.max
+    }
+
+    /// Set the number of health boxes.
+    ///
+    /// Returns why the track can't have that many boxes: it needs from 1 to
+    /// `MAX_HEALTH`, and can't shrink past the damage already marked.
+    ///
+    /// # Arguments
+    ///
+    /// * `max` - new number of boxes
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// health.set_max(7)?;
+    /// ```
+    pub fn set_max(&mut self, max: u64) -> Result<(), String> {
+        if !(1..=MAX_HEALTH).contains(&max) {
+            return Err(format!("Health can be from 1 to {}.", MAX_HEALTH));
+        }
+        let damage = self.bashing + self.lethal + self.aggravated;
+        if max < damage {
+            return Err(format!(
+                "That's fewer boxes than the {} already marked; heal some damage first.",
+                damage
+            ));
+        }
+        self.max = max;
+        Ok(())
+    }
+
+    /// Boxes marked with bashing damage.
+    #[cfg(test)]
+    pub fn bashing(&self) -> u64 {
+        self.bashing
+    }
+
+    /// Boxes marked with lethal damage.
+    #[cfg(test)]
+    pub fn lethal(&self) -> u64 {
+        self.lethal
+    }
+
+    /// Boxes marked with aggravated damage.
+    #[cfg(test)]
+    pub fn aggravated(&self) -> u64 {
+        self.aggravated
+    }
+
+    /// Mark damage on the track.
+    ///
+    /// Damage fills empty boxes first. Once the track is full, each further
+    /// point of bashing or lethal upgrades a bashing box to lethal, or a
+    /// lethal box to aggravated if there's no bashing left, and each point
+    /// of aggravated turns the least severe box aggravated. Damage never
+    /// marks more boxes than the track has.
+    ///
+    /// # Arguments
+    ///
+    /// * `kind` - kind of damage taken
+    /// * `amount` - points of damage taken
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// health.take(Damage::Lethal, 2);
+    /// ```
+    pub fn take(&mut self, kind: Damage, amount: u64) {
+        // Past three points a box, every box is aggravated and nothing changes
+        for _ in 0..amount.min(self.max.saturating_mul(3)) {
+            if self.bashing + self.lethal + self.aggravated < self.max {
+                match kind {
+                    Damage::Bashing => self.bashing += 1,
+                    Damage::Lethal => self.lethal += 1,
+                    Damage::Aggravated => self.aggravated += 1,
+                }
+            } else if self.bashing > 0 {
+                self.bashing -= 1;
+                match kind {
+                    Damage::Aggravated => self.aggravated += 1,
+                    _ => self.lethal += 1,
+                }
+            } else if self.lethal > 0 {
+                self.lethal -= 1;
+                self.aggravated += 1;
+            }
+        }
+    }
+
+    /// Heal damage from the track, stopping when none of that kind is left.
+    ///
+    /// # Arguments
+    ///
+    /// * `kind` - kind of damage healed
+    /// * `amount` - points of damage healed
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// health.heal(Damage::Bashing, 1);
+    /// ```
+    pub fn heal(&mut self, kind: Damage, amount: u64) {
+        let marked = match kind {
+            Damage::Bashing => &mut self.bashing,
+            Damage::Lethal => &mut self.lethal,
+            Damage::Aggravated => &mut self.aggravated,
+        };
+        *marked = marked.saturating_sub(amount);
+    }
+
+    /// Health boxes from left to right, marked "A", "L", "B", or " " when empty.
+    pub fn boxes(&self) -> Vec<&'static str> {
+        let damage = self.aggravated + self.lethal + self.bashing;
+        let mut boxes = vec!["A"; self.aggravated as usize];
+        boxes.extend(vec!["L"; self.lethal as usize]);
+        boxes.extend(vec!["B"; self.bashing as usize]);
+        boxes.extend(vec![" "; self.max.saturating_sub(damage) as usize]);
+        boxes
+    }
 }
 
 impl fmt::Display for Health {
@@ -30,73 +207,844 @@ impl fmt::Display for Health {
             return writeln!(f, "No health info");
         }
         writeln!(f, "Health (max {}):", self.max)?;
-        let mut boxes = vec![];
-        for _ in 0..self.aggravated {
-            boxes.push("A");
+        write!(f, "{}", render::health_table(&self.boxes()))
+    }
+}
+
+/// Dice added to any roll that includes a stat, like from a merit.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct BonusRule {
+    pub stat: String,
+    pub dice: i64,
+    pub source: String,
+}
+
+impl fmt::Display for BonusRule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {:+} ({})", self.stat, self.dice, self.source)
+    }
+}
+
+/// A temporary modifier to a stat, like from a spell or a drug.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Buff {
+    pub stat: String,
+    pub dice: i64,
+    pub source: String,
+    /// Scenes left before the buff expires.
+    pub scenes: u32,
+}
+
+impl fmt::Display for Buff {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} {:+} ({}, {} {} left)",
+            self.stat,
+            self.dice,
+            self.source,
+            self.scenes,
+            if self.scenes == 1 { "scene" } else { "scenes" }
+        )
+    }
+}
+
+/// A condition on a character, like Stunned or Frightened.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Condition {
+    pub name: String,
+    /// Channel of the scene the condition ends with, if it ends with one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scene_channel: Option<u64>,
+}
+
+impl fmt::Display for Condition {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.scene_channel {
+            Some(_) => write!(f, "{} (ends with the scene)", self.name),
+            None => write!(f, "{}", self.name),
         }
-        for _ in 0..self.lethal {
-            boxes.push("L");
+    }
+}
+
+/// Represents a single player character.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Character {
+    /// Discord ID of the user the character belongs to. Characters saved
+    /// before they were kept by ID have none until they're claimed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    id: Option<u64>,
+    /// Name shown for the character, which follows its user's Discord name.
+    name: String,
+    stats: HashMap<String, i64>,
+    health: Health,
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    modifiers: HashMap<String, String>,
+    #[serde(default, skip_serializing_if = "is_zero")]
+    beats: u64,
+    /// When the last weekly participation beat was awarded.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    last_beat_award: Option<u64>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    bonus_rules: Vec<BonusRule>,
+    /// Discord ID of the user who last changed the character.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    last_writer_id: Option<u64>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    buffs: Vec<Buff>,
+    /// Successes needed for an exceptional success on rolls using a stat.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    exceptional: HashMap<String, u64>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    conditions: Vec<Condition>,
+}
+
+fn is_zero(n: &u64) -> bool {
+    *n == 0
+}
+
+impl fmt::Display for Character {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.stats.is_empty() {
+            return write!(f, "No stats info");
+        }
+        let stats: Vec<(&str, String)> = self
+            .stats()
+            .into_iter()
+            .map(|(name, value)| match self.buff_total(name) {
+                0 => (name, value.to_string()),
+                buff => (name, format!("{} ({:+})", value, buff)),
+            })
+            .collect();
+        write!(f, "{}", render::stats_table(&stats))?;
+        if !self.modifiers.is_empty() {
+            let mut modifiers: Vec<_> = self.modifiers.iter().collect();
+            modifiers.sort();
+            write!(
+                f,
+                "Default modifiers: {}",
+                modifiers
+                    .iter()
+                    .map(|(k, v)| format!("{} {}", k, v))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )?;
+        }
+        let mut separator = if self.modifiers.is_empty() { "" } else { "\n" };
+        if !self.bonus_rules.is_empty() {
+            write!(
+                f,
+                "{}Bonus rules: {}",
+                separator,
+                self.bonus_rules
+                    .iter()
+                    .map(|r| r.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )?;
+            separator = "\n";
         }
-        for _ in 0..self.bashing {
-            boxes.push("B");
+        if !self.exceptional.is_empty() {
+            let mut exceptional: Vec<_> = self.exceptional.iter().collect();
+            exceptional.sort();
+            write!(
+                f,
+                "{}Exceptional success at: {}",
+                separator,
+                exceptional
+                    .iter()
+                    .map(|(k, v)| format!("{} {}", k, v))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )?;
+            separator = "\n";
         }
-        for _ in 0..(self.max - self.aggravated - self.lethal - self.bashing) {
-            boxes.push(" ");
+        if !self.conditions.is_empty() {
+            write!(
+                f,
+                "{}Conditions: {}",
+                separator,
+                self.conditions
+                    .iter()
+                    .map(|c| c.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )?;
+            separator = "\n";
+        }
+        if !self.buffs.is_empty() {
+            write!(
+                f,
+                "{}Buffs: {}",
+                separator,
+                self.buffs
+                    .iter()
+                    .map(|b| b.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )?;
+            separator = "\n";
+        }
+        if self.beats > 0 {
+            write!(f, "{}Beats: {}", separator, self.beats)?;
         }
-        let mut table = Table::new();
-        table.add_row(boxes.iter().map(|b| cell!(b)).collect());
-        write!(f, "{}", table)?;
         Ok(())
     }
-}
-
-/// Represents a single player character.
-#[derive(Clone, Debug, Deserialize, Serialize)]
-pub struct Character {
-    name: String,
-    stats: HashMap<String, i64>,
-    health: Health,
-}
+}
+
+impl Character {
+    /// Create a new struct.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - name of the character
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// let character = Character::new("Paul");
+    /// ```
+    pub fn new(name: &str) -> Self {
+        Character {
+            id: None,
+            name: name.to_owned(),
+            stats: HashMap::new(),
+            health: Health::new(),
+            modifiers: HashMap::new(),
+            beats: 0,
+            last_beat_award: None,
+            bonus_rules: vec![],
+            last_writer_id: None,
+            buffs: vec![],
+            exceptional: HashMap::new(),
+            conditions: vec![],
+        }
+    }
+
+    /// The character's name.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Discord ID of the user the character belongs to, if it's been claimed.
+    #[cfg(test)]
+    pub fn id(&self) -> Option<u64> {
+        self.id
+    }
+
+    /// The character's stats, sorted by name.
+    pub fn stats(&self) -> Vec<(&str, i64)> {
+        let mut stats: Vec<_> = self.stats.iter().map(|(k, &v)| (k.as_str(), v)).collect();
+        stats.sort();
+        stats
+    }
+
+    /// The character's default roll modifiers, sorted by stat.
+    pub fn modifiers(&self) -> Vec<(&str, &str)> {
+        let mut modifiers: Vec<_> = self
+            .modifiers
+            .iter()
+            .map(|(k, v)| (k.as_str(), v.as_str()))
+            .collect();
+        modifiers.sort();
+        modifiers
+    }
+
+    /// The character's health track.
+    pub fn health(&self) -> &Health {
+        &self.health
+    }
+
+    /// The character's health track, to mark or heal damage.
+    pub fn health_mut(&mut self) -> &mut Health {
+        &mut self.health
+    }
+
+    /// Attempt to get a stored value.
+    ///
+    /// If the value is not found in the store, 0 is returned.
+    /// The boolean argument represents whether or not the
+    /// value was returned. Useful in determining whether
+    /// or not the value was not found, or actually stored as 0.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - which key to fetch
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// let (found, value) = character.get_value("foo");
+    /// ```
+    pub fn get_value(&self, key: &str) -> (bool, i64) {
+        match self.stats.get(&canonical(key)) {
+            Some(i) => (true, *i),
+            None => (false, 0),
+        }
+    }
+
+    /// Sets the value by name.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - stats name to set
+    /// * `value`- stat value
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// let mut character = Character::new("Paul");
+    /// character.set_value("something", 100);
+    /// ```
+    pub fn set_value(&mut self, key: &str, value: i64) {
+        self.stats.insert(canonical(key), value);
+    }
+
+    /// Removes a stat.
+    ///
+    /// Returns whether there was one to remove.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - stat name
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// let removed = character.remove_value("strenght");
+    /// ```
+    pub fn remove_value(&mut self, key: &str) -> bool {
+        self.stats.remove(&canonical(key)).is_some()
+    }
+
+    /// Removes every stat, returning how many there were.
+    pub fn clear_values(&mut self) -> usize {
+        self.stats.drain().count()
+    }
+
+    /// Gets a stat's value including active buffs.
+    ///
+    /// Returns whether the stat itself was found, like `get_value`.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - stat name
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// let (found, value) = character.effective_value("strength");
+    /// ```
+    pub fn effective_value(&self, key: &str) -> (bool, i64) {
+        let (found, value) = self.get_value(key);
+        (found, value.saturating_add(self.buff_total(key)))
+    }
+
+    /// Total dice added to a stat by active buffs.
+    fn buff_total(&self, key: &str) -> i64 {
+        self.buffs_for(key).fold(0, |total, b| total.saturating_add(b.dice))
+    }
+
+    /// The active buffs on a stat.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - stat name
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// let buffs: Vec<&Buff> = character.buffs_for("strength").collect();
+    /// ```
+    pub fn buffs_for<'a>(&'a self, key: &str) -> impl Iterator<Item = &'a Buff> {
+        let key = canonical(key);
+        self.buffs.iter().filter(move |b| b.stat == key)
+    }
+
+    /// The character's active buffs, in the order they were added.
+    pub fn buffs(&self) -> &[Buff] {
+        &self.buffs
+    }
+
+    /// Add a buff. Buffs on the same stat stack.
+    ///
+    /// # Arguments
+    ///
+    /// * `buff` - buff to add
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// character.add_buff(buff);
+    /// ```
+    pub fn add_buff(&mut self, buff: Buff) {
+        self.buffs.push(Buff {
+            stat: canonical(&buff.stat),
+            ..buff
+        });
+    }
+
+    /// Remove every buff, returning how many there were.
+    pub fn clear_buffs(&mut self) -> usize {
+        self.buffs.drain(..).count()
+    }
+
+    /// Count down each buff by a scene, returning the buffs that expired.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// let expired = character.end_scene();
+    /// ```
+    pub fn end_scene(&mut self) -> Vec<Buff> {
+        for buff in &mut self.buffs {
+            buff.scenes = buff.scenes.saturating_sub(1);
+        }
+        let (expired, active) = self.buffs.drain(..).partition(|b| b.scenes == 0);
+        self.buffs = active;
+        expired
+    }
+
+    /// Get the default roll modifier stored for a stat.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - stat name
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// let modifier = character.get_modifier("brawl");
+    /// ```
+    pub fn get_modifier(&self, key: &str) -> Option<&str> {
+        self.modifiers.get(&canonical(key)).map(String::as_str)
+    }
+
+    /// Sets the default roll modifier for a stat.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - stat name
+    /// * `modifier` - modifier applied to pools including the stat, i.e. "8again"
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// character.set_modifier("brawl", "8again");
+    /// ```
+    pub fn set_modifier(&mut self, key: &str, modifier: &str) {
+        self.modifiers.insert(canonical(key), modifier.to_owned());
+    }
+
+    /// Removes the default roll modifier for a stat.
+    ///
+    /// Returns whether there was one to remove.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - stat name
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// let removed = character.remove_modifier("brawl");
+    /// ```
+    pub fn remove_modifier(&mut self, key: &str) -> bool {
+        self.modifiers.remove(&canonical(key)).is_some()
+    }
+
+    /// Store everything keyed by stat name under the canonical name.
+    ///
+    /// When several stats have the same canonical name, the highest value
+    /// is kept. When several modifiers do, the one already stored under the
+    /// canonical name is kept, otherwise the first by name. Dropped entries
+    /// are logged. Colliding exceptional success thresholds keep the lowest.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// character.canonicalize_names();
+    /// ```
+    pub fn canonicalize_names(&mut self) {
+        let mut stats: Vec<(String, i64)> = self.stats.drain().collect();
+        stats.sort();
+        for (key, value) in stats {
+            let name = canonical(&key);
+            match self.stats.get(&name) {
+                Some(&kept) if kept >= value => {
+                    warn!(
+                        "{}: dropped {} = {}, keeping {} = {}",
+                        self.name, key, value, name, kept
+                    );
+                }
+                Some(&dropped) => {
+                    warn!(
+                        "{}: dropped {} = {}, keeping {} = {}",
+                        self.name, name, dropped, key, value
+                    );
+                    self.stats.insert(name, value);
+                }
+                None => {
+                    self.stats.insert(name, value);
+                }
+            }
+        }
+        let mut modifiers: Vec<(String, String)> = self.modifiers.drain().collect();
+        modifiers.sort_by_key(|(key, _)| (*key != canonical(key), key.clone()));
+        for (key, modifier) in modifiers {
+            match self.modifiers.entry(canonical(&key)) {
+                Entry::Occupied(_) => {
+                    warn!("{}: dropped modifier {} for {}", self.name, modifier, key);
+                }
+                Entry::Vacant(entry) => {
+                    entry.insert(modifier);
+                }
+            }
+        }
+        for rule in &mut self.bonus_rules {
+            rule.stat = canonical(&rule.stat);
+        }
+        for buff in &mut self.buffs {
+            buff.stat = canonical(&buff.stat);
+        }
+        let exceptional: Vec<(String, u64)> = self.exceptional.drain().collect();
+        for (key, successes) in exceptional {
+            let lowest = self.exceptional.entry(canonical(&key)).or_insert(successes);
+            *lowest = (*lowest).min(successes);
+        }
+    }
+
+    /// Get the successes needed for an exceptional success with a stat, if set.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - stat name
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// let threshold = character.get_exceptional("firearms");
+    /// ```
+    pub fn get_exceptional(&self, key: &str) -> Option<u64> {
+        self.exceptional.get(&canonical(key)).copied()
+    }
+
+    /// Sets the successes needed for an exceptional success with a stat.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - stat name
+    /// * `successes` - successes needed
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// character.set_exceptional("firearms", 3);
+    /// ```
+    pub fn set_exceptional(&mut self, key: &str, successes: u64) {
+        self.exceptional.insert(canonical(key), successes);
+    }
+
+    /// Removes a stat's exceptional success threshold.
+    ///
+    /// Returns whether there was one to remove.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - stat name
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// let removed = character.remove_exceptional("firearms");
+    /// ```
+    pub fn remove_exceptional(&mut self, key: &str) -> bool {
+        self.exceptional.remove(&canonical(key)).is_some()
+    }
+
+    /// The character's bonus rules, in the order they were added.
+    pub fn bonus_rules(&self) -> &[BonusRule] {
+        &self.bonus_rules
+    }
+
+    /// Add a bonus rule. Rules for the same stat stack.
+    ///
+    /// # Arguments
+    ///
+    /// * `rule` - rule to add
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// character.add_bonus_rule(rule);
+    /// ```
+    pub fn add_bonus_rule(&mut self, rule: BonusRule) {
+        self.bonus_rules.push(BonusRule {
+            stat: canonical(&rule.stat),
+            ..rule
+        });
+    }
+
+    /// Remove a bonus rule by its 1-based position.
+    ///
+    /// # Arguments
+    ///
+    /// * `index` - position of the rule, as shown by `!stats bonusrule list`
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// let removed = character.remove_bonus_rule(1);
+    /// ```
+    pub fn remove_bonus_rule(&mut self, index: usize) -> Option<BonusRule> {
+        if index == 0 || index > self.bonus_rules.len() {
+            return None;
+        }
+        Some(self.bonus_rules.remove(index - 1))
+    }
+
+    /// Record which user is changing the character.
+    ///
+    /// # Arguments
+    ///
+    /// * `user_id` - Discord ID of the user
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// character.set_last_writer(message.author.id.0);
+    /// ```
+    pub fn set_last_writer(&mut self, user_id: u64) {
+        self.last_writer_id = Some(user_id);
+    }
+
+    /// Stats with names that rolls can't look up, and why.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// for (name, reason) in character.reserved_stats() { /* ... */ }
+    /// ```
+    pub fn reserved_stats(&self) -> Vec<(&str, &'static str)> {
+        let mut reserved: Vec<(&str, &'static str)> = self
+            .stats
+            .keys()
+            .filter_map(|name| reserved_reason(name).map(|reason| (name.as_str(), reason)))
+            .collect();
+        reserved.sort();
+        reserved
+    }
+
+    /// The character's conditions, in the order they were added.
+    pub fn conditions(&self) -> &[Condition] {
+        &self.conditions
+    }
+
+    /// Add a condition, replacing any condition with the same name.
+    ///
+    /// # Arguments
+    ///
+    /// * `condition` - condition to add
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// character.add_condition(condition);
+    /// ```
+    pub fn add_condition(&mut self, condition: Condition) {
+        self.conditions
+            .retain(|c| !c.name.eq_ignore_ascii_case(&condition.name));
+        self.conditions.push(condition);
+    }
+
+    /// Remove a condition by name, ignoring case.
+    ///
+    /// Returns whether the character had the condition.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - name of the condition
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// let removed = character.remove_condition("stunned");
+    /// ```
+    pub fn remove_condition(&mut self, name: &str) -> bool {
+        let before = self.conditions.len();
+        self.conditions.retain(|c| !c.name.eq_ignore_ascii_case(name));
+        self.conditions.len() != before
+    }
+
+    /// Remove the conditions that end with the scene in a channel,
+    /// returning them.
+    ///
+    /// # Arguments
+    ///
+    /// * `channel_id` - channel the scene ended in
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// let ended = character.end_scene_conditions(message.channel_id.0);
+    /// ```
+    pub fn end_scene_conditions(&mut self, channel_id: u64) -> Vec<Condition> {
+        let (ended, kept) = self
+            .conditions
+            .drain(..)
+            .partition(|c| c.scene_channel == Some(channel_id));
+        self.conditions = kept;
+        ended
+    }
+
+    /// Number of beats the character has.
+    pub fn beats(&self) -> u64 {
+        self.beats
+    }
+
+    /// Award the weekly participation beat, if not yet awarded this week.
+    ///
+    /// Returns whether a beat was awarded.
+    ///
+    /// # Arguments
+    ///
+    /// * `now` - current time, seconds since the epoch
+    /// * `week_start` - when the current week started, seconds since the epoch
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// let awarded = character.award_weekly_beat(now, week_start(now, 0, 0));
+    /// ```
+    pub fn award_weekly_beat(&mut self, now: u64, week_start: u64) -> bool {
+        if let Some(last) = self.last_beat_award {
+            if last >= week_start {
+                return false;
+            }
+        }
+        self.beats += 1;
+        self.last_beat_award = Some(now);
+        true
+    }
+
+    /// Whether the character has nothing worth keeping.
+    ///
+    /// The last writer is ignored, since on its own it only guards the name.
+    pub fn is_empty(&self) -> bool {
+        let health = &self.health;
+        self.stats.is_empty()
+            && health.max == 0
+            && health.bashing == 0
+            && health.lethal == 0
+            && health.aggravated == 0
+            && self.modifiers.is_empty()
+            && self.beats == 0
+            && self.last_beat_award.is_none()
+            && self.bonus_rules.is_empty()
+            && self.buffs.is_empty()
+            && self.exceptional.is_empty()
+            && self.conditions.is_empty()
+    }
+}
+
+/// Permission for one user to roll using another user's character.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Delegation {
+    pub grantor: u64,
+    pub grantee: u64,
+    pub expires: u64,
+}
+
+/// Collections of characters.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct CharacterStore {
+    characters: Vec<Character>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    delegations: Vec<Delegation>,
+}
+
+impl CharacterStore {
+    /// Find the position of a user's character.
+    ///
+    /// A character saved before characters were kept by ID can be claimed
+    /// by the user who last changed it, or by a user with its name if no
+    /// one else has changed it. A name match is preferred, so a user who
+    /// was renamed and left a duplicate behind gets the one they use now.
+    fn position(&self, user_id: u64, name: &str) -> Option<usize> {
+        let claimable = |c: &Character| {
+            c.id.is_none() && c.last_writer_id.is_none_or(|id| id == user_id)
+        };
+        self.characters
+            .iter()
+            .position(|c| c.id == Some(user_id))
+            .or_else(|| {
+                self.characters
+                    .iter()
+                    .position(|c| claimable(c) && c.name == name)
+            })
+            .or_else(|| {
+                self.characters
+                    .iter()
+                    .position(|c| claimable(c) && c.last_writer_id == Some(user_id))
+            })
+    }
+
+    /// Get a user's stored character.
+    ///
+    /// Returns an immutable reference, only usable for reading.
+    /// If the user has no stored character, None is returned.
+    ///
+    /// # Arguments
+    ///
+    /// * `user_id` - Discord ID of the user
+    /// * `name` - the user's name, to find a character saved before IDs were kept
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// let character = match character_store.get(user_id, "Paul") {
+    ///     Some(c) => c,
+    ///     None => panic!("No character found"),
+    /// };
+    /// ```
+    pub fn get(&self, user_id: u64, name: &str) -> Option<&Character> {
+        self.position(user_id, name).map(|i| &self.characters[i])
+    }
 
-impl fmt::Display for Character {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        if self.stats.is_empty() {
-            return write!(f, "No stats info");
-        }
-        let mut table = Table::new();
-        table.set_titles(row!["Name", "Value", "", "Name", "Value"]);
-        let size = self.stats.len();
-        let half_rounded = f64::ceil(self.stats.len() as f64 / 2f64) as u64;
-        let mut items: Vec<_> = self.stats.iter().map(|(k, &v)| (k, v)).collect();
-        items.sort_by(|a, b| a.partial_cmp(b).unwrap());
-        for index in 0..half_rounded {
-            let index = index as usize;
-            let index_upper = index + half_rounded as usize;
-            if index_upper >= size {
-                table.add_row(row![
-                    items[index].0,
-                    &format!("  {}", items[index].1),
-                    "",
-                    "",
-                    "",
-                ]);
-            } else {
-                table.add_row(row![
-                    items[index].0,
-                    &format!("  {}", items[index].1),
-                    "",
-                    items[index_upper].0,
-                    &format!("  {}", items[index_upper].1),
-                ]);
+    /// Get a user's stored character.
+    ///
+    /// Returns a mutable reference suitable for updating stats.
+    /// If the user has no character, a new one is created. A character
+    /// saved before IDs were kept is claimed for the user, and the
+    /// character's name is kept in step with the user's.
+    ///
+    /// # Arguments
+    ///
+    /// * `user_id` - Discord ID of the user
+    /// * `name` - the user's name
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// let character = character_store.get_mut(user_id, "Paul");
+    /// ```
+    pub fn get_mut(&mut self, user_id: u64, name: &str) -> &mut Character {
+        let i = match self.position(user_id, name) {
+            Some(i) => i,
+            None => {
+                self.characters.push(Character::new(name));
+                self.characters.len() - 1
             }
+        };
+        let character = &mut self.characters[i];
+        character.id = Some(user_id);
+        if character.name != name {
+            character.name = name.to_owned();
         }
-        table.set_format(*format::consts::FORMAT_NO_LINESEP_WITH_TITLE);
-        write!(f, "{}", table)
+        character
     }
-}
 
-impl Character {
-    /// Create a new struct.
+    /// Get a stored character by name, whoever it belongs to.
     ///
     /// # Arguments
     ///
@@ -105,115 +1053,164 @@ impl Character {
     /// # Examples
     ///
     /// ```rust
-    /// let character = Character::new("Paul");
+    /// let exists = character_store.get_by_name("Ada Bold").is_some();
     /// ```
-    pub fn new(name: &str) -> Self {
-        Character {
-            name: name.to_owned(),
-            stats: HashMap::new(),
-            health: Health::new(),
-        }
+    pub fn get_by_name(&self, name: &str) -> Option<&Character> {
+        self.characters.iter().find(|c| c.name == name)
     }
 
-    /// Attempt to get a stored value.
+    /// Get a stored character by name, whoever it belongs to.
     ///
-    /// If the value is not found in the store, 0 is returned.
-    /// The boolean argument represents whether or not the
-    /// value was returned. Useful in determining whether
-    /// or not the value was not found, or actually stored as 0.
+    /// If there is no character by that name, a new one is created
+    /// without a user, for the first user with its name to claim.
     ///
     /// # Arguments
     ///
-    /// * `key` - which key to fetch
+    /// * `name` - name of the character
     ///
     /// # Examples
     ///
     /// ```rust
-    /// let (found, value) = character.get_value("foo");
+    /// let character = character_store.get_by_name_mut("Ada Bold");
     /// ```
-    pub fn get_value(&self, key: &str) -> (bool, i64) {
-        let key = key.to_lowercase();
-        match self.stats.get(&key) {
-            Some(i) => (true, *i),
-            None => (false, 0),
+    pub fn get_by_name_mut(&mut self, name: &str) -> &mut Character {
+        match self.characters.iter().position(|c| c.name == name) {
+            Some(i) => &mut self.characters[i],
+            None => {
+                self.characters.push(Character::new(name));
+                self.characters.last_mut().unwrap()
+            }
         }
     }
 
-    /// Sets the value by name.
+    /// End the scene in a channel for every character, returning the
+    /// conditions that ended for each.
+    ///
+    /// Characters without any conditions ending are left out.
     ///
     /// # Arguments
     ///
-    /// * `key` - stats name to set
-    /// * `value`- stat value
+    /// * `channel_id` - channel the scene ended in
     ///
     /// # Examples
     ///
     /// ```rust
-    /// let mut character = Character::new("Paul");
-    /// character.set_value("something", 100);
+    /// for (name, ended) in character_store.end_scene_conditions(channel_id) { /* ... */ }
     /// ```
-    pub fn set_value(&mut self, key: &str, value: i64) {
-        self.stats.insert(key.to_lowercase().to_owned(), value);
+    pub fn end_scene_conditions(&mut self, channel_id: u64) -> Vec<(String, Vec<Condition>)> {
+        self.characters
+            .iter_mut()
+            .map(|c| (c.name.clone(), c.end_scene_conditions(channel_id)))
+            .filter(|(_, ended)| !ended.is_empty())
+            .collect()
     }
-}
 
-/// Collections of characters.
-#[derive(Debug, Deserialize, Serialize)]
-pub struct CharacterStore {
-    characters: Vec<Character>,
-}
+    /// Names of the characters that have nothing worth keeping.
+    pub fn empty_characters(&self) -> Vec<&str> {
+        self.characters
+            .iter()
+            .filter(|c| c.is_empty())
+            .map(|c| c.name())
+            .collect()
+    }
 
-impl CharacterStore {
-    /// Get a stored character by name.
+    /// Remove every character that has nothing worth keeping, returning
+    /// their names.
+    pub fn remove_empty(&mut self) -> Vec<String> {
+        let (empty, kept): (Vec<Character>, Vec<Character>) =
+            self.characters.drain(..).partition(Character::is_empty);
+        self.characters = kept;
+        empty.into_iter().map(|c| c.name).collect()
+    }
+
+    /// Grant a user permission to roll with the grantor's character.
     ///
-    /// Returns an immutable reference, only usable for reading.
-    /// If no stored character by that name is found, None is returned.
+    /// Replaces any existing delegation from the grantor.
     ///
     /// # Arguments
     ///
-    /// * `name` - name of the character to retrieve
+    /// * `grantor` - user ID of the character's owner
+    /// * `grantee` - user ID allowed to roll
+    /// * `expires` - epoch seconds when the delegation ends
     ///
     /// # Examples
     ///
     /// ```rust
-    /// let character = match character_store.get("Paul") {
-    ///     Some(c) => c,
-    ///     None => panic!("No character found"),
-    /// };
+    /// character_store.delegate(1, 2, now + 86400);
     /// ```
-    pub fn get(&self, name: &str) -> Option<&Character> {
-        for character in &self.characters {
-            if character.name == name {
-                return Some(character);
-            }
-        }
-        None
+    pub fn delegate(&mut self, grantor: u64, grantee: u64, expires: u64) {
+        self.delegations.retain(|d| d.grantor != grantor);
+        self.delegations.push(Delegation {
+            grantor,
+            grantee,
+            expires,
+        });
     }
 
-    /// Get a stored character by name.
+    /// Remove the grantor's delegation.
     ///
-    /// Returns a mutable reference suitable for updating stats.
-    /// If there is no character by that name found, a new one
-    /// is created.
+    /// Returns whether there was one to remove.
     ///
     /// # Arguments
     ///
-    /// * `name` - name of the character to retrieve
+    /// * `grantor` - user ID of the character's owner
+    /// * `now` - current epoch seconds
     ///
     /// # Examples
     ///
     /// ```rust
-    /// let character = match character_store.get_mut("Paul");
+    /// let revoked = character_store.revoke_delegation(1, now);
     /// ```
-    pub fn get_mut(&mut self, name: &str) -> &mut Character {
-        match self.characters.iter().position(|c| c.name == name) {
-            Some(i) => self.characters.get_mut(i).unwrap(),
-            None => {
-                let c = Character::new(&name);
-                self.characters.push(c);
-                self.characters.last_mut().unwrap()
-            }
-        }
+    pub fn revoke_delegation(&mut self, grantor: u64, now: u64) -> bool {
+        self.prune_delegations(now);
+        let before = self.delegations.len();
+        self.delegations.retain(|d| d.grantor != grantor);
+        before != self.delegations.len()
+    }
+
+    /// Returns whether the grantee may currently roll for the grantor.
+    ///
+    /// Expired delegations are dropped as a side effect.
+    ///
+    /// # Arguments
+    ///
+    /// * `grantor` - user ID of the character's owner
+    /// * `grantee` - user ID wanting to roll
+    /// * `now` - current epoch seconds
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// if character_store.is_delegated(1, 2, now) { /* ... */ }
+    /// ```
+    pub fn is_delegated(&mut self, grantor: u64, grantee: u64, now: u64) -> bool {
+        self.prune_delegations(now);
+        self.delegations
+            .iter()
+            .any(|d| d.grantor == grantor && d.grantee == grantee)
+    }
+
+    /// Drop delegations that have expired.
+    fn prune_delegations(&mut self, now: u64) {
+        self.delegations.retain(|d| d.expires > now);
+    }
+
+    /// Cleans up after saves to the store that a crash interrupted.
+    ///
+    /// Only call this on startup, before any command can save the store.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - path to the file
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// CharacterStore::recover(&LOAD_PATH)?;
+    /// ```
+    pub fn recover(path: &Path) -> Result<(), Error> {
+        recover_leftovers(path, |c| serde_json::from_str::<CharacterStore>(c).is_ok())?;
+        Ok(())
     }
 
     /// Loads the store from a JSON file.
@@ -232,12 +1229,24 @@ impl CharacterStore {
             Ok(c) => c,
             Err(_) => String::from(r#"{"characters":[]}"#),
         };
-        let cs = serde_json::from_str(&content)?;
+        let mut cs: CharacterStore = serde_json::from_str(&content)?;
+        for character in &mut cs.characters {
+            character.canonicalize_names();
+            for (stat, reason) in character.reserved_stats() {
+                warn!(
+                    "{}'s stat `{}` can't be rolled because {}",
+                    character.name, stat, reason
+                );
+            }
+        }
         Ok(cs)
     }
 
     /// Save the store to a JSON file.
     ///
+    /// The file is written next to the destination and then renamed over
+    /// it, so a failed write never leaves a partly saved store.
+    ///
     /// # Arguments
     ///
     /// * `path` - path to output file
@@ -249,14 +1258,16 @@ impl CharacterStore {
     /// ```
     pub fn save(&self, path: &Path) -> Result<(), Error> {
         let output = serde_json::to_string(&self)?;
-        fs::write(&path, &output)?;
+        atomic_write(path, output.as_bytes())?;
         Ok(())
     }
 }
 
 #[cfg(test)]
 mod test {
-    use super::{Character, CharacterStore};
+    use super::{
+        BonusRule, Buff, Character, CharacterStore, Condition, Damage, Health, MAX_HEALTH,
+    };
     use std::fs;
     use tempdir::TempDir;
 
@@ -294,7 +1305,8 @@ mod test {
 
         assert_eq!(cs.characters.len(), 1);
         assert_eq!(cs.characters[0].stats.len(), 10);
-        assert_eq!(cs.get("Paul Roberts").unwrap().get_value("wits"), (true, 3));
+        assert_eq!(cs.characters[0].id(), None);
+        assert_eq!(cs.get(1, "Paul Roberts").unwrap().get_value("wits"), (true, 3));
     }
 
     #[test]
@@ -304,6 +1316,7 @@ mod test {
         ch.set_value("a", 100);
         let cs = CharacterStore {
             characters: vec![ch],
+            delegations: vec![],
         };
         let output_path = temp.path().join("output.json");
         cs.save(output_path.as_path()).unwrap();
@@ -330,10 +1343,289 @@ mod test {
         assert_eq!(c.get_value("c"), (false, 0));
     }
 
+    #[test]
+    fn test_character_modifiers() {
+        let mut c = Character::new("A");
+        c.set_modifier("Brawl", "8again");
+
+        assert_eq!(c.get_modifier("brawl"), Some("8again"));
+        assert_eq!(c.get_modifier("BRAWL"), Some("8again"));
+        assert_eq!(c.get_modifier("firearms"), None);
+        assert!(c.remove_modifier("brawl"));
+        assert!(!c.remove_modifier("brawl"));
+        assert_eq!(c.get_modifier("brawl"), None);
+    }
+
+    #[test]
+    fn test_bonus_rules() {
+        let mut c = Character::new("A");
+        let rule = |stat: &str, dice, source: &str| BonusRule {
+            stat: stat.to_owned(),
+            dice,
+            source: source.to_owned(),
+        };
+        c.add_bonus_rule(rule("Firearms", 1, "Professional Training"));
+        c.add_bonus_rule(rule("firearms", 2, "Gun Nut"));
+
+        assert_eq!(
+            c.bonus_rules(),
+            &[rule("firearms", 1, "Professional Training"), rule("firearms", 2, "Gun Nut")][..]
+        );
+        assert_eq!(c.remove_bonus_rule(0), None);
+        assert_eq!(c.remove_bonus_rule(3), None);
+        assert_eq!(c.remove_bonus_rule(1), Some(rule("firearms", 1, "Professional Training")));
+        assert_eq!(c.bonus_rules(), &[rule("firearms", 2, "Gun Nut")][..]);
+        assert_eq!(c.bonus_rules()[0].to_string(), "firearms +2 (Gun Nut)");
+    }
+
+    #[test]
+    fn test_award_weekly_beat() {
+        let mut c = Character::new("A");
+
+        assert!(c.award_weekly_beat(100, 50));
+        assert!(!c.award_weekly_beat(150, 50));
+        assert_eq!(c.beats(), 1);
+        assert!(c.award_weekly_beat(700, 650));
+        assert_eq!(c.beats(), 2);
+    }
+
+    #[test]
+    fn test_stat_aliases() {
+        let mut character = Character::new("Paul");
+        character.set_value("DEX", 3);
+        character.set_modifier("Brawl.", "8again");
+
+        assert_eq!(character.get_value("dexterity"), (true, 3));
+        assert_eq!(character.get_value("Dex"), (true, 3));
+        assert_eq!(character.get_modifier("brawl"), Some("8again"));
+        assert_eq!(character.stats(), vec![("dexterity", 3)]);
+    }
+
+    #[test]
+    fn test_canonicalize_names() {
+        let json = r#"{
+            "name": "Paul",
+            "stats": {"dex": 2, "Dexterity": 4, "dexterity.": 3, "wits": 1, "wit": 3, "Brawl": 2},
+            "health": {"max": 0, "bashing": 0, "lethal": 0, "aggravated": 0},
+            "modifiers": {"str": "9again", "strength": "8again", "Occ": "rote"},
+            "bonus_rules": [{"stat": "STR", "dice": 1, "source": "Giant"}]
+        }"#;
+        let mut character: Character = serde_json::from_str(json).unwrap();
+        character.canonicalize_names();
+
+        assert_eq!(
+            character.stats(),
+            vec![("brawl", 2), ("dexterity", 4), ("wits", 3)]
+        );
+        assert_eq!(
+            character.modifiers(),
+            vec![("occult", "rote"), ("strength", "8again")]
+        );
+        assert_eq!(character.bonus_rules()[0].stat, "strength");
+    }
+
+    fn buff(stat: &str, dice: i64, scenes: u32) -> Buff {
+        Buff {
+            stat: stat.to_owned(),
+            dice,
+            source: String::from("Gift of Vigor"),
+            scenes,
+        }
+    }
+
+    #[test]
+    fn test_buffs_stack() {
+        let mut character = Character::new("Paul");
+        character.set_value("strength", 3);
+        character.add_buff(buff("Str", 2, 1));
+        character.add_buff(buff("strength", -1, 2));
+        character.add_buff(buff("wits", 1, 1));
+
+        assert_eq!(character.get_value("strength"), (true, 3));
+        assert_eq!(character.effective_value("strength"), (true, 4));
+        assert_eq!(character.effective_value("wits"), (false, 1));
+        assert_eq!(character.effective_value("brawl"), (false, 0));
+        assert!(character.to_string().contains("strength |   3 (+1)"));
+    }
+
+    #[test]
+    fn test_buffs_saturate() {
+        let mut character = Character::new("Paul");
+        character.set_value("strength", i64::MAX);
+        character.set_value("wits", i64::MIN);
+        character.add_buff(buff("strength", 2, 1));
+        character.add_buff(buff("wits", -1, 1));
+        character.add_buff(buff("brawl", i64::MAX, 1));
+        character.add_buff(buff("brawl", 1, 1));
+
+        assert_eq!(character.effective_value("strength"), (true, i64::MAX));
+        assert_eq!(character.effective_value("wits"), (true, i64::MIN));
+        assert_eq!(character.effective_value("brawl"), (false, i64::MAX));
+        assert!(character.to_string().contains(&format!("{} (+2)", i64::MAX)));
+    }
+
+    #[test]
+    fn test_buffs_expire() {
+        let mut character = Character::new("Paul");
+        character.set_value("strength", 3);
+        character.add_buff(buff("strength", 2, 1));
+        character.add_buff(buff("strength", 1, 2));
+
+        assert_eq!(character.end_scene(), vec![buff("strength", 2, 0)]);
+        assert_eq!(character.effective_value("strength"), (true, 4));
+        assert_eq!(character.buffs(), &[buff("strength", 1, 1)][..]);
+
+        assert_eq!(character.end_scene(), vec![buff("strength", 1, 0)]);
+        assert_eq!(character.effective_value("strength"), (true, 3));
+        assert!(character.end_scene().is_empty());
+
+        character.add_buff(buff("strength", 2, 3));
+        assert_eq!(character.clear_buffs(), 1);
+        assert!(character.buffs().is_empty());
+    }
+
+    fn condition(name: &str, scene_channel: Option<u64>) -> Condition {
+        Condition {
+            name: name.to_owned(),
+            scene_channel,
+        }
+    }
+
+    #[test]
+    fn test_conditions_replace_by_name() {
+        let mut character = Character::new("Paul");
+        character.add_condition(condition("Stunned", Some(5)));
+        character.add_condition(condition("stunned", None));
+        assert_eq!(character.conditions(), &[condition("stunned", None)][..]);
+        assert!(character.remove_condition("STUNNED"));
+        assert!(!character.remove_condition("Stunned"));
+    }
+
+    #[test]
+    fn test_scene_conditions_end_in_their_channel() {
+        let mut cs = CharacterStore {
+            characters: vec![],
+            delegations: vec![],
+        };
+        cs.get_mut(1, "Paul").add_condition(condition("Stunned", Some(5)));
+        cs.get_mut(1, "Paul").add_condition(condition("Frightened", None));
+        cs.get_mut(2, "Ada").add_condition(condition("Stunned", Some(6)));
+        cs.get_mut(3, "Ben").add_condition(condition("Blinded", Some(5)));
+
+        assert_eq!(
+            cs.end_scene_conditions(5),
+            vec![
+                (String::from("Paul"), vec![condition("Stunned", Some(5))]),
+                (String::from("Ben"), vec![condition("Blinded", Some(5))]),
+            ]
+        );
+        assert_eq!(
+            cs.get(1, "Paul").unwrap().conditions(),
+            &[condition("Frightened", None)][..]
+        );
+        assert_eq!(cs.get(2, "Ada").unwrap().conditions(), &[condition("Stunned", Some(6))][..]);
+        assert!(cs.end_scene_conditions(5).is_empty());
+    }
+
+    #[test]
+    fn test_conditions_default_when_missing() {
+        let character: Character = serde_json::from_str(
+            r#"{"name": "Paul", "stats": {},
+                "health": {"max": 7, "bashing": 0, "lethal": 0, "aggravated": 0}}"#,
+        )
+        .unwrap();
+        assert!(character.conditions().is_empty());
+        let saved: Character = serde_json::from_str(
+            r#"{"name": "Paul", "stats": {},
+                "health": {"max": 7, "bashing": 0, "lethal": 0, "aggravated": 0},
+                "conditions": [{"name": "Stunned", "scene_channel": 5}, {"name": "Frightened"}]}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            saved.conditions(),
+            &[condition("Stunned", Some(5)), condition("Frightened", None)][..]
+        );
+    }
+
+    #[test]
+    fn test_reserved_stats() {
+        let mut character = Character::new("Paul");
+        character.set_value("wits", 3);
+        character.set_value("5", 3);
+        character.set_value("Chance", 1);
+        character.set_value("9again", 2);
+        assert_eq!(
+            character.reserved_stats(),
+            vec![
+                ("5", "numbers in a roll are rolled as dice"),
+                ("9again", "it is a roll modifier"),
+                ("chance", "it has its own meaning in rolls"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_characters_are_per_user() {
+        let mut cs = CharacterStore {
+            characters: vec![],
+            delegations: vec![],
+        };
+        cs.get_mut(1, "Alex").set_value("wits", 3);
+        cs.get_mut(2, "Alex").set_value("wits", 1);
+
+        assert_eq!(cs.get(1, "Alex").unwrap().get_value("wits"), (true, 3));
+        assert_eq!(cs.get(2, "Alex").unwrap().get_value("wits"), (true, 1));
+        assert!(cs.get(3, "Alex").is_none());
+
+        // Renaming keeps the character, showing the new name
+        cs.get_mut(1, "Alexandra").set_value("wits", 4);
+
+        assert_eq!(cs.get(1, "Alex").unwrap().get_value("wits"), (true, 4));
+        assert_eq!(cs.get(1, "Alex").unwrap().name(), "Alexandra");
+        assert_eq!(cs.characters.len(), 2);
+    }
+
+    #[test]
+    fn test_legacy_characters_are_claimed() {
+        let temp = TempDir::new("dicebot").unwrap();
+        let json_data = r#"{"characters": [
+            {"name": "Ada", "stats": {"wits": 2}, "last_writer_id": 10,
+             "health": {"max": 0, "bashing": 0, "lethal": 0, "aggravated": 0}},
+            {"name": "Ben", "stats": {"wits": 3},
+             "health": {"max": 0, "bashing": 0, "lethal": 0, "aggravated": 0}},
+            {"name": "Old Cy", "stats": {"wits": 4}, "last_writer_id": 12,
+             "health": {"max": 0, "bashing": 0, "lethal": 0, "aggravated": 0}}
+        ]}"#;
+        let data_file = temp.path().join("data.json");
+        fs::write(&data_file, json_data).unwrap();
+        let mut cs = CharacterStore::from_file(&data_file).unwrap();
+
+        // Someone else with the name of a character last changed by another user
+        assert!(cs.get(11, "Ada").is_none());
+        // A name match when no one has changed the character
+        assert_eq!(cs.get(11, "Ben").unwrap().get_value("wits"), (true, 3));
+        // The last writer, who has since been renamed
+        assert_eq!(cs.get(12, "Cy").unwrap().get_value("wits"), (true, 4));
+
+        cs.get_mut(10, "Ada").set_value("wits", 5);
+        cs.get_mut(12, "Cy");
+        cs.save(&data_file).unwrap();
+        let cs = CharacterStore::from_file(&data_file).unwrap();
+
+        assert_eq!(cs.characters.len(), 3);
+        assert_eq!(cs.get(10, "Ada Bold").unwrap().get_value("wits"), (true, 5));
+        assert_eq!(cs.get(12, "Cy").unwrap().name(), "Cy");
+        assert_eq!(cs.get_by_name("Cy").unwrap().id(), Some(12));
+        assert_eq!(cs.get_by_name("Ben").unwrap().id(), None);
+    }
+
     #[test]
     fn test_get_mut() {
-        let mut cs = CharacterStore { characters: vec![] };
-        let c = cs.get_mut("Paul");
+        let mut cs = CharacterStore {
+            characters: vec![],
+            delegations: vec![],
+        };
+        let c = cs.get_mut(1, "Paul");
 
         assert_eq!(c.get_value("foo"), (false, 0));
 
@@ -341,4 +1633,211 @@ mod test {
 
         assert_eq!(c.get_value("foo"), (true, 1));
     }
+
+    #[test]
+    fn test_store_ignores_interrupted_save() {
+        let temp = TempDir::new("dicebot").unwrap();
+        let path = temp.path().join("data.json");
+        let mut cs = CharacterStore::from_file(&path).unwrap();
+        cs.get_mut(1, "Paul").set_value("wits", 3);
+        cs.save(&path).unwrap();
+        let leftover = temp.path().join("data.json.1234.0.tmp");
+        fs::write(&leftover, r#"{"characters":[{"na"#).unwrap();
+
+        CharacterStore::recover(&path).unwrap();
+        let cs = CharacterStore::from_file(&path).unwrap();
+        assert_eq!(cs.get(1, "Paul").unwrap().get_value("wits"), (true, 3));
+        assert!(!leftover.exists());
+    }
+
+    #[test]
+    fn test_remove_value() {
+        let mut c = Character::new("Paul");
+        c.set_value("strenght", 3);
+        c.set_value("wits", 2);
+
+        assert!(c.remove_value("Strenght"));
+        assert!(!c.remove_value("strenght"));
+        assert_eq!(c.get_value("strenght"), (false, 0));
+        assert_eq!(c.clear_values(), 1);
+        assert_eq!(c.clear_values(), 0);
+        assert!(c.stats().is_empty());
+    }
+
+    #[test]
+    fn test_is_empty() {
+        let mut c = Character::new("Paul");
+        assert!(c.is_empty());
+
+        c.set_last_writer(1);
+        assert!(c.is_empty());
+
+        c.set_value("wits", 0);
+        assert!(!c.is_empty());
+
+        let mut c = Character::new("Paul");
+        c.award_weekly_beat(100, 0);
+        assert!(!c.is_empty());
+
+        let mut c = Character::new("Paul");
+        c.add_condition(Condition {
+            name: String::from("Stunned"),
+            scene_channel: None,
+        });
+        assert!(!c.is_empty());
+
+        let mut c = Character::new("Paul");
+        c.health.bashing = 1;
+        assert!(!c.is_empty());
+    }
+
+    fn health(max: u64, bashing: u64, lethal: u64, aggravated: u64) -> Health {
+        Health {
+            max,
+            bashing,
+            lethal,
+            aggravated,
+        }
+    }
+
+    #[test]
+    fn test_health_take() {
+        let mut h = health(5, 0, 0, 0);
+        h.take(Damage::Bashing, 2);
+        h.take(Damage::Lethal, 1);
+        h.take(Damage::Aggravated, 1);
+        assert_eq!(h.boxes(), vec!["A", "L", "B", "B", " "]);
+
+        // Filling the track spills the rest over into upgrades
+        h.take(Damage::Lethal, 2);
+        assert_eq!(h.boxes(), vec!["A", "L", "L", "L", "B"]);
+    }
+
+    #[test]
+    fn test_health_take_upgrades_when_full() {
+        let mut h = health(3, 3, 0, 0);
+        h.take(Damage::Bashing, 1);
+        assert_eq!(h.boxes(), vec!["L", "B", "B"]);
+        h.take(Damage::Aggravated, 1);
+        assert_eq!(h.boxes(), vec!["A", "L", "B"]);
+        h.take(Damage::Bashing, 2);
+        assert_eq!(h.boxes(), vec!["A", "A", "L"]);
+        h.take(Damage::Lethal, 5);
+        assert_eq!(h.boxes(), vec!["A", "A", "A"]);
+        assert_eq!((h.bashing(), h.lethal(), h.aggravated()), (0, 0, 3));
+    }
+
+    #[test]
+    fn test_health_take_huge_amount() {
+        let mut h = health(5, 0, 0, 0);
+        h.take(Damage::Bashing, u64::MAX);
+        assert_eq!((h.bashing(), h.lethal(), h.aggravated()), (0, 0, 5));
+    }
+
+    #[test]
+    fn test_health_take_without_a_track() {
+        let mut h = health(0, 0, 0, 0);
+        h.take(Damage::Lethal, 2);
+        assert!(h.boxes().is_empty());
+        assert_eq!((h.bashing(), h.lethal(), h.aggravated()), (0, 0, 0));
+    }
+
+    #[test]
+    fn test_health_set_max() {
+        let mut h = health(0, 0, 0, 0);
+        assert_eq!(h.set_max(7), Ok(()));
+        assert_eq!(h.max(), 7);
+        assert_eq!(h.set_max(MAX_HEALTH), Ok(()));
+        assert!(h.set_max(0).is_err());
+        assert!(h.set_max(MAX_HEALTH + 1).is_err());
+        assert_eq!(h.max(), MAX_HEALTH);
+    }
+
+    #[test]
+    fn test_health_set_max_keeps_damage() {
+        let mut h = health(7, 2, 1, 1);
+        assert!(h.set_max(3).is_err());
+        assert_eq!(h.max(), 7);
+        assert_eq!(h.set_max(4), Ok(()));
+        assert_eq!(h.boxes(), vec!["A", "L", "B", "B"]);
+    }
+
+    #[test]
+    fn test_health_heal_clamps_at_zero() {
+        let mut h = health(5, 2, 1, 1);
+        h.heal(Damage::Bashing, 5);
+        h.heal(Damage::Aggravated, 1);
+        assert_eq!(h.boxes(), vec!["L", " ", " ", " ", " "]);
+        h.heal(Damage::Aggravated, 1);
+        assert_eq!((h.bashing(), h.lethal(), h.aggravated()), (0, 1, 0));
+    }
+
+    #[test]
+    fn test_damage_parse() {
+        assert_eq!(Damage::parse("Bashing"), Some(Damage::Bashing));
+        assert_eq!(Damage::parse("lethal"), Some(Damage::Lethal));
+        assert_eq!(Damage::parse("agg"), Some(Damage::Aggravated));
+        assert_eq!(Damage::parse("aggravated"), Some(Damage::Aggravated));
+        assert_eq!(Damage::parse("fire"), None);
+    }
+
+    #[test]
+    fn test_remove_empty() {
+        let mut cs = CharacterStore {
+            characters: vec![],
+            delegations: vec![],
+        };
+        cs.get_mut(1, "Ada");
+        cs.get_mut(2, "Ben").set_value("wits", 3);
+        cs.get_mut(3, "Cy").set_last_writer(3);
+
+        assert_eq!(cs.empty_characters(), vec!["Ada", "Cy"]);
+        assert_eq!(cs.remove_empty(), vec![String::from("Ada"), String::from("Cy")]);
+        assert!(cs.empty_characters().is_empty());
+        assert!(cs.get(2, "Ben").is_some());
+        assert!(cs.get(1, "Ada").is_none());
+    }
+
+    #[test]
+    fn test_delegation_expiry() {
+        let mut cs = CharacterStore {
+            characters: vec![],
+            delegations: vec![],
+        };
+        cs.delegate(1, 2, 100);
+
+        assert!(cs.is_delegated(1, 2, 50));
+        assert!(cs.is_delegated(1, 2, 99));
+        assert!(!cs.is_delegated(2, 1, 50));
+        assert!(!cs.is_delegated(1, 3, 50));
+        assert!(!cs.is_delegated(1, 2, 100));
+        assert!(cs.delegations.is_empty());
+    }
+
+    #[test]
+    fn test_delegation_replace_and_revoke() {
+        let mut cs = CharacterStore {
+            characters: vec![],
+            delegations: vec![],
+        };
+        cs.delegate(1, 2, 100);
+        cs.delegate(1, 3, 100);
+
+        assert!(!cs.is_delegated(1, 2, 50));
+        assert!(cs.is_delegated(1, 3, 50));
+        assert!(cs.revoke_delegation(1, 50));
+        assert!(!cs.revoke_delegation(1, 50));
+        assert!(!cs.is_delegated(1, 3, 50));
+    }
+
+    #[test]
+    fn test_revoke_expired_delegation() {
+        let mut cs = CharacterStore {
+            characters: vec![],
+            delegations: vec![],
+        };
+        cs.delegate(1, 2, 100);
+
+        assert!(!cs.revoke_delegation(1, 200));
+    }
 }
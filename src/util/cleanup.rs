@@ -0,0 +1,180 @@
+/// Discord's epoch, 2015-01-01, in milliseconds since the Unix epoch.
+const DISCORD_EPOCH_MS: u64 = 1_420_070_400_000;
+/// Oldest message Discord will bulk delete, in seconds.
+///
+/// A minute short of the real 14-day limit, so messages that age past it
+/// while a cleanup runs don't fail the whole batch.
+pub const BULK_DELETE_MAX_AGE: u64 = 14 * 86400 - 60;
+/// Most messages Discord accepts in one bulk delete.
+pub const BULK_DELETE_MAX: usize = 100;
+/// Fewest messages Discord accepts in one bulk delete.
+const BULK_DELETE_MIN: usize = 2;
+/// Most messages fetched from a channel's history in one request.
+pub const HISTORY_PAGE_MAX: u64 = 100;
+
+/// Returns when a message was sent, as seconds since the Unix epoch.
+///
+/// Discord IDs start with the milliseconds since Discord's epoch.
+///
+/// # Arguments
+///
+/// * `id` - message ID
+///
+/// # Examples
+///
+/// ```rust
+/// let sent = snowflake_time(message.id.0);
+/// ```
+pub fn snowflake_time(id: u64) -> u64 {
+    ((id >> 22) + DISCORD_EPOCH_MS) / 1000
+}
+
+/// Split a scan of a channel's history into page sizes Discord allows.
+///
+/// # Arguments
+///
+/// * `count` - number of messages to scan
+///
+/// # Examples
+///
+/// ```rust
+/// assert_eq!(history_pages(250), vec![100, 100, 50]);
+/// ```
+pub fn history_pages(count: u64) -> Vec<u64> {
+    let mut pages = vec![HISTORY_PAGE_MAX; (count / HISTORY_PAGE_MAX) as usize];
+    if !count.is_multiple_of(HISTORY_PAGE_MAX) {
+        pages.push(count % HISTORY_PAGE_MAX);
+    }
+    pages
+}
+
+/// How a set of messages will be deleted.
+#[derive(Debug, Default, PartialEq)]
+pub struct Deletion {
+    /// Batches of recent messages, each deleted with one request.
+    pub bulk: Vec<Vec<u64>>,
+    /// Messages deleted one request at a time.
+    pub single: Vec<u64>,
+    /// Messages too old to bulk delete, past the cap on single deletes.
+    pub skipped: usize,
+}
+
+impl Deletion {
+    /// Number of messages that will be deleted.
+    pub fn total(&self) -> usize {
+        self.bulk.iter().map(Vec::len).sum::<usize>() + self.single.len()
+    }
+}
+
+/// Plan how to delete messages, keeping the number of requests down.
+///
+/// Messages young enough to bulk delete are batched. Older ones, and a
+/// lone message left over from batching, are deleted one at a time, up to
+/// `max_single` of them; any more are skipped.
+///
+/// # Arguments
+///
+/// * `ids` - messages to delete
+/// * `now` - seconds since the Unix epoch
+/// * `max_single` - most messages to delete one at a time
+///
+/// # Examples
+///
+/// ```rust
+/// let deletion = plan_deletion(&ids, now(), MAX_SINGLE_DELETES);
+/// ```
+pub fn plan_deletion(ids: &[u64], now: u64, max_single: usize) -> Deletion {
+    let (recent, old): (Vec<u64>, Vec<u64>) = ids
+        .iter()
+        .partition(|&&id| now.saturating_sub(snowflake_time(id)) < BULK_DELETE_MAX_AGE);
+    let mut deletion = Deletion::default();
+    let mut single = vec![];
+    for batch in recent.chunks(BULK_DELETE_MAX) {
+        if batch.len() >= BULK_DELETE_MIN {
+            deletion.bulk.push(batch.to_vec());
+        } else {
+            single.extend_from_slice(batch);
+        }
+    }
+    single.extend(old);
+    deletion.skipped = single.len().saturating_sub(max_single);
+    single.truncate(max_single);
+    deletion.single = single;
+    deletion
+}
+
+#[cfg(test)]
+mod test {
+    use super::{history_pages, plan_deletion, snowflake_time, BULK_DELETE_MAX_AGE};
+
+    const DAY: u64 = 86400;
+    // Monday 2019-06-03 00:00:00 UTC
+    const NOW: u64 = 1_559_520_000;
+
+    /// A message ID for a message sent at the given time.
+    fn id_at(epoch: u64) -> u64 {
+        (epoch * 1000 - 1_420_070_400_000) << 22
+    }
+
+    #[test]
+    fn test_snowflake_time() {
+        // Example ID from Discord's documentation
+        assert_eq!(snowflake_time(175_928_847_299_117_063), 1_462_015_105);
+        assert_eq!(snowflake_time(id_at(NOW)), NOW);
+    }
+
+    #[test]
+    fn test_history_pages() {
+        assert_eq!(history_pages(50), vec![50]);
+        assert_eq!(history_pages(100), vec![100]);
+        assert_eq!(history_pages(250), vec![100, 100, 50]);
+        assert!(history_pages(0).is_empty());
+    }
+
+    #[test]
+    fn test_recent_messages_are_bulk_deleted() {
+        let ids: Vec<u64> = (1..=3).map(|i| id_at(NOW - i * DAY)).collect();
+        let deletion = plan_deletion(&ids, NOW, 5);
+
+        assert_eq!(deletion.bulk, vec![ids]);
+        assert!(deletion.single.is_empty());
+        assert_eq!(deletion.skipped, 0);
+        assert_eq!(deletion.total(), 3);
+    }
+
+    #[test]
+    fn test_bulk_batches_are_capped() {
+        let ids: Vec<u64> = (0..201).map(|i| id_at(NOW - i)).collect();
+        let deletion = plan_deletion(&ids, NOW, 5);
+
+        assert_eq!(deletion.bulk.len(), 2);
+        assert!(deletion.bulk.iter().all(|b| b.len() == 100));
+        // A lone message can't be bulk deleted
+        assert_eq!(deletion.single, vec![ids[200]]);
+        assert_eq!(deletion.total(), 201);
+    }
+
+    #[test]
+    fn test_old_messages_are_deleted_singly_up_to_cap() {
+        let recent = [id_at(NOW - DAY), id_at(NOW - 2 * DAY)];
+        let old: Vec<u64> = (15..20).map(|i| id_at(NOW - i * DAY)).collect();
+        let ids: Vec<u64> = recent.iter().chain(&old).cloned().collect();
+        let deletion = plan_deletion(&ids, NOW, 3);
+
+        assert_eq!(deletion.bulk, vec![recent.to_vec()]);
+        assert_eq!(deletion.single, old[..3].to_vec());
+        assert_eq!(deletion.skipped, 2);
+        assert_eq!(deletion.total(), 5);
+    }
+
+    #[test]
+    fn test_age_boundary() {
+        let just_recent = id_at(NOW - BULK_DELETE_MAX_AGE + 1);
+        let just_old = id_at(NOW - BULK_DELETE_MAX_AGE);
+        let deletion = plan_deletion(&[just_recent, just_old, just_recent], NOW, 0);
+
+        assert_eq!(deletion.bulk, vec![vec![just_recent, just_recent]]);
+        assert!(deletion.single.is_empty());
+        assert_eq!(deletion.skipped, 1);
+    }
+}
@@ -1,12 +1,44 @@
 use lazy_static::lazy_static;
 use std::path::Path;
 
+pub const PREFIX: &str = "!";
+/// Name of every command, in step with the command group in main.rs.
+pub const COMMANDS: &[&str] = &[
+    "admin",
+    "buff",
+    "changelog",
+    "check",
+    "cleanup",
+    "condition",
+    "damage",
+    "delegate",
+    "gm",
+    "help",
+    "health",
+    "invite",
+    "merit",
+    "permissions",
+    "roll",
+    "scene",
+    "settings",
+    "stats",
+    "version",
+    "vote",
+];
+
 #[cfg(not(test))]
 lazy_static! {
     pub static ref LOAD_PATH: &'static Path = Path::new("./data.json");
+    pub static ref SETTINGS_PATH: &'static Path = Path::new("./settings.json");
 }
 
 #[cfg(test)]
 lazy_static! {
     pub static ref LOAD_PATH: &'static Path = Path::new("./test-data.json");
+    pub static ref SETTINGS_PATH: &'static Path = Path::new("./test-settings.json");
+}
+
+lazy_static! {
+    pub static ref MERITS_DIR: &'static Path = Path::new("./merits");
+    pub static ref MERITS_ALLOWED_PATH: &'static Path = Path::new("./merits_allowed.txt");
 }
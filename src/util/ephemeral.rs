@@ -0,0 +1,164 @@
+use lazy_static::lazy_static;
+use log::{debug, warn};
+use serenity::{
+    http::Http,
+    model::id::{ChannelId, MessageId},
+};
+use std::{env, sync::{Arc, Mutex}, thread, time::Duration};
+use crate::util::time::now;
+
+/// Seconds before an ephemeral message is deleted, unless configured.
+const DEFAULT_DELAY_SECONDS: u64 = 60;
+
+lazy_static! {
+    pub static ref SCHEDULER: Mutex<Scheduler> = Mutex::new(Scheduler::new());
+}
+
+/// A message waiting to be deleted.
+#[derive(Debug, PartialEq)]
+struct Pending {
+    due: u64,
+    channel_id: u64,
+    message_id: u64,
+}
+
+/// Tracks messages that should be deleted at a later time.
+///
+/// Entries only live for the lifetime of the process.
+#[derive(Debug, Default)]
+pub struct Scheduler {
+    pending: Vec<Pending>,
+}
+
+impl Scheduler {
+    /// Create a new, empty scheduler.
+    pub fn new() -> Self {
+        Scheduler { pending: vec![] }
+    }
+
+    /// Schedule a message for deletion.
+    ///
+    /// Scheduling the same message again replaces its due time.
+    ///
+    /// # Arguments
+    ///
+    /// * `channel_id` - channel the message is in
+    /// * `message_id` - message to delete
+    /// * `due` - epoch seconds at which to delete it
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// scheduler.schedule(1, 2, now() + 60);
+    /// ```
+    pub fn schedule(&mut self, channel_id: u64, message_id: u64, due: u64) {
+        self.pending.retain(|p| p.message_id != message_id);
+        self.pending.push(Pending {
+            due,
+            channel_id,
+            message_id,
+        });
+    }
+
+    /// Remove and return the messages that are due for deletion.
+    ///
+    /// # Arguments
+    ///
+    /// * `now` - current epoch seconds
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// for (channel_id, message_id) in scheduler.take_due(now()) { /* ... */ }
+    /// ```
+    pub fn take_due(&mut self, now: u64) -> Vec<(u64, u64)> {
+        let (due, pending): (Vec<Pending>, Vec<Pending>) =
+            self.pending.drain(..).partition(|p| p.due <= now);
+        self.pending = pending;
+        due.into_iter()
+            .map(|p| (p.channel_id, p.message_id))
+            .collect()
+    }
+}
+
+/// Returns the configured deletion delay in seconds.
+///
+/// Read from the `EPHEMERAL_SECONDS` env var, defaulting to 60.
+pub fn delay_seconds() -> u64 {
+    env::var("EPHEMERAL_SECONDS")
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .unwrap_or(DEFAULT_DELAY_SECONDS)
+}
+
+/// Schedule a message for deletion after the configured delay.
+///
+/// # Arguments
+///
+/// * `channel_id` - channel the message is in
+/// * `message_id` - message to delete
+///
+/// # Examples
+///
+/// ```rust
+/// schedule_deletion(message.channel_id, message.id);
+/// ```
+pub fn schedule_deletion(channel_id: ChannelId, message_id: MessageId) {
+    SCHEDULER
+        .lock()
+        .unwrap()
+        .schedule(channel_id.0, message_id.0, now() + delay_seconds());
+}
+
+/// Start a background thread deleting scheduled messages as they come due.
+///
+/// # Arguments
+///
+/// * `http` - HTTP client to delete messages with
+///
+/// # Examples
+///
+/// ```rust
+/// spawn_worker(client.cache_and_http.http.clone());
+/// ```
+pub fn spawn_worker(http: Arc<Http>) {
+    thread::spawn(move || loop {
+        thread::sleep(Duration::from_secs(1));
+        let due = SCHEDULER.lock().unwrap().take_due(now());
+        for (channel_id, message_id) in due {
+            debug!("Deleting ephemeral message {} in {}", message_id, channel_id);
+            if let Err(e) = ChannelId(channel_id).delete_message(&http, MessageId(message_id)) {
+                warn!("Could not delete ephemeral message {}: {:?}", message_id, e);
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod test {
+    use super::Scheduler;
+
+    #[test]
+    fn test_take_due() {
+        let mut s = Scheduler::new();
+        s.schedule(1, 10, 100);
+        s.schedule(1, 11, 160);
+        s.schedule(2, 20, 100);
+
+        assert!(s.take_due(99).is_empty());
+        assert_eq!(s.take_due(100), vec![(1, 10), (2, 20)]);
+        assert!(s.take_due(100).is_empty());
+        assert_eq!(s.take_due(500), vec![(1, 11)]);
+        assert!(s.take_due(1000).is_empty());
+    }
+
+    #[test]
+    fn test_reschedule_replaces() {
+        let mut s = Scheduler::new();
+        s.schedule(1, 10, 100);
+        s.schedule(1, 10, 200);
+
+        assert!(s.take_due(150).is_empty());
+        assert_eq!(s.take_due(200), vec![(1, 10)]);
+    }
+}
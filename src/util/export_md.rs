@@ -0,0 +1,210 @@
+use crate::util::characters::Character;
+
+const DOT: char = '•';
+
+/// Escape text so Markdown shows it literally, including inside table cells.
+///
+/// # Arguments
+///
+/// * `text` - user-provided text
+///
+/// # Examples
+///
+/// ```rust
+/// let name = escape("Ada | `the` Bold");
+/// ```
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if "\\`*_#|[]<>~".contains(c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Show a stat value as dots when it is a usual rating.
+fn dots(value: i64) -> String {
+    if (1..=10).contains(&value) {
+        format!("{} {}", value, DOT.to_string().repeat(value as usize))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Render a character as a Markdown sheet.
+///
+/// Sections without anything in them are left out, and every list is
+/// sorted so the same character always renders the same way.
+///
+/// # Arguments
+///
+/// * `character` - character to render
+///
+/// # Examples
+///
+/// ```rust
+/// let sheet = render(&character);
+/// ```
+pub fn render(character: &Character) -> String {
+    let mut out = format!("# {}\n", escape(character.name()));
+
+    let stats = character.stats();
+    out.push_str("\n## Stats\n\n");
+    if stats.is_empty() {
+        out.push_str("No stats recorded.\n");
+    } else {
+        out.push_str("| Stat | Value |\n| --- | --- |\n");
+        for (name, value) in stats {
+            out.push_str(&format!("| {} | {} |\n", escape(name), dots(value)));
+        }
+    }
+
+    let modifiers = character.modifiers();
+    if !modifiers.is_empty() {
+        out.push_str("\n## Default modifiers\n\n");
+        for (stat, modifier) in modifiers {
+            out.push_str(&format!("- {}: {}\n", escape(stat), escape(modifier)));
+        }
+    }
+
+    if !character.bonus_rules().is_empty() {
+        out.push_str("\n## Bonus rules\n\n");
+        for (index, rule) in character.bonus_rules().iter().enumerate() {
+            out.push_str(&format!(
+                "{}. {} {:+} ({})\n",
+                index + 1,
+                escape(&rule.stat),
+                rule.dice,
+                escape(&rule.source)
+            ));
+        }
+    }
+
+    let health = character.health();
+    if health.max() > 0 {
+        let boxes: Vec<&str> = health
+            .boxes()
+            .iter()
+            .map(|&b| if b == " " { "☐" } else { b })
+            .collect();
+        out.push_str(&format!(
+            "\n## Health\n\nMax {}: {}\n",
+            health.max(),
+            boxes.join(" ")
+        ));
+    }
+
+    if character.beats() > 0 {
+        out.push_str(&format!("\n## Experience\n\nBeats: {}\n", character.beats()));
+    }
+    out
+}
+
+/// A file name for a character's sheet, keeping only safe characters.
+///
+/// # Arguments
+///
+/// * `name` - character name
+///
+/// # Examples
+///
+/// ```rust
+/// let file_name = file_name("Ada Bold");
+/// ```
+pub fn file_name(name: &str) -> String {
+    let stem: String = name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' { c } else { '_' })
+        .collect();
+    if stem.is_empty() {
+        String::from("character.md")
+    } else {
+        format!("{}.md", stem)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{dots, escape, file_name, render};
+    use crate::util::characters::{BonusRule, Character};
+
+    const FIXTURE: &str = r#"{
+        "name": "Ada | `Bold`",
+        "stats": {"wits": 3, "brawl": 2, "occult": 0, "size": 5, "animal_ken": 1},
+        "health": {"max": 7, "bashing": 1, "lethal": 1, "aggravated": 0},
+        "modifiers": {"brawl": "8again"},
+        "beats": 2
+    }"#;
+
+    #[test]
+    fn test_escape() {
+        assert_eq!(escape("plain"), "plain");
+        assert_eq!(escape("a|b"), "a\\|b");
+        assert_eq!(escape("`code` *em* _u_"), "\\`code\\` \\*em\\* \\_u\\_");
+        assert_eq!(escape("# [x]"), "\\# \\[x\\]");
+    }
+
+    #[test]
+    fn test_dots() {
+        assert_eq!(dots(0), "0");
+        assert_eq!(dots(3), "3 •••");
+        assert_eq!(dots(-1), "-1");
+        assert_eq!(dots(12), "12");
+    }
+
+    #[test]
+    fn test_render_fixture() {
+        let mut character: Character = serde_json::from_str(FIXTURE).unwrap();
+        character.add_bonus_rule(BonusRule {
+            stat: String::from("brawl"),
+            dice: 1,
+            source: String::from("Street | Fighting"),
+        });
+
+        assert_eq!(
+            render(&character),
+            "# Ada \\| \\`Bold\\`
+
+## Stats
+
+| Stat | Value |
+| --- | --- |
+| animal\\_ken | 1 • |
+| brawl | 2 •• |
+| occult | 0 |
+| size | 5 ••••• |
+| wits | 3 ••• |
+
+## Default modifiers
+
+- brawl: 8again
+
+## Bonus rules
+
+1. brawl +1 (Street \\| Fighting)
+
+## Health
+
+Max 7: L B ☐ ☐ ☐ ☐ ☐
+
+## Experience
+
+Beats: 2
+"
+        );
+    }
+
+    #[test]
+    fn test_render_empty() {
+        assert_eq!(render(&Character::new("Paul")), "# Paul\n\n## Stats\n\nNo stats recorded.\n");
+    }
+
+    #[test]
+    fn test_file_name() {
+        assert_eq!(file_name("Paul"), "Paul.md");
+        assert_eq!(file_name("Ada | Bold"), "Ada___Bold.md");
+        assert_eq!(file_name(""), "character.md");
+    }
+}
@@ -0,0 +1,515 @@
+use std::{collections::BTreeMap, fmt};
+use crate::util::{characters::Character, trace::TraceEvent};
+
+/// Most dice a roll expression can come to, and the largest number it can contain.
+pub const MAX_POOL: i64 = 100;
+
+/// A lexical token in a roll expression.
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Number(i64),
+    Ident(String),
+    Plus,
+    Minus,
+    LParen,
+    RParen,
+    Comma,
+}
+
+/// A token and where it starts in the expression.
+#[derive(Clone, Debug)]
+struct Spanned {
+    token: Token,
+    start: usize,
+    text: String,
+}
+
+/// An error parsing a roll expression.
+#[derive(Debug, PartialEq)]
+pub struct ParseError {
+    /// Character offset of the offending token.
+    pub position: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} at position {}", self.message, self.position + 1)
+    }
+}
+
+impl ParseError {
+    /// Render the error with a caret under the offending token.
+    ///
+    /// # Arguments
+    ///
+    /// * `line` - the expression that failed to parse
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// let text = error.render("strength + )");
+    /// ```
+    pub fn render(&self, line: &str) -> String {
+        format!("{}\n{}\n{}^", self, line, " ".repeat(self.position))
+    }
+}
+
+/// A parsed roll expression.
+#[derive(Debug, PartialEq)]
+pub enum Expr {
+    Number(i64),
+    Stat(String),
+    Neg(Box<Expr>),
+    Add(Box<Expr>, Box<Expr>),
+    Sub(Box<Expr>, Box<Expr>),
+    Min(Vec<Expr>),
+    Max(Vec<Expr>),
+}
+
+/// Stats looked up while evaluating an expression, in order.
+#[derive(Debug, Default, PartialEq)]
+pub struct Lookups {
+    pub found: Vec<(String, i64)>,
+    pub missing: Vec<String>,
+    /// Guild constants used for names the character has no stat for.
+    pub constants: Vec<(String, i64)>,
+    /// Numbers, stats and buffs as they were used.
+    pub trace: Vec<TraceEvent>,
+}
+
+impl Expr {
+    /// Evaluate the expression against a character's stats.
+    ///
+    /// A name the character has no stat for falls back to the guild's
+    /// constant of that name. Names that are neither count as 0 and are
+    /// recorded as missing.
+    ///
+    /// # Arguments
+    ///
+    /// * `character` - character to look stats up on
+    /// * `constants` - the guild's constants, keyed by lowercase name
+    /// * `lookups` - records which stats were found and missing
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// let mut lookups = Lookups::default();
+    /// let pool = parse("wits + 1")?.evaluate(&character, &guild.constants, &mut lookups);
+    /// ```
+    pub fn evaluate(
+        &self,
+        character: &Character,
+        constants: &BTreeMap<String, i64>,
+        lookups: &mut Lookups,
+    ) -> i64 {
+        match self {
+            Expr::Number(n) => {
+                lookups.trace.push(TraceEvent::Literal(*n));
+                *n
+            }
+            Expr::Stat(name) => {
+                let (found, mut val) = character.effective_value(name);
+                let constant = constants.get(&name.to_lowercase());
+                if found {
+                    lookups.found.push((name.clone(), val));
+                    lookups
+                        .trace
+                        .push(TraceEvent::Stat(name.clone(), character.get_value(name).1));
+                } else if let Some(&constant) = constant {
+                    val += constant;
+                    lookups.constants.push((name.clone(), constant));
+                    lookups.trace.push(TraceEvent::Constant(name.clone(), constant));
+                } else {
+                    lookups.missing.push(name.clone());
+                    lookups.trace.push(TraceEvent::Missing(name.clone()));
+                }
+                for buff in character.buffs_for(name) {
+                    lookups
+                        .trace
+                        .push(TraceEvent::Adjustment(format!("buff {}", buff.source), buff.dice));
+                }
+                val
+            }
+            Expr::Neg(e) => e.evaluate(character, constants, lookups).saturating_neg(),
+            Expr::Add(a, b) => a
+                .evaluate(character, constants, lookups)
+                .saturating_add(b.evaluate(character, constants, lookups)),
+            Expr::Sub(a, b) => a
+                .evaluate(character, constants, lookups)
+                .saturating_sub(b.evaluate(character, constants, lookups)),
+            Expr::Min(args) => args
+                .iter()
+                .map(|e| e.evaluate(character, constants, lookups))
+                .min()
+                .unwrap_or(0),
+            Expr::Max(args) => args
+                .iter()
+                .map(|e| e.evaluate(character, constants, lookups))
+                .max()
+                .unwrap_or(0),
+        }
+    }
+}
+
+/// Returns whether the character separates words in an expression.
+fn is_separator(c: char) -> bool {
+    c.is_whitespace() || "+-(),".contains(c)
+}
+
+/// Split an expression into tokens.
+///
+/// # Arguments
+///
+/// * `line` - expression text
+///
+/// # Examples
+///
+/// ```rust
+/// let tokens = tokenize("min(wits, 2)")?;
+/// ```
+fn tokenize(line: &str) -> Result<Vec<Spanned>, ParseError> {
+    let chars: Vec<char> = line.chars().collect();
+    let mut tokens = vec![];
+    let mut index = 0;
+    while index < chars.len() {
+        let c = chars[index];
+        let start = index;
+        let token = match c {
+            _ if c.is_whitespace() => {
+                index += 1;
+                continue;
+            }
+            '+' => Token::Plus,
+            '-' => Token::Minus,
+            '(' => Token::LParen,
+            ')' => Token::RParen,
+            ',' => Token::Comma,
+            _ => {
+                while index < chars.len() && !is_separator(chars[index]) {
+                    index += 1;
+                }
+                let word: String = chars[start..index].iter().collect();
+                let token = if word.chars().all(|c| c.is_ascii_digit()) {
+                    match word.parse::<i64>() {
+                        Ok(n) if n <= MAX_POOL => Token::Number(n),
+                        _ => {
+                            return Err(ParseError {
+                                position: start,
+                                message: format!("`{}` is too large", word),
+                            });
+                        }
+                    }
+                } else {
+                    Token::Ident(word.clone())
+                };
+                tokens.push(Spanned {
+                    token,
+                    start,
+                    text: word,
+                });
+                continue;
+            }
+        };
+        index += 1;
+        tokens.push(Spanned {
+            token,
+            start,
+            text: c.to_string(),
+        });
+    }
+    Ok(tokens)
+}
+
+/// Recursive-descent parser over a token list.
+struct Parser {
+    tokens: Vec<Spanned>,
+    index: usize,
+    end: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.index).map(|s| &s.token)
+    }
+
+    fn next(&mut self) -> Option<Spanned> {
+        let spanned = self.tokens.get(self.index).cloned();
+        self.index += 1;
+        spanned
+    }
+
+    fn unexpected(&self, spanned: Option<&Spanned>) -> ParseError {
+        match spanned {
+            Some(s) => ParseError {
+                position: s.start,
+                message: format!("unexpected `{}`", s.text),
+            },
+            None => ParseError {
+                position: self.end,
+                message: String::from("unexpected end of expression"),
+            },
+        }
+    }
+
+    fn expect(&mut self, token: &Token) -> Result<(), ParseError> {
+        match self.next() {
+            Some(ref s) if &s.token == token => Ok(()),
+            other => Err(self.unexpected(other.as_ref())),
+        }
+    }
+
+    /// expr := term (('+' | '-')? term)*
+    fn expr(&mut self) -> Result<Expr, ParseError> {
+        let mut left = self.term()?;
+        loop {
+            left = match self.peek() {
+                Some(Token::Plus) => {
+                    self.index += 1;
+                    Expr::Add(Box::new(left), Box::new(self.term()?))
+                }
+                Some(Token::Minus) => {
+                    self.index += 1;
+                    Expr::Sub(Box::new(left), Box::new(self.term()?))
+                }
+                Some(Token::Number(_)) | Some(Token::Ident(_)) | Some(Token::LParen) => {
+                    Expr::Add(Box::new(left), Box::new(self.term()?))
+                }
+                _ => return Ok(left),
+            };
+        }
+    }
+
+    /// term := ('+' | '-') term | atom
+    fn term(&mut self) -> Result<Expr, ParseError> {
+        match self.peek() {
+            Some(Token::Minus) => {
+                self.index += 1;
+                Ok(Expr::Neg(Box::new(self.term()?)))
+            }
+            Some(Token::Plus) => {
+                self.index += 1;
+                self.term()
+            }
+            _ => self.atom(),
+        }
+    }
+
+    /// atom := number | stat | function '(' expr (',' expr)* ')' | '(' expr ')'
+    fn atom(&mut self) -> Result<Expr, ParseError> {
+        let spanned = self.next();
+        match spanned.as_ref().map(|s| &s.token) {
+            Some(Token::Number(n)) => Ok(Expr::Number(*n)),
+            Some(Token::Ident(name)) => {
+                if self.peek() != Some(&Token::LParen) {
+                    return Ok(Expr::Stat(name.clone()));
+                }
+                let function = name.to_lowercase();
+                if function != "min" && function != "max" {
+                    let s = spanned.as_ref().unwrap();
+                    return Err(ParseError {
+                        position: s.start,
+                        message: format!("unknown function `{}`", s.text),
+                    });
+                }
+                self.index += 1;
+                let mut args = vec![self.expr()?];
+                while self.peek() == Some(&Token::Comma) {
+                    self.index += 1;
+                    args.push(self.expr()?);
+                }
+                self.expect(&Token::RParen)?;
+                if function == "min" {
+                    Ok(Expr::Min(args))
+                } else {
+                    Ok(Expr::Max(args))
+                }
+            }
+            Some(Token::LParen) => {
+                let inner = self.expr()?;
+                self.expect(&Token::RParen)?;
+                Ok(inner)
+            }
+            _ => Err(self.unexpected(spanned.as_ref())),
+        }
+    }
+}
+
+/// Parse a roll expression.
+///
+/// Supports stats, whole numbers, `+`, `-`, parentheses, and
+/// `min(...)`/`max(...)`. Terms written side by side are added.
+/// An empty expression is 0.
+///
+/// # Arguments
+///
+/// * `line` - expression text
+///
+/// # Examples
+///
+/// ```rust
+/// let expr = parse("min(wits, dexterity) + athletics")?;
+/// ```
+pub fn parse(line: &str) -> Result<Expr, ParseError> {
+    let tokens = tokenize(line)?;
+    if tokens.is_empty() {
+        return Ok(Expr::Number(0));
+    }
+    let mut parser = Parser {
+        tokens,
+        index: 0,
+        end: line.chars().count(),
+    };
+    let expr = parser.expr()?;
+    if parser.index < parser.tokens.len() {
+        let spanned = parser.tokens[parser.index].clone();
+        return Err(parser.unexpected(Some(&spanned)));
+    }
+    Ok(expr)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{parse, Expr, Lookups, ParseError};
+    use crate::util::{characters::Character, trace::TraceEvent};
+    use std::collections::BTreeMap;
+
+    fn character() -> Character {
+        let mut c = Character::new("A");
+        c.set_value("wits", 3);
+        c.set_value("dexterity", 2);
+        c.set_value("athletics", 1);
+        c.set_value("huge", i64::MAX);
+        c
+    }
+
+    fn eval(line: &str) -> (i64, Lookups) {
+        eval_with(line, &BTreeMap::new())
+    }
+
+    fn eval_with(line: &str, constants: &BTreeMap<String, i64>) -> (i64, Lookups) {
+        let mut lookups = Lookups::default();
+        let pool = parse(line).unwrap().evaluate(&character(), constants, &mut lookups);
+        (pool, lookups)
+    }
+
+    fn error(line: &str) -> ParseError {
+        parse(line).unwrap_err()
+    }
+
+    #[test]
+    fn test_evaluate_table() {
+        let cases = [
+            ("", 0),
+            ("5", 5),
+            ("wits", 3),
+            ("wits + 1", 4),
+            ("wits+1", 4),
+            ("wits - 1", 2),
+            ("wits- 1", 2),
+            ("-1", -1),
+            ("- wits", -3),
+            ("wits - -1", 4),
+            ("wits dexterity", 5),
+            ("wits + dexterity - athletics", 4),
+            ("(wits + dexterity) - athletics", 4),
+            ("wits - (dexterity - athletics)", 2),
+            ("wits - dexterity - athletics", 0),
+            ("min(wits, dexterity)", 2),
+            ("max(wits, dexterity)", 3),
+            ("min(wits, dexterity) + athletics", 3),
+            ("MIN(wits,dexterity,athletics)", 1),
+            ("max(wits - 2, 0)", 1),
+            ("max(min(wits, 2), athletics) + 1", 3),
+            ("min(wits)", 3),
+            ("((wits))", 3),
+            ("missing + 1", 1),
+            ("huge + 1", i64::MAX),
+            ("-huge - 100", i64::MIN),
+        ];
+        for (line, expected) in cases.iter() {
+            assert_eq!(eval(line).0, *expected, "expression: {}", line);
+        }
+    }
+
+    #[test]
+    fn test_evaluate_records_lookups() {
+        let (_, lookups) = eval("Wits + min(dexterity, speed) - athletcs");
+
+        assert_eq!(
+            lookups.found,
+            vec![(String::from("Wits"), 3), (String::from("dexterity"), 2)]
+        );
+        assert_eq!(lookups.missing, vec!["speed", "athletcs"]);
+    }
+
+    #[test]
+    fn test_evaluate_records_trace() {
+        let (_, lookups) = eval("wits - 1 + athletcs");
+
+        assert_eq!(
+            lookups.trace,
+            vec![
+                TraceEvent::Stat(String::from("wits"), 3),
+                TraceEvent::Literal(1),
+                TraceEvent::Missing(String::from("athletcs")),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_evaluate_constants() {
+        let mut constants = BTreeMap::new();
+        constants.insert(String::from("tension"), 2);
+        constants.insert(String::from("wits"), 5);
+
+        let (pool, lookups) = eval_with("wits + Tension - darkness", &constants);
+
+        // The character's own stat wins over a constant of the same name
+        assert_eq!(pool, 5);
+        assert_eq!(lookups.found, vec![(String::from("wits"), 3)]);
+        assert_eq!(lookups.constants, vec![(String::from("Tension"), 2)]);
+        assert_eq!(lookups.missing, vec!["darkness"]);
+        assert_eq!(lookups.trace[1], TraceEvent::Constant(String::from("Tension"), 2));
+    }
+
+    #[test]
+    fn test_parse_tree() {
+        assert_eq!(
+            parse("min(wits, 1) - 2").unwrap(),
+            Expr::Sub(
+                Box::new(Expr::Min(vec![Expr::Stat(String::from("wits")), Expr::Number(1)])),
+                Box::new(Expr::Number(2)),
+            )
+        );
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let cases = [
+            ("wits + )", 7, "unexpected `)`"),
+            ("wits +", 6, "unexpected end of expression"),
+            ("(wits + 1", 9, "unexpected end of expression"),
+            ("wits + 1)", 8, "unexpected `)`"),
+            ("min(wits dexterity", 18, "unexpected end of expression"),
+            ("min(wits,)", 9, "unexpected `)`"),
+            ("wits, 1", 4, "unexpected `,`"),
+            ("sqrt(wits)", 0, "unknown function `sqrt`"),
+            ("wits + 99999999999999999999", 7, "`99999999999999999999` is too large"),
+            ("wits + 101", 7, "`101` is too large"),
+        ];
+        for (line, position, message) in cases.iter() {
+            let e = error(line);
+            assert_eq!(e.position, *position, "expression: {}", line);
+            assert_eq!(e.message, *message, "expression: {}", line);
+        }
+    }
+
+    #[test]
+    fn test_render_error() {
+        assert_eq!(
+            error("wits + )").render("wits + )"),
+            "unexpected `)` at position 8\nwits + )\n       ^"
+        );
+    }
+}
@@ -0,0 +1,112 @@
+use rand::{RngCore, SeedableRng};
+use rand_chacha::ChaChaRng;
+use sha2::{Digest, Sha256};
+
+/// Length of a fair roll's seed, in bytes.
+pub const SEED_BYTES: usize = 32;
+
+/// Pick a new seed for a fair roll.
+///
+/// # Arguments
+///
+/// * `rng` - source of randomness
+///
+/// # Examples
+///
+/// ```rust
+/// let seed = new_seed(&mut thread_rng());
+/// ```
+pub fn new_seed<R: RngCore + ?Sized>(rng: &mut R) -> [u8; SEED_BYTES] {
+    let mut seed = [0; SEED_BYTES];
+    rng.fill_bytes(&mut seed);
+    seed
+}
+
+/// Write bytes as lowercase hex.
+pub fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Read a seed written by `to_hex`.
+///
+/// # Arguments
+///
+/// * `hex` - seed as 64 hex digits
+///
+/// # Examples
+///
+/// ```rust
+/// let seed = parse_seed(&to_hex(&seed)).unwrap();
+/// ```
+pub fn parse_seed(hex: &str) -> Option<[u8; SEED_BYTES]> {
+    let hex = hex.trim();
+    if hex.len() != SEED_BYTES * 2 || !hex.is_ascii() {
+        return None;
+    }
+    let mut seed = [0; SEED_BYTES];
+    for (i, byte) in seed.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16).ok()?;
+    }
+    Some(seed)
+}
+
+/// SHA-256 of a seed, as hex, which commits to it without revealing it.
+pub fn commitment(seed: &[u8; SEED_BYTES]) -> String {
+    to_hex(&Sha256::digest(seed))
+}
+
+/// The RNG a fair roll's dice come from.
+///
+/// ChaCha's output for a seed is fixed by its specification, so the dice
+/// can be reproduced by anyone with the seed.
+pub fn seeded_rng(seed: &[u8; SEED_BYTES]) -> ChaChaRng {
+    ChaChaRng::from_seed(*seed)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{commitment, new_seed, parse_seed, seeded_rng, to_hex, SEED_BYTES};
+    use crate::util::testing::ScriptedRng;
+    use rand::RngCore;
+
+    #[test]
+    fn test_hex_round_trip() {
+        let seed = new_seed(&mut ScriptedRng::new(&[1, 4, 9, 10]));
+        let hex = to_hex(&seed);
+
+        assert_eq!(hex.len(), SEED_BYTES * 2);
+        assert_eq!(parse_seed(&hex), Some(seed));
+        assert_eq!(parse_seed(&hex.to_uppercase()), Some(seed));
+    }
+
+    #[test]
+    fn test_parse_seed_rejects_bad_hex() {
+        assert_eq!(parse_seed(""), None);
+        assert_eq!(parse_seed(&"0".repeat(63)), None);
+        assert_eq!(parse_seed(&"g".repeat(64)), None);
+        assert_eq!(parse_seed(&"é".repeat(32)), None);
+        assert_eq!(parse_seed(&"0".repeat(64)), Some([0; SEED_BYTES]));
+    }
+
+    #[test]
+    fn test_commitment() {
+        assert_eq!(
+            commitment(&[0; SEED_BYTES]),
+            "66687aadf862bd776c8fc18b8e9f8e20089714856ee233b3902a591d0d5f2925"
+        );
+    }
+
+    #[test]
+    fn test_seeded_rng_is_deterministic() {
+        let a = [7; SEED_BYTES];
+        let b = [8; SEED_BYTES];
+        let first: Vec<u64> = (0..5).map(|_| seeded_rng(&a).next_u64()).collect();
+        let mut rng = seeded_rng(&a);
+        let sequence: Vec<u64> = (0..5).map(|_| rng.next_u64()).collect();
+        let mut again = seeded_rng(&a);
+
+        assert!(first.iter().all(|&n| n == first[0]));
+        assert_eq!(sequence, (0..5).map(|_| again.next_u64()).collect::<Vec<_>>());
+        assert_ne!(seeded_rng(&a).next_u64(), seeded_rng(&b).next_u64());
+    }
+}
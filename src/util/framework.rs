@@ -0,0 +1,117 @@
+use log::{debug, warn};
+use serenity::{
+    client::Context,
+    framework::{Framework, StandardFramework},
+    model::channel::Message,
+};
+use std::collections::BTreeMap;
+use threadpool::ThreadPool;
+use crate::util::{
+    aliases::expand_alias,
+    args::split_commands,
+    constants::SETTINGS_PATH,
+    respond::{commands_finished, track_commands},
+    settings::SettingsStore,
+};
+
+/// Most commands run from a single message.
+pub const MAX_COMMANDS: usize = 3;
+
+/// Framework that runs each `;`-separated command in a message that starts
+/// with the prefix.
+///
+/// Every command is dispatched through the standard framework on its own,
+/// so checks and rate limits apply to each. Commands from one message are
+/// run one after another so their replies are posted in order, without
+/// holding up other messages. Commands using one of the guild's aliases
+/// are expanded before they're dispatched.
+pub struct SplitFramework {
+    inner: StandardFramework,
+    prefix: &'static str,
+}
+
+impl SplitFramework {
+    /// Wrap a configured standard framework.
+    ///
+    /// # Arguments
+    ///
+    /// * `inner` - framework to dispatch each command to
+    /// * `prefix` - command prefix the framework is configured with
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// client.with_framework(SplitFramework::new(StandardFramework::new(), "!"));
+    /// ```
+    pub fn new(inner: StandardFramework, prefix: &'static str) -> Self {
+        SplitFramework { inner, prefix }
+    }
+}
+
+/// Load the aliases of the guild a message was sent in.
+fn guild_aliases(message: &Message) -> BTreeMap<String, String> {
+    let guild_id = match message.guild_id {
+        Some(g) => g.0,
+        None => return BTreeMap::new(),
+    };
+    match SettingsStore::from_file(&SETTINGS_PATH) {
+        Ok(settings) => settings
+            .get(guild_id)
+            .map(|g| g.aliases.clone())
+            .unwrap_or_default(),
+        Err(e) => {
+            warn!("Could not load settings for aliases: {}", e);
+            BTreeMap::new()
+        }
+    }
+}
+
+impl Framework for SplitFramework {
+    fn dispatch(&mut self, context: Context, mut message: Message, threadpool: &ThreadPool) {
+        let mut commands = split_commands(&message.content, self.prefix);
+        let mut any_expanded = false;
+        if message.content.trim_start().starts_with(self.prefix) {
+            let aliases = guild_aliases(&message);
+            for command in &mut commands {
+                if let Some(expanded) = expand_alias(command, self.prefix, &aliases) {
+                    debug!("Expanded alias `{}` to `{}`", command, expanded);
+                    *command = expanded;
+                    any_expanded = true;
+                }
+            }
+        }
+        if commands.len() < 2 {
+            if let (Some(command), true) = (commands.pop(), any_expanded) {
+                message.content = command;
+            }
+            self.inner.dispatch(context, message, threadpool);
+            return;
+        }
+        debug!("Message contains {} commands", commands.len());
+        let skipped = commands.len().saturating_sub(MAX_COMMANDS);
+        // The standard framework always runs a command on the pool it's given,
+        // so the message's commands queue on a single worker of their own
+        let commands_pool = ThreadPool::with_name(String::from("split commands"), 1);
+        let total = commands.len();
+        // The message is cleaned up once, after all of its commands have run
+        track_commands(&message);
+        for command in commands.into_iter().take(MAX_COMMANDS) {
+            let mut sub_message = message.clone();
+            sub_message.content = command;
+            self.inner.dispatch(context.clone(), sub_message, &commands_pool);
+        }
+        threadpool.execute(move || {
+            commands_pool.join();
+            commands_finished(&context, &message, total);
+            if skipped > 0 {
+                let response = format!(
+                    "Only the first {} commands in a message are run; skipped {}.",
+                    MAX_COMMANDS, skipped
+                );
+                if let Err(e) = message.channel_id.say(&context.http, &response) {
+                    debug!("Could not send skipped commands notice: {}", e);
+                }
+            }
+        });
+    }
+}
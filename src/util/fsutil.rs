@@ -0,0 +1,246 @@
+use log::warn;
+use std::{
+    ffi::OsString,
+    fs::{self, File},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    process,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+/// Counts writes in this process, so each gets its own temporary file.
+static WRITES: AtomicUsize = AtomicUsize::new(0);
+
+/// Where a write is staged before being renamed over its destination.
+///
+/// Each call returns a new name, made from the process ID and a counter,
+/// so concurrent writes to one file never share a temporary file.
+///
+/// # Arguments
+///
+/// * `path` - destination file
+///
+/// # Examples
+///
+/// ```rust
+/// let temp = temp_path(Path::new("data.json")); // data.json.1234.0.tmp
+/// ```
+pub fn temp_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().map(OsString::from).unwrap_or_default();
+    let write = WRITES.fetch_add(1, Ordering::SeqCst);
+    name.push(format!(".{}.{}.tmp", process::id(), write));
+    path.with_file_name(name)
+}
+
+/// Write a file so that it always holds either its old or its new contents.
+///
+/// The bytes are written and synced to a temporary file next to the
+/// destination, which is then renamed over it. Concurrent writes each
+/// use their own temporary file, and the last rename wins.
+///
+/// # Arguments
+///
+/// * `path` - destination file
+/// * `bytes` - new contents
+///
+/// # Examples
+///
+/// ```rust
+/// atomic_write(Path::new("./data.json"), b"{}")?;
+/// ```
+pub fn atomic_write(path: &Path, bytes: &[u8]) -> io::Result<()> {
+    let temp = temp_path(path);
+    let written = File::create(&temp).and_then(|mut file| {
+        file.write_all(bytes)?;
+        file.sync_all()?;
+        fs::rename(&temp, path)
+    });
+    if let Err(e) = written {
+        let _ = fs::remove_file(&temp);
+        return Err(e);
+    }
+    // Sync the directory too, so the rename itself survives a crash
+    #[cfg(unix)]
+    {
+        if let Err(e) = File::open(parent_dir(path)).and_then(|d| d.sync_all()) {
+            warn!("Could not sync {}: {}", parent_dir(path).display(), e);
+        }
+    }
+    Ok(())
+}
+
+/// The directory a file is in, `.` for a bare file name.
+fn parent_dir(path: &Path) -> &Path {
+    match path.parent() {
+        Some(d) if !d.as_os_str().is_empty() => d,
+        _ => Path::new("."),
+    }
+}
+
+/// Temporary files left next to a destination by writes that never finished.
+fn leftovers(path: &Path) -> io::Result<Vec<PathBuf>> {
+    let name = match path.file_name().and_then(|n| n.to_str()) {
+        Some(n) => format!("{}.", n),
+        None => return Ok(vec![]),
+    };
+    let entries = match fs::read_dir(parent_dir(path)) {
+        Ok(e) => e,
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => return Ok(vec![]),
+        Err(e) => return Err(e),
+    };
+    let mut found = vec![];
+    for entry in entries {
+        let entry = entry?;
+        let file_name = entry.file_name();
+        let file_name = file_name.to_string_lossy();
+        if file_name.starts_with(&name) && file_name.ends_with(".tmp") {
+            found.push(entry.path());
+        }
+    }
+    found.sort();
+    Ok(found)
+}
+
+/// Deal with temporary files left behind by interrupted `atomic_write`s.
+///
+/// When the destination exists it is complete, so the leftovers are
+/// removed. When it doesn't, the newest leftover holding valid contents
+/// takes its place. Only call this on startup, before anything saves, as
+/// it can't tell a leftover from a write still in progress.
+///
+/// # Arguments
+///
+/// * `path` - destination file
+/// * `is_valid` - whether a leftover's contents are complete
+///
+/// # Examples
+///
+/// ```rust
+/// recover_leftovers(&path, |c| serde_json::from_str::<Value>(c).is_ok())?;
+/// ```
+pub fn recover_leftovers<F: Fn(&str) -> bool>(path: &Path, is_valid: F) -> io::Result<()> {
+    let mut temps = leftovers(path)?;
+    temps.sort_by_key(|t| fs::metadata(t).and_then(|m| m.modified()).ok());
+    let mut needs_recovery = !path.exists();
+    for temp in temps.into_iter().rev() {
+        let usable = needs_recovery
+            && match fs::read_to_string(&temp) {
+                Ok(content) => is_valid(&content),
+                Err(_) => false,
+            };
+        if usable {
+            warn!("Recovering {} from an interrupted save", path.display());
+            fs::rename(&temp, path)?;
+            needs_recovery = false;
+        } else {
+            warn!("Removing {} left by an interrupted save", temp.display());
+            fs::remove_file(&temp)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::{atomic_write, leftovers, recover_leftovers, temp_path};
+    use std::{fs, path::Path, sync::Arc, thread};
+    use tempdir::TempDir;
+
+    fn complete(content: &str) -> bool {
+        content.ends_with('}')
+    }
+
+    #[test]
+    fn test_temp_path() {
+        let first = temp_path(Path::new("/srv/bot/settings.json"));
+        let second = temp_path(Path::new("/srv/bot/settings.json"));
+
+        assert_ne!(first, second);
+        assert_eq!(first.parent(), Some(Path::new("/srv/bot")));
+        let name = first.file_name().unwrap().to_str().unwrap();
+        assert!(name.starts_with("settings.json."), "{}", name);
+        assert!(name.ends_with(".tmp"), "{}", name);
+    }
+
+    #[test]
+    fn test_atomic_write() {
+        let temp = TempDir::new("dicebot").unwrap();
+        let path = temp.path().join("data.json");
+
+        atomic_write(&path, b"{\"old\":1}").unwrap();
+        atomic_write(&path, b"{\"new\":2}").unwrap();
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), "{\"new\":2}");
+        assert!(leftovers(&path).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_atomic_write_concurrent_writers() {
+        let temp = TempDir::new("dicebot").unwrap();
+        let path = Arc::new(temp.path().join("data.json"));
+        let contents = ["{\"writer\":\"a\"}", "{\"writer\":\"bb\"}"];
+
+        let writers: Vec<_> = contents
+            .iter()
+            .map(|&content| {
+                let path = Arc::clone(&path);
+                thread::spawn(move || {
+                    for _ in 0..50 {
+                        atomic_write(&path, content.as_bytes()).unwrap();
+                    }
+                })
+            })
+            .collect();
+        for writer in writers {
+            writer.join().unwrap();
+        }
+
+        let saved = fs::read_to_string(&*path).unwrap();
+        assert!(contents.contains(&saved.as_str()), "{}", saved);
+        assert!(leftovers(&path).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_recover_leftovers_keeps_old_contents() {
+        let temp = TempDir::new("dicebot").unwrap();
+        let path = temp.path().join("data.json");
+        atomic_write(&path, b"{\"old\":1}").unwrap();
+        // A save that stopped partway through writing its temporary file
+        fs::write(temp_path(&path), "{\"new\":").unwrap();
+        fs::write(temp_path(&path), "{\"new\":2}").unwrap();
+
+        recover_leftovers(&path, complete).unwrap();
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), "{\"old\":1}");
+        assert!(leftovers(&path).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_recover_leftovers_without_destination() {
+        let temp = TempDir::new("dicebot").unwrap();
+        let path = temp.path().join("data.json");
+
+        fs::write(temp_path(&path), "{\"new\":2}").unwrap();
+        fs::write(temp_path(&path), "{\"new\":").unwrap();
+        recover_leftovers(&path, complete).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "{\"new\":2}");
+        assert!(leftovers(&path).unwrap().is_empty());
+
+        let other = temp.path().join("settings.json");
+        fs::write(temp_path(&other), "{\"new\":").unwrap();
+        recover_leftovers(&other, complete).unwrap();
+        assert!(!other.exists());
+        assert!(leftovers(&other).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_recover_leftovers_without_leftovers() {
+        let temp = TempDir::new("dicebot").unwrap();
+        let path = temp.path().join("data.json");
+        fs::write(temp.path().join("data.json.bak"), "{}").unwrap();
+
+        recover_leftovers(&path, complete).unwrap();
+        assert!(!path.exists());
+        assert!(temp.path().join("data.json.bak").exists());
+    }
+}
@@ -0,0 +1,203 @@
+use chrono::{SecondsFormat, Utc};
+use log::Record;
+use log4rs::encode::{Encode, Write};
+use serde_json::{json, Value};
+use std::{collections::BTreeMap, error::Error};
+
+/// Record the command running on this thread, so its log lines carry it.
+///
+/// # Arguments
+///
+/// * `command` - name of the command
+/// * `user_id` - Discord ID of the user who ran it
+/// * `guild_id` - Discord ID of the guild it ran in, if any
+///
+/// # Examples
+///
+/// ```rust
+/// set_command_fields("roll", message.author.id.0, message.guild_id.map(|g| g.0));
+/// ```
+pub fn set_command_fields(command: &str, user_id: u64, guild_id: Option<u64>) {
+    log_mdc::insert("command", command);
+    log_mdc::insert("user", user_id.to_string());
+    match guild_id {
+        Some(guild_id) => log_mdc::insert("guild", guild_id.to_string()),
+        None => log_mdc::remove("guild"),
+    };
+}
+
+/// Stop tagging this thread's log lines with a command.
+pub fn clear_command_fields() {
+    log_mdc::clear();
+}
+
+/// Format one log line as a JSON object.
+///
+/// Fields are added as keys alongside the others, unless they'd replace one.
+///
+/// # Arguments
+///
+/// * `timestamp` - when the record was logged, in RFC 3339 format
+/// * `level` - record level, like "INFO"
+/// * `target` - module the record came from
+/// * `message` - formatted record message
+/// * `fields` - structured fields, like the command being run
+///
+/// # Examples
+///
+/// ```rust
+/// let line = json_line("2019-06-03T00:00:00Z", "INFO", "cod_dice_roller", "Starting up", &fields);
+/// ```
+pub fn json_line(
+    timestamp: &str,
+    level: &str,
+    target: &str,
+    message: &str,
+    fields: &BTreeMap<String, String>,
+) -> String {
+    let mut line = json!({
+        "timestamp": timestamp,
+        "level": level,
+        "target": target,
+        "message": message,
+    });
+    if let Value::Object(object) = &mut line {
+        for (key, value) in fields {
+            object.entry(key.as_str()).or_insert_with(|| json!(value));
+        }
+    }
+    line.to_string()
+}
+
+/// Encodes each log record as a JSON object on its own line, for log
+/// collectors that ingest JSON.
+#[derive(Debug, Default)]
+pub struct JsonEncoder;
+
+impl Encode for JsonEncoder {
+    fn encode(
+        &self,
+        w: &mut dyn Write,
+        record: &Record,
+    ) -> Result<(), Box<dyn Error + Sync + Send>> {
+        let mut fields = BTreeMap::new();
+        log_mdc::iter(|key, value| {
+            fields.insert(key.to_owned(), value.to_owned());
+        });
+        let line = json_line(
+            &Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true),
+            &record.level().to_string(),
+            record.target(),
+            &record.args().to_string(),
+            &fields,
+        );
+        writeln!(w, "{}", line)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{clear_command_fields, json_line, set_command_fields, JsonEncoder};
+    use log::{Level, Record};
+    use log4rs::encode::{writer::simple::SimpleWriter, Encode};
+    use serde_json::Value;
+    use std::collections::BTreeMap;
+
+    fn encode(message: &str) -> Value {
+        let mut writer = SimpleWriter(vec![]);
+        JsonEncoder
+            .encode(
+                &mut writer,
+                &Record::builder()
+                    .args(format_args!("{}", message))
+                    .level(Level::Info)
+                    .target("cod_dice_roller")
+                    .build(),
+            )
+            .unwrap();
+        serde_json::from_slice(&writer.0).unwrap()
+    }
+
+    #[test]
+    fn test_json_line_escapes_message() {
+        let message = "Error in command 'roll': \"bad\"\n\tat line 2 \\ done";
+        let line = json_line(
+            "2019-06-03T00:00:00.000Z",
+            "ERROR",
+            "cod_dice_roller",
+            message,
+            &BTreeMap::new(),
+        );
+        let value: Value = serde_json::from_str(&line).unwrap();
+
+        assert!(!line.contains('\n'));
+        assert_eq!(value["message"], message);
+        assert_eq!(value["level"], "ERROR");
+        assert_eq!(value["target"], "cod_dice_roller");
+        assert_eq!(value["timestamp"], "2019-06-03T00:00:00.000Z");
+    }
+
+    #[test]
+    fn test_json_line_adds_fields() {
+        let mut fields = BTreeMap::new();
+        fields.insert(String::from("command"), String::from("roll"));
+        fields.insert(String::from("level"), String::from("shadowed"));
+        let line = json_line("2019-06-03T00:00:00.000Z", "INFO", "cod_dice_roller", "hi", &fields);
+        let value: Value = serde_json::from_str(&line).unwrap();
+
+        assert_eq!(value["command"], "roll");
+        assert_eq!(value["level"], "INFO");
+    }
+
+    #[test]
+    fn test_encoder_writes_command_fields() {
+        set_command_fields("roll", 1, Some(2));
+        let value = encode("Rolling");
+
+        assert_eq!(value["message"], "Rolling");
+        assert_eq!(value["command"], "roll");
+        assert_eq!(value["user"], "1");
+        assert_eq!(value["guild"], "2");
+
+        set_command_fields("stats", 3, None);
+        let value = encode("Reading stats");
+
+        assert_eq!(value["command"], "stats");
+        assert_eq!(value["user"], "3");
+        assert!(value.get("guild").is_none());
+
+        clear_command_fields();
+        let value = encode("Idle");
+
+        assert!(value.get("command").is_none());
+        assert!(value.get("user").is_none());
+    }
+
+    #[test]
+    fn test_encoder_writes_one_object_per_line() {
+        let mut writer = SimpleWriter(vec![]);
+        for message in &["first \"quoted\"", "second\nsplit"] {
+            JsonEncoder
+                .encode(
+                    &mut writer,
+                    &Record::builder()
+                        .args(format_args!("{}", message))
+                        .level(Level::Info)
+                        .target("cod_dice_roller::commands::roll")
+                        .build(),
+                )
+                .unwrap();
+        }
+        let output = String::from_utf8(writer.0).unwrap();
+        let lines: Vec<&str> = output.lines().collect();
+
+        assert_eq!(lines.len(), 2);
+        let first: Value = serde_json::from_str(lines[0]).unwrap();
+        let second: Value = serde_json::from_str(lines[1]).unwrap();
+        assert_eq!(first["message"], "first \"quoted\"");
+        assert_eq!(second["message"], "second\nsplit");
+        assert_eq!(second["level"], "INFO");
+        assert_eq!(second["target"], "cod_dice_roller::commands::roll");
+    }
+}
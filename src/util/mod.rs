@@ -1,2 +1,30 @@
+pub mod aliases;
+pub mod args;
+pub mod audit;
 pub mod characters;
+pub mod cleanup;
 pub mod constants;
+pub mod ephemeral;
+pub mod export_md;
+pub mod expression;
+pub mod fairness;
+pub mod framework;
+pub mod fsutil;
+pub mod json_log;
+pub mod onboarding;
+pub mod outcome;
+pub mod permissions;
+pub mod polls;
+pub mod probability;
+pub mod render;
+pub mod respond;
+pub mod roll_tokens;
+pub mod roster;
+pub mod settings;
+pub mod stat_names;
+pub mod stat_values;
+pub mod text;
+#[cfg(test)]
+pub mod testing;
+pub mod time;
+pub mod trace;
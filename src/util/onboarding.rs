@@ -0,0 +1,84 @@
+/// Message posted when the bot joins a server.
+pub const ONBOARDING: &str = "Thanks for adding me! Use `!help` to see what I can do.
+Server admins can use `!settings` to set up weekly beats, custom merits and dice emoji.";
+
+/// A text channel the onboarding message could be posted in.
+#[derive(Debug)]
+pub struct Candidate {
+    pub id: u64,
+    /// Position in the server's channel list, from the top.
+    pub position: i64,
+    /// Whether the bot can send messages there.
+    pub writable: bool,
+}
+
+/// Pick the channel to post the onboarding message in.
+///
+/// The server's system channel is used if the bot can write to it,
+/// otherwise the highest writable channel in the channel list.
+///
+/// # Arguments
+///
+/// * `system_channel` - the server's system channel, if it has one
+/// * `candidates` - the server's text channels
+///
+/// # Examples
+///
+/// ```rust
+/// let channel = onboarding_channel(guild.system_channel_id.map(|c| c.0), &candidates);
+/// ```
+pub fn onboarding_channel(system_channel: Option<u64>, candidates: &[Candidate]) -> Option<u64> {
+    let writable = candidates.iter().filter(|c| c.writable);
+    if let Some(system) = system_channel {
+        if writable.clone().any(|c| c.id == system) {
+            return Some(system);
+        }
+    }
+    writable.min_by_key(|c| (c.position, c.id)).map(|c| c.id)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{onboarding_channel, Candidate};
+
+    fn candidate(id: u64, position: i64, writable: bool) -> Candidate {
+        Candidate {
+            id,
+            position,
+            writable,
+        }
+    }
+
+    #[test]
+    fn test_prefers_system_channel() {
+        let candidates = [candidate(1, 0, true), candidate(2, 1, true)];
+        assert_eq!(onboarding_channel(Some(2), &candidates), Some(2));
+    }
+
+    #[test]
+    fn test_falls_back_to_first_writable() {
+        let candidates = [
+            candidate(1, 0, false),
+            candidate(2, 3, true),
+            candidate(3, 1, true),
+            candidate(4, 2, false),
+        ];
+        // System channel is read-only
+        assert_eq!(onboarding_channel(Some(1), &candidates), Some(3));
+        // System channel isn't a text channel the bot can see
+        assert_eq!(onboarding_channel(Some(9), &candidates), Some(3));
+        assert_eq!(onboarding_channel(None, &candidates), Some(3));
+    }
+
+    #[test]
+    fn test_ties_use_lowest_id() {
+        let candidates = [candidate(5, 0, true), candidate(4, 0, true)];
+        assert_eq!(onboarding_channel(None, &candidates), Some(4));
+    }
+
+    #[test]
+    fn test_nowhere_to_post() {
+        assert_eq!(onboarding_channel(Some(1), &[candidate(1, 0, false)]), None);
+        assert_eq!(onboarding_channel(None, &[]), None);
+    }
+}
@@ -0,0 +1,202 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// How a roll turned out.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Ord, PartialEq, PartialOrd, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Outcome {
+    DramaticFailure,
+    Failure,
+    Success,
+    Exceptional,
+}
+
+/// Every outcome, from worst to best.
+pub const OUTCOMES: [Outcome; 4] = [
+    Outcome::DramaticFailure,
+    Outcome::Failure,
+    Outcome::Success,
+    Outcome::Exceptional,
+];
+
+impl Outcome {
+    /// Name used for the outcome in settings.
+    pub fn name(self) -> &'static str {
+        match self {
+            Outcome::DramaticFailure => "dramatic-failure",
+            Outcome::Failure => "failure",
+            Outcome::Success => "success",
+            Outcome::Exceptional => "exceptional",
+        }
+    }
+
+    /// Parse an outcome by its name, ignoring case.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - outcome name, like "dramatic-failure"
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// assert_eq!(Outcome::parse("Success"), Some(Outcome::Success));
+    /// ```
+    pub fn parse(name: &str) -> Option<Self> {
+        OUTCOMES
+            .iter()
+            .find(|o| o.name().eq_ignore_ascii_case(name))
+            .cloned()
+    }
+
+    /// Marker shown for the outcome when a guild hasn't chosen its own.
+    pub fn default_marker(self) -> &'static str {
+        match self {
+            Outcome::DramaticFailure => "‼️",
+            Outcome::Failure => "✖",
+            Outcome::Success => "✔",
+            Outcome::Exceptional => "✨",
+        }
+    }
+
+    /// Marker shown for the outcome, preferring the guild's own.
+    ///
+    /// # Arguments
+    ///
+    /// * `overrides` - markers chosen by the guild
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// let marker = outcome.marker(&guild.outcome_marker_symbols);
+    /// ```
+    pub fn marker(self, overrides: &BTreeMap<Outcome, String>) -> &str {
+        overrides
+            .get(&self)
+            .map_or(self.default_marker(), String::as_str)
+    }
+}
+
+/// Classify a roll of regular dice by its successes.
+///
+/// # Arguments
+///
+/// * `successes` - successes rolled
+/// * `exceptional` - successes needed for an exceptional success
+///
+/// # Examples
+///
+/// ```rust
+/// let outcome = classify(successes(&rolls), EXCEPTIONAL_SUCCESSES);
+/// ```
+pub fn classify(successes: u64, exceptional: u64) -> Outcome {
+    if successes == 0 {
+        Outcome::Failure
+    } else if successes >= exceptional {
+        Outcome::Exceptional
+    } else {
+        Outcome::Success
+    }
+}
+
+/// Classify a roll of chance dice by their faces.
+///
+/// A 10 succeeds and a 1 is a dramatic failure. With several chance dice,
+/// any success makes the roll a success, and otherwise any 1 makes it a
+/// dramatic failure.
+///
+/// # Arguments
+///
+/// * `faces` - faces rolled
+///
+/// # Examples
+///
+/// ```rust
+/// let outcome = classify_chance(&[1, 4]);
+/// ```
+pub fn classify_chance(faces: &[u64]) -> Outcome {
+    if faces.contains(&10) {
+        Outcome::Success
+    } else if faces.contains(&1) {
+        Outcome::DramaticFailure
+    } else {
+        Outcome::Failure
+    }
+}
+
+/// Returns whether text can be used as an outcome marker.
+///
+/// Markers are short and have no spaces or characters Discord treats as
+/// markup, so they can't mention anyone or break the message.
+///
+/// # Arguments
+///
+/// * `marker` - marker text
+///
+/// # Examples
+///
+/// ```rust
+/// assert!(is_valid_marker("✔"));
+/// ```
+pub fn is_valid_marker(marker: &str) -> bool {
+    let count = marker.chars().count();
+    count > 0
+        && count <= 8
+        && !marker
+            .chars()
+            .any(|c| c.is_whitespace() || "@#`*_~|<>".contains(c))
+}
+
+#[cfg(test)]
+mod test {
+    use super::{classify, classify_chance, is_valid_marker, Outcome, OUTCOMES};
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn test_classify() {
+        assert_eq!(classify(0, 5), Outcome::Failure);
+        assert_eq!(classify(1, 5), Outcome::Success);
+        assert_eq!(classify(4, 5), Outcome::Success);
+        assert_eq!(classify(5, 5), Outcome::Exceptional);
+        assert_eq!(classify(3, 3), Outcome::Exceptional);
+    }
+
+    #[test]
+    fn test_classify_chance() {
+        assert_eq!(classify_chance(&[10]), Outcome::Success);
+        assert_eq!(classify_chance(&[1]), Outcome::DramaticFailure);
+        assert_eq!(classify_chance(&[5]), Outcome::Failure);
+        assert_eq!(classify_chance(&[1, 10]), Outcome::Success);
+        assert_eq!(classify_chance(&[1, 5]), Outcome::DramaticFailure);
+    }
+
+    #[test]
+    fn test_parse() {
+        for outcome in &OUTCOMES {
+            assert_eq!(Outcome::parse(outcome.name()), Some(*outcome));
+        }
+        assert_eq!(Outcome::parse("Dramatic-Failure"), Some(Outcome::DramaticFailure));
+        assert_eq!(Outcome::parse("dramatic"), None);
+    }
+
+    #[test]
+    fn test_marker_falls_back_to_default() {
+        let mut overrides = BTreeMap::new();
+        overrides.insert(Outcome::Success, String::from("+"));
+
+        assert_eq!(Outcome::Success.marker(&overrides), "+");
+        assert_eq!(Outcome::Failure.marker(&overrides), "✖");
+        assert_eq!(Outcome::Exceptional.marker(&BTreeMap::new()), "✨");
+    }
+
+    #[test]
+    fn test_is_valid_marker() {
+        assert!(is_valid_marker("✔"));
+        assert!(is_valid_marker("‼️"));
+        assert!(is_valid_marker("[X]"));
+        assert!(!is_valid_marker(""));
+        assert!(!is_valid_marker("two words"));
+        assert!(!is_valid_marker("@everyone"));
+        assert!(!is_valid_marker("**"));
+        assert!(!is_valid_marker("123456789"));
+    }
+}
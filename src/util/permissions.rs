@@ -0,0 +1,166 @@
+use serenity::{
+    client::Context,
+    model::{channel::Message, permissions::Permissions},
+};
+
+/// A permission the bot needs, and what breaks without it.
+#[derive(Debug, PartialEq)]
+pub struct Requirement {
+    pub permission: Permissions,
+    pub name: &'static str,
+    pub features: &'static str,
+}
+
+/// Every permission the bot needs to function.
+pub static REQUIREMENTS: &[Requirement] = &[
+    Requirement {
+        permission: Permissions::READ_MESSAGES,
+        name: "Read Messages",
+        features: "all commands",
+    },
+    Requirement {
+        permission: Permissions::SEND_MESSAGES,
+        name: "Send Messages",
+        features: "all command replies",
+    },
+    Requirement {
+        permission: Permissions::EMBED_LINKS,
+        name: "Embed Links",
+        features: "!merit",
+    },
+    Requirement {
+        permission: Permissions::ATTACH_FILES,
+        name: "Attach Files",
+        features: "!merit",
+    },
+    Requirement {
+        permission: Permissions::ADD_REACTIONS,
+        name: "Add Reactions",
+        features: "acknowledging !stats edits (falls back to text)",
+    },
+    Requirement {
+        permission: Permissions::MANAGE_MESSAGES,
+        name: "Manage Messages",
+        features: "deleting invocations, ephemeral replies, and !cleanup",
+    },
+    Requirement {
+        permission: Permissions::READ_MESSAGE_HISTORY,
+        name: "Read Message History",
+        features: "!cleanup",
+    },
+];
+
+/// Returns the combined permission bits of all requirements.
+pub fn required_permissions() -> Permissions {
+    REQUIREMENTS
+        .iter()
+        .fold(Permissions::empty(), |acc, r| acc | r.permission)
+}
+
+/// Returns the OAuth2 URL for inviting the bot to a guild.
+///
+/// # Arguments
+///
+/// * `client_id` - the bot's application ID
+///
+/// # Examples
+///
+/// ```rust
+/// let url = invite_url(123456);
+/// ```
+pub fn invite_url(client_id: u64) -> String {
+    format!(
+        "https://discordapp.com/api/oauth2/authorize?client_id={}&scope=bot&permissions={}",
+        client_id,
+        required_permissions().bits()
+    )
+}
+
+/// Returns the requirements not satisfied by the permissions.
+///
+/// # Arguments
+///
+/// * `actual` - permissions the bot has
+///
+/// # Examples
+///
+/// ```rust
+/// let missing = missing_requirements(Permissions::SEND_MESSAGES);
+/// ```
+pub fn missing_requirements(actual: Permissions) -> Vec<&'static Requirement> {
+    REQUIREMENTS
+        .iter()
+        .filter(|r| !actual.contains(r.permission))
+        .collect()
+}
+
+/// Returns whether the message's author can manage the guild it was sent in.
+///
+/// Always false in direct messages.
+///
+/// # Arguments
+///
+/// * `context` - serenity context
+/// * `message` - message to check the author of
+///
+/// # Examples
+///
+/// ```rust
+/// if !is_admin(&context, &message) { /* ... */ }
+/// ```
+pub fn is_admin(context: &Context, message: &Message) -> bool {
+    match message.guild(&context.cache) {
+        Some(guild) => guild
+            .read()
+            .member_permissions(message.author.id)
+            .contains(Permissions::MANAGE_GUILD),
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{invite_url, missing_requirements, required_permissions, REQUIREMENTS};
+    use serenity::model::permissions::Permissions;
+
+    #[test]
+    fn test_invite_url() {
+        assert_eq!(
+            invite_url(1234),
+            "https://discordapp.com/api/oauth2/authorize?client_id=1234&scope=bot&permissions=126016"
+        );
+    }
+
+    #[test]
+    fn test_required_permissions() {
+        let required = required_permissions();
+
+        assert!(required.contains(Permissions::SEND_MESSAGES));
+        assert!(required.contains(Permissions::ADD_REACTIONS));
+        assert!(required.contains(Permissions::MANAGE_MESSAGES));
+        assert!(required.contains(Permissions::READ_MESSAGE_HISTORY));
+        assert!(!required.contains(Permissions::ADMINISTRATOR));
+    }
+
+    #[test]
+    fn test_missing_requirements() {
+        assert!(missing_requirements(required_permissions()).is_empty());
+        assert!(missing_requirements(Permissions::all()).is_empty());
+        assert_eq!(missing_requirements(Permissions::empty()).len(), REQUIREMENTS.len());
+
+        let missing = missing_requirements(
+            Permissions::READ_MESSAGES | Permissions::SEND_MESSAGES | Permissions::ADD_REACTIONS,
+        );
+        let names: Vec<_> = missing.iter().map(|r| r.name).collect();
+
+        assert_eq!(
+            names,
+            vec![
+                "Embed Links",
+                "Attach Files",
+                "Manage Messages",
+                "Read Message History"
+            ]
+        );
+    }
+}
@@ -0,0 +1,228 @@
+use lazy_static::lazy_static;
+use std::{collections::HashMap, sync::Mutex};
+
+/// Reactions a poll can be answered with, in the order they're shown.
+pub const OPTIONS: [&str; 3] = ["👍", "👎", "🤷"];
+
+lazy_static! {
+    pub static ref POLLS: Mutex<Polls> = Mutex::new(Polls::new());
+}
+
+/// Returns which option a reaction is for, if any.
+///
+/// # Arguments
+///
+/// * `emoji` - the reaction's emoji
+///
+/// # Examples
+///
+/// ```rust
+/// assert_eq!(option_index("👎"), Some(1));
+/// ```
+pub fn option_index(emoji: &str) -> Option<usize> {
+    OPTIONS.iter().position(|o| *o == emoji)
+}
+
+/// An open poll and the reactions it has collected.
+#[derive(Debug, PartialEq)]
+pub struct Poll {
+    pub question: String,
+    /// User and option of each reaction, in the order they arrived.
+    votes: Vec<(u64, usize)>,
+}
+
+impl Poll {
+    /// Create a new poll with no votes.
+    pub fn new(question: &str) -> Self {
+        Poll {
+            question: question.to_owned(),
+            votes: vec![],
+        }
+    }
+
+    /// Record a reaction.
+    ///
+    /// # Arguments
+    ///
+    /// * `user_id` - user who reacted
+    /// * `option` - index into `OPTIONS`
+    pub fn add(&mut self, user_id: u64, option: usize) {
+        if !self.votes.contains(&(user_id, option)) {
+            self.votes.push((user_id, option));
+        }
+    }
+
+    /// Forget a reaction that was taken back.
+    ///
+    /// # Arguments
+    ///
+    /// * `user_id` - user who removed the reaction
+    /// * `option` - index into `OPTIONS`
+    pub fn remove(&mut self, user_id: u64, option: usize) {
+        self.votes.retain(|v| *v != (user_id, option));
+    }
+
+    /// Count the votes for each option.
+    ///
+    /// Users who reacted with more than one option only have their
+    /// first reaction counted.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// let [yes, no, unsure] = poll.tally();
+    /// ```
+    pub fn tally(&self) -> [u64; 3] {
+        let mut counted: Vec<u64> = vec![];
+        let mut tally = [0; 3];
+        for (user_id, option) in &self.votes {
+            if !counted.contains(user_id) {
+                counted.push(*user_id);
+                tally[*option] += 1;
+            }
+        }
+        tally
+    }
+
+    /// Describe the tally, like "👍 2 · 👎 1 · 🤷 0".
+    pub fn tally_text(&self) -> String {
+        let parts: Vec<String> = OPTIONS
+            .iter()
+            .zip(self.tally().iter())
+            .map(|(option, votes)| format!("{} {}", option, votes))
+            .collect();
+        parts.join(" · ")
+    }
+
+    /// Describe the outcome, like "👍 wins with 2 of 3 votes".
+    pub fn outcome(&self) -> String {
+        let tally = self.tally();
+        let total: u64 = tally.iter().sum();
+        let best = *tally.iter().max().unwrap_or(&0);
+        if total == 0 {
+            return String::from("no votes were cast");
+        }
+        let leaders: Vec<&str> = OPTIONS
+            .iter()
+            .zip(tally.iter())
+            .filter(|(_, votes)| **votes == best)
+            .map(|(option, _)| *option)
+            .collect();
+        match leaders.as_slice() {
+            [winner] => format!("{} wins with {} of {} votes", winner, best, total),
+            _ => format!("tied between {}", leaders.join(" and ")),
+        }
+    }
+}
+
+/// Polls that are waiting to close, keyed by the poll message's ID.
+///
+/// Polls only live for the lifetime of the process, and each is removed
+/// when it closes.
+#[derive(Debug, Default)]
+pub struct Polls {
+    open: HashMap<u64, Poll>,
+}
+
+impl Polls {
+    /// Create a new, empty set of polls.
+    pub fn new() -> Self {
+        Polls {
+            open: HashMap::new(),
+        }
+    }
+
+    /// Start collecting votes on a poll message.
+    pub fn open(&mut self, message_id: u64, question: &str) {
+        self.open.insert(message_id, Poll::new(question));
+    }
+
+    /// Record a reaction, if it's on an open poll and is one of `OPTIONS`.
+    pub fn react(&mut self, message_id: u64, user_id: u64, emoji: &str) {
+        let option = option_index(emoji);
+        if let (Some(poll), Some(option)) = (self.open.get_mut(&message_id), option) {
+            poll.add(user_id, option);
+        }
+    }
+
+    /// Forget a reaction, if it's on an open poll.
+    pub fn unreact(&mut self, message_id: u64, user_id: u64, emoji: &str) {
+        let option = option_index(emoji);
+        if let (Some(poll), Some(option)) = (self.open.get_mut(&message_id), option) {
+            poll.remove(user_id, option);
+        }
+    }
+
+    /// Stop collecting votes, returning the poll.
+    pub fn close(&mut self, message_id: u64) -> Option<Poll> {
+        self.open.remove(&message_id)
+    }
+
+    /// Whether no polls are open.
+    #[cfg(test)]
+    pub fn is_empty(&self) -> bool {
+        self.open.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{option_index, Poll, Polls};
+
+    #[test]
+    fn test_option_index() {
+        assert_eq!(option_index("👍"), Some(0));
+        assert_eq!(option_index("🤷"), Some(2));
+        assert_eq!(option_index("🎲"), None);
+    }
+
+    #[test]
+    fn test_tally_counts_first_reaction() {
+        let mut poll = Poll::new("Take the sewer route?");
+        poll.add(1, 0);
+        poll.add(2, 1);
+        poll.add(1, 1);
+        poll.add(3, 2);
+        poll.add(2, 0);
+        poll.add(4, 0);
+        poll.add(4, 0);
+        assert_eq!(poll.tally(), [2, 1, 1]);
+        assert_eq!(poll.tally_text(), "👍 2 · 👎 1 · 🤷 1");
+        assert_eq!(poll.outcome(), "👍 wins with 2 of 4 votes");
+    }
+
+    #[test]
+    fn test_tally_after_removal() {
+        // Taking back the first reaction makes the next one count
+        let mut poll = Poll::new("Take the sewer route?");
+        poll.add(1, 0);
+        poll.add(1, 1);
+        poll.remove(1, 0);
+        assert_eq!(poll.tally(), [0, 1, 0]);
+        poll.remove(1, 1);
+        assert_eq!(poll.tally(), [0, 0, 0]);
+        assert_eq!(poll.outcome(), "no votes were cast");
+    }
+
+    #[test]
+    fn test_outcome_tie() {
+        let mut poll = Poll::new("Take the sewer route?");
+        poll.add(1, 0);
+        poll.add(2, 1);
+        assert_eq!(poll.outcome(), "tied between 👍 and 👎");
+    }
+
+    #[test]
+    fn test_polls_close() {
+        let mut polls = Polls::new();
+        polls.open(10, "Take the sewer route?");
+        polls.react(10, 1, "👎");
+        polls.react(10, 2, "🎲");
+        polls.react(11, 3, "👍");
+        polls.unreact(11, 3, "👍");
+        let poll = polls.close(10).unwrap();
+        assert_eq!(poll.tally(), [0, 1, 0]);
+        assert!(polls.is_empty());
+        assert!(polls.close(10).is_none());
+    }
+}
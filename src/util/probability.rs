@@ -0,0 +1,164 @@
+use rand::{
+    distributions::{Distribution, Uniform},
+    Rng,
+};
+
+/// Number of faces on a die.
+pub const FACES: usize = 10;
+/// Critical value of the chi-squared distribution with 9 degrees of
+/// freedom at the 95% level.
+pub const CRITICAL_VALUE_9DF: f64 = 16.919;
+
+/// Roll d10s, counting how often each face came up.
+///
+/// # Arguments
+///
+/// * `rolls` - number of d10s to roll
+/// * `rng` - source of randomness
+///
+/// # Examples
+///
+/// ```rust
+/// let counts = face_counts(100_000, &mut thread_rng());
+/// ```
+pub fn face_counts<R: Rng>(rolls: u64, rng: &mut R) -> [u64; FACES] {
+    let between = Uniform::new_inclusive(1, FACES as u64);
+    let mut counts = [0; FACES];
+    for _ in 0..rolls {
+        counts[between.sample(rng) as usize - 1] += 1;
+    }
+    counts
+}
+
+/// Pearson's chi-squared statistic of counts against a uniform expectation.
+///
+/// # Arguments
+///
+/// * `counts` - how often each outcome came up
+///
+/// # Examples
+///
+/// ```rust
+/// assert_eq!(chi_squared(&[10, 10]), 0.0);
+/// ```
+pub fn chi_squared(counts: &[u64]) -> f64 {
+    let total: u64 = counts.iter().sum();
+    if total == 0 {
+        return 0.0;
+    }
+    let expected = total as f64 / counts.len() as f64;
+    counts
+        .iter()
+        .map(|&observed| (observed as f64 - expected).powi(2) / expected)
+        .sum()
+}
+
+/// Result of checking a run of d10s for fairness.
+#[derive(Debug, PartialEq)]
+pub struct FairnessTest {
+    pub counts: [u64; FACES],
+    pub statistic: f64,
+}
+
+impl FairnessTest {
+    /// Roll d10s and test how they fell against a fair die.
+    ///
+    /// # Arguments
+    ///
+    /// * `rolls` - number of d10s to roll
+    /// * `rng` - source of randomness
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// let test = FairnessTest::run(100_000, &mut thread_rng());
+    /// ```
+    pub fn run<R: Rng>(rolls: u64, rng: &mut R) -> Self {
+        let counts = face_counts(rolls, rng);
+        FairnessTest {
+            counts,
+            statistic: chi_squared(&counts),
+        }
+    }
+
+    /// Whether the statistic is within the 95% threshold for a fair die.
+    pub fn passes(&self) -> bool {
+        self.statistic <= CRITICAL_VALUE_9DF
+    }
+
+    /// Describe the counts and verdict.
+    ///
+    /// The counts are in a code block.
+    pub fn report(&self) -> String {
+        let total: u64 = self.counts.iter().sum();
+        let lines: Vec<String> = self
+            .counts
+            .iter()
+            .enumerate()
+            .map(|(face, count)| format!("{:>2}: {}", face + 1, count))
+            .collect();
+        let verdict = if self.passes() {
+            "consistent with a fair die"
+        } else {
+            "unlikely from a fair die"
+        };
+        format!(
+            "Rolled {} d10s:\n```\n{}\n```\nChi-squared is {:.2} (95% threshold {:.2} \
+             for 9 degrees of freedom): {}.",
+            total,
+            lines.join("\n"),
+            self.statistic,
+            CRITICAL_VALUE_9DF,
+            verdict
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{chi_squared, face_counts, FairnessTest, FACES};
+    use crate::util::testing::ScriptedRng;
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!((actual - expected).abs() < 1e-9, "{} != {}", actual, expected);
+    }
+
+    #[test]
+    fn test_chi_squared() {
+        assert_close(chi_squared(&[10; 10]), 0.0);
+        assert_close(chi_squared(&[5, 15, 10, 10, 10, 10, 10, 10, 10, 10]), 5.0);
+        assert_close(chi_squared(&[20, 0]), 20.0);
+        // Textbook example: 60 rolls of a six-sided die
+        assert_close(chi_squared(&[5, 8, 9, 8, 10, 20]), 13.4);
+        assert_close(chi_squared(&[]), 0.0);
+    }
+
+    #[test]
+    fn test_face_counts() {
+        let mut rng = ScriptedRng::new(&[1, 10, 10, 3]);
+        assert_eq!(face_counts(4, &mut rng), [1, 0, 1, 0, 0, 0, 0, 0, 0, 2]);
+    }
+
+    #[test]
+    fn test_fair_faces_pass() {
+        let faces: Vec<u64> = (0..1000).map(|i| i % FACES as u64 + 1).collect();
+        let test = FairnessTest::run(1000, &mut ScriptedRng::new(&faces));
+        assert_eq!(test.counts, [100; FACES]);
+        assert!(test.passes());
+        assert!(test.report().ends_with(
+            "Chi-squared is 0.00 (95% threshold 16.92 for 9 degrees of freedom): \
+             consistent with a fair die."
+        ));
+    }
+
+    #[test]
+    fn test_biased_rng_fails() {
+        // Every fifth roll is forced to a 10
+        let faces: Vec<u64> = (0..1000)
+            .map(|i| if i % 5 == 0 { 10 } else { i % FACES as u64 + 1 })
+            .collect();
+        let test = FairnessTest::run(1000, &mut ScriptedRng::new(&faces));
+        assert!(!test.passes());
+        assert!(test.report().ends_with("unlikely from a fair die."));
+    }
+}
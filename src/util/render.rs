@@ -0,0 +1,145 @@
+use prettytable::{cell, format, row, Row, Table};
+use std::fmt;
+
+/// Pair up items for a two-column layout.
+///
+/// The left column is filled first, so reading down the left column
+/// and then the right keeps the items' order. When there is an odd
+/// number of items, the last row has nothing in its right column.
+///
+/// # Arguments
+///
+/// * `items` - items to lay out
+///
+/// # Examples
+///
+/// ```rust
+/// let rows = two_column_rows(&["a", "b", "c"]);
+/// ```
+pub fn two_column_rows<T>(items: &[T]) -> Vec<(&T, Option<&T>)> {
+    let (left, right) = items.split_at(items.len().div_ceil(2));
+    left.iter()
+        .enumerate()
+        .map(|(index, item)| (item, right.get(index)))
+        .collect()
+}
+
+/// Render stats as a two-column table.
+///
+/// # Arguments
+///
+/// * `stats` - stat names and values, in the order to show them
+///
+/// # Examples
+///
+/// ```rust
+/// let table = stats_table(&character.stats());
+/// ```
+pub fn stats_table<V: fmt::Display>(stats: &[(&str, V)]) -> String {
+    let mut table = Table::new();
+    table.set_titles(row!["Name", "Value", "", "Name", "Value"]);
+    for (left, right) in two_column_rows(stats) {
+        let (right_name, right_value) = match right {
+            Some((name, value)) => (name.to_string(), format!("  {}", value)),
+            None => (String::new(), String::new()),
+        };
+        table.add_row(row![
+            left.0,
+            &format!("  {}", left.1),
+            "",
+            right_name,
+            right_value,
+        ]);
+    }
+    table.set_format(*format::consts::FORMAT_NO_LINESEP_WITH_TITLE);
+    table.to_string()
+}
+
+/// Render health boxes as a one-row table.
+///
+/// # Arguments
+///
+/// * `boxes` - contents of each health box
+///
+/// # Examples
+///
+/// ```rust
+/// let table = health_table(&health.boxes());
+/// ```
+pub fn health_table(boxes: &[&str]) -> String {
+    let mut table = Table::new();
+    table.add_row(boxes.iter().map(|b| cell!(b)).collect());
+    table.to_string()
+}
+
+/// Render a summary table with titles.
+///
+/// # Arguments
+///
+/// * `titles` - column titles
+/// * `rows` - cells of each row
+///
+/// # Examples
+///
+/// ```rust
+/// let table = summary_table(&["Character", "Stats"], &[vec![name, count]]);
+/// ```
+pub fn summary_table(titles: &[&str], rows: &[Vec<String>]) -> String {
+    let mut table = Table::new();
+    table.set_titles(Row::new(titles.iter().map(|t| cell!(t)).collect()));
+    for cells in rows {
+        table.add_row(Row::new(cells.iter().map(|c| cell!(c)).collect()));
+    }
+    table.set_format(*format::consts::FORMAT_NO_LINESEP_WITH_TITLE);
+    table.to_string()
+}
+
+#[cfg(test)]
+mod test {
+    use super::{stats_table, summary_table, two_column_rows};
+
+    #[test]
+    fn test_two_column_rows() {
+        assert_eq!(two_column_rows::<u8>(&[]), vec![]);
+        assert_eq!(two_column_rows(&[1]), vec![(&1, None)]);
+        assert_eq!(two_column_rows(&[1, 2]), vec![(&1, Some(&2))]);
+        assert_eq!(two_column_rows(&[1, 2, 3]), vec![(&1, Some(&3)), (&2, None)]);
+        assert_eq!(
+            two_column_rows(&[1, 2, 3, 4]),
+            vec![(&1, Some(&3)), (&2, Some(&4))]
+        );
+        assert_eq!(
+            two_column_rows(&[1, 2, 3, 4, 5, 6, 7]),
+            vec![(&1, Some(&5)), (&2, Some(&6)), (&3, Some(&7)), (&4, None)]
+        );
+    }
+
+    #[test]
+    fn test_stats_table_rows() {
+        // Borders and the title take four lines; every other line is a row
+        for count in 1..=7 {
+            let stats: Vec<(&str, i64)> = (0..count).map(|i| ("stat", i)).collect();
+            let rows = stats_table(&stats).lines().count() - 4;
+            assert_eq!(rows, (count as usize).div_ceil(2), "{} stats", count);
+        }
+    }
+
+    #[test]
+    fn test_stats_table_odd() {
+        let table = stats_table(&[("brawl", 2), ("occult", 0), ("wits", 3)]);
+        let lines: Vec<&str> = table.lines().collect();
+        assert!(lines[3].contains("brawl") && lines[3].contains("wits"));
+        assert!(lines[4].contains("occult"));
+    }
+
+    #[test]
+    fn test_summary_table() {
+        let table = summary_table(
+            &["Character", "Stats"],
+            &[vec![String::from("Ada"), String::from("3")]],
+        );
+        assert_eq!(table.lines().count(), 5);
+        assert!(table.contains("Character"));
+        assert!(table.contains("Ada"));
+    }
+}
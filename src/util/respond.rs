@@ -0,0 +1,452 @@
+use lazy_static::lazy_static;
+use log::{debug, warn};
+use serenity::{
+    client::Context,
+    model::{
+        channel::{Message, ReactionType},
+        permissions::Permissions,
+    },
+};
+use std::{cell::Cell, collections::BTreeMap, sync::Mutex};
+use crate::util::{
+    constants::SETTINGS_PATH,
+    ephemeral::schedule_deletion,
+    settings::SettingsStore,
+    text::neutralize_mentions,
+    time::now,
+};
+
+const ACK_EMOJI: &str = "👍";
+const ACK_FALLBACK: &str = "Got it.";
+/// Commands whose invoking messages are never deleted, so changes to how
+/// the bot is set up stay visible.
+const KEPT_INVOCATIONS: &[&str] = &["admin", "settings"];
+/// Seconds between warnings that invoking messages can't be deleted.
+const DELETE_WARNING_SECONDS: u64 = 60 * 60;
+const DELETE_WARNING: &str = "I need the Manage Messages permission to delete commands here. \
+An admin can grant it, or turn this off with `!settings delete-invocations off`.";
+
+lazy_static! {
+    /// When each guild was last warned that invoking messages can't be deleted.
+    static ref DELETE_WARNINGS: Mutex<BTreeMap<u64, u64>> = Mutex::new(BTreeMap::new());
+    /// Commands run so far from each message holding several, and whether
+    /// each was handled.
+    static ref SPLIT_INVOCATIONS: Mutex<BTreeMap<u64, Vec<(String, bool)>>> =
+        Mutex::new(BTreeMap::new());
+}
+
+thread_local! {
+    /// Whether the command running on this thread has sent a refusal.
+    static REFUSED: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Length to keep messages under, leaving room below Discord's 2000 limit.
+pub const MESSAGE_LIMIT: usize = 1900;
+
+/// Returns the text reply to send when an acknowledgement
+/// reaction could not be added.
+///
+/// # Arguments
+///
+/// * `result` - result of the reaction attempt
+///
+/// # Examples
+///
+/// ```rust
+/// let fallback = ack_fallback(&message.react(&context, reaction));
+/// ```
+fn ack_fallback<T, E>(result: &Result<T, E>) -> Option<&'static str> {
+    match result {
+        Ok(_) => None,
+        Err(_) => Some(ACK_FALLBACK),
+    }
+}
+
+/// Acknowledge a message by reacting to it.
+///
+/// If the reaction fails, for example because the bot lacks
+/// the Add Reactions permission, a short text reply is sent instead.
+///
+/// # Arguments
+///
+/// * `context` - serenity context
+/// * `message` - message to acknowledge
+///
+/// # Examples
+///
+/// ```rust
+/// ack(&context, &message)?;
+/// ```
+pub fn ack(context: &Context, message: &Message) -> serenity::Result<()> {
+    let result = message.react(context, ReactionType::Unicode(ACK_EMOJI.to_owned()));
+    if let Err(e) = &result {
+        warn!("Could not add acknowledgement reaction: {:?}", e);
+    }
+    if let Some(text) = ack_fallback(&result) {
+        message.channel_id.say(&context.http, text)?;
+    }
+    Ok(())
+}
+
+/// A reply produced by a command handler.
+#[derive(Debug, PartialEq)]
+pub enum Reply {
+    /// Send the text to the channel.
+    Say(String),
+    /// Send the text to the channel, explaining why the command wasn't
+    /// carried out, like its usage. The invoking message is kept.
+    Refuse(String),
+    /// Send the text to the channel, deleting it and the
+    /// invoking message after a delay.
+    Ephemeral(String),
+    /// Acknowledge the invoking message.
+    Ack,
+    /// Send a file to the channel.
+    File { name: String, content: Vec<u8> },
+}
+
+/// What to do with the message that invoked a command once it's handled.
+#[derive(Debug, PartialEq)]
+pub enum Invocation {
+    /// Leave the message be.
+    Keep,
+    /// Delete the message.
+    Delete,
+    /// Leave the message, and warn that it can't be deleted.
+    Warn,
+}
+
+/// Decide what to do with the message that invoked some handled commands.
+///
+/// Messages are only deleted in a guild that deletes invocations, and
+/// never when they ran settings or admin commands. Without the Manage
+/// Messages permission the guild is warned, at most once an hour.
+///
+/// # Arguments
+///
+/// * `commands` - names of the commands the message ran
+/// * `in_guild` - whether the message was sent in a guild
+/// * `enabled` - whether the guild deletes invocations
+/// * `can_delete` - whether the bot can manage messages in the channel
+/// * `last_warned` - when the guild was last warned, in epoch seconds
+/// * `now` - current epoch seconds
+///
+/// # Examples
+///
+/// ```rust
+/// let action = invocation_action(&["roll"], true, true, false, None, now());
+/// ```
+pub fn invocation_action(
+    commands: &[&str],
+    in_guild: bool,
+    enabled: bool,
+    can_delete: bool,
+    last_warned: Option<u64>,
+    now: u64,
+) -> Invocation {
+    let kept = commands.iter().any(|c| KEPT_INVOCATIONS.contains(c));
+    if !in_guild || !enabled || kept {
+        Invocation::Keep
+    } else if can_delete {
+        Invocation::Delete
+    } else if last_warned.is_none_or(|t| now >= t + DELETE_WARNING_SECONDS) {
+        Invocation::Warn
+    } else {
+        Invocation::Keep
+    }
+}
+
+/// Record that a command has run, cleaning up its invocation.
+///
+/// A command is handled when it returned without an error and sent no
+/// refusal. Commands from a message holding several are only recorded, and
+/// cleaned up together by `commands_finished`.
+///
+/// # Arguments
+///
+/// * `context` - serenity context
+/// * `message` - message that invoked the command
+/// * `command` - name of the command that was run
+/// * `succeeded` - whether the command returned without an error
+///
+/// # Examples
+///
+/// ```rust
+/// command_finished(&context, &message, "roll", result.is_ok());
+/// ```
+pub fn command_finished(context: &Context, message: &Message, command: &str, succeeded: bool) {
+    let handled = !REFUSED.with(|r| r.replace(false)) && succeeded;
+    if let Some(commands) = SPLIT_INVOCATIONS.lock().unwrap().get_mut(&message.id.0) {
+        commands.push((command.to_owned(), handled));
+        return;
+    }
+    clean_up_invocation(context, message, &[(command.to_owned(), handled)]);
+}
+
+/// Start recording the commands run from a message holding several.
+///
+/// # Arguments
+///
+/// * `message` - message holding the commands
+///
+/// # Examples
+///
+/// ```rust
+/// track_commands(&message);
+/// ```
+pub fn track_commands(message: &Message) {
+    SPLIT_INVOCATIONS.lock().unwrap().insert(message.id.0, vec![]);
+}
+
+/// Clean up a message holding several commands once they have all run.
+///
+/// # Arguments
+///
+/// * `context` - serenity context
+/// * `message` - message holding the commands
+/// * `total` - how many commands the message holds
+///
+/// # Examples
+///
+/// ```rust
+/// commands_finished(&context, &message, 2);
+/// ```
+pub fn commands_finished(context: &Context, message: &Message, total: usize) {
+    let commands = SPLIT_INVOCATIONS
+        .lock()
+        .unwrap()
+        .remove(&message.id.0)
+        .unwrap_or_default();
+    // Keep messages with parts that weren't commands, or were skipped
+    if commands.len() < total {
+        debug!("Only {} of {} commands were run", commands.len(), total);
+        return;
+    }
+    clean_up_invocation(context, message, &commands);
+}
+
+/// Delete the message that invoked commands, if its guild asked for that.
+///
+/// Nothing is done unless every command was handled.
+///
+/// # Arguments
+///
+/// * `context` - serenity context
+/// * `message` - message that invoked the commands
+/// * `commands` - names of the commands, and whether each was handled
+///
+/// # Examples
+///
+/// ```rust
+/// clean_up_invocation(&context, &message, &[(String::from("roll"), true)]);
+/// ```
+fn clean_up_invocation(context: &Context, message: &Message, commands: &[(String, bool)]) {
+    if commands.is_empty() || commands.iter().any(|(_, handled)| !handled) {
+        return;
+    }
+    let commands: Vec<&str> = commands.iter().map(|(c, _)| c.as_str()).collect();
+    let command = commands.join(", ");
+    let guild_id = match message.guild_id {
+        Some(g) => g.0,
+        None => return,
+    };
+    let enabled = match SettingsStore::from_file(&SETTINGS_PATH) {
+        Ok(settings) => settings.get(guild_id).is_some_and(|g| g.delete_invocations),
+        Err(e) => {
+            warn!("Could not load settings to delete invocations: {}", e);
+            return;
+        }
+    };
+    if !enabled {
+        return;
+    }
+    let bot_id = context.cache.read().user.id;
+    let can_delete = message.guild(&context.cache).is_some_and(|guild| {
+        guild
+            .read()
+            .permissions_in(message.channel_id, bot_id)
+            .contains(Permissions::MANAGE_MESSAGES)
+    });
+    let now = now();
+    let action = {
+        let mut warnings = DELETE_WARNINGS.lock().unwrap();
+        let action = invocation_action(
+            &commands,
+            true,
+            enabled,
+            can_delete,
+            warnings.get(&guild_id).cloned(),
+            now,
+        );
+        if action == Invocation::Warn {
+            warnings.insert(guild_id, now);
+        }
+        action
+    };
+    match action {
+        Invocation::Keep => {}
+        Invocation::Delete => {
+            debug!("Deleting invocation of '{}'", command);
+            if let Err(e) = message.delete(context) {
+                warn!("Could not delete invocation of '{}': {:?}", command, e);
+            }
+        }
+        Invocation::Warn => {
+            if let Err(e) = message.channel_id.say(&context.http, DELETE_WARNING) {
+                warn!("Could not send delete invocation warning: {:?}", e);
+            }
+        }
+    }
+}
+
+/// Join items into as few messages as fit under a length limit.
+///
+/// # Arguments
+///
+/// * `items` - items to join
+/// * `separator` - text between items in the same message
+/// * `limit` - maximum length of each message
+///
+/// # Examples
+///
+/// ```rust
+/// let messages = chunk(&names, ", ", MESSAGE_LIMIT);
+/// ```
+pub fn chunk(items: &[String], separator: &str, limit: usize) -> Vec<String> {
+    let mut chunks = vec![];
+    let mut current = String::new();
+    for item in items {
+        if !current.is_empty() && current.len() + separator.len() + item.len() > limit {
+            chunks.push(current);
+            current = String::new();
+        }
+        if !current.is_empty() {
+            current.push_str(separator);
+        }
+        current.push_str(item);
+    }
+    if !current.is_empty() {
+        chunks.push(current);
+    }
+    chunks
+}
+
+/// Send a handler's replies in order.
+///
+/// Server and role mentions in text are broken first, so nothing a user
+/// stored can ping everyone.
+///
+/// # Arguments
+///
+/// * `context` - serenity context
+/// * `message` - message the handler responded to
+/// * `replies` - replies to send
+///
+/// # Examples
+///
+/// ```rust
+/// send(&context, &message, vec![Reply::Ack])?;
+/// ```
+pub fn send(context: &Context, message: &Message, replies: Vec<Reply>) -> serenity::Result<()> {
+    for reply in replies {
+        match reply {
+            Reply::Say(text) => {
+                message.channel_id.say(&context.http, neutralize_mentions(&text))?;
+            }
+            Reply::Refuse(text) => {
+                REFUSED.with(|r| r.set(true));
+                message.channel_id.say(&context.http, neutralize_mentions(&text))?;
+            }
+            Reply::Ephemeral(text) => {
+                let sent = message.channel_id.say(&context.http, neutralize_mentions(&text))?;
+                schedule_deletion(sent.channel_id, sent.id);
+                schedule_deletion(message.channel_id, message.id);
+            }
+            Reply::Ack => ack(context, message)?,
+            Reply::File { name, content } => {
+                message.channel_id.send_files(
+                    &context.http,
+                    vec![(content.as_slice(), name.as_str())],
+                    |m| m,
+                )?;
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        ack_fallback, chunk, invocation_action, Invocation, ACK_FALLBACK,
+        DELETE_WARNING_SECONDS,
+    };
+
+    fn strings(items: &[&str]) -> Vec<String> {
+        items.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_chunk() {
+        let items = strings(&["aaa", "bbb", "ccc"]);
+
+        assert_eq!(chunk(&items, ", ", 100), vec!["aaa, bbb, ccc"]);
+        assert_eq!(chunk(&items, ", ", 8), vec!["aaa, bbb", "ccc"]);
+        assert_eq!(chunk(&items, ", ", 3), vec!["aaa", "bbb", "ccc"]);
+        assert!(chunk(&[], ", ", 10).is_empty());
+    }
+
+    #[test]
+    fn test_chunk_oversized_item() {
+        let items = strings(&["a", "bbbbbbbbbb", "c"]);
+
+        assert_eq!(chunk(&items, "\n", 5), vec!["a", "bbbbbbbbbb", "c"]);
+    }
+
+    #[test]
+    fn test_ack_fallback() {
+        let ok: Result<(), &str> = Ok(());
+        let err: Result<(), &str> = Err("missing permissions");
+
+        assert_eq!(ack_fallback(&ok), None);
+        assert_eq!(ack_fallback(&err), Some(ACK_FALLBACK));
+    }
+
+    #[test]
+    fn test_invocation_action_commands() {
+        assert_eq!(invocation_action(&["roll"], true, true, true, None, 0), Invocation::Delete);
+        assert_eq!(invocation_action(&["stats"], true, true, true, None, 0), Invocation::Delete);
+        assert_eq!(invocation_action(&["settings"], true, true, true, None, 0), Invocation::Keep);
+        assert_eq!(invocation_action(&["admin"], true, true, true, None, 0), Invocation::Keep);
+        assert_eq!(invocation_action(&["roll"], true, false, true, None, 0), Invocation::Keep);
+        assert_eq!(invocation_action(&["roll"], false, true, true, None, 0), Invocation::Keep);
+        assert_eq!(
+            invocation_action(&["roll", "stats"], true, true, true, None, 0),
+            Invocation::Delete
+        );
+        assert_eq!(
+            invocation_action(&["roll", "settings"], true, true, true, None, 0),
+            Invocation::Keep
+        );
+    }
+
+    #[test]
+    fn test_invocation_action_without_permission() {
+        let now = 10_000;
+
+        assert_eq!(invocation_action(&["roll"], true, true, false, None, now), Invocation::Warn);
+        assert_eq!(
+            invocation_action(&["roll"], true, true, false, Some(now - 60), now),
+            Invocation::Keep
+        );
+        assert_eq!(
+            invocation_action(&["roll"], true, true, false, Some(now - DELETE_WARNING_SECONDS), now),
+            Invocation::Warn
+        );
+        assert_eq!(
+            invocation_action(&["settings"], true, true, false, None, now),
+            Invocation::Keep
+        );
+        assert_eq!(invocation_action(&["roll"], true, false, false, None, now), Invocation::Keep);
+    }
+}
@@ -0,0 +1,89 @@
+use lazy_static::lazy_static;
+use regex::Regex;
+
+pub const CHANCE: &str = "chance";
+pub const EPHEMERAL: &str = "ephemeral";
+pub const VERBOSE: &str = "verbose";
+pub const IGNORE_BONUSES: &str = "nobonus";
+pub const FAIR: &str = "fair";
+pub const MIN_ONE: &str = "min1";
+pub const ROTE: &str = "rote";
+pub const WILLPOWER: &str = "willpower";
+pub const WILLPOWER_SHORT: &str = "wp";
+pub const TRACE: &str = "trace";
+pub const EXTENDED: &str = "extended";
+/// Every fixed word with a meaning of its own in a roll line.
+///
+/// Stats can't be named after these, or rolls would never look them up,
+/// so new roll tokens belong here too.
+pub const RESERVED: &[&str] = &[
+    CHANCE,
+    EPHEMERAL,
+    VERBOSE,
+    IGNORE_BONUSES,
+    FAIR,
+    MIN_ONE,
+    ROTE,
+    WILLPOWER,
+    WILLPOWER_SHORT,
+    TRACE,
+    EXTENDED,
+];
+
+lazy_static! {
+    pub static ref REGEX_NUMERIC: Regex = Regex::new(r#"^\d+$"#).unwrap();
+    pub static ref REGEX_AGAIN: Regex = Regex::new(r#"^(?:no)?\d+again$"#).unwrap();
+}
+
+/// Returns why a name can't be used for a stat, if it can't.
+///
+/// # Arguments
+///
+/// * `name` - stat name, as stored
+///
+/// # Examples
+///
+/// ```rust
+/// assert!(reserved_reason("9again").is_some());
+/// ```
+pub fn reserved_reason(name: &str) -> Option<&'static str> {
+    if REGEX_NUMERIC.is_match(name) {
+        Some("numbers in a roll are rolled as dice")
+    } else if REGEX_AGAIN.is_match(name) {
+        Some("it is a roll modifier")
+    } else if RESERVED.iter().any(|t| t.eq_ignore_ascii_case(name)) {
+        Some("it has its own meaning in rolls")
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{reserved_reason, RESERVED};
+
+    #[test]
+    fn test_numbers_are_reserved() {
+        assert_eq!(reserved_reason("5"), Some("numbers in a roll are rolled as dice"));
+        assert_eq!(reserved_reason("10"), Some("numbers in a roll are rolled as dice"));
+        assert_eq!(reserved_reason("5th_sense"), None);
+    }
+
+    #[test]
+    fn test_modifiers_are_reserved() {
+        for name in &["9again", "8again", "10again", "no10again"] {
+            assert_eq!(reserved_reason(name), Some("it is a roll modifier"), "{}", name);
+        }
+        assert_eq!(reserved_reason("again"), None);
+    }
+
+    #[test]
+    fn test_tokens_are_reserved() {
+        for name in RESERVED {
+            assert_eq!(reserved_reason(name), Some("it has its own meaning in rolls"), "{}", name);
+        }
+        assert_eq!(reserved_reason("Chance"), Some("it has its own meaning in rolls"));
+        assert_eq!(reserved_reason("chances"), None);
+        assert_eq!(reserved_reason("wits"), None);
+    }
+}
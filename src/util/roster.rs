@@ -0,0 +1,266 @@
+use csv::{Error as CsvError, Position, ReaderBuilder, StringRecord, Trim};
+use std::{collections::HashSet, fmt};
+
+/// A problem with a roster, pointing at a cell or, when `column` is 0, a line.
+#[derive(Debug, PartialEq)]
+pub struct RosterError {
+    pub line: u64,
+    pub column: usize,
+    pub message: String,
+}
+
+impl fmt::Display for RosterError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.column == 0 {
+            write!(f, "line {}: {}", self.line, self.message)
+        } else {
+            write!(f, "line {}, column {}: {}", self.line, self.column, self.message)
+        }
+    }
+}
+
+/// One character from a roster.
+#[derive(Debug, PartialEq)]
+pub struct RosterRow {
+    /// Line of the roster the character is on.
+    pub line: u64,
+    pub name: String,
+    pub owner: Option<String>,
+    pub stats: Vec<(String, i64)>,
+}
+
+fn cell_error(line: u64, column: usize, message: String) -> RosterError {
+    RosterError {
+        line,
+        column,
+        message,
+    }
+}
+
+/// Line a position in the roster is on.
+///
+/// The reader's own line count skips blank lines, so lines are counted
+/// up to the position's byte offset instead. A record's offset can be
+/// before blank lines leading up to it, so those are skipped first.
+fn line_at(data: &[u8], position: Option<&Position>) -> u64 {
+    position.map_or(0, |p| {
+        let start = (p.byte() as usize).min(data.len());
+        let end = data[start..]
+            .iter()
+            .position(|&b| b != b'\n' && b != b'\r')
+            .map_or(data.len(), |i| start + i);
+        data[..end].iter().filter(|&&b| b == b'\n').count() as u64 + 1
+    })
+}
+
+fn csv_error(data: &[u8], error: &CsvError) -> RosterError {
+    cell_error(line_at(data, error.position()), 0, error.to_string())
+}
+
+/// Read the stat names from the header row.
+fn header_stats(line: u64, header: &StringRecord, errors: &mut Vec<RosterError>) -> Vec<String> {
+    if header.len() < 2 {
+        errors.push(cell_error(
+            line,
+            0,
+            String::from("the header needs a name column, an owner column and then stat names"),
+        ));
+    }
+    let mut seen = HashSet::new();
+    let mut stats = vec![];
+    for (index, name) in header.iter().enumerate().skip(2) {
+        let name = name.to_lowercase();
+        if name.is_empty() {
+            errors.push(cell_error(line, index + 1, String::from("stat name is missing")));
+        } else if !seen.insert(name.clone()) {
+            errors.push(cell_error(
+                line,
+                index + 1,
+                format!("stat `{}` is listed more than once", name),
+            ));
+        }
+        stats.push(name);
+    }
+    stats
+}
+
+/// Parse a CSV roster of characters.
+///
+/// The first row is a header: the character name column, the owner
+/// column, then one column per stat. Each following row is a character,
+/// with an optional owner (Discord tag or ID) and whole number stat values.
+/// Empty stat cells are left out. Every cell is checked, and all problems
+/// are returned together.
+///
+/// # Arguments
+///
+/// * `data` - contents of the CSV file
+///
+/// # Examples
+///
+/// ```rust
+/// let rows = parse_roster(b"name,owner,wits\nAda,,3\n")?;
+/// ```
+pub fn parse_roster(data: &[u8]) -> Result<Vec<RosterRow>, Vec<RosterError>> {
+    let mut reader = ReaderBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .trim(Trim::All)
+        .from_reader(data);
+    let mut records = reader.records();
+    let header = match records.next() {
+        Some(Ok(header)) => header,
+        Some(Err(e)) => return Err(vec![csv_error(data, &e)]),
+        None => return Err(vec![cell_error(1, 0, String::from("the roster is empty"))]),
+    };
+    let mut errors = vec![];
+    let stat_names = header_stats(line_at(data, header.position()), &header, &mut errors);
+    let mut names = HashSet::new();
+    let mut rows = vec![];
+    for record in records {
+        let record = match record {
+            Ok(r) => r,
+            Err(e) => {
+                errors.push(csv_error(data, &e));
+                continue;
+            }
+        };
+        let line = line_at(data, record.position());
+        if record.iter().all(str::is_empty) {
+            continue;
+        }
+        if record.len() > header.len() {
+            errors.push(cell_error(
+                line,
+                header.len() + 1,
+                format!("expected at most {} columns, found {}", header.len(), record.len()),
+            ));
+            continue;
+        }
+        let name = record.get(0).unwrap_or("");
+        if name.is_empty() {
+            errors.push(cell_error(line, 1, String::from("character name is missing")));
+        } else if !names.insert(name.to_owned()) {
+            errors.push(cell_error(
+                line,
+                1,
+                format!("`{}` is listed more than once", name),
+            ));
+        }
+        let mut stats = vec![];
+        for (index, value) in record.iter().enumerate().skip(2) {
+            if value.is_empty() {
+                continue;
+            }
+            match value.parse::<i64>() {
+                Ok(v) => stats.push((stat_names[index - 2].clone(), v)),
+                Err(_) => errors.push(cell_error(
+                    line,
+                    index + 1,
+                    format!("`{}` is not a whole number", value),
+                )),
+            }
+        }
+        rows.push(RosterRow {
+            line,
+            name: name.to_owned(),
+            owner: record.get(1).filter(|o| !o.is_empty()).map(str::to_owned),
+            stats,
+        });
+    }
+    if errors.is_empty() {
+        Ok(rows)
+    } else {
+        Err(errors)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{parse_roster, RosterError, RosterRow};
+
+    const FIXTURE: &str = "name,owner,Wits,Brawl,Occult
+Ada Bold,Ada#1234,3,2,
+\"Smith, John\",123456789,1,,4
+
+Nameless,,0,0,0
+";
+
+    const MALFORMED: &str = "name,owner,wits,brawl,wits
+Ada Bold,,three,2,1
+,,1,1,1
+Ada Bold,,1,1,1,9
+Ben,,2,x2,
+";
+
+    fn stats(pairs: &[(&str, i64)]) -> Vec<(String, i64)> {
+        pairs.iter().map(|&(s, v)| (s.to_owned(), v)).collect()
+    }
+
+    #[test]
+    fn test_parse_roster() {
+        assert_eq!(
+            parse_roster(FIXTURE.as_bytes()),
+            Ok(vec![
+                RosterRow {
+                    line: 2,
+                    name: String::from("Ada Bold"),
+                    owner: Some(String::from("Ada#1234")),
+                    stats: stats(&[("wits", 3), ("brawl", 2)]),
+                },
+                RosterRow {
+                    line: 3,
+                    name: String::from("Smith, John"),
+                    owner: Some(String::from("123456789")),
+                    stats: stats(&[("wits", 1), ("occult", 4)]),
+                },
+                RosterRow {
+                    line: 5,
+                    name: String::from("Nameless"),
+                    owner: None,
+                    stats: stats(&[("wits", 0), ("brawl", 0), ("occult", 0)]),
+                },
+            ])
+        );
+    }
+
+    #[test]
+    fn test_parse_roster_malformed() {
+        let errors: Vec<String> = parse_roster(MALFORMED.as_bytes())
+            .unwrap_err()
+            .iter()
+            .map(RosterError::to_string)
+            .collect();
+        assert_eq!(
+            errors,
+            vec![
+                "line 1, column 5: stat `wits` is listed more than once",
+                "line 2, column 3: `three` is not a whole number",
+                "line 3, column 1: character name is missing",
+                "line 4, column 6: expected at most 5 columns, found 6",
+                "line 5, column 4: `x2` is not a whole number",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_roster_empty() {
+        assert_eq!(
+            parse_roster(b""),
+            Err(vec![RosterError {
+                line: 1,
+                column: 0,
+                message: String::from("the roster is empty"),
+            }])
+        );
+        assert_eq!(
+            parse_roster(b"name\n").unwrap_err()[0].to_string(),
+            "line 1: the header needs a name column, an owner column and then stat names"
+        );
+    }
+
+    #[test]
+    fn test_parse_roster_header_only() {
+        assert_eq!(parse_roster(b"name,owner,wits\n"), Ok(vec![]));
+    }
+}
@@ -0,0 +1,400 @@
+use failure::Error;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs,
+    path::Path,
+};
+use crate::util::{
+    fsutil::{atomic_write, recover_leftovers},
+    outcome::Outcome,
+    roll_tokens::reserved_reason,
+};
+
+/// Longest name a guild constant can have.
+const MAX_CONSTANT_LEN: usize = 20;
+/// Most constants a guild can have.
+const MAX_CONSTANTS: usize = 25;
+/// Largest value a guild constant can have, either way.
+const MAX_CONSTANT_VALUE: i64 = 10;
+
+/// A merit added by a guild on top of the built-in list.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct CustomMerit {
+    pub name: String,
+    pub category: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub description: String,
+}
+
+/// When a guild's weekly participation beat resets.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct WeeklyBeat {
+    /// Day the week starts on, 0 for Monday through 6 for Sunday.
+    pub reset_day: u8,
+    /// Offset of the guild's local time from UTC, in minutes.
+    pub utc_offset_minutes: i32,
+}
+
+/// Settings for a single guild.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct GuildSettings {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub merits: Vec<CustomMerit>,
+    /// Award a beat for the first roll each week, if set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub weekly_beat: Option<WeeklyBeat>,
+    /// Emoji shown in place of die faces, keyed by face.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub dice_emoji: BTreeMap<u8, String>,
+    /// Prefix roll results with a marker for their outcome.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub outcome_markers: bool,
+    /// Markers chosen in place of the defaults, keyed by outcome.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub outcome_marker_symbols: BTreeMap<Outcome, String>,
+    /// Commands each alias expands to, keyed by lowercase alias name.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub aliases: BTreeMap<String, String>,
+    /// Delete the message that invoked a command once it has been handled.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub delete_invocations: bool,
+    /// Named numbers rolls can use, like `tension`, keyed by lowercase name.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub constants: BTreeMap<String, i64>,
+}
+
+impl GuildSettings {
+    /// Add a custom merit, replacing any with the same name.
+    ///
+    /// # Arguments
+    ///
+    /// * `merit` - merit to add
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// settings.add_merit(merit);
+    /// ```
+    pub fn add_merit(&mut self, merit: CustomMerit) {
+        self.remove_merit(&merit.name);
+        self.merits.push(merit);
+    }
+
+    /// Remove a custom merit by name, ignoring case.
+    ///
+    /// Returns whether there was one to remove.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - merit name
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// let removed = settings.remove_merit("Ancestral Blade");
+    /// ```
+    pub fn remove_merit(&mut self, name: &str) -> bool {
+        let before = self.merits.len();
+        self.merits.retain(|m| !m.name.eq_ignore_ascii_case(name));
+        before != self.merits.len()
+    }
+
+    /// Set a named constant for roll expressions, replacing any with the
+    /// same name.
+    ///
+    /// Returns why the constant can't be set otherwise.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - constant name, matched ignoring case
+    /// * `value` - number the name stands for in rolls
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// settings.set_constant("tension", 2)?;
+    /// ```
+    pub fn set_constant(&mut self, name: &str, value: i64) -> Result<(), String> {
+        let name = name.to_lowercase();
+        let valid_name = !name.is_empty()
+            && name.chars().count() <= MAX_CONSTANT_LEN
+            && name.chars().all(|c| c.is_alphanumeric() || c == '_');
+        if !valid_name {
+            return Err(format!(
+                "`{}` can't be a constant; use up to {} letters, numbers or _.",
+                name, MAX_CONSTANT_LEN
+            ));
+        }
+        if let Some(reason) = reserved_reason(&name) {
+            return Err(format!("`{}` can't be a constant, since {}.", name, reason));
+        }
+        if value.abs() > MAX_CONSTANT_VALUE {
+            return Err(format!(
+                "Constants can be from -{} to {}.",
+                MAX_CONSTANT_VALUE, MAX_CONSTANT_VALUE
+            ));
+        }
+        if !self.constants.contains_key(&name) && self.constants.len() >= MAX_CONSTANTS {
+            return Err(format!("A server can have at most {} constants.", MAX_CONSTANTS));
+        }
+        self.constants.insert(name, value);
+        Ok(())
+    }
+
+    /// Remove a named constant, ignoring case.
+    ///
+    /// Returns whether there was one to remove.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - constant name
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// let removed = settings.remove_constant("tension");
+    /// ```
+    pub fn remove_constant(&mut self, name: &str) -> bool {
+        self.constants.remove(&name.to_lowercase()).is_some()
+    }
+}
+
+/// Settings for a single user, shared across guilds.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct UserSettings {
+    /// IANA name of the user's time zone, like "Europe/Warsaw".
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timezone: Option<String>,
+}
+
+/// Settings for every guild the bot is in, and its users.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct SettingsStore {
+    #[serde(default)]
+    guilds: HashMap<u64, GuildSettings>,
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    users: HashMap<u64, UserSettings>,
+}
+
+impl SettingsStore {
+    /// Get a guild's settings, if any have been set.
+    ///
+    /// # Arguments
+    ///
+    /// * `guild_id` - guild to look up
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// let settings = settings_store.get(guild_id);
+    /// ```
+    pub fn get(&self, guild_id: u64) -> Option<&GuildSettings> {
+        self.guilds.get(&guild_id)
+    }
+
+    /// Get a guild's settings for updating, creating defaults if needed.
+    ///
+    /// # Arguments
+    ///
+    /// * `guild_id` - guild to look up
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// settings_store.get_mut(guild_id).add_merit(merit);
+    /// ```
+    pub fn get_mut(&mut self, guild_id: u64) -> &mut GuildSettings {
+        self.guilds.entry(guild_id).or_default()
+    }
+
+    /// Get a user's settings, if any have been set.
+    ///
+    /// # Arguments
+    ///
+    /// * `user_id` - user to look up
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// let timezone = settings_store.user(user_id).and_then(|u| u.timezone.clone());
+    /// ```
+    pub fn user(&self, user_id: u64) -> Option<&UserSettings> {
+        self.users.get(&user_id)
+    }
+
+    /// Get a user's settings for updating, creating defaults if needed.
+    ///
+    /// # Arguments
+    ///
+    /// * `user_id` - user to look up
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// settings_store.user_mut(user_id).timezone = None;
+    /// ```
+    pub fn user_mut(&mut self, user_id: u64) -> &mut UserSettings {
+        self.users.entry(user_id).or_default()
+    }
+
+    /// Cleans up after saves to the store that a crash interrupted.
+    ///
+    /// Only call this on startup, before any command can save the store.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - path to the file
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// SettingsStore::recover(&SETTINGS_PATH)?;
+    /// ```
+    pub fn recover(path: &Path) -> Result<(), Error> {
+        recover_leftovers(path, |c| serde_json::from_str::<SettingsStore>(c).is_ok())?;
+        Ok(())
+    }
+
+    /// Loads the store from a JSON file.
+    ///
+    /// A missing file results in an empty store.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - path to the file
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// let store = SettingsStore::from_file(&SETTINGS_PATH).unwrap();
+    /// ```
+    pub fn from_file(path: &Path) -> Result<Self, Error> {
+        let content = match fs::read_to_string(path) {
+            Ok(c) => c,
+            Err(_) => return Ok(SettingsStore::default()),
+        };
+        Ok(serde_json::from_str(&content)?)
+    }
+
+    /// Save the store to a JSON file.
+    ///
+    /// The file is written next to the destination and then renamed over
+    /// it, so a failed write never leaves partly saved settings.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - path to output file
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// settings_store.save(&SETTINGS_PATH).unwrap();
+    /// ```
+    pub fn save(&self, path: &Path) -> Result<(), Error> {
+        let output = serde_json::to_string(&self)?;
+        atomic_write(path, output.as_bytes())?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{CustomMerit, GuildSettings, SettingsStore, MAX_CONSTANTS};
+    use tempdir::TempDir;
+
+    fn merit(name: &str) -> CustomMerit {
+        CustomMerit {
+            name: name.to_owned(),
+            category: String::from("fighting"),
+            description: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_add_remove_merit() {
+        let mut gs = GuildSettings::default();
+        gs.add_merit(merit("Ancestral Blade"));
+        gs.add_merit(merit("ancestral blade"));
+
+        assert_eq!(gs.merits, vec![merit("ancestral blade")]);
+        assert!(gs.remove_merit("ANCESTRAL BLADE"));
+        assert!(!gs.remove_merit("Ancestral Blade"));
+    }
+
+    #[test]
+    fn test_set_remove_constant() {
+        let mut gs = GuildSettings::default();
+
+        assert_eq!(gs.set_constant("Tension", 2), Ok(()));
+        assert_eq!(gs.set_constant("tension", -10), Ok(()));
+        assert_eq!(gs.constants.get("tension"), Some(&-10));
+        assert_eq!(
+            gs.set_constant("darkness", 11),
+            Err(String::from("Constants can be from -10 to 10."))
+        );
+        assert_eq!(
+            gs.set_constant("dark-ness", 1),
+            Err(String::from(
+                "`dark-ness` can't be a constant; use up to 20 letters, numbers or _."
+            ))
+        );
+        assert_eq!(
+            gs.set_constant("ROTE", 1),
+            Err(String::from(
+                "`rote` can't be a constant, since it has its own meaning in rolls."
+            ))
+        );
+        assert!(gs.set_constant("9again", 1).is_err());
+        assert!(gs.remove_constant("TENSION"));
+        assert!(!gs.remove_constant("tension"));
+    }
+
+    #[test]
+    fn test_constant_limit() {
+        let mut gs = GuildSettings::default();
+        for i in 0..MAX_CONSTANTS {
+            gs.set_constant(&format!("c{}", i), 1).unwrap();
+        }
+
+        assert_eq!(
+            gs.set_constant("extra", 1),
+            Err(String::from("A server can have at most 25 constants."))
+        );
+        // Replacing one doesn't count against the limit
+        assert_eq!(gs.set_constant("c0", 2), Ok(()));
+    }
+
+    #[test]
+    fn test_store_round_trip() {
+        let temp = TempDir::new("dicebot").unwrap();
+        let path = temp.path().join("settings.json");
+        let mut store = SettingsStore::from_file(&path).unwrap();
+
+        assert!(store.get(1).is_none());
+
+        store.get_mut(1).add_merit(merit("Ancestral Blade"));
+        store.save(&path).unwrap();
+        let store = SettingsStore::from_file(&path).unwrap();
+
+        assert_eq!(store.get(1).unwrap().merits, vec![merit("Ancestral Blade")]);
+        assert!(store.get(2).is_none());
+    }
+
+    #[test]
+    fn test_user_settings_round_trip() {
+        let temp = TempDir::new("dicebot").unwrap();
+        let path = temp.path().join("settings.json");
+        // Stores saved before user settings existed still load
+        let mut store: SettingsStore = serde_json::from_str(r#"{"guilds":{}}"#).unwrap();
+
+        assert!(store.user(1).is_none());
+
+        store.user_mut(1).timezone = Some(String::from("Europe/Warsaw"));
+        store.save(&path).unwrap();
+        let store = SettingsStore::from_file(&path).unwrap();
+
+        assert_eq!(store.user(1).unwrap().timezone, Some(String::from("Europe/Warsaw")));
+        assert!(store.user(2).is_none());
+    }
+}
@@ -0,0 +1,95 @@
+/// Abbreviations and alternate spellings of the core attributes and skills,
+/// with the name each is stored under.
+const ALIASES: &[(&str, &str)] = &[
+    // Attributes
+    ("int", "intelligence"),
+    ("wit", "wits"),
+    ("res", "resolve"),
+    ("str", "strength"),
+    ("dex", "dexterity"),
+    ("sta", "stamina"),
+    ("stam", "stamina"),
+    ("pre", "presence"),
+    ("pres", "presence"),
+    ("man", "manipulation"),
+    ("manip", "manipulation"),
+    ("com", "composure"),
+    ("comp", "composure"),
+    // Mental skills
+    ("acad", "academics"),
+    ("computers", "computer"),
+    ("craft", "crafts"),
+    ("invest", "investigation"),
+    ("med", "medicine"),
+    ("occ", "occult"),
+    ("pol", "politics"),
+    ("sci", "science"),
+    // Physical skills
+    ("ath", "athletics"),
+    ("athl", "athletics"),
+    ("guns", "firearms"),
+    ("larc", "larceny"),
+    ("surv", "survival"),
+    ("weap", "weaponry"),
+    // Social skills
+    ("ak", "animal_ken"),
+    ("animalken", "animal_ken"),
+    ("emp", "empathy"),
+    ("expr", "expression"),
+    ("intim", "intimidation"),
+    ("pers", "persuasion"),
+    ("soc", "socialize"),
+    ("street", "streetwise"),
+    ("subt", "subterfuge"),
+];
+
+/// Returns the name a stat is stored under.
+///
+/// Surrounding punctuation is removed, the name is lowercased, and
+/// abbreviations of the core attributes and skills are expanded.
+///
+/// # Arguments
+///
+/// * `name` - stat name as typed
+///
+/// # Examples
+///
+/// ```rust
+/// assert_eq!(canonical("DEX"), "dexterity");
+/// ```
+pub fn canonical(name: &str) -> String {
+    let name = name
+        .trim_matches(|c: char| !c.is_alphanumeric())
+        .to_lowercase();
+    match ALIASES.iter().find(|(alias, _)| *alias == name) {
+        Some((_, canonical)) => (*canonical).to_owned(),
+        None => name,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{canonical, ALIASES};
+
+    #[test]
+    fn test_canonical() {
+        assert_eq!(canonical("dex"), "dexterity");
+        assert_eq!(canonical("DEX"), "dexterity");
+        assert_eq!(canonical("Dexterity"), "dexterity");
+        assert_eq!(canonical("dexterity."), "dexterity");
+        assert_eq!(canonical(" (wits), "), "wits");
+        assert_eq!(canonical("animal_ken"), "animal_ken");
+        assert_eq!(canonical("AK"), "animal_ken");
+        assert_eq!(canonical("Gift of Vigor"), "gift of vigor");
+        assert_eq!(canonical("custom_stat"), "custom_stat");
+    }
+
+    #[test]
+    fn test_aliases_are_canonical() {
+        for (alias, name) in ALIASES {
+            assert_eq!(canonical(name), *name, "{} is an alias itself", name);
+            assert_ne!(alias, name);
+            assert_eq!(ALIASES.iter().filter(|(a, _)| a == alias).count(), 1, "{}", alias);
+        }
+    }
+}
@@ -0,0 +1,117 @@
+/// Characters counted as one dot each in a stat value.
+pub const DOTS: [char; 3] = ['•', '*', 'o'];
+/// Longest run of dots accepted as a value.
+const MAX_DOTS: usize = 10;
+/// Values that can be written as words, indexed by their value.
+const NUMBER_WORDS: [&str; 11] = [
+    "zero", "one", "two", "three", "four", "five", "six", "seven", "eight", "nine", "ten",
+];
+/// The forms a stat value can take, for error messages.
+const VALUE_FORMS: &str = "a number like 3, dots like ••• (or *** or ooo), \
+                           a word like three, or 3 dots";
+
+/// Parse a stat value as typed or pasted by a player.
+///
+/// Accepts a whole number, a run of up to 10 dots, or a number from
+/// zero to ten written as a word. Any of these may be followed by
+/// "dot" or "dots". Anything else is an error that lists the forms
+/// accepted.
+///
+/// # Arguments
+///
+/// * `value` - value text
+///
+/// # Examples
+///
+/// ```rust
+/// assert_eq!(parse_stat_value("••"), Ok(2));
+/// ```
+pub fn parse_stat_value(value: &str) -> Result<i64, String> {
+    let value = value.trim();
+    let lower = value.to_lowercase();
+    let amount = ["dots", "dot"]
+        .iter()
+        .find(|suffix| lower.len() > suffix.len() && lower.ends_with(*suffix))
+        .map(|suffix| lower[..lower.len() - suffix.len()].trim_end())
+        .unwrap_or(&lower);
+    if let Ok(i) = amount.parse::<i64>() {
+        return Ok(i);
+    }
+    if let Some(i) = NUMBER_WORDS.iter().position(|w| *w == amount) {
+        return Ok(i as i64);
+    }
+    let dots: Vec<char> = amount.chars().filter(|c| !c.is_whitespace()).collect();
+    if !dots.is_empty() && dots.iter().all(|c| DOTS.contains(c)) {
+        if dots.len() > MAX_DOTS {
+            return Err(format!("`{}` is more than {} dots", value, MAX_DOTS));
+        }
+        return Ok(dots.len() as i64);
+    }
+    Err(format!("`{}` is not a stat value; use {}", value, VALUE_FORMS))
+}
+
+#[cfg(test)]
+mod test {
+    use super::parse_stat_value;
+
+    #[test]
+    fn test_accepted_forms() {
+        let cases = [
+            ("3", 3),
+            ("0", 0),
+            ("-1", -1),
+            (" 12 ", 12),
+            ("•", 1),
+            ("••", 2),
+            ("• • •", 3),
+            ("***", 3),
+            ("ooooo", 5),
+            ("o", 1),
+            ("•*o", 3),
+            ("••••••••••", 10),
+            ("three", 3),
+            ("Three", 3),
+            ("zero", 0),
+            ("ten", 10),
+            ("3 dots", 3),
+            ("1 dot", 1),
+            ("3dots", 3),
+            ("two dots", 2),
+            ("•• dots", 2),
+        ];
+        for (value, expected) in &cases {
+            assert_eq!(parse_stat_value(value), Ok(*expected), "{}", value);
+        }
+    }
+
+    #[test]
+    fn test_rejected_forms() {
+        let cases = [
+            "",
+            "lots",
+            "••x",
+            "3x",
+            "eleven",
+            "dots",
+            "dot",
+            "3 4",
+            "• 3",
+            "oops",
+            "-",
+        ];
+        for value in &cases {
+            let error = parse_stat_value(value).unwrap_err();
+            assert!(error.contains("is not a stat value"), "{}: {}", value, error);
+            assert!(error.contains("a word like three"), "{}", error);
+        }
+    }
+
+    #[test]
+    fn test_dots_capped() {
+        assert_eq!(
+            parse_stat_value("•••••••••••"),
+            Err(String::from("`•••••••••••` is more than 10 dots"))
+        );
+        assert!(parse_stat_value("ooooooooooo dots").is_err());
+    }
+}
@@ -0,0 +1,61 @@
+use rand::{Error, RngCore};
+
+/// Value range of a single step when mapping a `u64` onto a d10.
+const FACE_STEP: u64 = 1_844_674_407_370_955_162;
+
+/// Deterministic RNG that makes d10 rolls come up as the scripted faces.
+///
+/// Each face is encoded so that `Uniform::new_inclusive(1, 10)` samples it
+/// back out from a single `next_u64` call.
+pub struct ScriptedRng {
+    faces: Vec<u64>,
+    index: usize,
+}
+
+impl ScriptedRng {
+    /// Create a new RNG that yields the faces in order.
+    ///
+    /// # Arguments
+    ///
+    /// * `faces` - d10 faces, each 1 through 10
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// let mut rng = ScriptedRng::new(&[10, 3, 8]);
+    /// ```
+    pub fn new(faces: &[u64]) -> Self {
+        ScriptedRng {
+            faces: faces.to_vec(),
+            index: 0,
+        }
+    }
+}
+
+impl RngCore for ScriptedRng {
+    fn next_u32(&mut self) -> u32 {
+        self.next_u64() as u32
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let face = self
+            .faces
+            .get(self.index)
+            .cloned()
+            .expect("ScriptedRng ran out of faces");
+        self.index += 1;
+        (face - 1) * FACE_STEP
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        for chunk in dest.chunks_mut(8) {
+            let bytes = self.next_u64().to_le_bytes();
+            chunk.copy_from_slice(&bytes[..chunk.len()]);
+        }
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
@@ -0,0 +1,148 @@
+/// Most characters of user-provided text echoed into a message.
+pub const MAX_ECHOED_CHARS: usize = 64;
+/// Characters that format text in Discord markdown.
+const MARKDOWN: &[char] = &['\\', '*', '_', '~', '`', '|'];
+/// Zero-width space, which stops Discord from reading a mention.
+const ZERO_WIDTH_SPACE: char = '\u{200B}';
+
+/// Pick the singular or plural form of a word for a count.
+///
+/// # Arguments
+///
+/// * `n` - how many there are
+/// * `one` - word for exactly one
+/// * `many` - word for any other count
+///
+/// # Examples
+///
+/// ```rust
+/// let word = plural(3, "die", "dice");
+/// ```
+pub fn plural<'a>(n: i64, one: &'a str, many: &'a str) -> &'a str {
+    if n == 1 {
+        one
+    } else {
+        many
+    }
+}
+
+/// Format a count followed by the matching form of a word, like "1 die".
+///
+/// # Arguments
+///
+/// * `n` - how many there are
+/// * `one` - word for exactly one
+/// * `many` - word for any other count
+///
+/// # Examples
+///
+/// ```rust
+/// let text = count(3, "die", "dice");
+/// ```
+pub fn count(n: i64, one: &str, many: &str) -> String {
+    format!("{} {}", n, plural(n, one, many))
+}
+
+/// Break mentions that could ping a whole server or role, leaving user
+/// mentions alone.
+///
+/// # Arguments
+///
+/// * `text` - text about to be sent
+///
+/// # Examples
+///
+/// ```rust
+/// let text = neutralize_mentions("hi @everyone");
+/// ```
+pub fn neutralize_mentions(text: &str) -> String {
+    text.replace("@everyone", &format!("@{}everyone", ZERO_WIDTH_SPACE))
+        .replace("@here", &format!("@{}here", ZERO_WIDTH_SPACE))
+        .replace("<@&", &format!("<@{}&", ZERO_WIDTH_SPACE))
+}
+
+/// Make user-provided text safe to echo into a message, like a stat name
+/// or a bonus's source.
+///
+/// Markdown is escaped, server and role mentions are broken, and text past
+/// `MAX_ECHOED_CHARS` is cut off.
+///
+/// # Arguments
+///
+/// * `text` - text the user provided
+///
+/// # Examples
+///
+/// ```rust
+/// let name = sanitize("**Gun Nut**");
+/// ```
+pub fn sanitize(text: &str) -> String {
+    let mut escaped = String::new();
+    for c in text.chars().take(MAX_ECHOED_CHARS) {
+        if MARKDOWN.contains(&c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    if text.chars().count() > MAX_ECHOED_CHARS {
+        escaped.push('…');
+    }
+    neutralize_mentions(&escaped)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{count, neutralize_mentions, plural, sanitize, MAX_ECHOED_CHARS};
+
+    #[test]
+    fn test_plural() {
+        assert_eq!(plural(0, "die", "dice"), "dice");
+        assert_eq!(plural(1, "die", "dice"), "die");
+        assert_eq!(plural(2, "die", "dice"), "dice");
+        assert_eq!(plural(-1, "die", "dice"), "dice");
+    }
+
+    #[test]
+    fn test_count() {
+        assert_eq!(count(0, "success", "successes"), "0 successes");
+        assert_eq!(count(1, "success", "successes"), "1 success");
+        assert_eq!(count(5, "success", "successes"), "5 successes");
+    }
+
+    #[test]
+    fn test_sanitize() {
+        let cases = &[
+            ("Gun Nut", "Gun Nut"),
+            ("**bold**", "\\*\\*bold\\*\\*"),
+            ("animal_ken", "animal\\_ken"),
+            ("~~gone~~", "\\~\\~gone\\~\\~"),
+            ("`code`", "\\`code\\`"),
+            ("||spoiler||", "\\|\\|spoiler\\|\\|"),
+            ("back\\slash", "back\\\\slash"),
+            ("**@everyone**", "\\*\\*@\u{200B}everyone\\*\\*"),
+            ("@here", "@\u{200B}here"),
+            ("<@&1234>", "<@\u{200B}&1234>"),
+            ("<@1234>", "<@1234>"),
+        ];
+        for (hostile, safe) in cases.iter() {
+            assert_eq!(sanitize(hostile), *safe, "sanitizing {:?}", hostile);
+        }
+    }
+
+    #[test]
+    fn test_sanitize_caps_length() {
+        let long = "a".repeat(MAX_ECHOED_CHARS + 10);
+        let capped = sanitize(&long);
+        assert_eq!(capped.chars().count(), MAX_ECHOED_CHARS + 1);
+        assert!(capped.ends_with('…'));
+        assert_eq!(sanitize(&"a".repeat(MAX_ECHOED_CHARS)), "a".repeat(MAX_ECHOED_CHARS));
+    }
+
+    #[test]
+    fn test_neutralize_mentions() {
+        assert_eq!(
+            neutralize_mentions("<@1> rolled for @everyone, @here and <@&2>"),
+            "<@1> rolled for @\u{200B}everyone, @\u{200B}here and <@\u{200B}&2>"
+        );
+    }
+}
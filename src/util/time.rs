@@ -0,0 +1,329 @@
+use chrono::{Datelike, Duration, NaiveDate, TimeZone, Utc};
+use chrono_tz::{Tz, TZ_VARIANTS};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Most time zone names suggested for a name that isn't one.
+const MAX_SUGGESTIONS: usize = 3;
+/// Most edits a name can be from a time zone and still be suggested.
+const MAX_SUGGESTION_DISTANCE: usize = 2;
+
+/// Returns the current time as seconds since the Unix epoch.
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// Returns when the week containing `now` started, as seconds since the Unix epoch.
+///
+/// Weeks start at local midnight on the reset day, where local time is
+/// UTC shifted by a fixed offset.
+///
+/// # Arguments
+///
+/// * `now` - seconds since the Unix epoch
+/// * `reset_day` - day the week starts on, 0 for Monday through 6 for Sunday
+/// * `utc_offset_minutes` - offset of local time from UTC
+///
+/// # Examples
+///
+/// ```rust
+/// let start = week_start(now(), 0, 120);
+/// ```
+pub fn week_start(now: u64, reset_day: u8, utc_offset_minutes: i32) -> u64 {
+    let offset = i64::from(utc_offset_minutes) * 60;
+    let local = now as i64 + offset;
+    let days = local.div_euclid(86400);
+    // 1970-01-01 was a Thursday
+    let weekday = (days + 3).rem_euclid(7);
+    let days_since_reset = (weekday - i64::from(reset_day)).rem_euclid(7);
+    let start = (days - days_since_reset) * 86400 - offset;
+    start.max(0) as u64
+}
+
+/// Returns when the week containing `now` started in a time zone, as
+/// seconds since the Unix epoch.
+///
+/// Unlike `week_start`, the boundary follows the zone's daylight saving
+/// rules, so it is always local midnight on the reset day.
+///
+/// # Arguments
+///
+/// * `now` - seconds since the Unix epoch
+/// * `reset_day` - day the week starts on, 0 for Monday through 6 for Sunday
+/// * `tz` - time zone the week is counted in
+///
+/// # Examples
+///
+/// ```rust
+/// let start = zoned_week_start(now(), 0, chrono_tz::Europe::Warsaw);
+/// ```
+pub fn zoned_week_start(now: u64, reset_day: u8, tz: Tz) -> u64 {
+    let local = Utc.timestamp(now as i64, 0).with_timezone(&tz).naive_local().date();
+    let weekday = i64::from(local.weekday().num_days_from_monday());
+    let days_since_reset = (weekday - i64::from(reset_day)).rem_euclid(7);
+    let start_date: NaiveDate = local - Duration::days(days_since_reset);
+    // Midnight may be skipped by a daylight saving change; the day then
+    // starts at the first hour that exists
+    let start = (0..24)
+        .filter_map(|hour| tz.from_local_datetime(&start_date.and_hms(hour, 0, 0)).earliest())
+        .next()
+        .map(|start| start.timestamp())
+        .unwrap_or(0);
+    start.max(0) as u64
+}
+
+/// Number of single-character edits between two strings.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + if ca == *cb { 0 } else { 1 };
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+/// Parse an IANA time zone name, like "Europe/Warsaw", ignoring case.
+///
+/// If the name isn't a time zone, returns the closest time zone names,
+/// which may be empty. A bare city like "warsaw" suggests the zone
+/// named after it.
+///
+/// # Arguments
+///
+/// * `name` - time zone name
+///
+/// # Examples
+///
+/// ```rust
+/// let tz = parse_timezone("europe/warsaw").unwrap();
+/// ```
+pub fn parse_timezone(name: &str) -> Result<Tz, Vec<&'static str>> {
+    let name = name.trim().to_lowercase();
+    if let Some(tz) = TZ_VARIANTS.iter().find(|tz| tz.name().to_lowercase() == name) {
+        return Ok(*tz);
+    }
+    let mut close: Vec<(usize, &'static str)> = TZ_VARIANTS
+        .iter()
+        .map(|tz| {
+            let full = tz.name().to_lowercase();
+            let city = full.rsplit('/').next().unwrap_or(&full).to_owned();
+            let distance = edit_distance(&name, &full).min(edit_distance(&name, &city));
+            (distance, tz.name())
+        })
+        .filter(|(distance, _)| *distance <= MAX_SUGGESTION_DISTANCE)
+        .collect();
+    close.sort();
+    Err(close
+        .into_iter()
+        .take(MAX_SUGGESTIONS)
+        .map(|(_, name)| name)
+        .collect())
+}
+
+/// How a time is shown by Discord's timestamp markup.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TimeStyle {
+    /// Relative to now, like "in 2 hours".
+    Relative,
+    /// Full date and time.
+    Full,
+}
+
+/// Returns the UTC date and time of `epoch` in ISO-8601 format.
+fn iso_8601(epoch: u64) -> String {
+    let days = (epoch / 86400) as i64;
+    let seconds = epoch % 86400;
+    // Convert days since 1970-01-01 to a civil date, counting from 0000-03-01
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 { month_index + 3 } else { month_index - 9 };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60
+    )
+}
+
+/// Format a time for a message.
+///
+/// Uses Discord's timestamp markup, so each reader sees it in their own
+/// time zone. Markup isn't shown inside code blocks, so there the time is
+/// written in UTC as ISO-8601 instead.
+///
+/// # Arguments
+///
+/// * `epoch` - seconds since the Unix epoch
+/// * `style` - how Discord should show the time
+/// * `in_code_block` - whether the time will be shown in a code block
+///
+/// # Examples
+///
+/// ```rust
+/// let until = format_time(expires, TimeStyle::Relative, false);
+/// ```
+pub fn format_time(epoch: u64, style: TimeStyle, in_code_block: bool) -> String {
+    if in_code_block {
+        return iso_8601(epoch);
+    }
+    let flag = match style {
+        TimeStyle::Relative => 'R',
+        TimeStyle::Full => 'f',
+    };
+    format!("<t:{}:{}>", epoch, flag)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        edit_distance, format_time, iso_8601, parse_timezone, week_start, zoned_week_start,
+        TimeStyle,
+    };
+    use chrono_tz::{America, Etc, Europe};
+
+    // Monday 2019-06-03 00:00:00 UTC
+    const MONDAY: u64 = 1_559_520_000;
+    const DAY: u64 = 86400;
+    const HOUR: u64 = 3600;
+
+    #[test]
+    fn test_week_start_utc() {
+        assert_eq!(week_start(MONDAY, 0, 0), MONDAY);
+        assert_eq!(week_start(MONDAY + 6 * DAY + 23 * HOUR, 0, 0), MONDAY);
+        assert_eq!(week_start(MONDAY + 7 * DAY, 0, 0), MONDAY + 7 * DAY);
+        assert_eq!(week_start(MONDAY - 1, 0, 0), MONDAY - 7 * DAY);
+    }
+
+    #[test]
+    fn test_week_start_reset_day() {
+        // Sunday reset: Monday belongs to the week that started the day before
+        assert_eq!(week_start(MONDAY, 6, 0), MONDAY - DAY);
+        assert_eq!(week_start(MONDAY + 5 * DAY, 6, 0), MONDAY - DAY);
+        assert_eq!(week_start(MONDAY + 6 * DAY, 6, 0), MONDAY + 6 * DAY);
+        // Thursday reset
+        assert_eq!(week_start(MONDAY, 3, 0), MONDAY - 4 * DAY);
+    }
+
+    #[test]
+    fn test_week_start_offset() {
+        // 23:30 UTC Sunday is already Monday at UTC+01:00
+        let late_sunday = MONDAY - 30 * 60;
+        assert_eq!(week_start(late_sunday, 0, 0), MONDAY - 7 * DAY);
+        assert_eq!(week_start(late_sunday, 0, 60), MONDAY - HOUR);
+        // 02:00 UTC Monday is still Sunday at UTC-05:00
+        assert_eq!(week_start(MONDAY + 2 * HOUR, 0, -300), MONDAY - 7 * DAY + 5 * HOUR);
+        assert_eq!(week_start(MONDAY + 5 * HOUR, 0, -300), MONDAY + 5 * HOUR);
+    }
+
+    #[test]
+    fn test_week_start_across_daylight_saving_change() {
+        // A guild moving from UTC+01:00 to UTC+02:00 (as on 2019-03-31 in Europe)
+        // keeps the same local midnight boundary
+        let monday_after_change = 1_554_069_600 + 2 * HOUR; // 2019-04-01 00:00 UTC
+        assert_eq!(week_start(monday_after_change, 0, 60), monday_after_change - HOUR);
+        assert_eq!(week_start(monday_after_change, 0, 120), monday_after_change - 2 * HOUR);
+        assert_eq!(
+            week_start(monday_after_change - 90 * 60, 0, 120),
+            monday_after_change - 2 * HOUR
+        );
+        assert_eq!(
+            week_start(monday_after_change - 90 * 60, 0, 60),
+            monday_after_change - HOUR - 7 * DAY
+        );
+    }
+
+    #[test]
+    fn test_week_start_near_epoch() {
+        assert_eq!(week_start(0, 0, 0), 0);
+        assert_eq!(week_start(0, 3, 0), 0);
+    }
+
+    #[test]
+    fn test_zoned_week_start_across_zones() {
+        // Wednesday 2019-06-05 12:00 UTC
+        let wednesday = MONDAY + 2 * DAY + 12 * HOUR;
+        assert_eq!(zoned_week_start(wednesday, 0, Etc::UTC), MONDAY);
+        // Warsaw is UTC+02:00 in summer and New York is UTC-04:00
+        assert_eq!(zoned_week_start(wednesday, 0, Europe::Warsaw), MONDAY - 2 * HOUR);
+        assert_eq!(zoned_week_start(wednesday, 0, America::New_York), MONDAY + 4 * HOUR);
+        assert_eq!(zoned_week_start(wednesday, 6, Europe::Warsaw), MONDAY - DAY - 2 * HOUR);
+    }
+
+    #[test]
+    fn test_zoned_week_start_boundary() {
+        // 23:30 UTC on Sunday 2019-06-09 is already Monday in Warsaw,
+        // but still Sunday in UTC and New York
+        let next_monday = MONDAY + 7 * DAY;
+        let late_sunday = next_monday - 30 * 60;
+        assert_eq!(zoned_week_start(late_sunday, 0, Europe::Warsaw), next_monday - 2 * HOUR);
+        assert_eq!(zoned_week_start(late_sunday, 0, Etc::UTC), MONDAY);
+        assert_eq!(zoned_week_start(late_sunday, 0, America::New_York), MONDAY + 4 * HOUR);
+        // Agrees with a fixed offset while daylight saving doesn't change
+        assert_eq!(
+            zoned_week_start(late_sunday, 0, Europe::Warsaw),
+            week_start(late_sunday, 0, 120)
+        );
+    }
+
+    #[test]
+    fn test_zoned_week_start_across_daylight_saving_change() {
+        // Warsaw leaves UTC+02:00 for UTC+01:00 on Sunday 2019-10-27
+        let monday_before = 1_571_608_800; // 2019-10-21 00:00 in Warsaw
+        let monday_after = 1_572_217_200; // 2019-10-28 00:00 in Warsaw
+        assert_eq!(monday_after - monday_before, 7 * DAY + HOUR);
+        assert_eq!(zoned_week_start(monday_after - 30 * 60, 0, Europe::Warsaw), monday_before);
+        assert_eq!(zoned_week_start(monday_after + 30 * 60, 0, Europe::Warsaw), monday_after);
+        // A fixed offset kept from summer puts the boundary an hour early
+        assert_eq!(week_start(monday_after - 30 * 60, 0, 120), monday_after - HOUR);
+    }
+
+    #[test]
+    fn test_parse_timezone() {
+        assert_eq!(parse_timezone("Europe/Warsaw"), Ok(Europe::Warsaw));
+        assert_eq!(parse_timezone("america/new_york"), Ok(America::New_York));
+        assert_eq!(parse_timezone(" UTC "), Ok(chrono_tz::UTC));
+        assert_eq!(parse_timezone("Europe/Warsw"), Err(vec!["Europe/Warsaw"]));
+        assert_eq!(parse_timezone("warsaw"), Err(vec!["Europe/Warsaw"]));
+        assert_eq!(parse_timezone("not a zone"), Err(vec![]));
+    }
+
+    #[test]
+    fn test_edit_distance() {
+        assert_eq!(edit_distance("warsaw", "warsaw"), 0);
+        assert_eq!(edit_distance("warsw", "warsaw"), 1);
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+        assert_eq!(edit_distance("", "abc"), 3);
+    }
+
+    #[test]
+    fn test_iso_8601() {
+        assert_eq!(iso_8601(0), "1970-01-01T00:00:00Z");
+        assert_eq!(iso_8601(MONDAY), "2019-06-03T00:00:00Z");
+        assert_eq!(iso_8601(951_782_400 + 3661), "2000-02-29T01:01:01Z");
+        assert_eq!(iso_8601(1_735_689_599), "2024-12-31T23:59:59Z");
+    }
+
+    #[test]
+    fn test_format_time() {
+        assert_eq!(format_time(MONDAY, TimeStyle::Relative, false), "<t:1559520000:R>");
+        assert_eq!(format_time(MONDAY, TimeStyle::Full, false), "<t:1559520000:f>");
+        assert_eq!(format_time(MONDAY, TimeStyle::Full, true), "2019-06-03T00:00:00Z");
+    }
+}
@@ -0,0 +1,131 @@
+use std::fmt;
+use crate::util::text::sanitize;
+
+/// Most characters of a rendered trace, so it fits in a message with the roll.
+const MAX_TRACE_CHARS: usize = 1000;
+
+/// One step in working out a roll, recorded as the roll line is read.
+#[derive(Clone, Debug, PartialEq)]
+pub enum TraceEvent {
+    /// Tokens of the roll line, lowercased.
+    Tokens(Vec<String>),
+    /// A word with its own meaning in a roll, like `rote`.
+    Reserved(String),
+    /// An again modifier given in the roll line.
+    Modifier(String),
+    /// A stat's stored again modifier, used when the line gives none.
+    StatModifier(String, String),
+    /// A number in the roll line.
+    Literal(i64),
+    /// A stat found on the character, with its stored value.
+    Stat(String, i64),
+    /// A guild constant used for a name the character has no stat for.
+    Constant(String, i64),
+    /// A name the character has no stat for, counted as 0.
+    Missing(String),
+    /// Dice added to or taken from the pool, and what they came from.
+    Adjustment(String, i64),
+    /// The dice finally rolled, like `5 9again`.
+    Spec(String),
+}
+
+impl fmt::Display for TraceEvent {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TraceEvent::Tokens(tokens) => write!(f, "tokens: {}", tokens.join(" ")),
+            TraceEvent::Reserved(word) => write!(f, "{}: reserved word", word),
+            TraceEvent::Modifier(modifier) => write!(f, "{}: modifier", modifier),
+            TraceEvent::StatModifier(stat, modifier) => {
+                write!(f, "{}: modifier from {}", modifier, stat)
+            }
+            TraceEvent::Literal(n) => write!(f, "{}: literal", n),
+            TraceEvent::Stat(name, value) => write!(f, "{}: stat = {}", name, value),
+            TraceEvent::Constant(name, value) => write!(f, "{}: guild constant = {}", name, value),
+            TraceEvent::Missing(name) => write!(f, "{}: unknown, counted as 0", name),
+            TraceEvent::Adjustment(source, dice) => write!(f, "{:+} from {}", dice, source),
+            TraceEvent::Spec(spec) => write!(f, "rolled: {}", spec),
+        }
+    }
+}
+
+/// Render a roll's trace one event per line, for a code block.
+///
+/// Text the user provided is sanitized, and events past `MAX_TRACE_CHARS`
+/// are counted instead of shown.
+///
+/// # Arguments
+///
+/// * `events` - trace events in the order they happened
+///
+/// # Examples
+///
+/// ```rust
+/// let text = render_trace(&events);
+/// ```
+pub fn render_trace(events: &[TraceEvent]) -> String {
+    let mut lines: Vec<String> = vec![];
+    let mut length = 0;
+    for (shown, event) in events.iter().enumerate() {
+        let line = sanitize_event(event).to_string();
+        if length + line.len() > MAX_TRACE_CHARS {
+            lines.push(format!("... and {} more", events.len() - shown));
+            break;
+        }
+        length += line.len() + 1;
+        lines.push(line);
+    }
+    lines.join("\n")
+}
+
+/// Copy an event with the text the user provided sanitized.
+fn sanitize_event(event: &TraceEvent) -> TraceEvent {
+    match event {
+        TraceEvent::Tokens(tokens) => {
+            TraceEvent::Tokens(tokens.iter().map(|t| sanitize(t)).collect())
+        }
+        TraceEvent::StatModifier(stat, modifier) => {
+            TraceEvent::StatModifier(sanitize(stat), modifier.clone())
+        }
+        TraceEvent::Stat(name, value) => TraceEvent::Stat(sanitize(name), *value),
+        TraceEvent::Constant(name, value) => TraceEvent::Constant(sanitize(name), *value),
+        TraceEvent::Missing(name) => TraceEvent::Missing(sanitize(name)),
+        TraceEvent::Adjustment(source, dice) => TraceEvent::Adjustment(sanitize(source), *dice),
+        other => other.clone(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{render_trace, TraceEvent, MAX_TRACE_CHARS};
+
+    #[test]
+    fn test_render_trace() {
+        let events = vec![
+            TraceEvent::Tokens(vec![String::from("wits"), String::from("+"), String::from("1")]),
+            TraceEvent::Stat(String::from("wits"), 3),
+            TraceEvent::Literal(1),
+            TraceEvent::Missing(String::from("**x**")),
+            TraceEvent::Adjustment(String::from("Gun Nut"), 2),
+            TraceEvent::Spec(String::from("6 10again")),
+        ];
+        assert_eq!(
+            render_trace(&events),
+            "tokens: wits + 1\n\
+             wits: stat = 3\n\
+             1: literal\n\
+             \\*\\*x\\*\\*: unknown, counted as 0\n\
+             +2 from Gun Nut\n\
+             rolled: 6 10again"
+        );
+    }
+
+    #[test]
+    fn test_render_trace_length_guard() {
+        let events: Vec<TraceEvent> = (0..500).map(TraceEvent::Literal).collect();
+        let text = render_trace(&events);
+
+        assert!(text.len() <= MAX_TRACE_CHARS + 20, "{}", text.len());
+        assert!(text.ends_with("more"));
+        assert!(text.starts_with("0: literal\n1: literal"));
+    }
+}